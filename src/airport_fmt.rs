@@ -0,0 +1,266 @@
+use crate::{nasr, util};
+
+/// Maximum line length for wrapped text.
+const WRAP_WIDTH: usize = 100;
+
+/// Nearest airports farther than this (in meters) are left out of position reports.
+pub const REPORT_RADIUS: f64 = 15.0 * util::METERS_PER_NM;
+
+/// Text format for exported airport information.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+  Text,
+  Markdown,
+}
+
+/// Format airport information for the clipboard.
+/// - `info`: airport information
+/// - `format`: output format
+pub fn format(info: &nasr::AirportInfo, format: Format) -> String {
+  let title = format!("{} ({})", info.name, info.id);
+  let lat = util::format_lat(info.coord.y).unwrap_or_default();
+  let lon = util::format_lon(info.coord.x).unwrap_or_default();
+  let mut fields = vec![
+    ("Coordinates", format!("{lat}, {lon}")),
+    ("Type", info.airport_type.text().into()),
+    ("Use", info.airport_use.text().into()),
+  ];
+
+  if let Some(elevation) = info.elevation {
+    fields.push(("Elevation", format!("{elevation:.0} ft")));
+  }
+
+  if !info.fuel_types.is_empty() {
+    fields.push(("Fuel", info.fuel_types.clone()));
+  }
+
+  let mut text = String::new();
+  match format {
+    Format::Text => {
+      add_lines(&mut text, &title, "");
+      text.push_str(&"=".repeat(title.chars().count().min(WRAP_WIDTH)));
+      text.push('\n');
+      for (label, value) in fields {
+        add_lines(&mut text, &format!("{label}: {value}"), "  ");
+      }
+    }
+    Format::Markdown => {
+      add_lines(&mut text, &format!("## {title}"), "");
+      text.push('\n');
+      for (label, value) in fields {
+        add_lines(&mut text, &format!("- **{label}**: {value}"), "  ");
+      }
+    }
+  }
+  text
+}
+
+/// Format a position report for the clipboard, e.g.
+/// `37°27'41.00"N 122°06'54.00"W — 3.4 NM / 198° from PAO (Palo Alto) — San Francisco SEC`.
+/// The bearing is magnetic, like a radial. It's marked "°T" (true) if the airport's magnetic
+/// variation isn't known.
+/// - `coord`: reported position in decimal degrees (NAD 83)
+/// - `airports`: airports to measure from; the nearest one within [`REPORT_RADIUS`] is used
+/// - `chart`: chart name
+/// - `units`: display units
+pub fn position_report<'a, I: Iterator<Item = &'a nasr::AirportInfo>>(
+  coord: util::Coord,
+  airports: I,
+  chart: &str,
+  units: util::Units,
+) -> String {
+  let lat = util::format_lat(coord.y).unwrap_or_default();
+  let lon = util::format_lon(coord.x).unwrap_or_default();
+  let mut parts = vec![format!("{lat} {lon}")];
+
+  // The distance and bearing are from the airport to the position.
+  let nearest = airports
+    .map(|info| (info, distance_bearing(info.coord, coord)))
+    .filter(|(_, (dist, _))| *dist <= REPORT_RADIUS)
+    .min_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b));
+  if let Some((info, (dist, bearing))) = nearest {
+    let dist = util::format_distance(dist, units);
+    let name = util::title_case(&info.name.to_lowercase());
+    let (bearing, suffix) = match info.mag_var {
+      Some(mag_var) => (whole_degrees(bearing - mag_var), ""),
+      None => (whole_degrees(bearing), "T"),
+    };
+    let id = &info.id;
+    parts.push(format!("{dist} / {bearing:03}°{suffix} from {id} ({name})"));
+  }
+
+  if !chart.is_empty() {
+    parts.push(chart.into());
+  }
+  parts.join(" — ")
+}
+
+/// Distance in meters and true bearing in degrees between two coordinates. A flat approximation
+/// is used, which is close enough within [`REPORT_RADIUS`].
+/// - `from`: starting coordinate in decimal degrees
+/// - `to`: ending coordinate in decimal degrees
+fn distance_bearing(from: util::Coord, to: util::Coord) -> (f64, f64) {
+  let lat = ((from.y + to.y) * 0.5).to_radians();
+  let dx = (to.x - from.x) * util::METERS_PER_DEGREE * lat.cos();
+  let dy = (to.y - from.y) * util::METERS_PER_DEGREE;
+  (dx.hypot(dy), dx.atan2(dy).to_degrees())
+}
+
+/// Round a bearing to whole degrees, from 1 to 360.
+/// - `bearing`: bearing in degrees
+fn whole_degrees(bearing: f64) -> u32 {
+  let bearing = bearing.round().rem_euclid(360.0) as u32;
+  if bearing == 0 {
+    360
+  } else {
+    bearing
+  }
+}
+
+/// Wrap text into lines no longer than [`WRAP_WIDTH`] characters (unless a single word is longer).
+/// - `text`: text to wrap
+/// - `indent`: indent for continuation lines
+pub fn wrap(text: &str, indent: &str) -> Vec<String> {
+  let mut lines = Vec::new();
+  let mut line = String::new();
+  let mut len = 0;
+  for word in text.split_whitespace() {
+    let word_len = word.chars().count();
+    if len > 0 && len + 1 + word_len > WRAP_WIDTH {
+      lines.push(std::mem::take(&mut line));
+      line.push_str(indent);
+      len = indent.chars().count();
+    } else if len > 0 && !line.ends_with(' ') {
+      line.push(' ');
+      len += 1;
+    }
+    line.push_str(word);
+    len += word_len;
+  }
+
+  if !line.is_empty() {
+    lines.push(line);
+  }
+  lines
+}
+
+fn add_lines(text: &mut String, line: &str, indent: &str) {
+  for line in wrap(line, indent) {
+    text.push_str(&line);
+    text.push('\n');
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn airport() -> nasr::AirportInfo {
+    nasr::AirportInfo {
+      fid: 1,
+      id: "TVL".into(),
+      icao_id: Some("KTVL".into()),
+      state: "CA".into(),
+      name: "LAKE TAHOE".into(),
+      coord: util::Coord {
+        x: -119.99555,
+        y: 38.89389,
+      },
+      airport_type: nasr::AirportType::Airport,
+      airport_use: nasr::AirportUse::Public,
+      elevation: Some(6269.0),
+      mag_var: Some(14.0),
+      fuel_types: "100LL,A".into(),
+      fuel: nasr::FuelTypes::parse("100LL,A"),
+      runway: None,
+      desc: String::new(),
+    }
+  }
+
+  #[test]
+  fn test_text() {
+    let text = format(&airport(), Format::Text);
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines[0] == "LAKE TAHOE (TVL)");
+    assert!(lines[1] == "================");
+    assert!(lines[2] == "Coordinates: 38°53'38.00\"N, 119°59'43.98\"W");
+    assert!(lines[3] == "Type: Airport");
+    assert!(lines[4] == "Use: Public");
+    assert!(lines[5] == "Elevation: 6269 ft");
+    assert!(lines[6] == "Fuel: 100LL,A");
+  }
+
+  #[test]
+  fn test_markdown() {
+    let text = format(&airport(), Format::Markdown);
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines[0] == "## LAKE TAHOE (TVL)");
+    assert!(lines[1].is_empty());
+    assert!(lines[2].starts_with("- **Coordinates**: 38°53'"));
+    assert!(lines[3] == "- **Type**: Airport");
+    assert!(lines[4] == "- **Use**: Public");
+  }
+
+  #[test]
+  fn test_position_report() {
+    let units = util::Units::Aviation;
+    let tvl = airport();
+
+    // About 3.5 NM southwest of the airport.
+    let coord = util::Coord {
+      x: -120.0405,
+      y: 38.8468,
+    };
+    let text = position_report(coord, [&tvl].into_iter(), "San Francisco SEC", units);
+    let parts: Vec<&str> = text.split(" — ").collect();
+    assert!(parts[0] == "38°50'48.48\"N 120°02'25.80\"W");
+    assert!(parts[1] == "3.5 NM / 203° from TVL (Lake Tahoe)");
+    assert!(parts[2] == "San Francisco SEC");
+
+    // The bearing is true if the magnetic variation isn't known.
+    let mut no_var = airport();
+    no_var.mag_var = None;
+    let text = position_report(coord, [&no_var].into_iter(), "", units);
+    assert!(text.ends_with(" — 3.5 NM / 217°T from TVL (Lake Tahoe)"));
+
+    // West variation adds to the true bearing.
+    no_var.mag_var = Some(-10.0);
+    let text = position_report(coord, [&no_var].into_iter(), "", units);
+    assert!(text.ends_with(" — 3.5 NM / 227° from TVL (Lake Tahoe)"));
+
+    // The nearest airport is used.
+    let mut near = airport();
+    near.id = "NEAR".into();
+    near.coord = util::Coord {
+      x: -120.0405,
+      y: 38.8568,
+    };
+    let text = position_report(coord, [&tvl, &near].into_iter(), "", units);
+    assert!(text.ends_with(" — 0.6 NM / 166° from NEAR (Lake Tahoe)"));
+
+    // Airports beyond 15 NM are left out.
+    let far = util::Coord {
+      x: -120.4,
+      y: 38.89389,
+    };
+    let text = position_report(far, [&tvl].into_iter(), "San Francisco SEC", units);
+    assert!(text == "38°53'38.00\"N 120°24'00.00\"W — San Francisco SEC");
+
+    // Due north is 360.
+    let (_, bearing) = distance_bearing(coord, near.coord);
+    assert!(whole_degrees(bearing) == 360);
+    assert!(whole_degrees(359.6) == 360);
+    assert!(whole_degrees(-9.0) == 351);
+    assert!(whole_degrees(0.6) == 1);
+  }
+
+  #[test]
+  fn test_wrap() {
+    let text = "word ".repeat(50);
+    let lines = wrap(&text, "  ");
+    assert!(lines.len() == 3);
+    assert!(lines.iter().all(|line| line.chars().count() <= WRAP_WIDTH));
+    assert!(lines[1].starts_with("  word"));
+    assert!(wrap("", "").is_empty());
+  }
+}
@@ -1,28 +1,163 @@
-use crate::{chart, config, error_dlg, find_dlg, nasr, select_dlg, select_menu, touch, util};
+use crate::{
+  airport_fmt, ask_dlg, back, chart, companion, compare_dlg, config, diag_dlg, error_dlg, export,
+  export_dlg, find_dlg, flight_log, folder_watch, goto_anim, graticule, info_dlg, log_dlg,
+  name_dlg, nasr, palette_dlg, remarks_dlg, select_dlg, select_menu, terrain, texture_budget,
+  toast, touch, util, view_history, wind_dlg, wx, wx_dlg,
+};
 use eframe::{egui, emath, epaint, glow};
 use egui::scroll_area;
-use std::{ffi::OsStr, path, rc};
+use std::{collections, ffi::OsStr, mem, path, rc, time};
 
 pub struct App {
   config: config::Storage,
   win_info: util::WinInfo,
+
+  /// When the window size or position last changed, if it hasn't been saved since.
+  win_info_time: Option<time::Instant>,
+
+  /// Window info that the window was restored from, until its position has been checked.
+  restored_win: Option<util::WinInfo>,
   default_theme: egui::Visuals,
   asset_path: Option<path::PathBuf>,
   file_dlg: Option<egui_file::FileDialog>,
+  terrain_dlg: Option<egui_file::FileDialog>,
+  catalog_dlg: Option<egui_file::FileDialog>,
+  export_file_dlg: Option<(export::Settings, egui_file::FileDialog)>,
   find_dlg: Option<find_dlg::FindDlg>,
+  search_term: Option<String>,
   error_dlg: Option<error_dlg::ErrorDlg>,
+  diag_dlg: Option<diag_dlg::DiagDlg>,
+  log_dlg: Option<log_dlg::LogDlg>,
+  compare_dlg: Option<compare_dlg::CompareDlg>,
+  bookmark_dlg: Option<(BookmarkEdit, name_dlg::NameDlg)>,
+  wx_dlg: Option<wx_dlg::WxDlg>,
+  wind_dlg: Option<wind_dlg::WindDlg>,
+  info_dlg: Option<info_dlg::InfoDlg>,
+  palette_dlg: Option<palette_dlg::PaletteDlg>,
+  remarks_dlg: Option<remarks_dlg::RemarksDlg>,
+  highlight: Option<Highlight>,
+  export_dlg: Option<export_dlg::ExportDlg>,
+  pdf_export: Option<export::PdfExport>,
+  region_select: RegionSelect,
+  wx_reader: Option<wx::WxReader>,
+  companion: Option<companion::CompanionFinder>,
+  companion_dlg: Option<(path::PathBuf, ask_dlg::AskDlg)>,
+  ask_companion: bool,
+
+  /// Downloaded charts, for finding one that covers a coordinate.
+  catalog: Option<chart::catalog::Catalog>,
+
+  /// Charts that cover a coordinate that isn't on the current chart.
+  covering_charts: Option<(util::Coord, Vec<chart::catalog::CatalogEntry>)>,
+
+  /// Coordinate to go to once the chart that was just opened is showing.
+  pending_goto: Option<util::Coord>,
   select_dlg: select_dlg::SelectDlg,
   select_menu: select_menu::SelectMenu,
   airport_reader: Option<nasr::AirportReader>,
+
+  /// NASR zip file that the airport reader is opening, and whether it's from the last session.
+  airport_open: Option<(path::PathBuf, bool)>,
+  airport_count: Option<usize>,
+
+  /// Airspace shape file folder within the NASR zip (`None` if the download doesn't include it).
+  airspace_path: Option<path::PathBuf>,
   chart: Chart,
+  terrain: Option<Terrain>,
+  terrain_opacity: f32,
+
+  /// Nearby airport search radius in nautical miles at 1.0x zoom.
+  nearby_radius: f32,
+
+  /// Allow the chart bounds to be edited (hidden setting).
+  edit_bounds: bool,
+  nearby_search: Option<NearbySearch>,
+
+  /// Generation of the last nearby request. Replies to earlier requests are dropped.
+  nearby_gen: u64,
+
+  /// Position report waiting for its nearby airports, with the generation of the request.
+  position_report: Option<(u64, util::Coord)>,
+
+  /// Generation of the last airport search. Name matches for earlier searches are dropped.
+  search_gen: u64,
+
+  /// Runway ticks for the area around the chart view.
+  runway_tick_area: Option<RunwayTickArea>,
+
+  /// Generation of the last runway tick request. Replies to earlier requests are dropped.
+  runway_tick_gen: u64,
   airport_infos: AirportInfos,
+  flight_log: flight_log::FlightLog,
+  bookmarks: Vec<util::Bookmark>,
   long_press: touch::LongPressTracker,
+  gesture: touch::GestureTracker,
+
+  /// Pixels per point from the last frame, used to detect display scale changes.
+  pixels_per_point: Option<f32>,
   top_panel_height: u32,
   side_panel_width: u32,
   night_mode: bool,
+
+  /// Show the chart with more contrast when not in night mode.
+  high_contrast: bool,
+
+  /// UI font scale, applied to `text_styles`.
+  font_scale: f32,
+
+  /// Text styles at a font scale of one.
+  text_styles: collections::BTreeMap<egui::TextStyle, epaint::FontId>,
+
+  /// Show the chart in the other palette while the preview key is held.
+  palette_preview: bool,
+  chart_filter: util::ChartFilter,
+  magnify_filter: util::ChartFilter,
+  units: util::Units,
+  scale_bar: bool,
+  animate_goto: bool,
+  animate_zoom: bool,
+
+  /// Act on a single search result without showing the selection dialog.
+  auto_open: bool,
+
+  /// Watch the asset folder for newly downloaded FAA zip files.
+  watch_folder: bool,
+  folder_watcher: Option<folder_watch::FolderWatcher>,
+
+  /// Notices, and offers to open the zip files found by the folder watcher.
+  toasts: toast::Toasts<path::PathBuf>,
+  zoom_step: f32,
+
+  /// Reverse the scroll-wheel and touchpad zoom direction.
+  invert_zoom: bool,
+
+  /// Scroll-wheel and touchpad zoom sensitivity (1.0 is normal).
+  wheel_zoom: f32,
+
+  /// Touch screen pinch zoom sensitivity (1.0 is normal).
+  pinch_zoom: f32,
+  goto_anim: Option<goto_anim::GotoAnim>,
+  graticule: bool,
+
+  /// Outline the chart bounds used for airport searches.
+  show_bounds: bool,
+
+  /// Show the orientation of each airport's longest runway when zoomed in.
+  runway_ticks: bool,
+
+  /// Show the whole chart in an inset with the current view marked.
+  overview: bool,
+
+  /// Memory used by the retained textures.
+  texture_budget: texture_budget::TextureBudget,
   side_panel: bool,
   ui_enabled: bool,
   include_nph: bool,
+  open_last: bool,
+  session: Option<Session>,
+
+  /// Zip file from the command line, opened after the first frame.
+  startup_path: Option<path::PathBuf>,
 }
 
 impl App {
@@ -31,14 +166,17 @@ impl App {
     theme: Option<egui::Visuals>,
     scale: Option<f32>,
     config: config::Storage,
+    startup_path: Option<path::PathBuf>,
   ) -> Self {
     let ctx = &cc.egui_ctx;
     if let Some(theme) = theme {
       ctx.set_visuals(theme);
     }
 
+    // Apply the scale as a zoom factor so that it stays relative to the native scale of whichever
+    // monitor the window is on.
     if let Some(scale) = scale {
-      ctx.set_pixels_per_point(scale);
+      ctx.set_zoom_factor(scale);
     }
 
     let mut style = (*ctx.style()).clone();
@@ -52,6 +190,11 @@ impl App {
       font_id.size *= 1.1;
     }
 
+    // Then apply the font size setting.
+    let text_styles = style.text_styles.clone();
+    let font_scale = config.get_font_scale().unwrap_or(1.0);
+    style.text_styles = scaled_text_styles(&text_styles, font_scale);
+
     let default_theme = style.visuals.clone();
     ctx.set_style(style);
 
@@ -61,32 +204,182 @@ impl App {
       ctx.set_visuals(dark_theme());
     }
 
+    let high_contrast = config.get_high_contrast().unwrap_or(false);
+    let chart_filter = config.get_chart_filter().unwrap_or_default();
+    let magnify_filter = config
+      .get_magnify_filter()
+      .unwrap_or(util::ChartFilter::Sharp);
+    let units = config.get_units().unwrap_or_default();
+    let scale_bar = config.get_scale_bar().unwrap_or(true);
+    let animate_goto = config.get_animate_goto().unwrap_or(true);
+    let animate_zoom = config.get_animate_zoom().unwrap_or(true);
+    let auto_open = config.get_auto_open().unwrap_or(true);
+    let watch_folder = config.get_watch_folder().unwrap_or(false);
+    let zoom_step = config.get_zoom_step().unwrap_or(ZOOM_STEP);
+    let invert_zoom = config.get_invert_zoom().unwrap_or(false);
+    let wheel_zoom = config.get_wheel_zoom().unwrap_or(1.0);
+    let pinch_zoom = config.get_pinch_zoom().unwrap_or(1.0);
+    let graticule = config.get_graticule().unwrap_or(false);
+    let show_bounds = config.get_show_bounds().unwrap_or(false);
+    let runway_ticks = config.get_runway_ticks().unwrap_or(false);
+    let overview = config.get_overview().unwrap_or(false);
+    let texture_budget = config.get_texture_budget();
+    let texture_budget = texture_budget.unwrap_or(texture_budget::TextureBudget::DEFAULT_CAP_MB);
+    let terrain_opacity = config.get_terrain_opacity().unwrap_or(0.4);
+    let nearby_radius = config.get_nearby_radius().unwrap_or(0.5);
+    let edit_bounds = config.get_edit_bounds().unwrap_or(false);
+    let bookmarks = config.get_bookmarks();
+    let select_size = config.get_dialog_size(select_dlg::SelectDlg::NAME);
+    let open_last = config.get_open_last().unwrap_or(false);
+    let session = open_last.then(|| Session {
+      chart: config.get_last_chart(),
+      nasr: config.get_last_nasr(),
+    });
+
     let asset_path = if let Some(asset_path) = config.get_asset_path() {
       Some(asset_path.into())
     } else {
       dirs::download_dir()
     };
 
-    Self {
+    // Catalog the charts in the asset folder unless another folder has been chosen.
+    let catalog_path = config.get_catalog_path().map(path::PathBuf::from);
+    let catalog_path = catalog_path.or_else(|| asset_path.clone());
+    let catalog = catalog_path.map(|path| chart::catalog::Catalog::scan(path, ctx));
+
+    let restored_win = config.store_win().then(|| config.get_win_info());
+    let mut app = Self {
       config,
       win_info: util::WinInfo::default(),
+      win_info_time: None,
+      restored_win,
       default_theme,
       asset_path,
       file_dlg: None,
+      terrain_dlg: None,
+      catalog_dlg: None,
+      export_file_dlg: None,
       find_dlg: None,
+      search_term: None,
       error_dlg: None,
-      select_dlg: select_dlg::SelectDlg::new(),
+      diag_dlg: None,
+      log_dlg: None,
+      compare_dlg: None,
+      bookmark_dlg: None,
+      wx_dlg: None,
+      wind_dlg: None,
+      info_dlg: None,
+      palette_dlg: None,
+      remarks_dlg: None,
+      highlight: None,
+      export_dlg: None,
+      pdf_export: None,
+      region_select: RegionSelect::None,
+      wx_reader: None,
+      companion: None,
+      companion_dlg: None,
+      ask_companion: true,
+      catalog,
+      covering_charts: None,
+      pending_goto: None,
+      select_dlg: select_dlg::SelectDlg::new(select_size),
       select_menu: select_menu::SelectMenu::default(),
       airport_reader: None,
+      airport_open: None,
+      airport_count: None,
+      airspace_path: None,
       chart: Chart::None,
+      terrain: None,
+      terrain_opacity,
+      nearby_radius,
+      edit_bounds,
+      nearby_search: None,
+      nearby_gen: 0,
+      position_report: None,
+      search_gen: 0,
+      runway_tick_area: None,
+      runway_tick_gen: 0,
       airport_infos: AirportInfos::None,
+      flight_log: flight_log::FlightLog::default(),
+      bookmarks,
       long_press: touch::LongPressTracker::new(ctx),
+      gesture: touch::GestureTracker::default(),
+      pixels_per_point: None,
       top_panel_height: 0,
       side_panel_width: 0,
       night_mode,
+      high_contrast,
+      font_scale,
+      text_styles,
+      palette_preview: false,
+      chart_filter,
+      magnify_filter,
+      units,
+      scale_bar,
+      animate_goto,
+      animate_zoom,
+      auto_open,
+      watch_folder,
+      folder_watcher: None,
+      toasts: toast::Toasts::default(),
+      zoom_step,
+      invert_zoom,
+      wheel_zoom,
+      pinch_zoom,
+      goto_anim: None,
+      graticule,
+      show_bounds,
+      runway_ticks,
+      overview,
+      texture_budget: texture_budget::TextureBudget::new(texture_budget),
       side_panel: true,
       ui_enabled: true,
       include_nph: false,
+      open_last,
+      session,
+      startup_path,
+    };
+
+    app.update_folder_watcher(ctx);
+    app
+  }
+
+  /// Start, restart or stop the folder watcher to match the settings.
+  /// - `ctx`: egui context
+  fn update_folder_watcher(&mut self, ctx: &egui::Context) {
+    let folder = self.asset_path.as_ref().filter(|_| self.watch_folder);
+    let Some(folder) = folder else {
+      self.folder_watcher = None;
+      return;
+    };
+
+    if let Some(folder_watcher) = &self.folder_watcher {
+      if folder_watcher.folder() == folder {
+        return;
+      }
+    }
+
+    // Zip files seen in this folder during an earlier session. If the folder hasn't been watched
+    // before then the zip files that are already there are ignored.
+    let seen = self.config.get_seen_zips(folder);
+    self.folder_watcher = Some(folder_watch::FolderWatcher::new(folder, seen, ctx));
+  }
+
+  /// Process the folder watcher replies.
+  fn process_folder_watch(&mut self) {
+    if let Some(folder_watcher) = &self.folder_watcher {
+      for reply in folder_watcher.get_replies() {
+        match reply {
+          folder_watch::Reply::Found(path, kind) => {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let text = format!("New {} detected: {name} — Open?", kind.text());
+            self.toasts.push(text, path);
+          }
+          folder_watch::Reply::Seen(zips) => {
+            self.config.set_seen_zips(folder_watcher.folder(), &zips);
+          }
+        }
+      }
     }
   }
 
@@ -114,242 +407,1597 @@ impl App {
     self.file_dlg = Some(file_dlg);
   }
 
-  fn open_chart_data(&mut self, ctx: &egui::Context, path: &path::Path, file: &path::Path) {
-    self.chart = Chart::None;
+  fn select_terrain_file(&mut self) {
+    let filter = Box::new(|path: &path::Path| {
+      let ext = path.extension().and_then(|ext| ext.to_str());
+      ext.is_some_and(|ext| ext.eq_ignore_ascii_case("tif") || ext.eq_ignore_ascii_case("tiff"))
+    });
 
-    // Concatenate the VSI prefix and the file path.
-    let path = ["/vsizip/", path.to_str().unwrap()].concat();
-    let path = path::Path::new(path.as_str()).join(file);
-
-    match chart::RasterReader::new(path, ctx) {
-      Ok(chart_reader) => {
-        let proj4 = chart_reader.transform().get_proj4();
-        let bounds = chart_reader.transform().bounds().clone();
-        self.chart = Chart::Ready(Box::new(ChartInfo {
-          name: util::stem_string(file).unwrap(),
-          reader: rc::Rc::new(chart_reader),
-          texture: None,
-          disp_rect: util::Rect::default(),
-          scroll: Some(emath::pos2(0.0, 0.0)),
-          zoom: 1.0,
-        }));
+    let mut file_dlg = egui_file::FileDialog::open_file(self.asset_path.clone())
+      .title("Open Terrain File")
+      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0])
+      .default_size([525.0, 320.0])
+      .show_files_filter(filter)
+      .show_new_folder(false)
+      .show_rename(false)
+      .resizable(false);
+    file_dlg.open();
+    self.terrain_dlg = Some(file_dlg);
+  }
 
-        if let Some(nasr_reader) = &mut self.airport_reader {
-          nasr_reader.set_spatial_ref(proj4, bounds);
-        }
+  fn select_catalog_folder(&mut self) {
+    let folder = self
+      .catalog
+      .as_ref()
+      .map(|catalog| catalog.folder().to_owned());
+    let mut file_dlg = egui_file::FileDialog::select_folder(folder.or(self.asset_path.clone()))
+      .title("Chart Folder")
+      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0])
+      .default_size([525.0, 320.0])
+      .show_new_folder(false)
+      .show_rename(false)
+      .resizable(false);
+    file_dlg.open();
+    self.catalog_dlg = Some(file_dlg);
+  }
 
-        // If this is a heliport chart then include non-public heliports in searches.
-        self.include_nph = util::stem_str(file).unwrap().ends_with(" HEL");
-      }
-      Err(err) => {
-        self.error_dlg = Some(error_dlg::ErrorDlg::open(err));
-      }
+  /// Offer to open a downloaded chart that covers a coordinate that isn't on the current chart.
+  /// Shows an error if there isn't one.
+  /// - `coord`: NAD83 coordinate
+  /// - `name`: text for the error message
+  fn offer_covering_chart(&mut self, coord: util::Coord, name: &str) {
+    let mut entries = match &self.catalog {
+      Some(catalog) => catalog.find_covering(coord),
+      None => Vec::new(),
+    };
+
+    // Don't offer the chart that's already open.
+    if let Some(chart) = self.get_chart() {
+      let paths = chart.reader.paths();
+      entries.retain(|entry| {
+        let vsi_path = util::vsi_path("/vsizip/", &entry.path);
+        !vsi_path.is_ok_and(|vsi_path| paths.contains(&vsi_path.join(&entry.file)))
+      });
     }
+
+    if entries.is_empty() {
+      let text = format!("{name} is not on this chart");
+      self.error_dlg = Some(error_dlg::ErrorDlg::open(text.into()));
+      return;
+    }
+    self.covering_charts = Some((coord, entries));
   }
 
-  fn open_airport_data(&mut self, ctx: &egui::Context, path: &path::Path, zip: &path::Path) {
-    // Concatenate the VSI prefix and the file path.
-    let path = ["/vsizip//vsizip/", path.to_str().unwrap()].concat();
-    let path = path::Path::new(path.as_str());
-    let path = path.join(zip).join("APT_BASE.csv");
+  fn open_terrain_file(&mut self, ctx: &egui::Context, path: &path::Path) {
+    let Some(chart) = self.get_chart() else {
+      return;
+    };
+
+    match terrain::TerrainReader::new(path, chart.reader.transform(), ctx) {
+      Ok(reader) => {
+        self.terrain = Some(Terrain {
+          reader,
+          texture: None,
+        });
 
-    self.airport_reader = match nasr::AirportReader::new(path, ctx) {
-      Ok(nasr_reader) => {
-        if let Some(chart_reader) = self.get_chart_reader() {
-          let proj4 = chart_reader.transform().get_proj4();
-          let bounds = chart_reader.transform().bounds().clone();
-          nasr_reader.set_spatial_ref(proj4, bounds);
+        // Read the terrain for the part of the chart that's showing.
+        if let Some((part, _)) = self.get_chart_texture() {
+          self.request_image(part.rect, part.zoom.into());
         }
-        Some(nasr_reader)
       }
       Err(err) => {
+        log::error!("{err}");
         self.error_dlg = Some(error_dlg::ErrorDlg::open(err));
-        None
       }
     }
   }
 
-  fn request_image(&mut self, rect: util::Rect, zoom: f32) {
-    if let Some(reader) = self.get_chart_reader() {
-      let dark = self.night_mode;
-      let part = chart::ImagePart::new(rect, zoom, dark);
-      reader.read_image(part);
-    }
+  fn open_export_dlg(&mut self) {
+    let Some(chart) = self.get_chart() else {
+      return;
+    };
+
+    // The display rectangle is in zoomed pixels.
+    let view = chart.disp_rect.scaled(chart.zoom.recip());
+    let meters_per_px = chart.reader.transform().meters_per_pixel();
+    self.export_dlg = Some(export_dlg::ExportDlg::open(view, meters_per_px));
   }
 
-  fn get_chart(&self) -> Option<&ChartInfo> {
-    if let Chart::Ready(chart) = &self.chart {
-      return Some(chart);
-    }
-    None
+  fn select_export_file(&mut self, settings: export::Settings) {
+    let Some(chart) = self.get_chart() else {
+      return;
+    };
+
+    let name = format!("{}.pdf", chart.name);
+    let mut file_dlg = egui_file::FileDialog::save_file(self.asset_path.clone())
+      .title("Export PDF")
+      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0])
+      .default_size([525.0, 320.0])
+      .default_filename(name)
+      .show_rename(false)
+      .resizable(false);
+    file_dlg.open();
+    self.export_file_dlg = Some((settings, file_dlg));
   }
 
-  fn get_chart_reader(&self) -> Option<rc::Rc<chart::RasterReader>> {
-    if let Chart::Ready(chart) = &self.chart {
-      return Some(chart.reader.clone());
+  fn start_export(&mut self, ctx: &egui::Context, settings: export::Settings, path: path::PathBuf) {
+    let Some(chart) = self.get_chart() else {
+      return;
+    };
+
+    let mut path = path;
+    if !path
+      .extension()
+      .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+    {
+      path.set_extension("pdf");
     }
-    None
-  }
 
-  fn get_chart_zoom(&self) -> Option<f32> {
-    if let Chart::Ready(chart) = &self.chart {
-      return Some(chart.zoom);
+    let chart_paths = chart.reader.paths().to_vec();
+    let name = chart.name.clone();
+    self.pdf_export = Some(export::PdfExport::start(
+      chart_paths,
+      name,
+      settings,
+      path,
+      ctx,
+    ));
+    if let Some(export_dlg) = &mut self.export_dlg {
+      export_dlg.set_progress(Some(0.0));
     }
-    None
   }
 
-  fn set_chart_zoom(&mut self, val: f32) {
-    if let Chart::Ready(chart) = &mut self.chart {
-      if chart.zoom != val {
-        chart.zoom = val;
-        self.reset_airport_menu();
+  /// Let the user drag out the export region on the chart.
+  /// - `ui`: chart UI
+  /// - `rect`: chart view rectangle
+  /// - `offset`: chart scroll offset
+  /// - `zoom`: chart zoom
+  fn select_region(&mut self, ui: &egui::Ui, rect: emath::Rect, offset: emath::Vec2, zoom: f32) {
+    let Some(reader) = self.get_chart_reader() else {
+      return;
+    };
+
+    let size = reader.transform().px_size();
+    let bounds =
+      emath::Rect::from_min_size(emath::Pos2::ZERO, emath::vec2(size.w as f32, size.h as f32));
+    let to_chart = |pos: emath::Pos2| ((pos - rect.min + offset) / zoom).to_pos2();
+    let to_view = |pos: emath::Pos2| rect.min + pos.to_vec2() * zoom - offset;
+    let response = ui.interact(rect, ui.id().with("region_select"), egui::Sense::drag());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+
+    let region_use = match self.region_select {
+      RegionSelect::None => return,
+      RegionSelect::Waiting(region_use) => {
+        if response.drag_started() {
+          // Start from where the pointer was pressed, not where the drag was detected.
+          if let Some(pos) = ui.input(|state| state.pointer.press_origin()) {
+            self.region_select = RegionSelect::Dragging(region_use, to_chart(pos));
+          }
+        }
+
+        // Outline the current bounds override.
+        let chart_bounds = self
+          .get_chart()
+          .and_then(|chart| self.config.get_chart_bounds(&chart.name))
+          .filter(|_| region_use == RegionUse::Bounds);
+        if let Some(rect) = chart_bounds {
+          let rect: emath::Rect = rect.into();
+          let view = emath::Rect::from_min_max(to_view(rect.min), to_view(rect.max));
+          painter.rect_stroke(view, 0.0, (2.0, visuals.selection.bg_fill));
+        }
+        region_use
       }
-    }
+      RegionSelect::Dragging(region_use, start) => {
+        let end = ui
+          .input(|state| state.pointer.latest_pos())
+          .map_or(start, to_chart);
+        let region = emath::Rect::from_two_pos(start, end).intersect(bounds);
+        if response.drag_stopped() {
+          self.region_select = RegionSelect::None;
+          if region.width() >= 1.0 && region.height() >= 1.0 {
+            let rect = util::Rect {
+              pos: util::Pos {
+                x: region.min.x as i32,
+                y: region.min.y as i32,
+              },
+              size: util::Size {
+                w: region.width() as u32,
+                h: region.height() as u32,
+              },
+            };
+
+            match region_use {
+              RegionUse::Export => {
+                if let Some(export_dlg) = &mut self.export_dlg {
+                  export_dlg.set_region(rect);
+                }
+              }
+              RegionUse::Bounds => self.set_chart_bounds(Some(rect)),
+            }
+          }
+          return;
+        }
+
+        let view = emath::Rect::from_min_max(to_view(region.min), to_view(region.max));
+        let color = visuals.selection.bg_fill;
+        painter.rect(view, 0.0, color.gamma_multiply(0.25), (2.0, color));
+        region_use
+      }
+    };
+
+    // Show a hint at the top of the chart.
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let text = match region_use {
+      RegionUse::Export => "Drag to select the region to export (Esc to cancel)",
+      RegionUse::Bounds => "Drag to select the area searched for airports (Esc to cancel)",
+    };
+    let galley = painter.layout_no_wrap(text.into(), font_id, visuals.strong_text_color());
+    let pos = emath::pos2(rect.center().x - galley.size().x * 0.5, rect.top() + 12.0);
+    let back = emath::Rect::from_min_size(pos, galley.size()).expand(4.0);
+    painter.rect_filled(back, 3.0, visuals.extreme_bg_color.gamma_multiply(0.8));
+    painter.galley(pos, galley, visuals.strong_text_color());
   }
 
-  fn get_chart_texture(&self) -> Option<&(chart::ImagePart, egui::TextureHandle)> {
-    if let Chart::Ready(chart) = &self.chart {
-      return chart.texture.as_ref();
+  fn open_zip_file(&mut self, ctx: &egui::Context, path: path::PathBuf) {
+    match util::get_zip_info(&path) {
+      Ok(info) => match info {
+        util::ZipInfo::Chart(files) => {
+          if files.len() > 1 {
+            let combine = can_combine(&path, &files);
+            self.chart = Chart::Load(path, files, combine);
+
+            // Remove the chart spatial reference from the airport reader.
+            if let Some(airport_reader) = &self.airport_reader {
+              airport_reader.clear_spatial_ref();
+            }
+          } else {
+            self.open_chart_data(ctx, &path, &files);
+          }
+        }
+        util::ZipInfo::Aero { csv, shp } => {
+          self.open_airport_data(ctx, &path, &csv, shp.as_deref());
+        }
+      },
+      Err(err) => {
+        log::error!("{err}");
+        self.error_dlg = Some(error_dlg::ErrorDlg::open(err));
+      }
     }
-    None
   }
 
-  fn set_chart_image(
-    &mut self,
-    ctx: &egui::Context,
-    part: chart::ImagePart,
-    image: epaint::ColorImage,
-  ) {
-    if let Chart::Ready(chart) = &mut self.chart {
-      let texture = ctx.load_texture("chart_image", image, Default::default());
-      chart.texture = Some((part, texture));
+  /// Look for NASR data to go with a chart or a chart to go with NASR data.
+  fn find_companion(&mut self, ctx: &egui::Context, zip: &path::Path, aero: bool) {
+    self.companion = None;
+    self.companion_dlg = None;
+    if self.ask_companion {
+      self.companion = companion::CompanionFinder::new(zip, aero, ctx);
     }
   }
 
-  fn set_chart_disp_rect(&mut self, rect: util::Rect) {
-    #[cfg(feature = "mobile")]
-    let mut offset = emath::Pos2::ZERO;
+  /// Open a chart from a zip file.
+  /// - `path`: path to the chart zip file
+  /// - `files`: chart file within the zip file, or two adjacent chart files to combine
+  fn open_chart_data(&mut self, ctx: &egui::Context, path: &path::Path, files: &[path::PathBuf]) {
+    self.chart = Chart::None;
 
-    if let Chart::Ready(chart) = &mut self.chart {
-      if chart.disp_rect != rect {
-        #[cfg(feature = "mobile")]
-        if chart.disp_rect.size.h != rect.size.h {
-          // Recenter on size change to account for the on-screen keyboard or screen rotation.
-          offset.x = rect.pos.x as f32 + (chart.disp_rect.size.w as f32 - rect.size.w as f32) * 0.5;
-          offset.y = rect.pos.y as f32 + (chart.disp_rect.size.h as f32 - rect.size.h as f32) * 0.5;
-        }
+    // The terrain is registered to the chart.
+    self.terrain = None;
+    self.highlight = None;
+    self.goto_anim = None;
+    self.palette_dlg = None;
 
-        chart.disp_rect = rect;
-        self.reset_airport_menu();
+    // Concatenate the VSI prefix and the file paths.
+    let vsi_path = match util::vsi_path("/vsizip/", path) {
+      Ok(vsi_path) => vsi_path,
+      Err(err) => {
+        log::error!("{err}");
+        self.error_dlg = Some(error_dlg::ErrorDlg::open(err));
+        return;
+      }
+    };
+
+    let reader = match files {
+      [file] => chart::RasterReader::new(vsi_path.join(file), ctx),
+      [a, b] => chart::RasterReader::new_combined([vsi_path.join(a), vsi_path.join(b)], ctx),
+      _ => {
+        let err = "Unable to open chart: expected one or two files";
+        log::error!("{err}");
+        self.error_dlg = Some(error_dlg::ErrorDlg::open(err.into()));
+        return;
       }
+    };
+
+    // The chart is shown once the reader replies that it's open.
+    self.chart = Chart::Opening(Box::new(ChartOpen {
+      path: path.to_owned(),
+      files: files.to_vec(),
+      reader,
+      session: false,
+    }));
+  }
+
+  /// Finish opening the chart once the reader has replied.
+  fn check_chart_open(&mut self, ctx: &egui::Context) {
+    let Chart::Opening(open) = &self.chart else {
+      return;
+    };
+
+    let replies = open.reader.get_replies();
+    let result = replies.into_iter().find_map(|reply| match reply {
+      chart::RasterReply::Opened => Some(Ok(())),
+      chart::RasterReply::OpenError(err) => Some(Err(err)),
+      _ => None,
+    });
+
+    let Some(result) = result else {
+      return;
+    };
+
+    let Chart::Opening(open) = mem::replace(&mut self.chart, Chart::None) else {
+      unreachable!();
+    };
+
+    let ChartOpen {
+      path,
+      files,
+      reader,
+      session,
+    } = *open;
+
+    if let Err(err) = result {
+      self.report_open_error(ctx, err, session.then_some("chart"));
+      return;
+    }
+
+    self.chart = Chart::Ready(Box::new(ChartInfo {
+      name: files
+        .iter()
+        .map(|file| util::stem_str(file).unwrap())
+        .collect::<Vec<_>>()
+        .join(" + "),
+      reader: rc::Rc::new(reader),
+      texture: None,
+      alt_texture: None,
+      alt_request: None,
+      disp_rect: util::Rect::default(),
+      disp_zoom: 1.0,
+      scroll: Some(emath::pos2(0.0, 0.0)),
+      scroll_remainder: emath::Vec2::ZERO,
+      panel_size: emath::Vec2::ZERO,
+      recenter: false,
+      zoom_origin: None,
+      pinch: None,
+      zoom: 1.0,
+      request_time: None,
+      coast_request: 0.0,
+      thumbnail: None,
+      thumbnail_request: None,
+      history: view_history::ViewHistory::default(),
+      failures: collections::HashMap::new(),
+      errors: collections::HashSet::new(),
+    }));
+
+    if let (Some(nasr_reader), Some((proj4, bounds))) =
+      (&self.airport_reader, self.get_spatial_ref())
+    {
+      nasr_reader.set_spatial_ref(proj4, bounds);
     }
 
-    #[cfg(feature = "mobile")]
-    if offset != emath::Pos2::ZERO {
-      self.set_chart_scroll(offset);
+    self.config.set_last_chart(&path, &files);
+
+    // If this is a heliport chart then include non-public heliports in searches.
+    self.include_nph = files
+      .iter()
+      .any(|file| util::stem_str(file).unwrap().ends_with(" HEL"));
+
+    if self.airport_reader.is_none() {
+      self.find_companion(ctx, &path, true);
     }
   }
 
-  fn take_chart_scroll(&mut self) -> Option<emath::Pos2> {
-    if let Chart::Ready(chart) = &mut self.chart {
-      return chart.scroll.take();
+  /// Report a chart or NASR data file that couldn't be opened.
+  /// - `err`: error from the reader
+  /// - `session`: what was being reopened from the last session, which is only reported with a
+  ///   notice
+  fn report_open_error(&mut self, ctx: &egui::Context, err: util::Error, session: Option<&str>) {
+    log::error!("{err}");
+    match session {
+      Some(what) => self.set_notice(ctx, format!("Unable to reopen the last {what}")),
+      None => self.error_dlg = Some(error_dlg::ErrorDlg::open(err)),
     }
-    None
   }
 
-  fn set_chart_scroll(&mut self, pos: emath::Pos2) {
-    if let Chart::Ready(chart) = &mut self.chart {
-      chart.scroll = Some(pos.floor());
+  /// Open the NASR data from a zip file.
+  /// - `path`: path to the NASR zip file
+  /// - `csv`: location of the CSV files within the NASR zip file
+  /// - `shp`: airspace shape file folder within the NASR zip file, if the download includes it
+  fn open_airport_data(
+    &mut self,
+    ctx: &egui::Context,
+    path: &path::Path,
+    csv: &util::CsvPath,
+    shp: Option<&path::Path>,
+  ) {
+    // Concatenate the VSI prefix and the file path.
+    let vsi_path = match csv.vsi_file(path, "APT_BASE.csv") {
+      Ok(vsi_path) => vsi_path,
+      Err(err) => {
+        log::error!("{err}");
+        self.error_dlg = Some(error_dlg::ErrorDlg::open(err));
+        self.airport_reader = None;
+        self.airport_open = None;
+        self.airspace_path = None;
+        return;
+      }
+    };
+
+    // Release the old airport data before the new reader opens its own.
+    self.close_airport_reader();
+
+    // The reader shows as indexing until it replies that the data is open. Requests made in the
+    // meantime are handled once it is.
+    let nasr_reader = nasr::AirportReader::new(vsi_path, ctx);
+    if let Some((proj4, bounds)) = self.get_spatial_ref() {
+      nasr_reader.set_spatial_ref(proj4, bounds);
     }
+
+    self.airport_reader = Some(nasr_reader);
+    self.airport_open = Some((path.to_owned(), false));
+    self.airport_count = None;
+    self.airspace_path = shp.map(path::Path::to_owned);
   }
 
-  fn reset_airport_menu(&mut self) -> bool {
-    if matches!(self.airport_infos, AirportInfos::Menu(_, _)) {
-      self.airport_infos = AirportInfos::None;
-      return true;
+  /// Stop the airport reader's thread, which cancels any indexing in progress and releases the
+  /// airport data without waiting for it.
+  fn close_airport_reader(&mut self) {
+    if let Some(nasr_reader) = self.airport_reader.take() {
+      nasr_reader.shutdown(nasr::AirportReader::SHUTDOWN_TIMEOUT);
     }
-    false
   }
 
-  /// Pan the map to a NAD83 coordinate.
-  fn goto_coord(&mut self, coord: util::Coord) {
-    if let Some(chart) = self.get_chart() {
-      if let Ok(px) = chart.reader.transform().nad83_to_px(coord) {
-        let chart_size = chart.reader.transform().px_size();
-        if chart_size.contains(px) {
-          let x = px.x as f32 - 0.5 * chart.disp_rect.size.w as f32;
-          let y = px.y as f32 - 0.5 * chart.disp_rect.size.h as f32;
-          self.set_chart_zoom(1.0);
-          self.set_chart_scroll(emath::pos2(x, y));
+  /// Reopen the chart and NASR data from the last session. Files that are missing or can't be
+  /// opened are reported with a notice rather than an error dialog.
+  fn open_session(&mut self, ctx: &egui::Context, session: Session) {
+    let mut failed = Vec::new();
+    if let Some((path, files)) = session.chart {
+      if let Ok(util::ZipInfo::Chart(contents)) = util::get_zip_info(&path) {
+        if files.iter().all(|file| contents.contains(file)) {
+          self.open_chart_data(ctx, &path, &files);
         }
       }
-    }
-  }
 
-  fn toggle_side_panel(&mut self, visible: bool) {
-    if self.side_panel == visible {
-      return;
+      match &mut self.chart {
+        Chart::Opening(open) => open.session = true,
+        _ => failed.push("chart"),
+      }
     }
 
-    self.side_panel = visible;
-    if let Some(chart) = self.get_chart() {
-      // Scroll the chart to account for the left panel.
-      let pos = chart.disp_rect.pos;
-      let offset = self.side_panel_width as f32 * 0.5 + 1.0;
-      let offset = if !self.side_panel {
-        pos.x as f32 - offset
+    if let Some(path) = session.nasr {
+      if let Ok(util::ZipInfo::Aero { csv, shp }) = util::get_zip_info(&path) {
+        self.open_airport_data(ctx, &path, &csv, shp.as_deref());
+      }
+
+      if let Some((_, session)) = &mut self.airport_open {
+        *session = true;
+
+        // Both halves are open so there's nothing for the companion finder to offer.
+        self.companion = None;
+        self.companion_dlg = None;
       } else {
-        pos.x as f32 + offset
-      };
+        failed.push("NASR data");
+      }
+    }
 
-      self.set_chart_scroll(emath::pos2(offset, pos.y as f32));
+    if !failed.is_empty() {
+      self.error_dlg = None;
+      let text = format!("Unable to reopen the last {}", failed.join(" or "));
+      log::warn!("{text}");
+      self.set_notice(ctx, text);
     }
   }
 
-  fn get_chart_replies(&self) -> Vec<chart::RasterReply> {
-    if let Some(chart_reader) = &self.get_chart_reader() {
-      return chart_reader.get_replies();
+  /// Let the user know when few airports fall within the chart bounds, since searches will then
+  /// come up empty.
+  /// - `count`: number of airports in the spatial index
+  fn check_airport_count(&mut self, ctx: &egui::Context, count: usize) {
+    let prev = self.airport_count.replace(count);
+    if count == 0 || prev.is_some_and(|prev| count * 10 < prev) {
+      let text = format!("Only {count} airports fall within this chart's bounds");
+      log::warn!("{text}");
+      self.set_notice(ctx, text);
     }
-    Vec::new()
   }
 
-  fn get_airport_replies(&self) -> Vec<nasr::AirportReply> {
-    if let Some(airport_reader) = &self.airport_reader {
-      return airport_reader.get_replies();
+  fn set_notice(&mut self, ctx: &egui::Context, text: String) {
+    self.toasts.message(ctx, text);
+  }
+
+  /// Palette for the chart image.
+  fn chart_palette(&self) -> chart::Palette {
+    if self.night_mode {
+      chart::Palette::Dark
+    } else if self.high_contrast {
+      chart::Palette::Contrast
+    } else {
+      chart::Palette::Light
     }
-    Vec::new()
   }
 
-  fn set_night_mode(&mut self, ctx: &egui::Context, night_mode: bool) {
-    if self.night_mode == night_mode {
-      return;
+  /// Palette for the palette preview (night mode if it's off, otherwise the day palette).
+  fn preview_palette(&self) -> chart::Palette {
+    if !self.night_mode {
+      chart::Palette::Dark
+    } else if self.high_contrast {
+      chart::Palette::Contrast
+    } else {
+      chart::Palette::Light
     }
+  }
 
-    self.night_mode = night_mode;
+  /// Request the current image in the other palette for the palette preview.
+  fn request_alt_image(&mut self) {
+    let palette = self.preview_palette();
+    if let Chart::Ready(chart) = &mut self.chart {
+      let Some((part, _)) = &chart.texture else {
+        return;
+      };
+
+      if chart.get_alt_texture().is_none() {
+        let part = chart::ImagePart::new(part.rect, part.zoom.into(), palette);
+        if chart.alt_request.as_ref() != Some(&part) {
+          chart.reader.read_alt_image(part.clone());
+          chart.alt_request = Some(part);
+        }
+      }
+    }
+  }
+
+  fn request_image(&mut self, rect: util::Rect, zoom: f32) {
+    let palette = self.chart_palette();
+    if let Chart::Ready(chart) = &mut self.chart {
+      let part = chart::ImagePart::new(rect, zoom, palette);
+
+      // Don't keep retrying parts of the chart that can't be read.
+      if !chart.is_bad_part(&part) {
+        // Terrain reads use the same part so that the two images line up.
+        if let Some(terrain) = &self.terrain {
+          terrain.reader.read_image(part.clone());
+        }
+        chart.reader.read_image(part);
+        chart.request_time.get_or_insert_with(time::Instant::now);
+      }
+    }
+  }
+
+  fn set_chart_error(&mut self, part: chart::ImagePart, err: util::Error) {
+    if let Chart::Ready(chart) = &mut self.chart {
+      let text = format!(
+        "Unable to read chart image at {}, {} ({} x {})\n{err}",
+        part.rect.pos.x, part.rect.pos.y, part.rect.size.w, part.rect.size.h
+      );
+      log::error!("{text}");
+
+      *chart.failures.entry(part).or_default() += 1;
+      chart.request_time = None;
+
+      // Only show each distinct error once.
+      if chart.errors.insert(err.into_owned()) {
+        self.error_dlg = Some(error_dlg::ErrorDlg::open(text.into()));
+      }
+    }
+  }
+
+  fn get_chart(&self) -> Option<&ChartInfo> {
+    if let Chart::Ready(chart) = &self.chart {
+      return Some(chart);
+    }
+    None
+  }
+
+  /// Get the chart's spatial reference and the bounds used for airport searches.
+  fn get_spatial_ref(&self) -> Option<(String, util::Bounds)> {
+    let chart = self.get_chart()?;
+    let bounds = self.get_search_bounds()?;
+    Some((chart.reader.transform().get_proj4(), bounds))
+  }
+
+  /// Bounds of the current chart used for airport searches, in chart coordinates.
+  fn get_search_bounds(&self) -> Option<util::Bounds> {
+    let chart = self.get_chart()?;
+    let transform = chart.reader.transform();
+    let bounds = match self.config.get_chart_bounds(&chart.name) {
+      Some(rect) => {
+        // Pixel rows increase to the south.
+        let (x, y) = (rect.pos.x as f64, rect.pos.y as f64);
+        let (w, h) = (rect.size.w as f64, rect.size.h as f64);
+        util::Bounds {
+          min: transform.px_to_chart(util::Coord { x, y: y + h }),
+          max: transform.px_to_chart(util::Coord { x: x + w, y }),
+        }
+      }
+      None => transform.bounds().clone(),
+    };
+    Some(bounds)
+  }
+
+  /// Override the bounds used for airport searches on the current chart and re-index the
+  /// airports.
+  /// - `rect`: bounds in chart pixels or `None` to use the whole chart
+  fn set_chart_bounds(&mut self, rect: Option<util::Rect>) {
+    let Some(chart) = self.get_chart() else {
+      return;
+    };
+
+    let name = chart.name.clone();
+    match rect {
+      Some(rect) => self.config.set_chart_bounds(&name, rect),
+      None => self.config.remove_chart_bounds(&name),
+    }
+
+    self.airport_count = None;
+    if let (Some(nasr_reader), Some((proj4, bounds))) =
+      (&self.airport_reader, self.get_spatial_ref())
+    {
+      nasr_reader.set_spatial_ref(proj4, bounds);
+    }
+  }
+
+  fn get_chart_reader(&self) -> Option<rc::Rc<chart::RasterReader>> {
+    if let Chart::Ready(chart) = &self.chart {
+      return Some(chart.reader.clone());
+    }
+    None
+  }
+
+  fn get_chart_zoom(&self) -> Option<f32> {
+    if let Chart::Ready(chart) = &self.chart {
+      return Some(chart.zoom);
+    }
+    None
+  }
+
+  fn set_chart_zoom(&mut self, val: f32) {
+    if let Chart::Ready(chart) = &mut self.chart {
+      // The airport menu stays open; its search is refreshed for the new zoom.
+      chart.zoom = val;
+    }
+  }
+
+  /// Zoom the chart so that it fills the view and center it.
+  fn fit_chart(&mut self) {
+    self.goto_anim = None;
+    self.push_view_history();
+    if let Chart::Ready(chart) = &self.chart {
+      let zoom = chart.get_min_zoom();
+      let chart_size: emath::Vec2 = chart.reader.transform().px_size().into();
+      let disp_size: emath::Vec2 = chart.disp_rect.size.into();
+      let pos = ((chart_size * zoom - disp_size) * 0.5).max(emath::Vec2::ZERO);
+      self.set_chart_zoom(zoom);
+      self.set_chart_scroll(pos.to_pos2());
+    }
+  }
+
+  fn set_terrain_image(
+    &mut self,
+    ctx: &egui::Context,
+    part: chart::ImagePart,
+    image: epaint::ColorImage,
+  ) {
+    let options = self.texture_options();
+    if let Some(terrain) = &mut self.terrain {
+      store_texture(
+        ctx,
+        &mut terrain.texture,
+        "terrain_image",
+        part,
+        image,
+        options,
+      );
+      self.check_texture_budget();
+    }
+  }
+
+  fn get_terrain_replies(&self) -> Vec<terrain::TerrainReply> {
+    if let Some(terrain) = &self.terrain {
+      return terrain.reader.get_replies();
+    }
+    Vec::new()
+  }
+
+  fn get_export_replies(&self) -> Vec<export::ExportReply> {
+    if let Some(pdf_export) = &self.pdf_export {
+      return pdf_export.get_replies();
+    }
+    Vec::new()
+  }
+
+  fn get_chart_texture(&self) -> Option<&(chart::ImagePart, egui::TextureHandle)> {
+    if let Chart::Ready(chart) = &self.chart {
+      return chart.texture.as_ref();
+    }
+    None
+  }
+
+  fn set_chart_image(
+    &mut self,
+    ctx: &egui::Context,
+    part: chart::ImagePart,
+    image: epaint::ColorImage,
+  ) {
+    let options = self.texture_options();
+    let palette = self.chart_palette();
+    let preview = self.preview_palette();
+    if let Chart::Ready(chart) = &mut self.chart {
+      chart.failures.remove(&part);
+
+      // Images in the other palette are for the palette preview. Anything else is from before a
+      // palette change.
+      if part.palette != palette {
+        if part.palette == preview {
+          store_texture(
+            ctx,
+            &mut chart.alt_texture,
+            "chart_alt_image",
+            part,
+            image,
+            options,
+          );
+        }
+        return;
+      }
+
+      chart.request_time = None;
+      store_texture(ctx, &mut chart.texture, "chart_image", part, image, options);
+
+      // Drop the palette preview image once it no longer matches.
+      if chart.get_alt_texture().is_none() {
+        chart.alt_texture = None;
+      }
+    }
+    self.check_texture_budget();
+  }
+
+  /// Record the retained texture sizes and drop the least important textures if they use more
+  /// memory than the budget allows. Dropped textures are requested again when they're next needed.
+  fn check_texture_budget(&mut self) {
+    use texture_budget::TextureSlot;
+    let size = |slot: &Option<(chart::ImagePart, egui::TextureHandle)>| {
+      slot.as_ref().map(|(_, texture)| texture.size())
+    };
+
+    let budget = &mut self.texture_budget;
+    let terrain = self.terrain.as_ref().map(|terrain| &terrain.texture);
+    budget.record(TextureSlot::Terrain, terrain.and_then(size));
+
+    let Chart::Ready(chart) = &mut self.chart else {
+      budget.record(TextureSlot::AltPalette, None);
+      budget.record(TextureSlot::Thumbnail, None);
+      budget.record(TextureSlot::Chart, None);
+      return;
+    };
+
+    budget.record(TextureSlot::Chart, size(&chart.texture));
+    budget.record(TextureSlot::AltPalette, size(&chart.alt_texture));
+    budget.record(TextureSlot::Thumbnail, size(&chart.thumbnail));
+
+    // Textures that are on screen would only be read and stored again.
+    let mut shown = Vec::new();
+    if self.palette_preview {
+      shown.push(TextureSlot::AltPalette);
+    }
+    if self.overview {
+      shown.push(TextureSlot::Thumbnail);
+    }
+
+    for (slot, bytes) in budget.evict(&shown) {
+      let megabytes = bytes as f64 / (1024.0 * 1024.0);
+      let name = slot.text();
+      log::info!("Dropped the {name} texture ({megabytes:.1} MB) to stay within the budget");
+      match slot {
+        TextureSlot::AltPalette => {
+          chart.alt_texture = None;
+          chart.alt_request = None;
+        }
+        TextureSlot::Thumbnail => {
+          chart.thumbnail = None;
+          chart.thumbnail_request = None;
+        }
+        TextureSlot::Terrain | TextureSlot::Chart => unreachable!(),
+      }
+    }
+  }
+
+  /// Suggest faster storage if chart reads are slow. The hint is only ever shown once.
+  fn check_read_speed(&mut self, ctx: &egui::Context) {
+    if self.config.get_slow_read_hint().unwrap_or(false) {
+      return;
+    }
+
+    let Some(chart) = self.get_chart() else {
+      return;
+    };
+
+    if chart.reader.read_stats().is_slow() {
+      self.config.set_slow_read_hint(true);
+      let text = "Chart reads are slow. Copying the chart zip file to faster storage or unzipping \
+                  it may help.";
+      self.set_notice(ctx, text.into());
+    }
+  }
+
+  fn set_chart_thumbnail(
+    &mut self,
+    ctx: &egui::Context,
+    part: chart::ImagePart,
+    image: epaint::ColorImage,
+  ) {
+    if let Chart::Ready(chart) = &mut self.chart {
+      // The thumbnail is always shown reduced, so smooth it.
+      let options = epaint::textures::TextureOptions::LINEAR;
+      let texture = ctx.load_texture("chart_thumbnail", image, options);
+      chart.thumbnail = Some((part, texture));
+    }
+    self.check_texture_budget();
+  }
+
+  /// Show the overview inset in the bottom-right corner of the chart view. Returns a new scroll
+  /// offset if the inset was clicked or dragged.
+  /// - `ui`: chart view UI
+  /// - `rect`: visible chart rectangle
+  /// - `offset`: scroll offset of the chart view
+  /// - `zoom`: current chart zoom
+  fn show_overview(
+    &mut self,
+    ui: &egui::Ui,
+    rect: emath::Rect,
+    offset: emath::Vec2,
+    zoom: f32,
+  ) -> Option<emath::Pos2> {
+    let palette = self.chart_palette();
+    let Chart::Ready(chart) = &mut self.chart else {
+      return None;
+    };
+
+    // Request a thumbnail in the current palette. The old one is shown until it arrives.
+    let current = chart.thumbnail.as_ref().map(|(part, _)| part.palette);
+    if current != Some(palette) && chart.thumbnail_request != Some(palette) {
+      chart.reader.read_thumbnail(OVERVIEW_READ_WIDTH, palette);
+      chart.thumbnail_request = Some(palette);
+    }
+
+    let (_, texture) = chart.thumbnail.as_ref()?;
+    let chart_size: emath::Vec2 = chart.reader.transform().px_size().into();
+    let scale = (OVERVIEW_SIZE / chart_size.x).min(OVERVIEW_SIZE / chart_size.y);
+    let size = chart_size * scale;
+    let corner = rect.right_bottom() - size - emath::vec2(8.0, 8.0);
+    let inset = emath::Rect::from_min_size(corner, size);
+    if !rect.contains_rect(inset.expand(2.0)) {
+      return None;
+    }
+
+    let visuals = ui.visuals();
+    let painter = ui.painter_at(rect);
+    let uv = emath::Rect::from_min_max(emath::pos2(0.0, 0.0), emath::pos2(1.0, 1.0));
+    painter.rect_filled(inset.expand(2.0), 2.0, visuals.extreme_bg_color);
+    painter.image(texture.id(), inset, uv, epaint::Color32::WHITE);
+
+    // Mark the part of the chart that's in view.
+    let min = inset.min + offset * scale / zoom;
+    let view = emath::Rect::from_min_size(min, rect.size() * scale / zoom).intersect(inset);
+    let color = epaint::Color32::from_rgb(200, 30, 30);
+    painter.rect_stroke(view, 0.0, epaint::Stroke::new(1.5, color));
+
+    // Center the view on the point that's clicked or dragged.
+    let id = ui.id().with("overview");
+    let response = ui.interact(inset, id, egui::Sense::click_and_drag());
+    if !(response.clicked() || response.dragged()) {
+      return None;
+    }
+
+    let pos = response.interact_pointer_pos()?;
+    let px = (pos - inset.min) / scale;
+    let pos = (px * zoom - rect.size() * 0.5).max(emath::Vec2::ZERO);
+    Some(pos.to_pos2())
+  }
+
+  fn set_chart_disp_rect(&mut self, rect: util::Rect) {
+    if let Chart::Ready(chart) = &mut self.chart {
+      if chart.disp_rect != rect {
+        // Only close the airport menu when the chart is panned, not when it's zoomed.
+        let zoomed = chart.disp_zoom != chart.zoom;
+        chart.disp_rect = rect;
+        chart.disp_zoom = chart.zoom;
+        if !zoomed {
+          self.reset_airport_menu();
+        }
+      }
+    }
+  }
+
+  /// Keep the center of the chart view in place when the size of the chart panel changes because
+  /// of the side panel (or, on mobile, the on-screen keyboard or screen rotation).
+  /// - `size`: unrounded size of the chart panel for this frame
+  fn recenter_chart(&mut self, size: emath::Vec2) {
+    if let Chart::Ready(chart) = &mut self.chart {
+      let old_size = mem::replace(&mut chart.panel_size, size);
+
+      #[cfg(feature = "mobile")]
+      if old_size.y != size.y {
+        chart.recenter = true;
+      }
+
+      if mem::take(&mut chart.recenter) && old_size != emath::Vec2::ZERO && old_size != size {
+        let offset = chart.scroll.unwrap_or_else(|| chart.disp_rect.pos.into());
+        let remainder = &mut chart.scroll_remainder;
+        chart.scroll = Some(util::recenter_offset(offset, old_size, size, remainder));
+      }
+    }
+  }
+
+  /// Get the pinch anchor for the current gesture.
+  /// - `begin`: true if this is the start of a new gesture
+  /// - `new`: creates the anchor
+  fn get_pinch_anchor(
+    &mut self,
+    begin: bool,
+    new: impl FnOnce() -> touch::PinchAnchor,
+  ) -> touch::PinchAnchor {
+    let Chart::Ready(chart) = &mut self.chart else {
+      return new();
+    };
+
+    if begin {
+      chart.pinch = None;
+    }
+    *chart.pinch.get_or_insert_with(new)
+  }
+
+  /// Get the unrounded scroll offset set by the last zoom if the chart hasn't been scrolled since.
+  /// - `pos`: current scroll offset
+  fn get_zoom_origin(&self, pos: emath::Vec2) -> emath::Vec2 {
+    if let Chart::Ready(chart) = &self.chart {
+      if let Some(origin) = chart.zoom_origin {
+        if origin.round() == pos {
+          return origin;
+        }
+      }
+    }
+    pos
+  }
+
+  /// Set the scroll offset after zooming, keeping the unrounded offset for the next zoom.
+  fn set_zoom_scroll(&mut self, pos: emath::Vec2) {
+    if let Chart::Ready(chart) = &mut self.chart {
+      chart.zoom_origin = Some(pos);
+      chart.scroll = Some(pos.round().to_pos2());
+    }
+  }
+
+  fn take_chart_scroll(&mut self) -> Option<emath::Pos2> {
+    if let Chart::Ready(chart) = &mut self.chart {
+      return chart.scroll.take();
+    }
+    None
+  }
+
+  fn set_chart_scroll(&mut self, pos: emath::Pos2) {
+    if let Chart::Ready(chart) = &mut self.chart {
+      chart.scroll = Some(pos.floor());
+    }
+  }
+
+  /// Open the airport info window and request the airport's details.
+  fn show_airport_info(&mut self, info: &nasr::AirportInfo) {
+    if let Some(nasr_reader) = &self.airport_reader {
+      nasr_reader.detail(info.id.clone());
+      let size = self.config.get_dialog_size(info_dlg::InfoDlg::NAME);
+      let dlg = info_dlg::InfoDlg::open(info.desc.clone(), info.id.clone(), size);
+      self.info_dlg = Some(dlg);
+    }
+  }
+
+  fn show_airport_remarks(&mut self, info: &nasr::AirportInfo) {
+    if let Some(nasr_reader) = &self.airport_reader {
+      nasr_reader.remarks(info.id.clone());
+      let dlg = remarks_dlg::RemarksDlg::open(info.desc.clone(), info.id.clone());
+      self.remarks_dlg = Some(dlg);
+    }
+  }
+
+  /// Request runway ticks around the chart view if it has moved outside the area that was last
+  /// requested.
+  /// - `offset`: scroll offset of the chart view
+  /// - `size`: size of the chart view
+  /// - `zoom`: current chart zoom
+  fn request_runway_ticks(&mut self, offset: emath::Vec2, size: emath::Vec2, zoom: f32) {
+    let (Some(chart), Some(nasr_reader)) = (self.get_chart(), &self.airport_reader) else {
+      return;
+    };
+
+    if !nasr_reader.airport_spatial_idx() {
+      return;
+    }
+
+    // Visible area in chart coordinates. Pixel rows increase to the south.
+    let transform = chart.reader.transform();
+    let min = offset / zoom;
+    let max = (offset + size) / zoom;
+    let view = util::Bounds {
+      min: transform.px_to_chart(util::Coord::from(emath::pos2(min.x, max.y))),
+      max: transform.px_to_chart(util::Coord::from(emath::pos2(max.x, min.y))),
+    };
+
+    if let Some(area) = &self.runway_tick_area {
+      let (outer, inner) = (&area.bounds, &view);
+      if inner.min.x >= outer.min.x
+        && inner.min.y >= outer.min.y
+        && inner.max.x <= outer.max.x
+        && inner.max.y <= outer.max.y
+      {
+        return;
+      }
+    }
+
+    // Request extra area around the view so that panning doesn't request the ticks every frame.
+    let dx = (view.max.x - view.min.x) * RunwayTickArea::MARGIN;
+    let dy = (view.max.y - view.min.y) * RunwayTickArea::MARGIN;
+    let bounds = util::Bounds {
+      min: util::Coord {
+        x: view.min.x - dx,
+        y: view.min.y - dy,
+      },
+      max: util::Coord {
+        x: view.max.x + dx,
+        y: view.max.y + dy,
+      },
+    };
+
+    self.runway_tick_gen += 1;
+    nasr_reader.runway_ticks(self.runway_tick_gen, bounds.clone());
+
+    // Keep showing the old ticks until the reply arrives.
+    let ticks = self.runway_tick_area.take().map(|area| area.ticks);
+    self.runway_tick_area = Some(RunwayTickArea {
+      bounds,
+      generation: self.runway_tick_gen,
+      ticks: ticks.unwrap_or_default(),
+    });
+  }
+
+  /// Request airports near a chart location for the airport menu.
+  /// - `px`: location in chart pixels
+  /// - `lcc`: location in chart coordinates
+  /// - `radius`: search radius in meters
+  /// - `zoom`: chart zoom that the radius was set for
+  fn search_nearby(&mut self, px: emath::Pos2, lcc: util::Coord, radius: f64, zoom: f32) {
+    self.nearby_search = None;
+    if let Some(nasr_reader) = &self.airport_reader {
+      if nasr_reader.airport_spatial_idx() {
+        self.nearby_gen += 1;
+        nasr_reader.nearby(self.nearby_gen, lcc, radius);
+        self.nearby_search = Some(NearbySearch {
+          px,
+          lcc,
+          radius,
+          zoom,
+          generation: self.nearby_gen,
+          settle: None,
+        });
+      }
+    }
+  }
+
+  /// Search again with a radius scaled for the new zoom once the zoom has settled.
+  fn refresh_nearby_search(&mut self, ctx: &egui::Context) {
+    if !self.airport_menu_open() {
+      return;
+    }
+
+    let (Some(zoom), Some(search)) = (self.get_chart_zoom(), &mut self.nearby_search) else {
+      return;
+    };
+
+    // Ignore small zoom changes.
+    let ratio = search.zoom / zoom;
+    if (ratio - 1.0).abs() <= NearbySearch::ZOOM_CHANGE {
+      search.settle = None;
+      return;
+    }
+
+    // Restart the wait whenever the zoom changes.
+    let now = ctx.input(|state| state.time);
+    let since = match search.settle {
+      Some((settle_zoom, time)) if settle_zoom == zoom => time,
+      _ => {
+        search.settle = Some((zoom, now));
+        now
+      }
+    };
+
+    let remaining = NearbySearch::SETTLE_TIME - (now - since);
+    if remaining > 0.0 {
+      ctx.request_repaint_after(time::Duration::from_secs_f64(remaining));
+      return;
+    }
+
+    // The results are replaced in place when the reply arrives.
+    let (px, lcc, radius) = (search.px, search.lcc, search.radius * ratio as f64);
+    self.search_nearby(px, lcc, radius, zoom);
+  }
+
+  fn airport_menu_open(&self) -> bool {
+    matches!(self.airport_infos, AirportInfos::Menu(..))
+  }
+
+  fn reset_airport_menu(&mut self) -> bool {
+    if matches!(self.airport_infos, AirportInfos::Menu(..)) {
+      self.airport_infos = AirportInfos::None;
+      return true;
+    }
+    false
+  }
+
+  /// Pan the map to a NAD83 coordinate.
+  fn goto_coord(&mut self, coord: util::Coord) -> bool {
+    self.goto_coord_zoom(coord, 1.0)
+  }
+
+  /// Pan the map to a NAD83 coordinate and set the zoom.
+  fn goto_coord_zoom(&mut self, coord: util::Coord, zoom: f32) -> bool {
+    if let Some(chart) = self.get_chart() {
+      if let Ok(px) = chart.reader.transform().nad83_to_px(coord) {
+        let chart_size = chart.reader.transform().px_size();
+        if chart_size.contains(px) {
+          if self.animate_goto && chart.texture.is_some() {
+            // Animate from the current view center.
+            let size: emath::Vec2 = chart.disp_rect.size.into();
+            let pos: emath::Pos2 = chart.disp_rect.pos.into();
+            let from = (pos + size * 0.5) / chart.zoom;
+            let to = emath::pos2(px.x as f32, px.y as f32);
+            let min_zoom = chart.get_min_zoom();
+            let anim = goto_anim::GotoAnim::new(from, chart.zoom, to, zoom, size, min_zoom);
+            self.push_view_history();
+            self.goto_anim = Some(anim);
+            return true;
+          }
+
+          let x = px.x as f32 * zoom - 0.5 * chart.disp_rect.size.w as f32;
+          let y = px.y as f32 * zoom - 0.5 * chart.disp_rect.size.h as f32;
+          self.push_view_history();
+          self.set_chart_zoom(zoom);
+          self.set_chart_scroll(emath::pos2(x, y));
+          return true;
+        }
+      }
+    }
+    false
+  }
+
+  /// Record the current view in the history before jumping away from it.
+  fn push_view_history(&mut self) {
+    if let Chart::Ready(chart) = &mut self.chart {
+      let state = chart.view_state();
+      chart.history.push(state);
+    }
+  }
+
+  /// Go back or forward in the view history.
+  /// - `forward`: go forward instead of back
+  fn step_view_history(&mut self, forward: bool) {
+    let Chart::Ready(chart) = &mut self.chart else {
+      return;
+    };
+
+    let current = chart.view_state();
+    let state = if forward {
+      chart.history.forward(current)
+    } else {
+      chart.history.back(current)
+    };
+
+    let Some(state) = state else {
+      return;
+    };
+
+    let size: emath::Vec2 = chart.disp_rect.size.into();
+    if self.animate_goto && chart.texture.is_some() {
+      let min_zoom = chart.get_min_zoom();
+      let (from, from_zoom) = (current.center, current.zoom);
+      let (to, to_zoom) = (state.center, state.zoom);
+      let anim = goto_anim::GotoAnim::new(from, from_zoom, to, to_zoom, size, min_zoom);
+      self.goto_anim = Some(anim);
+      return;
+    }
+
+    let pos = state.center * state.zoom - size * 0.5;
+    self.goto_anim = None;
+    self.set_chart_zoom(state.zoom);
+    self.set_chart_scroll(pos);
+  }
+
+  /// Mark a location on the chart for a few seconds.
+  fn set_highlight(&mut self, ctx: &egui::Context, coord: util::Coord) {
+    if let Some(chart) = self.get_chart() {
+      if let Ok(px) = chart.reader.transform().nad83_to_px(coord) {
+        let until = ctx.input(|state| state.time) + Highlight::DURATION;
+        let px = emath::pos2(px.x as f32, px.y as f32);
+        self.highlight = Some(Highlight { px, until });
+      }
+    }
+  }
+
+  /// Advance the go-to animation (if any) by one frame.
+  fn step_goto_anim(&mut self, ctx: &egui::Context) {
+    let Some(anim) = &mut self.goto_anim else {
+      return;
+    };
+
+    let (center, zoom, done) = anim.step(ctx.input(|state| state.time));
+    if done {
+      self.goto_anim = None;
+    } else {
+      ctx.request_repaint();
+    }
+
+    // Set the zoom directly so that a menu opened for the destination stays open.
+    if let Chart::Ready(chart) = &mut self.chart {
+      let size: emath::Vec2 = chart.disp_rect.size.into();
+      let pos = center * zoom - size * 0.5;
+      chart.zoom = zoom;
+      chart.scroll = Some(pos.floor());
+    }
+  }
+
+  /// Add the pending search term to the search history.
+  fn record_search_term(&mut self) {
+    if let Some(term) = self.search_term.take() {
+      self.config.push_search_term(&term);
+    }
+  }
+
+  /// Zoom that makes the view `PATTERN_SPAN` across, which gives the same view of an airport's
+  /// surroundings on any chart.
+  fn pattern_zoom(&self) -> Option<f32> {
+    let chart = self.get_chart()?;
+    let meters_per_px = chart.reader.transform().meters_per_pixel();
+    let width = chart.disp_rect.size.w as f32;
+    let min_zoom = chart.get_min_zoom();
+    let zoom = util::span_zoom(width, meters_per_px, PATTERN_SPAN, min_zoom, MAX_ZOOM);
+    Some(zoom)
+  }
+
+  /// Pan the map to an airport or navaid, zoom in to `PATTERN_SPAN` and add it to the flight log.
+  fn zoom_to_facility(&mut self, facility: &nasr::Facility) {
+    let Some(zoom) = self.pattern_zoom() else {
+      return;
+    };
+
+    if self.goto_coord_zoom(facility.coord(), zoom) {
+      self.flight_log.add(facility.desc().into());
+    }
+  }
+
+  /// Pan the map to an airport and add it to the flight log.
+  fn goto_airport(&mut self, info: &nasr::AirportInfo) {
+    if self.goto_coord(info.coord) {
+      self.flight_log.add(info.desc.clone());
+    }
+  }
+
+  fn can_find_nearest(&self) -> bool {
+    let spatial_idx = self
+      .airport_reader
+      .as_ref()
+      .is_some_and(|nasr_reader| nasr_reader.airport_spatial_idx());
+    spatial_idx && matches!(self.chart, Chart::Ready(_))
+  }
+
+  /// Request the airports closest to the center of the view.
+  fn find_nearest(&mut self) {
+    if !self.can_find_nearest() {
+      return;
+    }
+
+    if let (Some(chart), Some(nasr_reader)) = (self.get_chart(), &self.airport_reader) {
+      let pos: emath::Pos2 = chart.disp_rect.pos.into();
+      let size: emath::Vec2 = chart.disp_rect.size.into();
+      let center = (pos + size * 0.5) / chart.zoom;
+      let lcc = chart.reader.transform().px_to_chart(center.into());
+      nasr_reader.nearest(lcc);
+    }
+    self.reset_airport_menu();
+  }
+
+  /// Pan to the closest airport and list the others in the select menu.
+  /// - `infos`: airports with their distances in meters, nearest first
+  fn show_nearest(&mut self, ctx: &egui::Context, infos: Vec<(nasr::AirportInfo, f64)>) {
+    let Some((nearest, dist)) = infos.first() else {
+      let err = "No public airports within 40 NM of the center of the chart view";
+      self.error_dlg = Some(error_dlg::ErrorDlg::open(err.into()));
+      return;
+    };
+
+    let dist = util::format_distance(*dist, self.units);
+    let title = format!("Nearest: {}, {dist}", nearest.desc);
+    let coord = nearest.coord;
+    self.goto_airport(nearest);
+
+    // Show the menu over the center of the chart area.
+    let screen = ctx.screen_rect();
+    let left = if self.side_panel {
+      self.side_panel_width as f32
+    } else {
+      0.0
+    };
+    let top = self.top_panel_height as f32;
+    let pos = emath::pos2(
+      left + (screen.width() - left) * 0.5,
+      top + (screen.height() - top) * 0.5,
+    );
+    self.select_menu.set_pos(pos);
+
+    let infos = infos
+      .into_iter()
+      .map(|(info, _)| nasr::Facility::Airport(info))
+      .collect();
+    self.airport_infos = AirportInfos::Menu(coord, title, Some(infos));
+    self.nearby_search = None;
+  }
+
+  /// Copy a position report for a location to the clipboard. The airports around the location are
+  /// looked up first, if there's NASR data.
+  /// - `coord`: location in decimal degrees (NAD 83)
+  fn request_position_report(&mut self, ctx: &egui::Context, coord: util::Coord) {
+    let chart = self.get_chart();
+    let lcc = chart.and_then(|chart| chart.reader.transform().nad83_to_chart(coord).ok());
+    if let (Some(lcc), Some(nasr_reader)) = (lcc, &self.airport_reader) {
+      self.nearby_gen += 1;
+      nasr_reader.nearby(self.nearby_gen, lcc, airport_fmt::REPORT_RADIUS);
+      self.position_report = Some((self.nearby_gen, coord));
+      return;
+    }
+
+    self.copy_position_report(ctx, coord, &[]);
+  }
+
+  /// Copy a position report to the clipboard.
+  /// - `coord`: location in decimal degrees (NAD 83)
+  /// - `infos`: facilities around the location
+  fn copy_position_report(
+    &mut self,
+    ctx: &egui::Context,
+    coord: util::Coord,
+    infos: &[nasr::Facility],
+  ) {
+    let nph = self.include_nph;
+    let iter = infos.iter().filter(|info| info.included(nph));
+    let airports = iter.filter_map(nasr::Facility::airport);
+    let chart = self.get_chart().map_or("", |chart| chart.name.as_str());
+    let text = airport_fmt::position_report(coord, airports, chart, self.units);
+    ctx.output_mut(|state| state.copied_text = text.clone());
+    self.set_notice(ctx, format!("Copied: {text}"));
+  }
+
+  fn goto_navaid(&mut self, info: &nasr::NavaidInfo) {
+    if self.goto_coord(info.coord) {
+      self.flight_log.add(info.desc.clone());
+    }
+  }
+
+  fn goto_facility(&mut self, facility: &nasr::Facility) {
+    match facility {
+      nasr::Facility::Airport(info) => self.goto_airport(info),
+      nasr::Facility::Navaid(info) => self.goto_navaid(info),
+    }
+  }
+
+  /// Act on a search result.
+  /// - `facility`: airport or navaid
+  /// - `intent`: what the search was for
+  fn open_facility(&mut self, facility: &nasr::Facility, intent: nasr::SearchIntent) {
+    match (intent, facility.airport()) {
+      (nasr::SearchIntent::Info, Some(info)) => self.show_airport_info(info),
+      _ => self.goto_facility(facility),
+    }
+  }
+
+  fn toggle_side_panel(&mut self, visible: bool) {
+    if self.side_panel == visible {
+      return;
+    }
+
+    self.side_panel = visible;
+    if let Chart::Ready(chart) = &mut self.chart {
+      // Scroll the chart to account for the left panel once the new panel size is known.
+      chart.recenter = true;
+    }
+  }
+
+  fn get_chart_replies(&self) -> Vec<chart::RasterReply> {
+    if let Some(chart_reader) = &self.get_chart_reader() {
+      return chart_reader.get_replies();
+    }
+    Vec::new()
+  }
+
+  /// Get the next batch of airport replies. Replies beyond the budget are left for the next frame.
+  fn get_airport_replies(&self, ctx: &egui::Context) -> Vec<nasr::AirportReply> {
+    if let Some(airport_reader) = &self.airport_reader {
+      let replies = airport_reader.get_replies_budgeted(AIRPORT_REPLY_BUDGET);
+      if replies.len() == AIRPORT_REPLY_BUDGET {
+        ctx.request_repaint();
+      }
+      return replies;
+    }
+    Vec::new()
+  }
+
+  fn set_night_mode(&mut self, ctx: &egui::Context, night_mode: bool) {
+    if self.night_mode == night_mode {
+      return;
+    }
+
+    self.night_mode = night_mode;
+
+    // Set the theme.
+    ctx.set_visuals(if night_mode {
+      dark_theme()
+    } else {
+      self.default_theme.clone()
+    });
+
+    // Store the night mode flag.
+    self.config.set_night_mode(night_mode);
+
+    // Use the palette preview image if it's current, otherwise request a new image.
+    if let Chart::Ready(chart) = &mut self.chart {
+      if chart.get_alt_texture().is_some() {
+        mem::swap(&mut chart.texture, &mut chart.alt_texture);
+        return;
+      }
+    }
+
+    if let Some((part, _)) = self.get_chart_texture() {
+      self.request_image(part.rect, part.zoom.into());
+    }
+  }
+
+  fn set_high_contrast(&mut self, high_contrast: bool) {
+    if self.high_contrast == high_contrast {
+      return;
+    }
+
+    self.high_contrast = high_contrast;
+    self.config.set_high_contrast(high_contrast);
+
+    if self.night_mode {
+      // Only the palette preview image uses the day palette.
+      if let Chart::Ready(chart) = &mut self.chart {
+        chart.alt_texture = None;
+        chart.alt_request = None;
+      }
+    } else if let Some((part, _)) = self.get_chart_texture() {
+      self.request_image(part.rect, part.zoom.into());
+    }
+  }
+
+  fn set_font_scale(&mut self, ctx: &egui::Context, font_scale: f32) {
+    self.font_scale = font_scale;
+    self.config.set_font_scale(font_scale);
+
+    let text_styles = scaled_text_styles(&self.text_styles, font_scale);
+    ctx.style_mut(|style| style.text_styles = text_styles);
+  }
+
+  fn set_chart_filter(&mut self, chart_filter: util::ChartFilter) {
+    if self.chart_filter == chart_filter {
+      return;
+    }
+
+    self.chart_filter = chart_filter;
+
+    // Store the filter.
+    self.config.set_chart_filter(chart_filter);
+
+    // Request a new image so that the texture is recreated with the new filter.
+    if let Some((part, _)) = self.get_chart_texture() {
+      self.request_image(part.rect, part.zoom.into());
+    }
+  }
 
-    // Set the theme.
-    ctx.set_visuals(if night_mode {
-      dark_theme()
-    } else {
-      self.default_theme.clone()
-    });
+  fn set_magnify_filter(&mut self, magnify_filter: util::ChartFilter) {
+    if self.magnify_filter == magnify_filter {
+      return;
+    }
 
-    // Store the night mode flag.
-    self.config.set_night_mode(night_mode);
+    self.magnify_filter = magnify_filter;
+
+    // Store the filter.
+    self.config.set_magnify_filter(magnify_filter);
 
-    // Request a new image.
+    // Request a new image so that the texture is recreated with the new filter.
     if let Some((part, _)) = self.get_chart_texture() {
       self.request_image(part.rect, part.zoom.into());
     }
   }
 
+  /// Texture options for the chart and terrain images.
+  fn texture_options(&self) -> epaint::textures::TextureOptions {
+    epaint::textures::TextureOptions {
+      magnification: self.magnify_filter.texture_filter(),
+      ..self.chart_filter.texture_options()
+    }
+  }
+
+  /// Respond to a display scale change, such as when the window is moved to a monitor with a
+  /// different DPI. Touches that are in progress were recorded in the old points.
+  fn check_scale(&mut self, ctx: &egui::Context) {
+    let pixels_per_point = ctx.pixels_per_point();
+    let Some(old) = self.pixels_per_point.replace(pixels_per_point) else {
+      return;
+    };
+
+    if pixels_per_point != old {
+      log::info!("Display scale changed from {old} to {pixels_per_point}");
+      let ratio = old / pixels_per_point;
+      self.long_press.rescale(ratio);
+      self.gesture.rescale(ratio);
+    }
+  }
+
+  /// Save the window size and position once they've settled, so that a crash doesn't lose them.
+  fn save_win_info(&mut self, ctx: &egui::Context) {
+    let Some(changed) = self.win_info_time else {
+      return;
+    };
+
+    let elapsed = changed.elapsed();
+    if elapsed < WIN_INFO_DELAY {
+      ctx.request_repaint_after(WIN_INFO_DELAY - elapsed);
+      return;
+    }
+
+    self.config.set_win_info(&self.win_info);
+    self.win_info_time = None;
+  }
+
+  /// Move the window onto a connected monitor if it was restored onto one that's gone.
+  fn check_restored_win(&mut self, ctx: &egui::Context) {
+    let Some(restored) = &self.restored_win else {
+      return;
+    };
+
+    let viewport = ctx.input(|state| state.viewport().clone());
+    let (Some(outer), Some(monitor)) = (viewport.outer_rect, viewport.monitor_size) else {
+      // Not known until the window is shown.
+      return;
+    };
+
+    if let Some(pos) = restored.check_restored(outer, monitor) {
+      log::info!("Moving the window onto the primary monitor");
+      ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(pos));
+    }
+    self.restored_win = None;
+  }
+
   fn process_input(&mut self, ctx: &egui::Context) -> InputEvents {
-    let mut events = InputEvents::new(ctx);
+    let mut events = InputEvents::new();
+    let mut back = false;
     events.secondary_click = self.long_press.check();
+    let typing = ctx.wants_keyboard_input();
 
     ctx.input(|state| {
       // Get the window size info.
-      self.win_info = util::WinInfo::new(state.viewport());
+      let win_info = util::WinInfo::new(state.viewport());
+      if win_info != self.win_info {
+        self.win_info = win_info;
+        self.win_info_time = Some(time::Instant::now());
+      }
+
+      // Holding D previews the other palette.
+      self.palette_preview =
+        self.ui_enabled && !typing && state.modifiers.is_none() && state.key_down(egui::Key::D);
 
       // Process events.
       for event in &state.events {
@@ -363,20 +2011,31 @@ impl App {
           } if *pressed && !*repeat && self.ui_enabled => {
             match key {
               egui::Key::Escape => {
-                // Remove the airport infos.
-                if !self.reset_airport_menu() {
-                  // No airport menu. Close the side panel.
-                  self.toggle_side_panel(false);
-                }
+                // Handled once the input has been read.
+                back = true;
               }
               egui::Key::F if modifiers.command_only() => {
                 if let Some(nasr_reader) = &self.airport_reader {
                   if nasr_reader.airport_basic_idx() && matches!(self.chart, Chart::Ready(_)) {
-                    self.find_dlg = Some(find_dlg::FindDlg::open());
+                    let terms = self.config.get_search_terms();
+                    let size = self.config.get_dialog_size(find_dlg::FindDlg::NAME);
+                    self.find_dlg = Some(find_dlg::FindDlg::open(terms, size));
                     self.reset_airport_menu();
                   }
                 }
               }
+              egui::Key::F if modifiers.is_none() && !typing => {
+                self.fit_chart();
+              }
+              egui::Key::N if modifiers.command_only() => {
+                self.find_nearest();
+              }
+              egui::Key::ArrowLeft if *modifiers == egui::Modifiers::ALT && !typing => {
+                self.step_view_history(false);
+              }
+              egui::Key::ArrowRight if *modifiers == egui::Modifiers::ALT && !typing => {
+                self.step_view_history(true);
+              }
               egui::Key::Q if modifiers.command_only() => {
                 events.quit = true;
                 self.reset_airport_menu();
@@ -390,7 +2049,10 @@ impl App {
             phase,
             pos,
             force: _,
-          } => self.long_press.initiate(*id, *phase, *pos),
+          } => {
+            self.long_press.initiate(*id, *phase, *pos);
+            self.gesture.initiate(*id, *phase, *pos);
+          }
           egui::Event::PointerButton {
             pos,
             button,
@@ -400,58 +2062,224 @@ impl App {
             events.secondary_click = Some(*pos);
           }
           egui::Event::Zoom(val) => {
+            // Scale the zoom in log space so that the sensitivity is symmetric.
+            let val = val.powf(self.wheel_zoom);
             events.zoom_pos = state.pointer.hover_pos();
-            events.zoom_mod *= val;
+            events.zoom_mod *= if self.invert_zoom { val.recip() } else { val };
           }
           _ => (),
         }
       }
     });
+
+    // Combine pan and zoom from a two-finger gesture.
+    if let Some(gesture) = self.gesture.take() {
+      events.zoom_pos = Some(gesture.anchor);
+      events.zoom_mod *= gesture.zoom.powf(self.pinch_zoom);
+      events.pan = gesture.pan;
+      events.drag = gesture.drag;
+      events.pinch = Some(gesture);
+    }
+
+    // The context can't be changed while the input is being read.
+    if back {
+      self.go_back(ctx);
+    }
+
     events
   }
+
+  /// Handle the back action (the Escape key). See `back::BackState` for the order that things are
+  /// closed in. Modal dialogs handle the Escape key themselves.
+  fn go_back(&mut self, ctx: &egui::Context) {
+    let state = back::BackState {
+      selecting: !matches!(self.region_select, RegionSelect::None),
+      menu: matches!(self.airport_infos, AirportInfos::Menu(..)),
+      window: back::top_window(ctx),
+      side_panel: self.side_panel,
+      mobile: cfg!(feature = "mobile"),
+    };
+
+    match state.resolve() {
+      Some(back::BackAction::StopSelect) => self.region_select = RegionSelect::None,
+      Some(back::BackAction::CloseMenu) => {
+        self.reset_airport_menu();
+      }
+      Some(back::BackAction::CloseWindow(name)) => back::close_window(ctx, name),
+      Some(back::BackAction::CloseSidePanel) => self.toggle_side_panel(false),
+      Some(back::BackAction::Minimize) => {
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+      }
+      None => (),
+    }
+  }
 }
 
 impl eframe::App for App {
   fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
     // Process input.
+    self.check_scale(ctx);
+    back::begin_frame(ctx);
     let events = self.process_input(ctx);
+    self.check_restored_win(ctx);
+    self.save_win_info(ctx);
+    if self.palette_preview {
+      self.request_alt_image();
+    }
 
     // Process chart raster replies.
+    self.check_chart_open(ctx);
     for reply in self.get_chart_replies() {
       match reply {
         chart::RasterReply::Image(part, image) => {
           self.set_chart_image(ctx, part, image);
+          self.check_read_speed(ctx);
+        }
+        chart::RasterReply::Error(part, err) => {
+          self.set_chart_error(part, err);
+        }
+        chart::RasterReply::Thumbnail(part, image) => {
+          self.set_chart_thumbnail(ctx, part, image);
+        }
+        chart::RasterReply::Pixel(px, value) => {
+          if let Some(palette_dlg) = &mut self.palette_dlg {
+            palette_dlg.set_pixel(px, value);
+          }
+        }
+        chart::RasterReply::Opened | chart::RasterReply::OpenError(_) => {
+          // Handled by `check_chart_open`.
+        }
+      }
+    }
+
+    // Process chart catalog scan replies.
+    if let Some(catalog) = &mut self.catalog {
+      catalog.update();
+    }
+
+    // Process terrain replies.
+    for reply in self.get_terrain_replies() {
+      match reply {
+        terrain::TerrainReply::Image(part, image) => {
+          self.set_terrain_image(ctx, part, image);
         }
-        chart::RasterReply::Error(_, err) => {
-          println!("{err}");
+        terrain::TerrainReply::Error(err) => {
+          log::error!("Unable to read terrain image\n{err}");
         }
       }
     }
 
-    // Process NASR airport replies.
-    for reply in self.get_airport_replies() {
+    // Process NASR airport replies. Every reply in the batch is handled in order, so an error
+    // doesn't hide the replies after it. Only name matches and nearby results for searches that
+    // have since been replaced are dropped.
+    for reply in self.get_airport_replies(ctx) {
       match reply {
-        nasr::AirportReply::Airport(info) => {
-          self.goto_coord(info.coord);
+        nasr::AirportReply::Opened => {
+          if let Some((path, _)) = self.airport_open.take() {
+            if matches!(self.chart, Chart::None) {
+              self.find_companion(ctx, &path, false);
+            }
+            self.config.set_last_nasr(&path);
+          }
+        }
+        nasr::AirportReply::OpenError(err) => {
+          let session = self.airport_open.take().is_some_and(|(_, session)| session);
+          self.airport_reader = None;
+          self.airspace_path = None;
+          self.report_open_error(ctx, err, session.then_some("NASR data"));
         }
-        nasr::AirportReply::Nearby(infos) => {
-          if !infos.is_empty() {
-            if let AirportInfos::Menu(_, airport_list) = &mut self.airport_infos {
+        nasr::AirportReply::Airport(info) => self.goto_airport(&info),
+        nasr::AirportReply::Navaid(info) => self.goto_navaid(&info),
+        nasr::AirportReply::Nearby(generation, infos) => {
+          // These may be the airports for a position report.
+          if let Some((report, coord)) = self.position_report {
+            if report == generation {
+              self.position_report = None;
+              self.copy_position_report(ctx, coord, &infos);
+              continue;
+            }
+          }
+
+          // Drop replies to searches that have since been replaced.
+          let current = self.nearby_search.as_ref();
+          if current.is_some_and(|search| search.generation == generation) {
+            // An empty list lets the menu offer to expand the search.
+            if let AirportInfos::Menu(_, _, airport_list) = &mut self.airport_infos {
               *airport_list = Some(infos);
             }
           }
         }
-        nasr::AirportReply::Search(infos) => match infos.len() {
-          0 => unreachable!(),
-          1 => self.goto_coord(infos[0].coord),
-          _ => self.airport_infos = AirportInfos::Dialog(infos),
-        },
+        nasr::AirportReply::Nearest(infos) => {
+          self.show_nearest(ctx, infos);
+        }
+        nasr::AirportReply::Search(generation, _, _) if generation != self.search_gen => (),
+        nasr::AirportReply::Search(_, intent, result) => {
+          // Keep the term if there are more matches so that they can be asked for.
+          let more = result.total > result.matches.len();
+          let term = self.search_term.clone().filter(|_| more);
+          self.record_search_term();
+
+          // The ID match goes above the name matches.
+          let exact = result.exact.is_some();
+          let total = result.total + exact as usize;
+          let infos: Vec<nasr::Facility> = result.exact.into_iter().chain(result.matches).collect();
+          match infos.len() {
+            0 => unreachable!(),
+            1 if self.auto_open => self.open_facility(&infos[0], intent),
+            _ => {
+              let more = term.map(|term| (term, total));
+              self.airport_infos = AirportInfos::Dialog(infos, exact, more);
+            }
+          }
+        }
+        nasr::AirportReply::Find(term, infos) => {
+          if let Some(find_dlg) = &mut self.find_dlg {
+            find_dlg.set_results(term, infos);
+          }
+        }
+        nasr::AirportReply::Runways(id, ends) => {
+          if let Some(wind_dlg) = &mut self.wind_dlg {
+            if wind_dlg.id() == id {
+              wind_dlg.set_runways(ends);
+            }
+          }
+        }
+        nasr::AirportReply::Detail(detail) => {
+          if let Some(info_dlg) = &mut self.info_dlg {
+            if info_dlg.id() == detail.info.id {
+              info_dlg.set_detail(detail);
+            }
+          }
+        }
+        nasr::AirportReply::Remarks(id, remarks) => {
+          if let Some(remarks_dlg) = &mut self.remarks_dlg {
+            if remarks_dlg.id() == id {
+              remarks_dlg.set_remarks(remarks);
+            }
+          }
+        }
+        nasr::AirportReply::RunwayTicks(generation, ticks) => {
+          let area = self.runway_tick_area.as_mut();
+          if let Some(area) = area.filter(|area| area.generation == generation) {
+            area.ticks = ticks;
+          }
+        }
+        nasr::AirportReply::Indexed(count) => {
+          // The ticks are for the previous spatial index.
+          self.runway_tick_area = None;
+          self.check_airport_count(ctx, count);
+        }
         nasr::AirportReply::Error(err) => {
+          self.search_term = None;
+          log::error!("{err}");
           self.error_dlg = Some(error_dlg::ErrorDlg::open(err));
         }
       }
     }
 
+    // Process the folder watcher replies.
+    self.process_folder_watch();
+
     // Show the file dialog if set.
     if let Some(file_dlg) = &mut self.file_dlg {
       if file_dlg.show(ctx).visible() {
@@ -459,35 +2287,16 @@ impl eframe::App for App {
       } else {
         if file_dlg.selected() {
           if let Some(path) = file_dlg.path() {
+            let path = path.to_owned();
+
             // Save the folder path.
-            if let Some(path) = path.parent().and_then(|p| p.to_str()) {
-              self.config.set_asset_path(path.into());
-              self.asset_path = Some(path.into());
+            if let Some(folder) = path.parent().and_then(|p| p.to_str()) {
+              self.config.set_asset_path(folder.into());
+              self.asset_path = Some(folder.into());
+              self.update_folder_watcher(ctx);
             }
 
-            let path = path.to_owned();
-            match util::get_zip_info(&path) {
-              Ok(info) => match info {
-                util::ZipInfo::Chart(files) => {
-                  if files.len() > 1 {
-                    self.chart = Chart::Load(path, files);
-
-                    // Remove the chart spatial reference from the airport reader.
-                    if let Some(airport_reader) = &self.airport_reader {
-                      airport_reader.clear_spatial_ref();
-                    }
-                  } else {
-                    self.open_chart_data(ctx, &path, files.first().unwrap());
-                  }
-                }
-                util::ZipInfo::Aero { csv, shp: _ } => {
-                  self.open_airport_data(ctx, &path, &csv);
-                }
-              },
-              Err(err) => {
-                self.error_dlg = Some(error_dlg::ErrorDlg::open(err));
-              }
-            }
+            self.open_zip_file(ctx, path);
           }
         }
         self.file_dlg = None;
@@ -495,15 +2304,117 @@ impl eframe::App for App {
       }
     }
 
+    // Show the terrain file dialog if set.
+    if let Some(file_dlg) = &mut self.terrain_dlg {
+      if file_dlg.show(ctx).visible() {
+        self.ui_enabled = false;
+      } else {
+        let path = file_dlg.path().filter(|_| file_dlg.selected());
+        if let Some(path) = path.map(|path| path.to_owned()) {
+          self.open_terrain_file(ctx, &path);
+        }
+        self.terrain_dlg = None;
+        self.ui_enabled = true;
+      }
+    }
+
+    // Show the chart folder dialog if set.
+    if let Some(file_dlg) = &mut self.catalog_dlg {
+      if file_dlg.show(ctx).visible() {
+        self.ui_enabled = false;
+      } else {
+        let path = file_dlg.path().filter(|_| file_dlg.selected());
+        if let Some(path) = path.map(|path| path.to_owned()) {
+          if let Some(text) = path.to_str() {
+            self.config.set_catalog_path(text.into());
+          }
+          self.catalog = Some(chart::catalog::Catalog::scan(path, ctx));
+        }
+        self.catalog_dlg = None;
+        self.ui_enabled = true;
+      }
+    }
+
+    // Check for PDF export replies.
+    for reply in self.get_export_replies() {
+      match reply {
+        export::ExportReply::Progress(progress) => {
+          if let Some(export_dlg) = &mut self.export_dlg {
+            export_dlg.set_progress(Some(progress));
+          }
+        }
+        export::ExportReply::Done => {
+          self.pdf_export = None;
+          self.export_dlg = None;
+          self.ui_enabled = true;
+        }
+        export::ExportReply::Error(err) => {
+          self.pdf_export = None;
+          if let Some(export_dlg) = &mut self.export_dlg {
+            export_dlg.set_progress(None);
+          }
+          log::error!("{err}");
+          self.error_dlg = Some(error_dlg::ErrorDlg::open(err));
+        }
+      }
+    }
+
+    // Show the export file dialog if set.
+    if let Some((_, file_dlg)) = &mut self.export_file_dlg {
+      if file_dlg.show(ctx).visible() {
+        self.ui_enabled = false;
+      } else {
+        let path = file_dlg.path().filter(|_| file_dlg.selected());
+        let path = path.map(|path| path.to_owned());
+        let (settings, _) = self.export_file_dlg.take().unwrap();
+        if let Some(path) = path {
+          self.start_export(ctx, settings, path);
+        }
+      }
+    }
+
+    // Show the export dialog. It's hidden while choosing a file or selecting a region.
+    let hidden =
+      self.export_file_dlg.is_some() || !matches!(self.region_select, RegionSelect::None);
+    if let Some(export_dlg) = self.export_dlg.as_mut().filter(|_| !hidden) {
+      self.ui_enabled = false;
+      match export_dlg.show(ctx) {
+        export_dlg::Response::None => (),
+        export_dlg::Response::Cancel => {
+          // Dropping the export cancels it.
+          self.pdf_export = None;
+          self.export_dlg = None;
+          self.ui_enabled = true;
+        }
+        export_dlg::Response::SelectRegion => {
+          self.region_select = RegionSelect::Waiting(RegionUse::Export);
+          self.ui_enabled = true;
+        }
+        export_dlg::Response::Export(settings) => {
+          self.select_export_file(settings);
+        }
+      }
+    }
+
     // Show the selection dialog if there's a chart choice to be made.
-    if let Chart::Load(path, files) = &self.chart {
+    if let Chart::Load(path, files, combine) = &self.chart {
       self.ui_enabled = false;
       let choices = files.iter().map(|f| util::stem_str(f).unwrap());
-      if let Some(response) = self.select_dlg.show(ctx, choices) {
+      let choices = choices.chain(combine.then_some("Open Combined"));
+      if let Some(response) = self.select_dlg.show(ctx, choices, false, None) {
         self.ui_enabled = true;
+        let size = self.select_dlg.size();
+        self
+          .config
+          .set_dialog_size(select_dlg::SelectDlg::NAME, size);
         if let select_dlg::Response::Index(index) = response {
+          // The last choice opens both charts as one.
+          let files = files
+            .get(index)
+            .map_or_else(|| files.clone(), |file| vec![file.clone()]);
+
           // Clone the parameters in order to avoid simultaneously borrowing self as immutable and mutable.
-          self.open_chart_data(ctx, &path.clone(), &files[index].clone());
+          self.open_chart_data(ctx, &path.clone(), &files);
         } else {
           self.chart = Chart::None;
         }
@@ -511,33 +2422,285 @@ impl eframe::App for App {
     }
 
     // Show the selection dialog if there's an airport choice to be made.
-    if let AirportInfos::Dialog(infos) = &self.airport_infos {
+    if let AirportInfos::Dialog(infos, exact, more) = &self.airport_infos {
       self.ui_enabled = false;
-      let iter = infos.iter().map(|info| info.desc.as_str());
-      if let Some(response) = self.select_dlg.show(ctx, iter) {
+      let units = self.units;
+      let exact = *exact;
+      let total = more.as_ref().map(|(_, total)| *total);
+      let iter = infos.iter().enumerate().map(|(index, info)| {
+        let mut choice = select_dlg::Choice::facility(info, units);
+        choice.pinned = exact && index == 0;
+        choice
+      });
+      if let Some(response) = self.select_dlg.show(ctx, iter, true, total) {
         self.ui_enabled = true;
-        if let select_dlg::Response::Index(index) = response {
-          self.goto_coord(infos[index].coord);
+        let size = self.select_dlg.size();
+        self
+          .config
+          .set_dialog_size(select_dlg::SelectDlg::NAME, size);
+        let infos = mem::replace(&mut self.airport_infos, AirportInfos::None);
+        if let AirportInfos::Dialog(infos, _, more) = infos {
+          match response {
+            select_dlg::Response::Index(index) => self.goto_facility(&infos[index]),
+            select_dlg::Response::Zoom(index) => self.zoom_to_facility(&infos[index]),
+            select_dlg::Response::Info(index) => {
+              if let Some(info) = infos[index].airport() {
+                self.show_airport_info(info);
+              }
+            }
+            select_dlg::Response::Remarks(index) => {
+              if let Some(info) = infos[index].airport() {
+                self.show_airport_remarks(info);
+              }
+            }
+            select_dlg::Response::Indexes(indexes) => {
+              // Only airports can be compared.
+              let mut infos: Vec<Option<nasr::Facility>> = infos.into_iter().map(Some).collect();
+              let infos = indexes
+                .iter()
+                .filter_map(|index| infos[*index].take()?.into_airport())
+                .collect();
+              self.compare_dlg = Some(compare_dlg::CompareDlg::open(infos));
+            }
+            select_dlg::Response::ShowAll => {
+              if let (Some((term, _)), Some(nasr_reader)) = (more, &self.airport_reader) {
+                self.search_gen += 1;
+                let intent = nasr::SearchIntent::Goto;
+                nasr_reader.search(self.search_gen, term, self.include_nph, true, intent);
+              }
+            }
+            select_dlg::Response::Close => (),
+          }
+        }
+      }
+    }
+
+    // Show the selection dialog if there are charts that cover a coordinate.
+    if let Some((coord, entries)) = &self.covering_charts {
+      self.ui_enabled = false;
+      let choices = entries.iter().map(|entry| entry.name());
+      if let Some(response) = self.select_dlg.show(ctx, choices, false, None) {
+        self.ui_enabled = true;
+        let size = self.select_dlg.size();
+        self
+          .config
+          .set_dialog_size(select_dlg::SelectDlg::NAME, size);
+
+        let coord = *coord;
+        let entry = match response {
+          select_dlg::Response::Index(index) => entries.get(index).cloned(),
+          _ => None,
+        };
+
+        self.covering_charts = None;
+        if let Some(entry) = entry {
+          self.open_chart_data(ctx, &entry.path, &[entry.file]);
+          if matches!(self.chart, Chart::Ready(_)) {
+            self.pending_goto = Some(coord);
+          }
+        }
+      }
+    }
+
+    // Show the airport comparison.
+    if let Some(compare_dlg) = &mut self.compare_dlg {
+      self.ui_enabled = false;
+      if !compare_dlg.show(ctx, self.units) {
+        self.compare_dlg = None;
+        self.ui_enabled = true;
+      }
+    }
+
+    // Show the find dialog.
+    if let Some(find_dialog) = &mut self.find_dlg {
+      self.ui_enabled = false;
+      let response = find_dialog.show(ctx, |term| {
+        if util::parse_coord(&term).is_some() {
+          return;
+        }
+
+        if let Some(nasr_reader) = &self.airport_reader {
+          nasr_reader.find(term, self.include_nph);
+        }
+      });
+
+      let size = find_dialog.size();
+
+      match response {
+        find_dlg::Response::None => (),
+        find_dlg::Response::Cancel => {
+          self.ui_enabled = true;
+          self.find_dlg = None;
+        }
+        find_dlg::Response::Term(term, intent) => {
+          self.ui_enabled = true;
+          self.find_dlg = None;
+          if let Some(coord) = util::parse_coord(&term) {
+            // Coordinates skip the airport search.
+            if self.goto_coord(coord) {
+              self.set_highlight(ctx, coord);
+              self.config.push_search_term(&term);
+            } else {
+              self.offer_covering_chart(coord, term.trim());
+            }
+          } else if let Some(nasr_reader) = &self.airport_reader {
+            self.search_gen += 1;
+            let nph = self.include_nph;
+            nasr_reader.search(self.search_gen, term.clone(), nph, false, intent);
+
+            // Only record the term if the search is successful.
+            self.search_term = Some(term);
+          }
+        }
+        find_dlg::Response::Airport(info) => {
+          self.ui_enabled = true;
+          self.find_dlg = None;
+          self.config.push_search_term(&info.id);
+          self.goto_airport(&info);
+        }
+        find_dlg::Response::ClearHistory => {
+          self.config.clear_search_terms();
+        }
+      }
+
+      if self.find_dlg.is_none() {
+        self.config.set_dialog_size(find_dlg::FindDlg::NAME, size);
+      }
+    }
+
+    // Check for weather replies.
+    if let Some(wx_reader) = &self.wx_reader {
+      while let Some((station, reply)) = wx_reader.get_reply() {
+        if let Some(wx_dlg) = &mut self.wx_dlg {
+          if wx_dlg.station() == Some(station.as_str()) {
+            wx_dlg.set_reply(reply);
+          }
+        }
+      }
+    }
+
+    // Show the runway wind window.
+    if let Some(wind_dlg) = &mut self.wind_dlg {
+      if !wind_dlg.show(ctx) {
+        self.wind_dlg = None;
+      }
+    }
+
+    // Show the airport remarks window.
+    if let Some(remarks_dlg) = &mut self.remarks_dlg {
+      if !remarks_dlg.show(ctx) {
+        self.remarks_dlg = None;
+      }
+    }
+
+    // Show the palette inspector.
+    if let Some(palette_dlg) = &mut self.palette_dlg {
+      if !palette_dlg.show(ctx) {
+        self.palette_dlg = None;
+      }
+    }
+
+    // Show the airport info window.
+    if let Some(info_dlg) = &mut self.info_dlg {
+      match info_dlg.show(ctx, self.units) {
+        info_dlg::Response::None => (),
+        info_dlg::Response::Close => {
+          let size = info_dlg.size();
+          self.config.set_dialog_size(info_dlg::InfoDlg::NAME, size);
+          self.info_dlg = None;
+          self.highlight = None;
+        }
+        info_dlg::Response::ShowOnChart(coord) => {
+          // Pan to the airport and mark it, leaving the window open.
+          if self.goto_coord(coord) {
+            self.set_highlight(ctx, coord);
+          }
+        }
+        info_dlg::Response::ZoomOnChart(coord) => {
+          let zoom = self.pattern_zoom();
+          if zoom.is_some_and(|zoom| self.goto_coord_zoom(coord, zoom)) {
+            self.set_highlight(ctx, coord);
+          }
+        }
+      }
+    }
+
+    // Show the weather window.
+    if let Some(wx_dlg) = &mut self.wx_dlg {
+      if !wx_dlg.show(ctx) {
+        self.wx_dlg = None;
+      }
+    }
+
+    // Check if a companion zip file was found.
+    if let Some(companion) = &self.companion {
+      if let Some(path) = companion.get_reply() {
+        // Make sure that data of this type still hasn't been loaded.
+        let needed = if companion.aero() {
+          self.airport_reader.is_none()
+        } else {
+          matches!(self.chart, Chart::None)
+        };
+
+        if needed {
+          let name = path.file_name().unwrap_or_default().to_string_lossy();
+          let dlg = ask_dlg::AskDlg::open(format!("Also load {name}?"));
+          self.companion_dlg = Some((path, dlg));
+        }
+        self.companion = None;
+      }
+    }
+
+    // Show the companion prompt (non-modal).
+    if self.ui_enabled {
+      if let Some((path, dlg)) = &mut self.companion_dlg {
+        if let Some(response) = dlg.show(ctx) {
+          let path = path.clone();
+          self.companion_dlg = None;
+          match response {
+            ask_dlg::Response::Yes => self.open_zip_file(ctx, path),
+            ask_dlg::Response::No => (),
+            ask_dlg::Response::Never => self.ask_companion = false,
+          }
         }
-        self.airport_infos = AirportInfos::None;
       }
     }
 
-    // Show the find dialog.
-    if let Some(find_dialog) = &mut self.find_dlg {
+    // Show the diagnostics dialog.
+    if let Some(diag_dlg) = &mut self.diag_dlg {
+      self.ui_enabled = false;
+      if !diag_dlg.show(ctx) {
+        self.diag_dlg = None;
+        self.ui_enabled = true;
+      }
+    }
+
+    // Show the event log.
+    if let Some(log_dlg) = &mut self.log_dlg {
+      self.ui_enabled = false;
+      if !log_dlg.show(ctx) {
+        self.log_dlg = None;
+        self.ui_enabled = true;
+      }
+    }
+
+    // Show the bookmark name dialog.
+    if let Some((edit, name_dlg)) = &mut self.bookmark_dlg {
       self.ui_enabled = false;
-      match find_dialog.show(ctx) {
-        find_dlg::Response::None => (),
-        find_dlg::Response::Cancel => {
+      match name_dlg.show(ctx) {
+        name_dlg::Response::None => (),
+        name_dlg::Response::Cancel => {
+          self.bookmark_dlg = None;
           self.ui_enabled = true;
-          self.find_dlg = None;
         }
-        find_dlg::Response::Term(term) => {
-          self.ui_enabled = true;
-          self.find_dlg = None;
-          if let Some(nasr_reader) = &self.airport_reader {
-            nasr_reader.search(term, self.include_nph);
+        name_dlg::Response::Name(name) => {
+          match *edit {
+            BookmarkEdit::Add(coord) => self.bookmarks.push(util::Bookmark { name, coord }),
+            BookmarkEdit::Rename(index) => self.bookmarks[index].name = name,
           }
+          self.config.set_bookmarks(&self.bookmarks);
+          self.bookmark_dlg = None;
+          self.ui_enabled = true;
         }
       }
     }
@@ -551,12 +2714,117 @@ impl eframe::App for App {
       }
     }
 
+    // Show the notice (if any) until it expires, then the offers to open zip files.
+    if let Some(toast::Response::Action(path)) = self.toasts.show(ctx, "Open", self.ui_enabled) {
+      self.open_zip_file(ctx, path);
+    }
+
     // Show airport choices in a popup.
-    if let AirportInfos::Menu(lat_lon, infos) = &self.airport_infos {
+    self.refresh_nearby_search(ctx);
+    if let AirportInfos::Menu(coord, lat_lon, infos) = &self.airport_infos {
+      // Non-public heliports are filtered here so that they can be shown or hidden without
+      // searching again.
+      let nph = self.include_nph;
       let infos = infos.as_ref();
-      let iter = infos.map(|v| v.iter().map(|info| info.desc.as_str()));
-      if let Some(_response) = self.select_menu.show(ctx, lat_lon, iter) {
-        self.airport_infos = AirportInfos::None;
+      let toggle = infos.and_then(|infos| {
+        let any = infos.iter().any(nasr::Facility::non_public_heliport);
+        any.then_some(nph)
+      });
+
+      // Menu choices index into the facilities that are shown.
+      let shown: Vec<usize> = infos.map_or_else(Vec::new, |infos| {
+        let iter = infos.iter().enumerate();
+        iter
+          .filter_map(|(index, info)| info.included(nph).then_some(index))
+          .collect()
+      });
+
+      let iter = infos.map(|infos| {
+        let iter = shown.iter().map(|index| &infos[*index]);
+        iter.map(|info| (info.desc(), info.airport().is_some()))
+      });
+
+      if let Some(response) = self.select_menu.show(ctx, lat_lon, iter, toggle) {
+        // Only airports have a context menu.
+        let airport = |index: usize| infos.and_then(|infos| infos[shown[index]].airport());
+        let mut goto = None;
+        let mut close = true;
+        let mut expand = false;
+        let mut report = None;
+        match (response, infos) {
+          (select_menu::Response::Index(index), Some(_)) => goto = Some(shown[index]),
+          (select_menu::Response::Expand, _) => {
+            // Search again at double the radius without closing the menu.
+            expand = true;
+            close = false;
+          }
+          (select_menu::Response::Heliports, _) => {
+            // This also applies to searches until another chart is opened.
+            self.include_nph = !nph;
+            close = false;
+          }
+          (select_menu::Response::Copy(index, format), _) => {
+            if let Some(info) = airport(index) {
+              let text = airport_fmt::format(info, format);
+              ctx.output_mut(|state| state.copied_text = text);
+            }
+          }
+          (select_menu::Response::Bookmark, _) => {
+            let dlg = name_dlg::NameDlg::open("Add Bookmark", String::new());
+            self.bookmark_dlg = Some((BookmarkEdit::Add(*coord), dlg));
+          }
+          (select_menu::Response::PositionReport, _) => report = Some(*coord),
+          (select_menu::Response::Winds(index), _) => {
+            if let (Some(nasr_reader), Some(info)) = (&self.airport_reader, airport(index)) {
+              nasr_reader.runways(info.id.clone());
+              self.wind_dlg = Some(wind_dlg::WindDlg::open(info.desc.clone(), info.id.clone()));
+            }
+          }
+          (select_menu::Response::Info(index), _) => {
+            if let (Some(nasr_reader), Some(info)) = (&self.airport_reader, airport(index)) {
+              nasr_reader.detail(info.id.clone());
+              let size = self.config.get_dialog_size(info_dlg::InfoDlg::NAME);
+              let dlg = info_dlg::InfoDlg::open(info.desc.clone(), info.id.clone(), size);
+              self.info_dlg = Some(dlg);
+            }
+          }
+          (select_menu::Response::Remarks(index), _) => {
+            if let (Some(nasr_reader), Some(info)) = (&self.airport_reader, airport(index)) {
+              nasr_reader.remarks(info.id.clone());
+              let dlg = remarks_dlg::RemarksDlg::open(info.desc.clone(), info.id.clone());
+              self.remarks_dlg = Some(dlg);
+            }
+          }
+          (select_menu::Response::Weather(index), _) => {
+            if let Some(info) = airport(index) {
+              let station = wx::station_id(&info.id, info.icao_id.as_deref(), &info.state);
+              if let Some(station) = &station {
+                let wx_reader = self.wx_reader.get_or_insert_with(|| wx::WxReader::new(ctx));
+                wx_reader.request(station.clone());
+              }
+              self.wx_dlg = Some(wx_dlg::WxDlg::open(info.desc.clone(), station, info.coord));
+            }
+          }
+          _ => (),
+        }
+
+        if close {
+          // Center the chart on the chosen airport or navaid.
+          let infos = mem::replace(&mut self.airport_infos, AirportInfos::None);
+          if let (Some(index), AirportInfos::Menu(_, _, Some(infos))) = (goto, infos) {
+            self.goto_facility(&infos[index]);
+          }
+        } else if let (true, AirportInfos::Menu(_, _, infos)) = (expand, &mut self.airport_infos) {
+          // Waiting for the new search.
+          *infos = None;
+          if let Some(search) = self.nearby_search.take() {
+            self.search_nearby(search.px, search.lcc, search.radius * 2.0, search.zoom);
+          }
+        }
+
+        if let Some(coord) = report {
+          self.request_position_report(ctx, coord);
+        }
       }
     }
 
@@ -568,87 +2836,609 @@ impl eframe::App for App {
           self.toggle_side_panel(!self.side_panel);
         }
 
+        let mut close_nasr = false;
         if let Some(nasr_reader) = &self.airport_reader {
-          if nasr_reader.airport_basic_idx() {
+          let busy = nasr_reader.request_count() > 0 || nasr_reader.is_indexing();
+          if nasr_reader.airport_basic_idx() || busy {
             let text = 'text: {
               const APT: &str = "APT";
-              if nasr_reader.request_count() > 0 {
+              if busy {
                 ctx.output_mut(|state| state.cursor_icon = egui::CursorIcon::Progress);
                 break 'text egui::RichText::new(APT).strong();
               }
               egui::RichText::new(APT)
             };
 
+            // Warn if the NASR data is not current.
+            let mut warning = None;
+            if let Some((effective, expiration)) = nasr_reader.effective_range() {
+              let today = util::Date::today();
+              if today >= expiration {
+                warning = Some(format!("NASR data expired on {expiration}"));
+              } else if today < effective {
+                warning = Some(format!("NASR data is not effective until {effective}"));
+              }
+            }
+
             ui.separator();
-            ui.label(text);
+            if let Some(warning) = warning {
+              let text = text.color(ui.visuals().warn_fg_color);
+              ui.label(text).on_hover_text(warning);
+            } else {
+              ui.label(text);
+            }
+
+            // Allow the NASR data to be closed while it's busy.
+            if busy && ui.small_button("×").on_hover_text("Cancel").clicked() {
+              close_nasr = true;
+            }
           }
         }
 
+        if close_nasr {
+          self.close_airport_reader();
+          self.airport_open = None;
+          self.airport_count = None;
+          self.airspace_path = None;
+          self.reset_airport_menu();
+        }
+
+        let mut fit_chart = false;
+        let mut view_step = None;
         if let Chart::Ready(chart) = &mut self.chart {
           if let Some(nasr_reader) = &self.airport_reader {
             if nasr_reader.airport_spatial_idx() && ui.button("🔎").clicked() {
-              self.find_dlg = Some(find_dlg::FindDlg::open());
+              let terms = self.config.get_search_terms();
+              let size = self.config.get_dialog_size(find_dlg::FindDlg::NAME);
+              self.find_dlg = Some(find_dlg::FindDlg::open(terms, size));
             }
           }
 
+          // Back and forward buttons.
+          ui.add_enabled_ui(chart.history.can_go_back(), |ui| {
+            let response = ui.small_button("⏴");
+            if response.on_hover_text("Back (Alt+Left)").clicked() {
+              view_step = Some(false);
+            }
+          });
+
+          ui.add_enabled_ui(chart.history.can_go_forward(), |ui| {
+            let response = ui.small_button("⏵");
+            if response.on_hover_text("Forward (Alt+Right)").clicked() {
+              view_step = Some(true);
+            }
+          });
+
           ui.separator();
-          ui.label(&chart.name);
+          let sense = egui::Sense::click();
+          let response = if let Some(edition) = chart.reader.edition() {
+            // Flag an expired chart in amber.
+            let mut text = egui::RichText::new(&chart.name);
+            let mut hover = edition.to_string();
+            if edition.expired(util::Date::today()) {
+              text = text.color(ui.visuals().warn_fg_color);
+              hover = format!("Chart edition expired\n{hover}");
+            }
+
+            let response = ui.add(egui::Label::new(text).sense(sense));
+            if let Some(number) = edition.number {
+              ui.label(egui::RichText::new(format!("Ed. {number}")).weak());
+            }
+            response.on_hover_text(hover)
+          } else {
+            ui.add(egui::Label::new(&chart.name).sense(sense))
+          };
+
+          // Triple-clicking the chart name opens the palette inspector.
+          if response.triple_clicked() {
+            let palette = &chart.reader.info().palette;
+            self.palette_dlg = Some(palette_dlg::PaletteDlg::open(palette));
+          }
 
           ui.with_layout(egui::Layout::right_to_left(emath::Align::Center), |ui| {
             // Zoom-in button.
-            ui.add_enabled_ui(chart.zoom < 1.0, |ui| {
+            ui.add_enabled_ui(chart.zoom < MAX_ZOOM, |ui| {
               if let Some(font_id) = ui.style().text_styles.get(&egui::TextStyle::Monospace) {
                 let text = egui::RichText::new("+").font(font_id.clone());
                 let widget = egui::Button::new(text);
                 if ui.add_sized([21.0, 21.0], widget).clicked() {
-                  let new_zoom = (chart.zoom * 2.0).min(1.0);
-                  if new_zoom != chart.zoom {
-                    chart.scroll = Some(chart.get_zoom_pos(new_zoom).round());
-                    chart.zoom = new_zoom;
-                  }
+                  let goto_anim = self.animate_zoom.then_some(&mut self.goto_anim);
+                  zoom_chart(chart, goto_anim, self.zoom_step);
+                }
+              }
+            });
+
+            // Zoom-out button.
+            let min_zoom = chart.get_min_zoom();
+            ui.add_enabled_ui(chart.zoom > min_zoom, |ui| {
+              if let Some(font_id) = ui.style().text_styles.get(&egui::TextStyle::Monospace) {
+                let text = egui::RichText::new("-").font(font_id.clone());
+                let widget = egui::Button::new(text);
+                if ui.add_sized([21.0, 21.0], widget).clicked() {
+                  let goto_anim = self.animate_zoom.then_some(&mut self.goto_anim);
+                  zoom_chart(chart, goto_anim, self.zoom_step.recip());
                 }
               }
-            });
+            });
+
+            // Fit button.
+            if ui.button("Fit").on_hover_text("Fit chart (F)").clicked() {
+              fit_chart = true;
+            }
+          });
+        }
+
+        if fit_chart {
+          self.fit_chart();
+        }
+
+        if let Some(forward) = view_step {
+          self.step_view_history(forward);
+        }
+      });
+    });
+
+    if self.side_panel {
+      self.side_panel_width = side_panel(self.side_panel_width, ctx, |ui| {
+        ui.set_enabled(self.ui_enabled);
+
+        ui.horizontal(|ui| {
+          let button = egui::Button::new("Open Zip File");
+          if ui.add_sized(ui.available_size(), button).clicked() {
+            self.select_zip_file();
+          }
+        });
+
+        ui.horizontal(|ui| {
+          if ui
+            .checkbox(&mut self.open_last, "Reopen Last Files")
+            .on_hover_text("Open the last chart and NASR data on startup")
+            .clicked()
+          {
+            self.config.set_open_last(self.open_last);
+          }
+        });
+
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.separator();
+
+        ui.horizontal(|ui| {
+          let mut night_mode = self.night_mode;
+          if ui.checkbox(&mut night_mode, "Night Mode").clicked() {
+            self.set_night_mode(ctx, night_mode);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          let mut high_contrast = self.high_contrast;
+          let response = ui
+            .checkbox(&mut high_contrast, "High Contrast")
+            .on_hover_text("Show the chart with more contrast and color (not in night mode)");
+          if response.clicked() {
+            self.set_high_contrast(high_contrast);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          ui.label("Font Size");
+          let mut font_scale = self.font_scale;
+          let slider = egui::Slider::new(&mut font_scale, config::Storage::FONT_SCALE)
+            .step_by(0.05)
+            .custom_formatter(|val, _| format!("{:.0}%", val * 100.0));
+          if ui.add(slider).changed() {
+            self.set_font_scale(ctx, font_scale);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          if ui.checkbox(&mut self.scale_bar, "Scale Bar").clicked() {
+            self.config.set_scale_bar(self.scale_bar);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          if ui
+            .checkbox(&mut self.animate_goto, "Animate Go To")
+            .clicked()
+          {
+            self.config.set_animate_goto(self.animate_goto);
+            self.goto_anim = None;
+          }
+        });
+
+        ui.horizontal(|ui| {
+          let response = ui
+            .checkbox(&mut self.auto_open, "Auto-Open Single Result")
+            .on_hover_text("Skip the selection dialog when a search finds only one match");
+          if response.clicked() {
+            self.config.set_auto_open(self.auto_open);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          let response = ui
+            .checkbox(&mut self.watch_folder, "Watch Folder for Downloads")
+            .on_hover_text("Offer to open FAA zip files as they're downloaded to the asset folder");
+          if response.clicked() {
+            self.config.set_watch_folder(self.watch_folder);
+            self.update_folder_watcher(ctx);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          if ui
+            .checkbox(&mut self.animate_zoom, "Animate Zoom")
+            .clicked()
+          {
+            self.config.set_animate_zoom(self.animate_zoom);
+            self.goto_anim = None;
+          }
+        });
+
+        ui.horizontal(|ui| {
+          ui.label("Zoom Step");
+          let slider = egui::Slider::new(&mut self.zoom_step, 1.1..=2.0)
+            .step_by(0.05)
+            .custom_formatter(|val, _| format!("{val:.2}×"));
+          if ui.add(slider).changed() {
+            self.config.set_zoom_step(self.zoom_step);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          if ui.checkbox(&mut self.graticule, "Lat/Lon Grid").clicked() {
+            self.config.set_graticule(self.graticule);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          if ui.checkbox(&mut self.show_bounds, "Show Bounds").clicked() {
+            self.config.set_show_bounds(self.show_bounds);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          let response = ui
+            .checkbox(&mut self.runway_ticks, "Runway Ticks")
+            .on_hover_text("Show the longest runway's orientation at each airport when zoomed in");
+          if response.clicked() {
+            self.config.set_runway_ticks(self.runway_ticks);
+            self.runway_tick_area = None;
+          }
+        });
+
+        ui.horizontal(|ui| {
+          let response = ui
+            .checkbox(&mut self.overview, "Overview Map")
+            .on_hover_text("Show the whole chart with the current view marked");
+          if response.clicked() {
+            self.config.set_overview(self.overview);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          if let Some(terrain) = &self.terrain {
+            ui.label(format!("Terrain: {}", terrain.reader.name()));
+            if ui
+              .small_button("✖")
+              .on_hover_text("Close Terrain")
+              .clicked()
+            {
+              // Dropping the terrain stops its reader and frees its texture.
+              self.terrain = None;
+            }
+          } else {
+            let enabled = matches!(self.chart, Chart::Ready(_));
+            let button = egui::Button::new("Open Terrain File…");
+            if ui.add_enabled(enabled, button).clicked() {
+              self.select_terrain_file();
+            }
+          }
+        });
+
+        if self.terrain.is_some() {
+          ui.horizontal(|ui| {
+            ui.label("Opacity");
+            let slider = egui::Slider::new(&mut self.terrain_opacity, 0.0..=1.0).show_value(false);
+            if ui.add(slider).changed() {
+              self.config.set_terrain_opacity(self.terrain_opacity);
+            }
+          });
+        }
+
+        ui.horizontal(|ui| {
+          ui.label("Search Radius");
+          let slider = egui::Slider::new(&mut self.nearby_radius, 0.25..=5.0)
+            .step_by(0.25)
+            .suffix(" NM");
+          if ui.add(slider).changed() {
+            self.config.set_nearby_radius(self.nearby_radius);
+          }
+        });
+
+        ui.horizontal(|ui| {
+          let mut chart_filter = self.chart_filter;
+          ui.label("Scaling");
+          egui::ComboBox::from_id_source("chart_filter")
+            .selected_text(chart_filter.label())
+            .show_ui(ui, |ui| {
+              for filter in [util::ChartFilter::Sharp, util::ChartFilter::Smooth] {
+                ui.selectable_value(&mut chart_filter, filter, filter.label());
+              }
+            });
+          self.set_chart_filter(chart_filter);
+        });
+
+        ui.horizontal(|ui| {
+          let mut magnify_filter = self.magnify_filter;
+          ui.label("Magnify");
+          egui::ComboBox::from_id_source("magnify_filter")
+            .selected_text(magnify_filter.label())
+            .show_ui(ui, |ui| {
+              for filter in [util::ChartFilter::Sharp, util::ChartFilter::Smooth] {
+                ui.selectable_value(&mut magnify_filter, filter, filter.label());
+              }
+            });
+          self.set_magnify_filter(magnify_filter);
+        });
+
+        ui.horizontal(|ui| {
+          let units = self.units;
+          ui.label("Units");
+          egui::ComboBox::from_id_source("units")
+            .selected_text(self.units.label())
+            .show_ui(ui, |ui| {
+              for units in [util::Units::Aviation, util::Units::Metric] {
+                ui.selectable_value(&mut self.units, units, units.label());
+              }
+            });
+          if self.units != units {
+            self.config.set_units(self.units);
+          }
+        });
+
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.separator();
+
+        ui.horizontal(|ui| {
+          let enabled = self.can_find_nearest();
+          let button = egui::Button::new("Nearest Airport");
+          let response = ui.add_enabled(enabled, button).on_hover_text("Ctrl+N");
+          if response.clicked() {
+            self.find_nearest();
+          }
+        });
+
+        ui.horizontal(|ui| {
+          let enabled = matches!(self.chart, Chart::Ready(_));
+          let button = egui::Button::new("Print / Export PDF…");
+          if ui.add_enabled(enabled, button).clicked() {
+            self.open_export_dlg();
+          }
+        });
+
+        if self.edit_bounds {
+          ui.horizontal(|ui| {
+            let enabled = matches!(self.chart, Chart::Ready(_));
+            let button = egui::Button::new("Edit Bounds");
+            let response = ui.add_enabled(enabled, button);
+            let response = response.on_hover_text("Set the area searched for airports");
+            if response.clicked() {
+              self.region_select = RegionSelect::Waiting(RegionUse::Bounds);
+            }
+
+            let enabled = self
+              .get_chart()
+              .is_some_and(|chart| self.config.get_chart_bounds(&chart.name).is_some());
+            let button = egui::Button::new("Reset");
+            if ui.add_enabled(enabled, button).clicked() {
+              self.set_chart_bounds(None);
+            }
+          });
+        }
+
+        ui.horizontal(|ui| {
+          let button = egui::Button::new("Diagnostics");
+          if ui.add_sized(ui.available_size(), button).clicked() {
+            let mut info = diag_dlg::DiagnosticsInfo::default();
+            if let Some(chart) = self.get_chart() {
+              info.add_chart(&chart.name, &chart.reader);
+            }
+
+            if let Some(nasr_reader) = &self.airport_reader {
+              info.add_nasr(nasr_reader, self.airspace_path.as_deref());
+            }
+
+            let budget = &self.texture_budget;
+            info.add_textures(budget.total(), budget.cap());
+
+            self.diag_dlg = Some(diag_dlg::DiagDlg::open(info));
+          }
+        });
+
+        ui.horizontal(|ui| {
+          let button = egui::Button::new("Event Log");
+          if ui.add_sized(ui.available_size(), button).clicked() {
+            self.log_dlg = Some(log_dlg::LogDlg::open());
+          }
+        });
+
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.separator();
+
+        egui::CollapsingHeader::new("Input").show(ui, |ui| {
+          ui.horizontal(|ui| {
+            let response = ui
+              .checkbox(&mut self.invert_zoom, "Invert Wheel Zoom")
+              .on_hover_text("Reverse the scroll wheel and touchpad zoom direction");
+            if response.clicked() {
+              self.config.set_invert_zoom(self.invert_zoom);
+            }
+          });
+
+          let range = config::Storage::ZOOM_SENSITIVITY;
+          ui.horizontal(|ui| {
+            ui.label("Wheel Zoom");
+            let slider = egui::Slider::new(&mut self.wheel_zoom, range.clone())
+              .logarithmic(true)
+              .custom_formatter(|val, _| format!("{val:.2}×"));
+            if ui.add(slider).changed() {
+              self.config.set_wheel_zoom(self.wheel_zoom);
+            }
+          });
+
+          ui.horizontal(|ui| {
+            ui.label("Pinch Zoom");
+            let slider = egui::Slider::new(&mut self.pinch_zoom, range)
+              .logarithmic(true)
+              .custom_formatter(|val, _| format!("{val:.2}×"));
+            if ui.add(slider).changed() {
+              self.config.set_pinch_zoom(self.pinch_zoom);
+            }
+          });
+        });
+
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.separator();
+
+        egui::CollapsingHeader::new("Chart Catalog").show(ui, |ui| {
+          if let Some(catalog) = &self.catalog {
+            let folder = catalog.folder().to_string_lossy();
+            ui.label(egui::RichText::new(folder).weak())
+              .on_hover_text("Folder searched for charts that cover a coordinate");
+
+            match catalog.progress() {
+              Some((done, total)) => {
+                ui.horizontal(|ui| {
+                  ui.spinner();
+                  if total > 0 {
+                    ui.label(format!("Scanning {done} of {total}"));
+                  } else {
+                    ui.label("Scanning");
+                  }
+                });
+              }
+              None => {
+                ui.label(format!("{} charts", catalog.chart_count()));
+              }
+            }
+          }
+
+          ui.horizontal(|ui| {
+            if ui.button("Chart Folder…").clicked() {
+              self.select_catalog_folder();
+            }
+
+            let folder = self.catalog.as_ref().and_then(|catalog| {
+              let done = catalog.progress().is_none();
+              done.then(|| catalog.folder().to_owned())
+            });
+
+            let button = egui::Button::new("Rescan");
+            if ui.add_enabled(folder.is_some(), button).clicked() {
+              if let Some(folder) = folder {
+                self.catalog = Some(chart::catalog::Catalog::scan(folder, ui.ctx()));
+              }
+            }
+          });
+        });
+
+        ui.add_space(ui.spacing().item_spacing.y);
+        ui.separator();
+
+        egui::CollapsingHeader::new("Bookmarks").show(ui, |ui| {
+          if self.bookmarks.is_empty() {
+            ui.label(egui::RichText::new("Right-click the chart to add").weak());
+          }
+
+          let mut action = None;
+          let count = self.bookmarks.len();
+          for (index, bookmark) in self.bookmarks.iter().enumerate() {
+            ui.horizontal(|ui| {
+              if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                action = Some(BookmarkAction::Delete(index));
+              }
 
-            // Zoom-out button.
-            let min_zoom = chart.get_min_zoom();
-            ui.add_enabled_ui(chart.zoom > min_zoom, |ui| {
-              if let Some(font_id) = ui.style().text_styles.get(&egui::TextStyle::Monospace) {
-                let text = egui::RichText::new("-").font(font_id.clone());
-                let widget = egui::Button::new(text);
-                if ui.add_sized([21.0, 21.0], widget).clicked() {
-                  let new_zoom = (chart.zoom * 0.5).max(min_zoom);
-                  if new_zoom != chart.zoom {
-                    chart.scroll = Some(chart.get_zoom_pos(new_zoom).round());
-                    chart.zoom = new_zoom;
-                  }
+              if ui.small_button("✏").on_hover_text("Rename").clicked() {
+                action = Some(BookmarkAction::Rename(index));
+              }
+
+              ui.add_enabled_ui(index > 0, |ui| {
+                if ui.small_button("⏶").on_hover_text("Move up").clicked() {
+                  action = Some(BookmarkAction::Swap(index - 1, index));
+                }
+              });
+
+              ui.add_enabled_ui(index + 1 < count, |ui| {
+                if ui.small_button("⏷").on_hover_text("Move down").clicked() {
+                  action = Some(BookmarkAction::Swap(index, index + 1));
                 }
+              });
+
+              if ui.link(&bookmark.name).clicked() {
+                action = Some(BookmarkAction::Goto(index));
               }
             });
-          });
-        }
-      });
-    });
-
-    if self.side_panel {
-      self.side_panel_width = side_panel(self.side_panel_width, ctx, |ui| {
-        ui.set_enabled(self.ui_enabled);
+          }
 
-        ui.horizontal(|ui| {
-          let button = egui::Button::new("Open Zip File");
-          if ui.add_sized(ui.available_size(), button).clicked() {
-            self.select_zip_file();
+          match action {
+            Some(BookmarkAction::Goto(index)) => {
+              let bookmark = self.bookmarks[index].clone();
+              if self.goto_coord(bookmark.coord) {
+                self.flight_log.add(bookmark.name);
+              } else {
+                self.offer_covering_chart(bookmark.coord, &bookmark.name);
+              }
+            }
+            Some(BookmarkAction::Rename(index)) => {
+              let name = self.bookmarks[index].name.clone();
+              let dlg = name_dlg::NameDlg::open("Rename Bookmark", name);
+              self.bookmark_dlg = Some((BookmarkEdit::Rename(index), dlg));
+            }
+            Some(BookmarkAction::Swap(a, b)) => {
+              self.bookmarks.swap(a, b);
+              self.config.set_bookmarks(&self.bookmarks);
+            }
+            Some(BookmarkAction::Delete(index)) => {
+              self.bookmarks.remove(index);
+              self.config.set_bookmarks(&self.bookmarks);
+            }
+            None => (),
           }
         });
 
         ui.add_space(ui.spacing().item_spacing.y);
         ui.separator();
 
-        ui.horizontal(|ui| {
-          let mut night_mode = self.night_mode;
-          if ui.checkbox(&mut night_mode, "Night Mode").clicked() {
-            self.set_night_mode(ctx, night_mode);
+        egui::CollapsingHeader::new("Flight Log").show(ui, |ui| {
+          if self.flight_log.is_empty() {
+            ui.label(egui::RichText::new("Nothing yet").weak());
+          } else {
+            egui::ScrollArea::vertical()
+              .max_height(200.0)
+              .show(ui, |ui| {
+                for entry in self.flight_log.entries() {
+                  ui.label(format!("{}  {}", entry.time_text(), entry.text));
+                }
+              });
           }
+
+          ui.horizontal(|ui| {
+            ui.add_enabled_ui(!self.flight_log.is_empty(), |ui| {
+              if ui.button("Clear").clicked() {
+                self.flight_log.clear();
+              }
+
+              let response = ui.button("Export");
+              if response
+                .on_hover_text("Copy the log to the clipboard")
+                .clicked()
+              {
+                let text = self.flight_log.to_text();
+                ctx.output_mut(|state| state.copied_text = text);
+              }
+            });
+          });
         });
       });
     }
@@ -656,14 +3446,29 @@ impl eframe::App for App {
     central_panel(ctx, self.side_panel, |ui| {
       ui.set_enabled(self.ui_enabled);
       if let Some(reader) = self.get_chart_reader() {
+        // User input on the chart cancels the go-to animation.
+        let rect = ui.max_rect();
+        let pressed = ctx.input(|state| {
+          let pointer = &state.pointer;
+          pointer.any_pressed() && pointer.press_origin().is_some_and(|pos| rect.contains(pos))
+        });
+        if events.zoom_pos.is_some() || pressed {
+          self.goto_anim = None;
+        }
+
+        self.step_goto_anim(ctx);
+
         let zoom = self.get_chart_zoom().unwrap();
+        self.recenter_chart(ui.max_rect().size());
         let scroll = self.take_chart_scroll();
+        let selecting = !matches!(self.region_select, RegionSelect::None);
         let widget = if let Some(pos) = &scroll {
           egui::ScrollArea::both().scroll_offset(pos.to_vec2())
         } else {
           egui::ScrollArea::both()
         }
-        .scroll_bar_visibility(scroll_area::ScrollBarVisibility::AlwaysVisible);
+        .scroll_bar_visibility(scroll_area::ScrollBarVisibility::AlwaysVisible)
+        .drag_to_scroll(!selecting);
 
         ui.spacing_mut().scroll.bar_inner_margin = 0.0;
 
@@ -677,7 +3482,11 @@ impl eframe::App for App {
           ui.allocate_rect(rect, egui::Sense::hover());
 
           // Place the image.
-          if let Some((part, texture)) = self.get_chart_texture() {
+          let alt = self
+            .get_chart()
+            .and_then(ChartInfo::get_alt_texture)
+            .filter(|_| self.palette_preview);
+          if let Some((part, texture)) = alt.or_else(|| self.get_chart_texture()) {
             let scale = zoom * part.zoom.inverse();
             let rect = util::scale_rect(part.rect.into(), scale);
             let rect = rect.translate(cursor_pos.to_vec2());
@@ -688,8 +3497,132 @@ impl eframe::App for App {
               ui.image((texture.id(), rect.size()));
             });
           }
+
+          // Blend the terrain shading over the chart.
+          let terrain = self
+            .terrain
+            .as_ref()
+            .and_then(|terrain| terrain.texture.as_ref());
+          if let Some((part, texture)) = terrain {
+            let scale = zoom * part.zoom.inverse();
+            let rect = util::scale_rect(part.rect.into(), scale);
+            let rect = rect.translate(cursor_pos.to_vec2());
+            let mut clip = ui.clip_rect();
+            clip.max -= emath::Vec2::splat(ui.spacing().scroll.bar_width * 0.5);
+            let uv = emath::Rect::from_min_max(emath::pos2(0.0, 0.0), emath::pos2(1.0, 1.0));
+            let tint = epaint::Color32::from_white_alpha((self.terrain_opacity * 255.0) as u8);
+            ui.painter()
+              .with_clip_rect(clip)
+              .image(texture.id(), rect, uv, tint);
+          }
         });
 
+        if !self.bookmarks.is_empty() {
+          let offset = response.state.offset;
+          draw_bookmarks(
+            ui,
+            response.inner_rect,
+            reader.transform(),
+            offset,
+            zoom,
+            &self.bookmarks,
+          );
+        }
+
+        if self.graticule {
+          let offset = response.state.offset;
+          graticule::draw(ui, response.inner_rect, reader.transform(), offset, zoom);
+        }
+
+        if let Some(bounds) = self.get_search_bounds().filter(|_| self.show_bounds) {
+          let offset = response.state.offset;
+          draw_bounds(
+            ui,
+            response.inner_rect,
+            reader.transform(),
+            offset,
+            zoom,
+            &bounds,
+          );
+        }
+
+        if self.runway_ticks && zoom >= RunwayTickArea::MIN_ZOOM {
+          let offset = response.state.offset;
+          self.request_runway_ticks(offset, response.inner_rect.size(), zoom);
+          if let Some(area) = &self.runway_tick_area {
+            draw_runway_ticks(
+              ui,
+              response.inner_rect,
+              reader.transform(),
+              offset,
+              zoom,
+              &area.ticks,
+            );
+          }
+        }
+
+        if selecting {
+          let offset = response.state.offset;
+          self.select_region(ui, response.inner_rect, offset, zoom);
+        }
+
+        // Show the area that was searched for nearby airports.
+        if let Some(search) = self
+          .nearby_search
+          .as_ref()
+          .filter(|_| self.airport_menu_open())
+        {
+          let offset = response.state.offset;
+          let mpp = reader.transform().meters_per_pixel();
+          draw_search_radius(ui, response.inner_rect, offset, zoom, search, mpp);
+        }
+
+        // Show the highlighted location.
+        if let Some(highlight) = &self.highlight {
+          let remaining = highlight.until - ctx.input(|state| state.time);
+          if remaining > 0.0 {
+            let offset = response.state.offset;
+            draw_highlight(
+              ui,
+              response.inner_rect,
+              offset,
+              zoom,
+              highlight.px,
+              remaining,
+            );
+            ctx.request_repaint();
+          } else {
+            self.highlight = None;
+          }
+        }
+
+        if self.scale_bar {
+          let meters_per_px = reader.transform().meters_per_pixel() / zoom as f64;
+          draw_scale_bar(ui, response.inner_rect, meters_per_px, self.units);
+        }
+
+        if self.overview {
+          let offset = response.state.offset;
+          if let Some(pos) = self.show_overview(ui, response.inner_rect, offset, zoom) {
+            self.goto_anim = None;
+            self.push_view_history();
+            self.set_chart_scroll(pos);
+            ctx.request_repaint();
+          }
+        }
+
+        // Show a loading indicator while the first image is read or when a read is slow.
+        if let Some(chart) = self.get_chart() {
+          let elapsed = chart.request_time.map(|time| time.elapsed());
+          let loading = chart.texture.is_none() && chart.errors.is_empty();
+          if loading || elapsed.is_some_and(|elapsed| elapsed >= LOADING_DELAY) {
+            draw_loading(ui, response.inner_rect);
+            ctx.request_repaint_after(time::Duration::from_millis(100));
+          } else if let Some(elapsed) = elapsed {
+            ctx.request_repaint_after(LOADING_DELAY - elapsed);
+          }
+        }
+
         // Set a new display rectangle.
         let pos = response.state.offset;
         let display_rect = util::Rect {
@@ -698,6 +3631,38 @@ impl eframe::App for App {
         };
         self.set_chart_disp_rect(display_rect);
 
+        // Read the chart pixel under the pointer for the palette inspector.
+        if let Some(palette_dlg) = &mut self.palette_dlg {
+          let hover = ctx.input(|state| state.pointer.hover_pos());
+          if let Some(hover) = hover.filter(|_| ui.rect_contains_pointer(response.inner_rect)) {
+            let px = (hover - response.inner_rect.min + pos) / zoom;
+            let px = util::Pos {
+              x: px.x.floor() as i32,
+              y: px.y.floor() as i32,
+            };
+            if palette_dlg.set_pointer(px) {
+              reader.read_pixel(px);
+            }
+          }
+        }
+
+        // Track where the view comes to rest for the view history.
+        let dragging = ctx.input(|state| state.pointer.any_down());
+        let moving = response.state.velocity() != emath::Vec2::ZERO;
+        if scroll.is_none() && self.goto_anim.is_none() && !dragging && !moving {
+          if let Chart::Ready(chart) = &mut self.chart {
+            let state = chart.view_state();
+            chart.history.settle(state, display_rect.size.into());
+          }
+        }
+
+        // Go to the coordinate that the chart was opened for.
+        if let Some(coord) = self.pending_goto.take() {
+          if self.goto_coord(coord) {
+            self.set_highlight(ctx, coord);
+          }
+        }
+
         // Make sure the image position lands on an even pixel.
         if response.state.velocity() == emath::vec2(0.0, 0.0) {
           let floored = pos.floor();
@@ -709,13 +3674,36 @@ impl eframe::App for App {
         // Get the minimum zoom.
         let min_zoom = self.get_chart().unwrap().get_min_zoom();
 
+        // Throttle image requests while animating or coasting after a drag. The scroll area
+        // stops coasting at the edges, and a request is always made once it comes to rest.
+        let now = ctx.input(|state| state.time);
+        let mut due = self
+          .goto_anim
+          .as_mut()
+          .is_none_or(|anim| anim.request_due(now));
+
+        let moving = response.state.velocity() != emath::Vec2::ZERO;
+        if moving && !ctx.input(|state| state.pointer.any_down()) {
+          if let Chart::Ready(chart) = &mut self.chart {
+            due = due && chart.coast_request_due(now);
+          }
+        }
+
         if let Some((part, _)) = self.get_chart_texture() {
           // Make sure the zoom is not below the minimum.
           let request_zoom = zoom.max(min_zoom);
 
+          // When magnified, read the chart at full resolution and let the texture scale it up.
+          let (rect, read_zoom) = if request_zoom > 1.0 {
+            let size = self.get_chart().unwrap().reader.transform().px_size();
+            (util::magnified_rect(display_rect, request_zoom, size), 1.0)
+          } else {
+            (display_rect, request_zoom)
+          };
+
           // Request a new image if needed.
-          if part.rect != display_rect || part.zoom != request_zoom.into() {
-            self.request_image(display_rect, request_zoom);
+          if due && (part.rect != rect || part.zoom != read_zoom.into()) {
+            self.request_image(rect, read_zoom);
           }
 
           if request_zoom != zoom {
@@ -730,15 +3718,26 @@ impl eframe::App for App {
         if let Some(zoom_pos) = events.zoom_pos {
           if response.inner_rect.contains(zoom_pos) {
             let new_zoom = zoom * events.zoom_mod;
-            if new_zoom != zoom {
+            if new_zoom != zoom || events.pan != emath::Vec2::ZERO {
               // Correct and set the new zoom value.
-              let new_zoom = new_zoom.clamp(min_zoom, 1.0);
+              let new_zoom = new_zoom.clamp(min_zoom, MAX_ZOOM);
               self.set_chart_zoom(new_zoom);
 
-              // Attempt to keep the point under the mouse cursor the same.
-              let zoom_pos = zoom_pos - response.inner_rect.min;
-              let pos = (pos + zoom_pos) * new_zoom / zoom - zoom_pos;
-              self.set_chart_scroll(pos.to_pos2().round());
+              // Attempt to keep the point under the mouse cursor (or touch centroid) the same.
+              let min = response.inner_rect.min;
+              let pos = if let Some(pinch) = &events.pinch {
+                // Use the chart point that was under the touch centroid when the gesture started.
+                // The scroll area has already been dragged by the primary touch, so undo that.
+                let anchor = self.get_pinch_anchor(pinch.begin, || {
+                  touch::PinchAnchor::new(pos + events.drag, pinch.anchor - min, zoom)
+                });
+                anchor.offset(pinch.center - min, new_zoom)
+              } else {
+                let pos = self.get_zoom_origin(pos);
+                let zoom_pos = zoom_pos - min;
+                (pos + zoom_pos) * new_zoom / zoom - zoom_pos
+              };
+              self.set_zoom_scroll(pos);
 
               ctx.request_repaint();
             }
@@ -754,20 +3753,32 @@ impl eframe::App for App {
               let lat = util::format_lat(nad83.y).unwrap();
               let lon = util::format_lon(nad83.x).unwrap();
               self.select_menu.set_pos(click_pos);
-              self.airport_infos = AirportInfos::Menu(format!("{lat}, {lon}"), None);
-              if let Some(nasr_reader) = &self.airport_reader {
-                if nasr_reader.airport_spatial_idx() {
-                  // 1/2 nautical mile (926 meters) is the search radius at 1.0x zoom.
-                  let radius = 926.0 / zoom as f64;
-                  nasr_reader.nearby(lcc, radius, self.include_nph);
-                }
-              }
+              self.airport_infos = AirportInfos::Menu(nad83, format!("{lat}, {lon}"), None);
+
+              // The search radius is set for 1.0x zoom.
+              let radius = self.nearby_radius as f64 * util::METERS_PER_NM / zoom as f64;
+              self.search_nearby(pos.to_pos2(), lcc, radius, zoom);
             }
           }
         }
+      } else if matches!(self.chart, Chart::Opening(_)) {
+        draw_loading(ui, ui.max_rect());
+        ctx.request_repaint_after(time::Duration::from_millis(100));
       }
     });
 
+    // Reopen the last session after the first frame so that dialogs have a window.
+    if let Some(session) = self.session.take() {
+      self.open_session(ctx, session);
+      ctx.request_repaint();
+    }
+
+    // Open the zip file from the command line. Invalid files show the error dialog.
+    if let Some(path) = self.startup_path.take() {
+      self.open_zip_file(ctx, path);
+      ctx.request_repaint();
+    }
+
     if events.quit {
       ctx.send_viewport_cmd(egui::ViewportCommand::Close);
     }
@@ -794,49 +3805,253 @@ impl eframe::App for App {
   }
 }
 
+enum BookmarkAction {
+  Goto(usize),
+  Rename(usize),
+  Swap(usize, usize),
+  Delete(usize),
+}
+
+enum BookmarkEdit {
+  Add(util::Coord),
+  Rename(usize),
+}
+
 enum AirportInfos {
   None,
-  Menu(String, Option<Vec<nasr::AirportInfo>>),
-  Dialog(Vec<nasr::AirportInfo>),
+  Menu(util::Coord, String, Option<Vec<nasr::Facility>>),
+  /// Facilities to choose from, whether the first one is an ID match, and the search term and
+  /// total number of matches if there are more.
+  Dialog(Vec<nasr::Facility>, bool, Option<(String, usize)>),
 }
 
 struct InputEvents {
   zoom_mod: f32,
   zoom_pos: Option<emath::Pos2>,
+  pan: emath::Vec2,
+  drag: emath::Vec2,
+  pinch: Option<touch::Gesture>,
   secondary_click: Option<emath::Pos2>,
   quit: bool,
 }
 
 impl InputEvents {
-  fn new(ctx: &egui::Context) -> Self {
-    // Init zoom with multi-touch if available.
-    let (zoom_mod, zoom_pos) = if let Some(multi_touch) = ctx.multi_touch() {
-      (multi_touch.zoom_delta, Some(multi_touch.start_pos))
-    } else {
-      (1.0, None)
-    };
-
+  fn new() -> Self {
     Self {
-      zoom_mod,
-      zoom_pos,
+      zoom_mod: 1.0,
+      zoom_pos: None,
+      pan: emath::Vec2::ZERO,
+      drag: emath::Vec2::ZERO,
+      pinch: None,
       secondary_click: None,
       quit: false,
     }
   }
 }
 
+/// Temporary marker for a location on the chart.
+struct Highlight {
+  /// Location in chart pixels.
+  px: emath::Pos2,
+
+  /// Time when the marker is removed.
+  until: f64,
+}
+
+impl Highlight {
+  /// How long the marker is shown, in seconds.
+  const DURATION: f64 = 5.0;
+}
+
+/// Files to reopen from the last session.
+struct Session {
+  /// Chart zip file and the chart files within it.
+  chart: Option<(path::PathBuf, Vec<path::PathBuf>)>,
+
+  /// NASR zip file.
+  nasr: Option<path::PathBuf>,
+}
+
+/// Runway ticks for an area around the chart view.
+struct RunwayTickArea {
+  /// Requested area in chart coordinates.
+  bounds: util::Bounds,
+
+  /// Generation of the request.
+  generation: u64,
+
+  /// Ticks from the last reply.
+  ticks: Vec<nasr::RunwayTick>,
+}
+
+impl RunwayTickArea {
+  /// Extra area requested on each side of the view, as a fraction of the view size. The ticks
+  /// are requested again once the view has moved by more than this.
+  const MARGIN: f64 = 0.25;
+
+  /// Minimum chart zoom for showing the ticks.
+  const MIN_ZOOM: f32 = 0.5;
+}
+
+/// Nearby airport search from the select menu.
+struct NearbySearch {
+  /// Searched position in chart pixels.
+  px: emath::Pos2,
+
+  /// Searched position in chart coordinates.
+  lcc: util::Coord,
+
+  /// Search radius in meters.
+  radius: f64,
+
+  /// Chart zoom that the radius was set for.
+  zoom: f32,
+
+  /// Generation of the request.
+  generation: u64,
+
+  /// Changed zoom and the time (in seconds) when it was first seen.
+  settle: Option<(f32, f64)>,
+}
+
+impl NearbySearch {
+  /// Relative zoom change needed to search again.
+  const ZOOM_CHANGE: f32 = 0.1;
+
+  /// How long (in seconds) the zoom must hold steady before searching again.
+  const SETTLE_TIME: f64 = 0.3;
+}
+
+/// Selecting a region on the chart.
+enum RegionSelect {
+  None,
+  Waiting(RegionUse),
+
+  /// Dragging from this chart pixel position.
+  Dragging(RegionUse, emath::Pos2),
+}
+
+/// What a selected region is for.
+#[derive(Clone, Copy, PartialEq)]
+enum RegionUse {
+  /// Region to export.
+  Export,
+
+  /// Bounds used for airport searches.
+  Bounds,
+}
+
 const MIN_ZOOM: f32 = 1.0 / 8.0;
+const MAX_ZOOM: f32 = 2.0;
+
+/// Ground distance across the view when zooming to an airport (10 NM), about the size of a
+/// traffic pattern and its surroundings.
+const PATTERN_SPAN: f64 = 10.0 * util::METERS_PER_NM;
+const ZOOM_STEP: f32 = std::f32::consts::SQRT_2;
+
+/// How long the window has to stay put before its size and position are saved.
+const WIN_INFO_DELAY: time::Duration = time::Duration::from_secs(3);
+
+/// Maximum number of airport replies handled in one frame.
+const AIRPORT_REPLY_BUDGET: usize = 8;
+
+/// How long an image request can be outstanding before the loading indicator is shown.
+const LOADING_DELAY: time::Duration = time::Duration::from_millis(300);
+
+/// Minimum time (in seconds) between image requests while the chart coasts after a drag.
+const COAST_REQUEST_INTERVAL: f64 = 0.08;
+
+/// Width of the chart thumbnail that's read for the overview inset.
+const OVERVIEW_READ_WIDTH: u32 = 256;
+
+/// Maximum width or height of the overview inset.
+const OVERVIEW_SIZE: f32 = 160.0;
+
+/// Terrain shading layer.
+struct Terrain {
+  reader: terrain::TerrainReader,
+  texture: Option<(chart::ImagePart, egui::TextureHandle)>,
+}
 
 struct ChartInfo {
   name: String,
   reader: rc::Rc<chart::RasterReader>,
   texture: Option<(chart::ImagePart, egui::TextureHandle)>,
+
+  /// Image in the other palette for the palette preview.
+  alt_texture: Option<(chart::ImagePart, egui::TextureHandle)>,
+
+  /// Last image part requested for the palette preview.
+  alt_request: Option<chart::ImagePart>,
   disp_rect: util::Rect,
+
+  /// Zoom when the display rectangle was last set.
+  disp_zoom: f32,
   scroll: Option<emath::Pos2>,
+
+  /// Sub-pixel part of the scroll offset lost when recentering.
+  scroll_remainder: emath::Vec2,
+
+  /// Size of the chart panel on the previous frame.
+  panel_size: emath::Vec2,
+
+  /// Recenter the chart when the panel size changes.
+  recenter: bool,
+
+  /// Unrounded scroll offset set by the last zoom.
+  zoom_origin: Option<emath::Vec2>,
+
+  /// Chart point held under the touch centroid during a pinch gesture.
+  pinch: Option<touch::PinchAnchor>,
   zoom: f32,
+
+  /// Time when the outstanding image request was made.
+  request_time: Option<time::Instant>,
+
+  /// Time (in seconds) of the last image request while coasting after a drag.
+  coast_request: f64,
+
+  /// Reduced image of the whole chart for the overview inset.
+  thumbnail: Option<(chart::ImagePart, egui::TextureHandle)>,
+
+  /// Palette of the last thumbnail request.
+  thumbnail_request: Option<chart::Palette>,
+
+  /// Back and forward view history.
+  history: view_history::ViewHistory,
+  failures: collections::HashMap<chart::ImagePart, u32>,
+  errors: collections::HashSet<String>,
 }
 
 impl ChartInfo {
+  /// Get the palette preview image if it matches the current image.
+  fn get_alt_texture(&self) -> Option<&(chart::ImagePart, egui::TextureHandle)> {
+    let (part, _) = self.texture.as_ref()?;
+    self
+      .alt_texture
+      .as_ref()
+      .filter(|(alt, _)| alt.rect == part.rect && alt.zoom == part.zoom)
+  }
+
+  /// Check if a new image should be requested while the chart is coasting after a drag.
+  /// - `now`: current time in seconds
+  fn coast_request_due(&mut self, now: f64) -> bool {
+    if now - self.coast_request >= COAST_REQUEST_INTERVAL {
+      self.coast_request = now;
+      return true;
+    }
+    false
+  }
+
+  /// True if reading this part of the chart has failed too many times.
+  fn is_bad_part(&self, part: &chart::ImagePart) -> bool {
+    const MAX_FAILURES: u32 = 3;
+    self
+      .failures
+      .get(part)
+      .is_some_and(|count| *count >= MAX_FAILURES)
+  }
+
   fn get_min_zoom(&self) -> f32 {
     let chart_size: emath::Vec2 = self.reader.transform().px_size().into();
     let disp_size: emath::Vec2 = self.disp_rect.size.into();
@@ -845,6 +4060,16 @@ impl ChartInfo {
     sw.max(sh).max(MIN_ZOOM)
   }
 
+  /// Get the current view center and zoom.
+  fn view_state(&self) -> view_history::ViewState {
+    let pos: emath::Pos2 = self.disp_rect.pos.into();
+    let size: emath::Vec2 = self.disp_rect.size.into();
+    view_history::ViewState {
+      center: (pos + size * 0.5) / self.zoom,
+      zoom: self.zoom,
+    }
+  }
+
   fn get_zoom_pos(&self, zoom: f32) -> emath::Pos2 {
     let pos: emath::Pos2 = self.disp_rect.pos.into();
     let size: emath::Vec2 = self.disp_rect.size.into();
@@ -858,10 +4083,78 @@ impl ChartInfo {
 
 enum Chart {
   None,
-  Load(path::PathBuf, Vec<path::PathBuf>),
+  /// Chart zip file with several charts to choose from, and whether two of them can be combined.
+  Load(path::PathBuf, Vec<path::PathBuf>, bool),
+  Opening(Box<ChartOpen>),
   Ready(Box<ChartInfo>),
 }
 
+/// Chart that's being opened on the reader's thread.
+struct ChartOpen {
+  /// Chart zip file and the chart files within it.
+  path: path::PathBuf,
+  files: Vec<path::PathBuf>,
+  reader: chart::RasterReader,
+
+  /// Reopened from the last session, so a failure is only reported with a notice.
+  session: bool,
+}
+
+/// Check if a chart zip file holds exactly two charts that can be read as one.
+/// - `path`: path to the chart zip file
+/// - `files`: chart files within the zip file
+fn can_combine(path: &path::Path, files: &[path::PathBuf]) -> bool {
+  let [a, b] = files else {
+    return false;
+  };
+
+  let Ok(vsi_path) = util::vsi_path("/vsizip/", path) else {
+    return false;
+  };
+  chart::can_combine(&vsi_path.join(a), &vsi_path.join(b))
+}
+
+/// Store a chart or terrain image in a texture slot. The existing texture is updated in place when
+/// the image size hasn't changed (panning at a steady zoom), otherwise a new texture is allocated.
+/// - `ctx`: egui context
+/// - `slot`: texture slot to fill
+/// - `name`: debug name for a new texture
+/// - `part`: image part that the image was read for
+/// - `image`: image data
+/// - `options`: texture options
+fn store_texture(
+  ctx: &egui::Context,
+  slot: &mut Option<(chart::ImagePart, egui::TextureHandle)>,
+  name: &str,
+  part: chart::ImagePart,
+  image: epaint::ColorImage,
+  options: epaint::textures::TextureOptions,
+) {
+  if let Some((old_part, texture)) = slot {
+    if texture.size() == image.size {
+      texture.set(image, options);
+      *old_part = part;
+      return;
+    }
+  }
+
+  *slot = Some((part, ctx.load_texture(name, image, options)));
+}
+
+/// Text styles with their font sizes scaled.
+/// - `text_styles`: text styles at a font scale of one
+/// - `scale`: font scale
+fn scaled_text_styles(
+  text_styles: &collections::BTreeMap<egui::TextStyle, epaint::FontId>,
+  scale: f32,
+) -> collections::BTreeMap<egui::TextStyle, epaint::FontId> {
+  let iter = text_styles.iter().map(|(text_style, font_id)| {
+    let font_id = epaint::FontId::new(font_id.size * scale, font_id.family.clone());
+    (text_style.clone(), font_id)
+  });
+  iter.collect()
+}
+
 fn dark_theme() -> egui::Visuals {
   let mut visuals = egui::Visuals::dark();
   visuals.extreme_bg_color = epaint::Color32::from_gray(20);
@@ -924,6 +4217,313 @@ fn side_panel<R>(
   response.response.rect.width().ceil() as u32
 }
 
+/// Draw a pin at each bookmark that is on the chart.
+/// - `ui`: chart view UI
+/// - `rect`: visible chart rectangle
+/// - `transform`: chart transformation
+/// - `offset`: scroll offset of the chart view
+/// - `zoom`: current chart zoom
+/// - `bookmarks`: bookmarks to draw
+fn draw_bookmarks(
+  ui: &egui::Ui,
+  rect: emath::Rect,
+  transform: &chart::Transform,
+  offset: emath::Vec2,
+  zoom: f32,
+  bookmarks: &[util::Bookmark],
+) {
+  let painter = ui.painter_at(rect);
+  let font_id = egui::TextStyle::Small.resolve(ui.style());
+  let color = epaint::Color32::from_rgb(200, 30, 30);
+  let stroke = epaint::Stroke::new(2.0, color);
+  let chart_size = transform.px_size();
+  for bookmark in bookmarks {
+    let Ok(px) = transform.nad83_to_px(bookmark.coord) else {
+      continue;
+    };
+
+    if !chart_size.contains(px) {
+      continue;
+    }
+
+    // The pin's point is at the bookmark location.
+    let pos = rect.min + emath::Pos2::from(px).to_vec2() * zoom - offset;
+    if !rect.expand(20.0).contains(pos) {
+      continue;
+    }
+
+    let head = pos - emath::vec2(0.0, 14.0);
+    painter.line_segment([pos, head], stroke);
+    painter.circle(
+      head,
+      5.0,
+      color,
+      epaint::Stroke::new(1.0, epaint::Color32::WHITE),
+    );
+    painter.text(
+      head + emath::vec2(8.0, 0.0),
+      emath::Align2::LEFT_CENTER,
+      &bookmark.name,
+      font_id.clone(),
+      color,
+    );
+  }
+}
+
+/// Outline the chart bounds.
+/// - `ui`: chart view UI
+/// - `rect`: visible chart rectangle
+/// - `transform`: chart transformation
+/// - `offset`: scroll offset of the chart view
+/// - `zoom`: current chart zoom
+/// - `bounds`: bounds in chart coordinates
+fn draw_bounds(
+  ui: &egui::Ui,
+  rect: emath::Rect,
+  transform: &chart::Transform,
+  offset: emath::Vec2,
+  zoom: f32,
+  bounds: &util::Bounds,
+) {
+  let (min, max) = (bounds.min, bounds.max);
+  let corners = [
+    (min.x, min.y),
+    (max.x, min.y),
+    (max.x, max.y),
+    (min.x, max.y),
+  ];
+  let points = corners
+    .into_iter()
+    .map(|coord| {
+      let px = transform.chart_to_px(coord.into());
+      rect.min + emath::Pos2::from(px).to_vec2() * zoom - offset
+    })
+    .collect();
+
+  // The painter clips the outline to the chart view.
+  let color = epaint::Color32::from_rgb(0xE0, 0x00, 0xE0);
+  let shape = epaint::PathShape::closed_line(points, epaint::Stroke::new(2.0, color));
+  ui.painter_at(rect).add(shape);
+}
+
+/// Draw the runway orientation ticks over the airports.
+/// - `ui`: chart view UI
+/// - `rect`: visible chart rectangle
+/// - `transform`: chart transformation
+/// - `offset`: scroll offset of the chart view
+/// - `zoom`: current chart zoom
+/// - `ticks`: runway ticks
+fn draw_runway_ticks(
+  ui: &egui::Ui,
+  rect: emath::Rect,
+  transform: &chart::Transform,
+  offset: emath::Vec2,
+  zoom: f32,
+  ticks: &[nasr::RunwayTick],
+) {
+  let painter = ui.painter_at(rect);
+  let color = ui.visuals().selection.stroke.color;
+  for tick in ticks {
+    let [a, b] = tick.ends.map(|coord| {
+      let px = transform.chart_to_px(coord);
+      rect.min + emath::Pos2::from(px).to_vec2() * zoom - offset
+    });
+
+    // Soft surface runways are drawn fainter.
+    let color = if tick.hard {
+      color
+    } else {
+      color.gamma_multiply(0.5)
+    };
+    painter.line_segment([a, b], (3.0, color));
+  }
+}
+
+/// Draw the radius of a nearby search as a shaded circle around the searched position.
+/// - `ui`: chart view UI
+/// - `rect`: visible chart rectangle
+/// - `offset`: chart scroll offset
+/// - `zoom`: chart zoom
+/// - `search`: nearby search to draw
+/// - `meters_per_px`: meters per chart pixel
+fn draw_search_radius(
+  ui: &egui::Ui,
+  rect: emath::Rect,
+  offset: emath::Vec2,
+  zoom: f32,
+  search: &NearbySearch,
+  meters_per_px: f64,
+) {
+  let center = rect.min + search.px.to_vec2() * zoom - offset;
+  let radius = (search.radius / meters_per_px) as f32 * zoom;
+  let color = ui.visuals().selection.bg_fill;
+  let painter = ui.painter_at(rect);
+  painter.circle(center, radius, color.gamma_multiply(0.15), (1.5, color));
+}
+
+/// Zoom the chart about the center of the view.
+/// - `chart`: chart to zoom
+/// - `goto_anim`: animation to use, or `None` to zoom immediately
+/// - `factor`: zoom factor
+fn zoom_chart(
+  chart: &mut ChartInfo,
+  goto_anim: Option<&mut Option<goto_anim::GotoAnim>>,
+  factor: f32,
+) {
+  let min_zoom = chart.get_min_zoom();
+  let Some(goto_anim) = goto_anim else {
+    let new_zoom = step_zoom(chart.zoom, factor, min_zoom);
+    if new_zoom != chart.zoom {
+      chart.scroll = Some(chart.get_zoom_pos(new_zoom).round());
+      chart.zoom = new_zoom;
+    }
+    return;
+  };
+
+  // Continue from where a running animation is headed so that repeated clicks add up.
+  let pos: emath::Pos2 = chart.disp_rect.pos.into();
+  let size: emath::Vec2 = chart.disp_rect.size.into();
+  let center = (pos + size * 0.5) / chart.zoom;
+  let (target, zoom) = goto_anim
+    .as_ref()
+    .map_or((center, chart.zoom), |anim| anim.target());
+  let new_zoom = step_zoom(zoom, factor, min_zoom);
+  if new_zoom != zoom {
+    *goto_anim = Some(goto_anim::GotoAnim::zoom(
+      center, chart.zoom, target, new_zoom,
+    ));
+  }
+}
+
+/// Get the zoom after one zoom step, kept within the allowed range.
+/// - `zoom`: zoom to step from
+/// - `factor`: zoom factor
+/// - `min_zoom`: lowest allowed zoom
+fn step_zoom(zoom: f32, factor: f32, min_zoom: f32) -> f32 {
+  (zoom * factor).clamp(min_zoom, MAX_ZOOM)
+}
+
+fn draw_highlight(
+  ui: &egui::Ui,
+  rect: emath::Rect,
+  offset: emath::Vec2,
+  zoom: f32,
+  px: emath::Pos2,
+  remaining: f64,
+) {
+  // Pulse the ring and fade it out over the last second.
+  let center = rect.min + px.to_vec2() * zoom - offset;
+  let pulse = (remaining * std::f64::consts::TAU).sin() as f32;
+  let radius = 16.0 + 4.0 * pulse;
+  let alpha = remaining.min(1.0) as f32;
+  let color = ui.visuals().selection.stroke.color.gamma_multiply(alpha);
+  let painter = ui.painter_at(rect);
+  painter.circle_stroke(center, radius, (3.0, color));
+}
+
+/// Draw a "Loading chart" indicator in the middle of the chart view. The spinner only advances
+/// when something else repaints, so the caller decides how often that happens.
+fn draw_loading(ui: &egui::Ui, rect: emath::Rect) {
+  const RADIUS: f32 = 6.0;
+  let painter = ui.painter_at(rect);
+  let visuals = ui.visuals();
+  let font_id = egui::TextStyle::Body.resolve(ui.style());
+  let text = "Loading chart…";
+  let galley = painter.layout_no_wrap(text.into(), font_id, visuals.strong_text_color());
+  let width = RADIUS * 2.0 + 8.0 + galley.size().x;
+  let left = rect.center().x - width * 0.5;
+  let top = rect.center().y - galley.size().y * 0.5;
+  let back =
+    emath::Rect::from_min_size(emath::pos2(left, top), emath::vec2(width, galley.size().y));
+  painter.rect_filled(
+    back.expand(6.0),
+    3.0,
+    visuals.extreme_bg_color.gamma_multiply(0.8),
+  );
+
+  // Three quarters of a circle that turns once a second.
+  let center = emath::pos2(left + RADIUS, rect.center().y);
+  let start = ui.input(|state| state.time) as f32 * std::f32::consts::TAU;
+  let points = (0..=24)
+    .map(|index| {
+      let angle = start + index as f32 / 24.0 * std::f32::consts::TAU * 0.75;
+      center + RADIUS * emath::vec2(angle.cos(), angle.sin())
+    })
+    .collect();
+  let stroke = epaint::Stroke::new(2.0, visuals.strong_text_color());
+  painter.add(epaint::Shape::line(points, stroke));
+  painter.galley(
+    emath::pos2(left + RADIUS * 2.0 + 8.0, top),
+    galley,
+    visuals.strong_text_color(),
+  );
+}
+
+/// Draw a scale bar in the bottom-left corner of the chart view.
+/// - `ui`: chart view UI
+/// - `rect`: visible chart rectangle
+/// - `meters_per_px`: meters per screen pixel at the current zoom
+/// - `units`: distance units to show
+fn draw_scale_bar(ui: &egui::Ui, rect: emath::Rect, meters_per_px: f64, units: util::Units) {
+  const MIN_WIDTH: f64 = 80.0;
+  const NICE_LENGTHS: [f64; 10] = [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+  let units_per_px = units.distance(meters_per_px);
+  if units_per_px <= 0.0 {
+    return;
+  }
+
+  // Choose the shortest "nice" length that is at least the minimum width. Adjacent lengths differ
+  // by at most 2.5x, so the bar will be no wider than 200 pixels.
+  let Some(len) = NICE_LENGTHS
+    .into_iter()
+    .find(|len| len / units_per_px >= MIN_WIDTH)
+  else {
+    return;
+  };
+
+  let width = (len / units_per_px) as f32;
+  let visuals = ui.visuals();
+  let stroke = epaint::Stroke::new(2.0, visuals.strong_text_color());
+  let margin = 12.0;
+  let height = 6.0;
+  let left = rect.left() + margin;
+  let bottom = rect.bottom() - margin - ui.spacing().scroll.bar_width;
+  let right = left + width;
+
+  let painter = ui.painter_at(rect);
+  let label = format!("{len} {}", units.distance_abv());
+  let font_id = egui::TextStyle::Small.resolve(ui.style());
+  let galley = painter.layout_no_wrap(label, font_id, visuals.strong_text_color());
+  let back = emath::Rect::from_min_max(
+    emath::pos2(left - 4.0, bottom - height - galley.size().y - 6.0),
+    emath::pos2(right.max(left + galley.size().x) + 4.0, bottom + 4.0),
+  );
+  painter.rect_filled(back, 3.0, visuals.extreme_bg_color.gamma_multiply(0.8));
+  painter.line_segment(
+    [emath::pos2(left, bottom), emath::pos2(right, bottom)],
+    stroke,
+  );
+  painter.line_segment(
+    [
+      emath::pos2(left, bottom),
+      emath::pos2(left, bottom - height),
+    ],
+    stroke,
+  );
+  painter.line_segment(
+    [
+      emath::pos2(right, bottom),
+      emath::pos2(right, bottom - height),
+    ],
+    stroke,
+  );
+  painter.galley(
+    emath::pos2(left, bottom - height - galley.size().y - 2.0),
+    galley,
+    visuals.strong_text_color(),
+  );
+}
+
 fn central_panel<R>(ctx: &egui::Context, left: bool, contents: impl FnOnce(&mut egui::Ui) -> R) {
   let available = ctx.available_rect();
   let left = if left { 1.0 } else { 0.0 };
@@ -944,3 +4544,35 @@ fn central_panel<R>(ctx: &egui::Context, left: bool, contents: impl FnOnce(&mut
     contents(ui);
   });
 }
+
+#[cfg(test)]
+mod test {
+  use super::{step_zoom, MAX_ZOOM, MIN_ZOOM, ZOOM_STEP};
+
+  #[test]
+  fn test_step_zoom() {
+    // Two steps of the default size double the zoom.
+    let zoom = step_zoom(step_zoom(0.5, ZOOM_STEP, MIN_ZOOM), ZOOM_STEP, MIN_ZOOM);
+    assert!((zoom - 1.0).abs() < 1.0e-6);
+
+    // Stepping out is the inverse of stepping in.
+    let zoom = step_zoom(zoom, 1.5, MIN_ZOOM);
+    let zoom = step_zoom(zoom, 1.5_f32.recip(), MIN_ZOOM);
+    assert!((zoom - 1.0).abs() < 1.0e-6);
+
+    // Repeated steps accumulate until they reach the limits.
+    let mut zoom = 1.0;
+    for _ in 0..8 {
+      zoom = step_zoom(zoom, 1.25, MIN_ZOOM);
+    }
+    assert!(zoom == MAX_ZOOM);
+
+    for _ in 0..8 {
+      zoom = step_zoom(zoom, 0.5, 0.25);
+    }
+    assert!(zoom == 0.25);
+
+    // A chart that fills the view at a higher zoom raises the minimum.
+    assert!(step_zoom(0.5, 0.5, 0.4) == 0.4);
+  }
+}
@@ -0,0 +1,47 @@
+use eframe::{egui, emath};
+
+/// Non-modal question with yes, no and "don't ask again" choices.
+pub struct AskDlg {
+  text: String,
+}
+
+impl AskDlg {
+  pub fn open(text: String) -> Self {
+    Self { text }
+  }
+
+  pub fn show(&mut self, ctx: &egui::Context) -> Option<Response> {
+    let mut response = None;
+    egui::Window::new(egui::RichText::from("❓  Open").strong())
+      .collapsible(false)
+      .resizable(false)
+      .anchor(emath::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+      .show(ctx, |ui| {
+        ui.add_space(8.0);
+        ui.label(&self.text);
+        ui.add_space(8.0);
+        ui.separator();
+        ui.horizontal(|ui| {
+          if ui.button("Yes").clicked() {
+            response = Some(Response::Yes);
+          }
+
+          if ui.button("No").clicked() {
+            response = Some(Response::No);
+          }
+
+          if ui.button("Don't ask again").clicked() {
+            response = Some(Response::Never);
+          }
+        });
+      });
+    response
+  }
+}
+
+#[derive(Eq, PartialEq)]
+pub enum Response {
+  Yes,
+  No,
+  Never,
+}
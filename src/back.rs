@@ -0,0 +1,215 @@
+use eframe::egui;
+
+/// What the back action (the Escape key) does, highest priority first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BackAction {
+  /// Stop selecting a chart region.
+  StopSelect,
+
+  /// Close the airport menu.
+  CloseMenu,
+
+  /// Close the most recently opened window.
+  CloseWindow(&'static str),
+
+  /// Close the side panel.
+  CloseSidePanel,
+
+  /// Minimize the app instead of leaving it with nothing to close (mobile only).
+  Minimize,
+}
+
+/// What's showing when the back action happens.
+#[derive(Default)]
+pub struct BackState {
+  /// A chart region is being selected.
+  pub selecting: bool,
+
+  /// The airport menu is open.
+  pub menu: bool,
+
+  /// Most recently opened window that closes on the back action.
+  pub window: Option<&'static str>,
+
+  /// The side panel is visible.
+  pub side_panel: bool,
+
+  /// Running on a mobile device.
+  pub mobile: bool,
+}
+
+impl BackState {
+  /// Decide what the back action does. Returns `None` if there's nothing to do.
+  pub fn resolve(&self) -> Option<BackAction> {
+    if self.selecting {
+      return Some(BackAction::StopSelect);
+    }
+
+    if self.menu {
+      return Some(BackAction::CloseMenu);
+    }
+
+    if let Some(name) = self.window {
+      return Some(BackAction::CloseWindow(name));
+    }
+
+    if self.side_panel {
+      return Some(BackAction::CloseSidePanel);
+    }
+
+    self.mobile.then_some(BackAction::Minimize)
+  }
+}
+
+/// Register a window that closes on the back action. Windows call this every frame that they're
+/// shown, so they don't need to be listed anywhere else. Returns true if the window should close.
+/// - `ctx`: egui context
+/// - `name`: unique window name
+pub fn register(ctx: &egui::Context, name: &'static str) -> bool {
+  with_windows(ctx, |windows| windows.register(name))
+}
+
+/// Start tracking the windows for a new frame. Call this before any windows are shown.
+/// - `ctx`: egui context
+pub fn begin_frame(ctx: &egui::Context) {
+  with_windows(ctx, Windows::begin_frame);
+}
+
+/// Most recently opened window that's still showing.
+/// - `ctx`: egui context
+pub fn top_window(ctx: &egui::Context) -> Option<&'static str> {
+  with_windows(ctx, |windows| windows.top())
+}
+
+/// Ask a window to close. It closes the next time it registers.
+/// - `ctx`: egui context
+/// - `name`: window name
+pub fn close_window(ctx: &egui::Context, name: &'static str) {
+  with_windows(ctx, |windows| windows.close = Some(name));
+}
+
+/// Access the registered windows, which are kept in the egui context's temporary data.
+fn with_windows<R>(ctx: &egui::Context, f: impl FnOnce(&mut Windows) -> R) -> R {
+  let id = egui::Id::new("back_windows");
+  ctx.data_mut(|data| f(data.get_temp_mut_or_default(id)))
+}
+
+/// Windows that have registered for the back action.
+#[derive(Clone, Default)]
+struct Windows {
+  /// Windows shown during the last frame, in the order that they were opened.
+  shown: Vec<&'static str>,
+
+  /// Windows shown so far this frame.
+  showing: Vec<&'static str>,
+
+  /// Window that has been asked to close.
+  close: Option<&'static str>,
+}
+
+impl Windows {
+  fn register(&mut self, name: &'static str) -> bool {
+    if self.close == Some(name) {
+      self.close = None;
+      return true;
+    }
+
+    if !self.showing.contains(&name) {
+      self.showing.push(name);
+    }
+    false
+  }
+
+  fn begin_frame(&mut self) {
+    // Windows that are still showing keep their place, new ones go on top.
+    let showing = &self.showing;
+    let mut shown: Vec<&'static str> = self
+      .shown
+      .iter()
+      .copied()
+      .filter(|name| showing.contains(name))
+      .collect();
+    for name in showing {
+      if !shown.contains(name) {
+        shown.push(name);
+      }
+    }
+
+    // Forget a close request for a window that's gone.
+    if self.close.is_some_and(|name| !shown.contains(&name)) {
+      self.close = None;
+    }
+
+    self.shown = shown;
+    self.showing.clear();
+  }
+
+  fn top(&self) -> Option<&'static str> {
+    self.shown.last().copied()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{BackAction, BackState, Windows};
+
+  #[test]
+  fn test_resolve() {
+    let mut state = BackState {
+      selecting: true,
+      menu: true,
+      window: Some("info"),
+      side_panel: true,
+      mobile: true,
+    };
+    assert!(state.resolve() == Some(BackAction::StopSelect));
+
+    state.selecting = false;
+    assert!(state.resolve() == Some(BackAction::CloseMenu));
+
+    state.menu = false;
+    assert!(state.resolve() == Some(BackAction::CloseWindow("info")));
+
+    state.window = None;
+    assert!(state.resolve() == Some(BackAction::CloseSidePanel));
+
+    // Only mobile minimizes when there's nothing left to close.
+    state.side_panel = false;
+    assert!(state.resolve() == Some(BackAction::Minimize));
+
+    state.mobile = false;
+    assert!(state.resolve().is_none());
+  }
+
+  #[test]
+  fn test_windows() {
+    let mut windows = Windows::default();
+    windows.register("info");
+    windows.register("wind");
+    windows.begin_frame();
+    assert!(windows.top() == Some("wind"));
+
+    // Show order doesn't change the opening order.
+    windows.register("wind");
+    windows.register("info");
+    windows.register("remarks");
+    windows.begin_frame();
+    assert!(windows.top() == Some("remarks"));
+
+    // Closing the top window uncovers the one before it.
+    windows.close = windows.top();
+    assert!(!windows.register("wind") && !windows.register("info"));
+    assert!(windows.register("remarks"));
+    windows.begin_frame();
+    assert!(windows.top() == Some("wind"));
+
+    // A close request for a window that's gone is dropped.
+    windows.close = Some("wind");
+    windows.register("info");
+    windows.begin_frame();
+    assert!(windows.close.is_none() && windows.top() == Some("info"));
+
+    windows.begin_frame();
+    assert!(windows.top().is_none());
+  }
+}
@@ -0,0 +1,490 @@
+use crate::{chart, nasr, util};
+use eframe::egui;
+use std::{collections, fmt::Write, path, time};
+
+/// Run the benchmark: open a chart and NASR data without a window, time a fixed sequence of
+/// requests through the readers and print a table of the timings.
+/// - `chart_zip`: chart zip file
+/// - `nasr_zip`: NASR zip file
+pub fn run(chart_zip: &path::Path, nasr_zip: &path::Path) -> Result<(), util::Error> {
+  // The readers request repaints, which a context without a window ignores.
+  let ctx = egui::Context::default();
+  let mut timings = Vec::new();
+
+  let (chart_reader, open) = open_chart(chart_zip, &ctx)?;
+  timings.push(Timing::single("Chart open", open));
+  timings.extend(read_chart(&chart_reader)?);
+
+  // The airports are indexed for the whole chart.
+  let transform = chart_reader.transform();
+  let proj4 = transform.get_proj4();
+  let bounds = transform.bounds().clone();
+  let (airport_reader, open, index) = open_nasr(nasr_zip, proj4, bounds.clone(), &ctx)?;
+  timings.push(Timing::single("NASR open", open));
+  timings.push(Timing::single("NASR index", index));
+
+  let (nearby, airports) = nearby_queries(&airport_reader, &bounds)?;
+  timings.push(nearby);
+  timings.push(id_lookups(&airport_reader, &airports)?);
+  timings.push(name_searches(&airport_reader, &airports)?);
+
+  print!("{}", table(&timings));
+  Ok(())
+}
+
+/// Get the zip files for the benchmark from the command line arguments.
+/// - `args`: command line arguments (not including the program)
+///
+/// Returns `None` if the benchmark wasn't asked for.
+pub fn parse_args<I: Iterator<Item = String>>(
+  args: I,
+) -> Option<Result<(path::PathBuf, path::PathBuf), util::Error>> {
+  let mut benchmark = false;
+  let mut paths = Vec::new();
+  for arg in args {
+    match arg.as_str() {
+      "--benchmark" => benchmark = true,
+      _ if arg.starts_with("--") => (),
+      _ => paths.push(path::PathBuf::from(arg)),
+    }
+  }
+
+  if !benchmark {
+    return None;
+  }
+
+  Some(match <[path::PathBuf; 2]>::try_from(paths) {
+    Ok([chart, nasr]) => Ok((chart, nasr)),
+    Err(_) => Err(USAGE.into()),
+  })
+}
+
+/// Command line usage for the benchmark.
+const USAGE: &str = "Usage: aviate --benchmark <chart.zip> <nasr.zip>";
+
+/// Size of the simulated view, in pixels.
+const VIEW: util::Size = util::Size { w: 1280, h: 800 };
+
+/// Zoom levels read at the chart center after the panning reads.
+const ZOOM_LEVELS: [f32; 2] = [0.5, 0.25];
+
+/// Number of each kind of NASR request.
+const ID_LOOKUPS: usize = 100;
+const NAME_SEARCHES: usize = 20;
+
+/// The nearby queries are made at the centers of a grid over the chart.
+const NEARBY_GRID: (usize, usize) = (5, 4);
+
+/// Nearby query distance in meters (20 NM).
+const NEARBY_DIST: f64 = 20.0 * util::METERS_PER_NM;
+
+/// How long to wait for a chart read or NASR request.
+const REPLY_TIMEOUT: time::Duration = time::Duration::from_secs(60);
+
+/// How long to wait for a chart or NASR data to open and be indexed.
+const OPEN_TIMEOUT: time::Duration = time::Duration::from_secs(600);
+
+/// Times taken by one kind of operation.
+struct Timing {
+  name: &'static str,
+  times: Vec<time::Duration>,
+}
+
+impl Timing {
+  fn single(name: &'static str, time: time::Duration) -> Self {
+    Self {
+      name,
+      times: vec![time],
+    }
+  }
+}
+
+/// Open a chart and wait for it to be ready.
+/// - `zip`: chart zip file
+/// - `ctx`: egui context for the reader
+fn open_chart(
+  zip: &path::Path,
+  ctx: &egui::Context,
+) -> Result<(chart::RasterReader, time::Duration), util::Error> {
+  let util::ZipInfo::Chart(files) = util::get_zip_info(zip)? else {
+    let zip = zip.display();
+    return Err(format!("No chart found in {zip}").into());
+  };
+
+  let vsi_path = util::vsi_path("/vsizip/", zip)?;
+  let start = time::Instant::now();
+  let reader = match files.as_slice() {
+    [file] => chart::RasterReader::new(vsi_path.join(file), ctx),
+    [a, b] => chart::RasterReader::new_combined([vsi_path.join(a), vsi_path.join(b)], ctx),
+    _ => return Err("Unable to open chart: expected one or two files".into()),
+  };
+
+  wait_for(
+    "the chart to open",
+    OPEN_TIMEOUT,
+    |timeout| reader.wait_reply(timeout),
+    |reply| match reply {
+      chart::RasterReply::Opened => Ok(Some(())),
+      chart::RasterReply::OpenError(err) => Err(err),
+      _ => Ok(None),
+    },
+  )?;
+  Ok((reader, start.elapsed()))
+}
+
+/// Read the chart the way the view does: fitted to the view, panned at full size and zoomed out.
+/// - `reader`: opened chart reader
+fn read_chart(reader: &chart::RasterReader) -> Result<Vec<Timing>, util::Error> {
+  let size = reader.transform().px_size();
+  let (w, h) = (size.w as f32, size.h as f32);
+
+  // The whole chart, fitted to the view.
+  let fit = (VIEW.w as f32 / w).min(VIEW.h as f32 / h).min(1.0);
+  let fit_rect = util::Rect {
+    pos: util::Pos::default(),
+    size: util::Size {
+      w: ((w * fit) as u32).max(1),
+      h: ((h * fit) as u32).max(1),
+    },
+  };
+  let fit_time = read_part(reader, fit_rect, fit)?;
+
+  // Pans along the diagonal at full size.
+  let mut pans = Vec::new();
+  for step in 1..=3 {
+    let center = (w * step as f32 / 4.0, h * step as f32 / 4.0);
+    pans.push(read_part(reader, view_rect(center, size, 1.0), 1.0)?);
+  }
+
+  // Zoomed out at the center.
+  let mut zooms = Vec::new();
+  for zoom in ZOOM_LEVELS {
+    let center = (w * zoom / 2.0, h * zoom / 2.0);
+    zooms.push(read_part(reader, view_rect(center, size, zoom), zoom)?);
+  }
+
+  Ok(vec![
+    Timing::single("Chart read (fit)", fit_time),
+    Timing {
+      name: "Chart read (pan)",
+      times: pans,
+    },
+    Timing {
+      name: "Chart read (zoom)",
+      times: zooms,
+    },
+  ])
+}
+
+/// The part of a chart in a view.
+/// - `center`: view center in zoomed pixels
+/// - `size`: full size of the chart
+/// - `zoom`: zoom level
+fn view_rect(center: (f32, f32), size: util::Size, zoom: f32) -> util::Rect {
+  let rect = util::Rect {
+    pos: util::Pos {
+      x: center.0 as i32 - VIEW.w as i32 / 2,
+      y: center.1 as i32 - VIEW.h as i32 / 2,
+    },
+    size: VIEW,
+  };
+
+  let size = util::Size {
+    w: ((size.w as f32 * zoom) as u32).max(1),
+    h: ((size.h as f32 * zoom) as u32).max(1),
+  };
+  rect.fitted(size)
+}
+
+/// Read part of a chart and time how long the image takes to arrive.
+/// - `reader`: opened chart reader
+/// - `rect`: area to read, in zoomed pixels
+/// - `zoom`: zoom level
+fn read_part(
+  reader: &chart::RasterReader,
+  rect: util::Rect,
+  zoom: f32,
+) -> Result<time::Duration, util::Error> {
+  let part = chart::ImagePart::new(rect, zoom, chart::Palette::default());
+  let start = time::Instant::now();
+  reader.read_image(part.clone());
+  wait_for(
+    "a chart read",
+    REPLY_TIMEOUT,
+    |timeout| reader.wait_reply(timeout),
+    |reply| match reply {
+      chart::RasterReply::Image(read, _) if read == part => Ok(Some(())),
+      chart::RasterReply::Error(read, err) if read == part => Err(err),
+      _ => Ok(None),
+    },
+  )?;
+  Ok(start.elapsed())
+}
+
+/// Open NASR data and wait for it to be indexed for the chart.
+/// - `zip`: NASR zip file
+/// - `proj4`: chart spatial reference
+/// - `bounds`: chart bounds
+/// - `ctx`: egui context for the reader
+///
+/// Returns the reader with the open and index times.
+fn open_nasr(
+  zip: &path::Path,
+  proj4: String,
+  bounds: util::Bounds,
+  ctx: &egui::Context,
+) -> Result<(nasr::AirportReader, time::Duration, time::Duration), util::Error> {
+  let util::ZipInfo::Aero { csv, .. } = util::get_zip_info(zip)? else {
+    let zip = zip.display();
+    return Err(format!("No NASR data found in {zip}").into());
+  };
+
+  let vsi_path = csv.vsi_file(zip, "APT_BASE.csv")?;
+  let start = time::Instant::now();
+  let reader = nasr::AirportReader::new(vsi_path, ctx);
+  reader.set_spatial_ref(proj4, bounds);
+  wait_airport(
+    &reader,
+    "the NASR data to open",
+    OPEN_TIMEOUT,
+    |reply| match reply {
+      nasr::AirportReply::Opened => Some(()),
+      _ => None,
+    },
+  )?;
+
+  let open = start.elapsed();
+  let start = time::Instant::now();
+  wait_airport(
+    &reader,
+    "the NASR data to be indexed",
+    OPEN_TIMEOUT,
+    |reply| match reply {
+      nasr::AirportReply::Indexed(_) => Some(()),
+      _ => None,
+    },
+  )?;
+  Ok((reader, open, start.elapsed()))
+}
+
+/// Make nearby queries over the chart.
+/// - `reader`: indexed airport reader
+/// - `bounds`: chart bounds
+///
+/// Returns the timing and the airports that were found, sorted by ID.
+fn nearby_queries(
+  reader: &nasr::AirportReader,
+  bounds: &util::Bounds,
+) -> Result<(Timing, Vec<nasr::AirportInfo>), util::Error> {
+  let (cols, rows) = NEARBY_GRID;
+  let w = (bounds.max.x - bounds.min.x) / cols as f64;
+  let h = (bounds.max.y - bounds.min.y) / rows as f64;
+  let mut airports = collections::BTreeMap::new();
+  let mut times = Vec::new();
+  for index in 0..cols * rows {
+    let coord = util::Coord {
+      x: bounds.min.x + w * ((index % cols) as f64 + 0.5),
+      y: bounds.min.y + h * ((index / cols) as f64 + 0.5),
+    };
+
+    let generation = index as u64;
+    let start = time::Instant::now();
+    reader.nearby(generation, coord, NEARBY_DIST);
+    let found = wait_airport(
+      reader,
+      "a nearby query",
+      REPLY_TIMEOUT,
+      |reply| match reply {
+        nasr::AirportReply::Nearby(gen, found) if gen == generation => Some(found),
+        _ => None,
+      },
+    )?;
+    times.push(start.elapsed());
+
+    for facility in found {
+      if let nasr::Facility::Airport(info) = facility {
+        airports.insert(info.id.clone(), info);
+      }
+    }
+  }
+
+  if airports.is_empty() {
+    return Err("No airports were found on the chart".into());
+  }
+
+  let timing = Timing {
+    name: "NASR nearby",
+    times,
+  };
+  Ok((timing, airports.into_values().collect()))
+}
+
+/// Look up airports by ID.
+/// - `reader`: indexed airport reader
+/// - `airports`: airports to look up, repeated as needed
+fn id_lookups(
+  reader: &nasr::AirportReader,
+  airports: &[nasr::AirportInfo],
+) -> Result<Timing, util::Error> {
+  let mut times = Vec::new();
+  for info in airports.iter().cycle().take(ID_LOOKUPS) {
+    let start = time::Instant::now();
+    reader.airport(info.id.clone());
+    wait_airport(reader, "an ID lookup", REPLY_TIMEOUT, |reply| match reply {
+      nasr::AirportReply::Airport(found) if found.id == info.id => Some(()),
+      _ => None,
+    })?;
+    times.push(start.elapsed());
+  }
+
+  Ok(Timing {
+    name: "NASR ID lookup",
+    times,
+  })
+}
+
+/// Search for airports by name.
+/// - `reader`: indexed airport reader
+/// - `airports`: airports whose names are searched for, repeated as needed
+fn name_searches(
+  reader: &nasr::AirportReader,
+  airports: &[nasr::AirportInfo],
+) -> Result<Timing, util::Error> {
+  let mut times = Vec::new();
+  for (index, info) in airports.iter().cycle().take(NAME_SEARCHES).enumerate() {
+    let generation = index as u64;
+    let intent = nasr::SearchIntent::Goto;
+    let start = time::Instant::now();
+    reader.search(generation, info.name.clone(), false, false, intent);
+    wait_airport(
+      reader,
+      "a name search",
+      REPLY_TIMEOUT,
+      |reply| match reply {
+        nasr::AirportReply::Search(gen, _, _) if gen == generation => Some(()),
+        _ => None,
+      },
+    )?;
+    times.push(start.elapsed());
+  }
+
+  Ok(Timing {
+    name: "NASR name search",
+    times,
+  })
+}
+
+/// Wait for an airport reader reply. Error replies end the wait with the error.
+/// - `reader`: airport reader
+/// - `what`: what's being waited for, for the timeout error
+/// - `timeout`: how long to wait
+/// - `matched`: returns a value for the reply that's being waited for
+fn wait_airport<T>(
+  reader: &nasr::AirportReader,
+  what: &str,
+  timeout: time::Duration,
+  mut matched: impl FnMut(nasr::AirportReply) -> Option<T>,
+) -> Result<T, util::Error> {
+  wait_for(
+    what,
+    timeout,
+    |timeout| reader.wait_reply(timeout),
+    |reply| match reply {
+      nasr::AirportReply::OpenError(err) | nasr::AirportReply::Error(err) => Err(err),
+      reply => Ok(matched(reply)),
+    },
+  )
+}
+
+/// Wait for a reply, ignoring the replies that aren't for the current request.
+/// - `what`: what's being waited for, for the timeout error
+/// - `timeout`: how long to wait
+/// - `wait`: waits for the next reply
+/// - `matched`: returns a value for the reply that's being waited for
+fn wait_for<R, T>(
+  what: &str,
+  timeout: time::Duration,
+  wait: impl Fn(time::Duration) -> Option<R>,
+  mut matched: impl FnMut(R) -> Result<Option<T>, util::Error>,
+) -> Result<T, util::Error> {
+  let start = time::Instant::now();
+  loop {
+    let remaining = timeout.saturating_sub(start.elapsed());
+    let Some(reply) = wait(remaining) else {
+      return Err(format!("Timed out waiting for {what}").into());
+    };
+
+    if let Some(value) = matched(reply)? {
+      return Ok(value);
+    }
+  }
+}
+
+/// Nearest rank percentile.
+/// - `sorted`: sorted times
+/// - `pct`: percentile
+fn percentile(sorted: &[time::Duration], pct: usize) -> Option<time::Duration> {
+  let rank = (sorted.len() * pct).div_ceil(100);
+  sorted.get(rank.checked_sub(1)?).copied()
+}
+
+/// Format the timings as a table.
+/// - `timings`: timings in the order they were taken
+fn table(timings: &[Timing]) -> String {
+  let ms = |time: Option<time::Duration>| match time {
+    Some(time) => format!("{:.1} ms", time.as_secs_f64() * 1000.0),
+    None => String::from("-"),
+  };
+
+  let mut text = format!(
+    "{:<20}{:>6}{:>12}{:>12}\n",
+    "Operation", "Count", "p50", "p95"
+  );
+  for timing in timings {
+    let mut sorted = timing.times.clone();
+    sorted.sort_unstable();
+
+    let p50 = ms(percentile(&sorted, 50));
+    let p95 = ms(percentile(&sorted, 95));
+    let count = sorted.len();
+    writeln!(text, "{:<20}{count:>6}{p50:>12}{p95:>12}", timing.name).unwrap();
+  }
+  text
+}
+
+#[cfg(test)]
+mod test {
+  use super::{parse_args, percentile, table, Timing};
+  use std::time::Duration;
+
+  #[test]
+  fn test_parse_args() {
+    let args = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+    assert!(args(&["chart.zip"]).is_none());
+
+    let Some(Ok((chart, nasr))) = args(&["--benchmark", "chart.zip", "nasr.zip"]) else {
+      panic!("benchmark paths expected");
+    };
+    assert!(chart.to_str() == Some("chart.zip"));
+    assert!(nasr.to_str() == Some("nasr.zip"));
+
+    assert!(matches!(args(&["--benchmark", "chart.zip"]), Some(Err(_))));
+  }
+
+  #[test]
+  fn test_timing_table() {
+    let sorted: Vec<Duration> = (1..=20).map(Duration::from_millis).collect();
+    assert!(percentile(&sorted, 50) == Some(Duration::from_millis(10)));
+    assert!(percentile(&sorted, 95) == Some(Duration::from_millis(19)));
+    assert!(percentile(&[], 50).is_none());
+
+    let timing = Timing {
+      name: "Chart read (pan)",
+      times: vec![Duration::from_millis(30), Duration::from_micros(12_500)],
+    };
+    let text = table(&[timing]);
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines.len() == 2);
+    assert!(lines[1] == "Chart read (pan)         2     12.5 ms     30.0 ms");
+  }
+}
@@ -1,68 +1,109 @@
-use crate::util;
+pub mod catalog;
+pub mod edition;
+
+use crate::{coalesce, util};
 use eframe::{egui, epaint};
-use gdal::{raster, spatial_ref};
-use std::{any, path, sync::mpsc, thread};
+use gdal::{raster, spatial_ref, Metadata};
+use std::{
+  any, cell, collections, ffi, mem, ops, path,
+  sync::{self, mpsc},
+  thread, time,
+};
 
 /// RasterReader is used for opening and reading [VFR charts](https://www.faa.gov/air_traffic/flight_info/aeronav/digital_products/vfr/) in zipped GEO-TIFF format.
 pub struct RasterReader {
-  transform: Transform,
-  tx: mpsc::Sender<ImagePart>,
+  paths: Vec<path::PathBuf>,
+
+  /// Transformation and raster properties, set once the chart has been opened.
+  opened: cell::OnceCell<(Transform, RasterInfo)>,
+  orx: mpsc::Receiver<Result<(TransformParts, RasterInfo), util::Error>>,
+  tx: coalesce::Sender<ImagePart>,
   rx: mpsc::Receiver<RasterReply>,
+
+  /// Reply sender and egui context for thumbnail, palette preview and pixel reads.
+  ttx: mpsc::Sender<RasterReply>,
+  ctx: egui::Context,
+
+  /// Request sender for the palette preview's thread, started when first needed.
+  alt_tx: cell::OnceCell<coalesce::Sender<ImagePart>>,
+
+  /// Request sender for the palette inspector's pixel thread, started when first needed.
+  probe_tx: cell::OnceCell<coalesce::Sender<util::Pos>>,
+
+  /// Open and read timing from the reader's thread.
+  stats: sync::Arc<sync::Mutex<ReadStats>>,
 }
 
 impl RasterReader {
-  /// Create a new chart raster reader.
+  /// Create a new chart raster reader. The chart is opened on the reader's thread, and the result
+  /// is returned as either `RasterReply::Opened` or `RasterReply::OpenError`.
   /// - `path`: chart file path
   /// - `ctx`: egui context for requesting a repaint
-  pub fn new<P: AsRef<path::Path>>(path: P, ctx: &egui::Context) -> Result<Self, util::Error> {
-    RasterReader::_new(path.as_ref(), ctx.clone())
+  pub fn new<P: AsRef<path::Path>>(path: P, ctx: &egui::Context) -> Self {
+    RasterReader::_new(vec![path.as_ref().to_owned()], ctx.clone())
   }
 
-  fn _new(path: &path::Path, ctx: egui::Context) -> Result<Self, util::Error> {
-    // Open the chart source.
-    let (source, transform, palette) = RasterSource::open(path)?;
+  /// Create a chart raster reader for two adjacent charts that are read as one.
+  /// - `paths`: chart file paths
+  /// - `ctx`: egui context for requesting a repaint
+  pub fn new_combined(paths: [path::PathBuf; 2], ctx: &egui::Context) -> Self {
+    RasterReader::_new(paths.into(), ctx.clone())
+  }
 
-    // Create the communication channels.
-    let (tx, trx) = mpsc::channel();
+  fn _new(paths: Vec<path::PathBuf>, ctx: egui::Context) -> Self {
+    // Create the communication channels. Only the most recent image request matters, so requests
+    // that are still waiting when a new one arrives are replaced.
+    let (tx, trx) = coalesce::channel();
     let (ttx, rx) = mpsc::channel();
+    let (otx, orx) = mpsc::channel();
+    let stats = sync::Arc::new(sync::Mutex::new(ReadStats::default()));
 
     // Create the thread.
+    let reader_paths = paths.clone();
+    let reader_ttx = ttx.clone();
+    let reader_ctx = ctx.clone();
+    let reader_stats = stats.clone();
     thread::Builder::new()
       .name(any::type_name::<RasterReader>().to_owned())
       .spawn(move || {
-        // Convert the color palette.
-        let light: Vec<epaint::Color32> = palette.iter().map(util::color).collect();
-        let dark: Vec<epaint::Color32> = palette.iter().map(util::inverted_color).collect();
-        drop(palette);
+        // Open the chart source on this thread so that the GDAL datasets are only used from the
+        // thread that opened them. The transformation isn't `Send`, so its parts are sent instead.
+        let start = time::Instant::now();
+        let opened = ChartSource::open(&reader_paths).and_then(|(source, transform)| {
+          let parts = transform.parts()?;
+          let info = source.info();
+          Ok((source, parts, info))
+        });
 
-        // Wait for a message. Exit when the connection is closed.
-        while let Ok(request) = trx.recv() {
-          let mut part = request;
-
-          // GDAL doesn't have any way to cancel a raster read operation and the
-          // requests can pile up during a long read, so grab all the pending
-          // requests in order to get to the most recent.
-          while let Ok(request) = trx.try_recv() {
-            part = request;
+        if let Ok(mut stats) = reader_stats.lock() {
+          stats.open_time = Some(start.elapsed());
+        }
+
+        let source = match opened {
+          Ok((source, parts, info)) => {
+            otx.send(Ok((parts, info))).ok();
+            ctx.request_repaint();
+            source
           }
+          Err(err) => {
+            // The reader may have been dropped while this was being opened.
+            otx.send(Err(err)).ok();
+            ctx.request_repaint();
+            return;
+          }
+        };
 
+        // Wait for a message. Exit when the connection is closed.
+        while let Some(part) = trx.recv() {
           // Read the image data.
-          match source.read(&part) {
-            Ok(gdal_image) => {
-              let (w, h) = gdal_image.size;
-              let mut image = epaint::ColorImage {
-                size: [w, h],
-                pixels: Vec::with_capacity(w * h),
-              };
-
-              // Choose the palette.
-              let colors = if part.dark { &dark } else { &light };
-
-              // Convert the image to RGBA.
-              for val in gdal_image.data {
-                image.pixels.push(colors[val as usize]);
-              }
+          let start = time::Instant::now();
+          let result = source.read_image(&part);
+          if let Ok(mut stats) = reader_stats.lock() {
+            stats.add_read(start.elapsed());
+          }
 
+          match result {
+            Ok(image) => {
               // Send it.
               ttx.send(RasterReply::Image(part, image)).unwrap();
 
@@ -79,36 +120,675 @@ impl RasterReader {
       })
       .unwrap();
 
-    Ok(Self { transform, tx, rx })
+    Self {
+      paths,
+      opened: cell::OnceCell::new(),
+      orx,
+      tx,
+      rx,
+      ttx: reader_ttx,
+      ctx: reader_ctx,
+      alt_tx: cell::OnceCell::new(),
+      probe_tx: cell::OnceCell::new(),
+      stats,
+    }
+  }
+
+  /// Get the chart file paths (two for a combined chart).
+  pub fn paths(&self) -> &[path::PathBuf] {
+    &self.paths
   }
 
   /// Get the transformation.
+  /// > **NOTE**: panics if the chart hasn't been opened (see `RasterReply::Opened`).
   pub fn transform(&self) -> &Transform {
-    &self.transform
+    &self.opened().0
   }
 
-  /// Kick-off an image read operation.
+  /// Get the raster properties.
+  /// > **NOTE**: panics if the chart hasn't been opened (see `RasterReply::Opened`).
+  pub fn info(&self) -> &RasterInfo {
+    &self.opened().1
+  }
+
+  /// Get the chart edition details, if the chart's metadata has them.
+  pub fn edition(&self) -> Option<&edition::ChartEdition> {
+    self.opened.get()?.1.edition.as_ref()
+  }
+
+  /// Get the chart open and read timing.
+  pub fn read_stats(&self) -> ReadStats {
+    self.stats.lock().unwrap().clone()
+  }
+
+  /// Kick-off an image read operation. This replaces any previous request that hasn't been started.
   /// - `part`: the area to read from the source image.
   pub fn read_image(&self, part: ImagePart) {
-    self.tx.send(part).unwrap();
+    self.tx.send(part);
+  }
+
+  /// Kick-off an image read for the palette preview. This uses its own thread and chart source so
+  /// that it doesn't replace, or get replaced by, the image requests for the view. It replaces any
+  /// previous preview request that hasn't been started.
+  /// - `part`: the area to read from the source image.
+  pub fn read_alt_image(&self, part: ImagePart) {
+    let tx = self.alt_tx.get_or_init(|| {
+      let (tx, trx) = coalesce::channel();
+      let paths = self.paths.clone();
+      let ttx = self.ttx.clone();
+      let ctx = self.ctx.clone();
+      thread::Builder::new()
+        .name(format!("{} preview", any::type_name::<RasterReader>()))
+        .spawn(move || {
+          let source = match ChartSource::open(&paths) {
+            Ok((source, _)) => source,
+            Err(err) => {
+              log::warn!("Unable to open chart for the palette preview\n{err}");
+              return;
+            }
+          };
+
+          // Wait for a message. Exit when the connection is closed.
+          while let Some(part) = trx.recv() {
+            match source.read_image(&part) {
+              Ok(image) => {
+                // The reader may have been dropped while this was being read.
+                if ttx.send(RasterReply::Image(part, image)).is_err() {
+                  return;
+                }
+                ctx.request_repaint();
+              }
+              Err(err) => log::warn!("Unable to read chart preview image\n{err}"),
+            }
+          }
+        })
+        .unwrap();
+      tx
+    });
+    tx.send(part);
+  }
+
+  /// Kick-off a read of the whole chart, reduced to a thumbnail. This uses its own thread and
+  /// chart source so that it doesn't replace or hold up the image requests for the view.
+  /// - `width`: thumbnail width in pixels
+  /// - `palette`: chart colors
+  pub fn read_thumbnail(&self, width: u32, palette: Palette) {
+    let size = self.transform().px_size();
+    let zoom = (width as f32 / size.w as f32).min(1.0);
+    let rect = util::Rect {
+      pos: util::Pos::default(),
+      size: util::Size {
+        w: ((size.w as f32 * zoom) as u32).max(1),
+        h: ((size.h as f32 * zoom) as u32).max(1),
+      },
+    };
+
+    let part = ImagePart::new(rect, zoom, palette);
+    let paths = self.paths.clone();
+    let ttx = self.ttx.clone();
+    let ctx = self.ctx.clone();
+    thread::Builder::new()
+      .name(format!("{} thumbnail", any::type_name::<RasterReader>()))
+      .spawn(move || {
+        let image = ChartSource::open(&paths)
+          .map_err(|err| format!("{err}"))
+          .and_then(|(source, _)| source.read_image(&part).map_err(|err| format!("{err}")));
+
+        match image {
+          Ok(image) => {
+            // The reader may have been dropped while this was being read.
+            if ttx.send(RasterReply::Thumbnail(part, image)).is_ok() {
+              ctx.request_repaint();
+            }
+          }
+          Err(err) => log::warn!("Unable to read chart thumbnail\n{err}"),
+        }
+      })
+      .unwrap();
+  }
+
+  /// Kick-off a read of the raw value of a single chart pixel, which is returned as
+  /// `RasterReply::Pixel`. This is meant for inspecting the chart colors rather than drawing, so it
+  /// uses its own thread and chart source. It replaces any previous pixel request that hasn't been
+  /// started.
+  /// - `px`: pixel position (full resolution)
+  pub fn read_pixel(&self, px: util::Pos) {
+    let tx = self.probe_tx.get_or_init(|| {
+      let (tx, trx) = coalesce::channel();
+      let paths = self.paths.clone();
+      let ttx = self.ttx.clone();
+      let ctx = self.ctx.clone();
+      thread::Builder::new()
+        .name(format!("{} probe", any::type_name::<RasterReader>()))
+        .spawn(move || {
+          let source = match ChartSource::open(&paths) {
+            Ok((source, _)) => source,
+            Err(err) => {
+              log::warn!("Unable to open chart for reading pixels\n{err}");
+              return;
+            }
+          };
+
+          // Wait for a message. Exit when the connection is closed.
+          while let Some(px) = trx.recv() {
+            let value = source.read_pixel(px).unwrap_or_else(|err| {
+              log::warn!("Unable to read chart pixel\n{err}");
+              None
+            });
+
+            // The reader may have been dropped while this was being read.
+            if ttx.send(RasterReply::Pixel(px, value)).is_err() {
+              return;
+            }
+            ctx.request_repaint();
+          }
+        })
+        .unwrap();
+      tx
+    });
+    tx.send(px);
   }
 
   /// Get all available replies.
   pub fn get_replies(&self) -> Vec<RasterReply> {
-    self.rx.try_iter().collect()
+    let mut replies = Vec::new();
+    if let Ok(result) = self.orx.try_recv() {
+      replies.extend(self.open_reply(result));
+    }
+
+    replies.extend(self.rx.try_iter());
+    replies
+  }
+
+  /// Wait for the next reply. This is for driving the reader without a UI (e.g. benchmarking).
+  /// - `timeout`: how long to wait
+  pub fn wait_reply(&self, timeout: time::Duration) -> Option<RasterReply> {
+    if self.opened.get().is_none() {
+      // Nothing else is sent until the chart has been opened.
+      let result = self.orx.recv_timeout(timeout).ok()?;
+      return self.open_reply(result);
+    }
+    self.rx.recv_timeout(timeout).ok()
+  }
+
+  fn open_reply(
+    &self,
+    result: Result<(TransformParts, RasterInfo), util::Error>,
+  ) -> Option<RasterReply> {
+    // Rebuild the transformation on this thread.
+    let result = result.and_then(|(parts, info)| Ok((parts.build()?, info)));
+    match result {
+      Ok(opened) => {
+        let set = self.opened.set(opened).is_ok();
+        set.then_some(RasterReply::Opened)
+      }
+      Err(err) => Some(RasterReply::OpenError(err)),
+    }
+  }
+
+  fn opened(&self) -> &(Transform, RasterInfo) {
+    self.opened.get().expect("chart has not been opened")
   }
 }
 
+/// Chart raster properties.
+pub struct RasterInfo {
+  /// Color table entries (empty for RGB charts).
+  pub palette: Vec<raster::RgbaEntry>,
+
+  /// Number of overview (reduced resolution) levels.
+  pub overview_count: usize,
+
+  /// Edition details, if the chart's metadata has them.
+  pub edition: Option<edition::ChartEdition>,
+}
+
 pub enum RasterReply {
+  /// Chart was opened. The transformation and raster properties are now available.
+  Opened,
+
+  /// Chart couldn't be opened. The reader is no longer usable.
+  OpenError(util::Error),
+
   /// Image result from a read operation.
   Image(ImagePart, epaint::ColorImage),
 
   /// Error message from a read operation.
-  #[allow(dead_code)]
   Error(ImagePart, util::Error),
+
+  /// Reduced image of the whole chart from `read_thumbnail`.
+  Thumbnail(ImagePart, epaint::ColorImage),
+
+  /// Raw value of a chart pixel from `read_pixel`, if it's on the chart.
+  Pixel(util::Pos, Option<PixelValue>),
+}
+
+/// Chart open and read timing, for spotting slow storage.
+#[derive(Clone, Default)]
+pub struct ReadStats {
+  /// Time taken to open the chart.
+  pub open_time: Option<time::Duration>,
+
+  /// Number of image reads.
+  pub read_count: usize,
+
+  /// Most recent read times, oldest first.
+  recent: collections::VecDeque<time::Duration>,
+}
+
+impl ReadStats {
+  /// Number of recent reads that the average and percentile are taken from.
+  const WINDOW: usize = 50;
+
+  /// Number of reads needed before the reads can be considered slow.
+  const MIN_READS: usize = 10;
+
+  /// 95th percentile read time that's considered slow.
+  const SLOW_READ: time::Duration = time::Duration::from_millis(400);
+
+  /// Add the time taken by an image read.
+  /// - `duration`: read time
+  pub fn add_read(&mut self, duration: time::Duration) {
+    if self.recent.len() == ReadStats::WINDOW {
+      self.recent.pop_front();
+    }
+    self.recent.push_back(duration);
+    self.read_count += 1;
+  }
+
+  /// Average of the recent read times.
+  pub fn average(&self) -> Option<time::Duration> {
+    let count = self.recent.len() as u32;
+    (count > 0).then(|| self.recent.iter().sum::<time::Duration>() / count)
+  }
+
+  /// 95th percentile of the recent read times.
+  pub fn p95(&self) -> Option<time::Duration> {
+    let mut sorted: Vec<time::Duration> = self.recent.iter().copied().collect();
+    sorted.sort_unstable();
+
+    // Nearest rank.
+    let rank = (sorted.len() * 95).div_ceil(100);
+    sorted.get(rank.checked_sub(1)?).copied()
+  }
+
+  /// True if enough reads have been timed and they're slow.
+  pub fn is_slow(&self) -> bool {
+    self.recent.len() >= ReadStats::MIN_READS
+      && self.p95().is_some_and(|p95| p95 > ReadStats::SLOW_READ)
+  }
+}
+
+/// Reads chart images on the calling thread. [`RasterReader`] uses this on its own thread, and it's
+/// also used directly by jobs that have their own thread, such as exporting.
+pub struct ImageSource {
+  source: RasterSource,
+  palette: Vec<raster::RgbaEntry>,
+  light: Vec<epaint::Color32>,
+  dark: Vec<epaint::Color32>,
+  contrast: Vec<epaint::Color32>,
+
+  /// Band buffers that are reused from one read to the next.
+  bands: cell::RefCell<Vec<Vec<u8>>>,
+}
+
+impl ImageSource {
+  /// Open a chart image source.
+  /// - `path`: chart file path
+  pub fn open<P: AsRef<path::Path>>(path: P) -> Result<(Self, Transform), util::Error> {
+    let (source, transform, palette) = RasterSource::open(path.as_ref())?;
+
+    // Convert the color palette.
+    let light = convert_palette(&palette, util::color);
+    let dark = convert_palette(&palette, util::inverted_color);
+    let contrast = convert_palette(&palette, util::contrast_color);
+    Ok((
+      Self {
+        source,
+        palette,
+        light,
+        dark,
+        contrast,
+        bands: cell::RefCell::default(),
+      },
+      transform,
+    ))
+  }
+
+  /// Read part of the chart as an RGBA image.
+  /// - `part`: the area to read from the source image
+  pub fn read_image(
+    &self,
+    part: &ImagePart,
+  ) -> Result<epaint::ColorImage, gdal::errors::GdalError> {
+    let mut bands = self.bands.borrow_mut();
+    self.source.read(part, &mut bands)?;
+    let (w, h): (usize, usize) = part.rect.size.into();
+    let mut image = epaint::ColorImage {
+      size: [w, h],
+      pixels: Vec::with_capacity(w * h),
+    };
+
+    // Convert the image to RGBA.
+    match bands.as_slice() {
+      [indexes] => {
+        // Choose the palette. Palettes are padded to `PAL_LEN` so any index is in range.
+        let colors = match part.palette {
+          Palette::Light => &self.light,
+          Palette::Dark => &self.dark,
+          Palette::Contrast => &self.contrast,
+        };
+        for val in indexes {
+          image.pixels.push(colors[*val as usize]);
+        }
+      }
+      [r, g, b] => {
+        let convert = match part.palette {
+          Palette::Light => epaint::Color32::from_rgb,
+          Palette::Dark => util::inverted_rgb,
+          Palette::Contrast => util::contrast_rgb,
+        };
+
+        let rgb = r.iter().zip(g).zip(b);
+        for ((r, g), b) in rgb {
+          image.pixels.push(convert(*r, *g, *b));
+        }
+      }
+      _ => unreachable!(),
+    }
+    Ok(image)
+  }
+
+  /// Read the raw value of a single pixel. Returns `None` if the position is outside the chart.
+  /// - `px`: pixel position (full resolution)
+  pub fn read_pixel(&self, px: util::Pos) -> Result<Option<PixelValue>, gdal::errors::GdalError> {
+    let size = self.source.px_size;
+    if px.x < 0 || px.y < 0 || px.x >= size.w as i32 || px.y >= size.h as i32 {
+      return Ok(None);
+    }
+
+    let rect = util::Rect {
+      pos: px,
+      size: util::Size { w: 1, h: 1 },
+    };
+    let part = ImagePart::new(rect, 1.0, Palette::Light);
+    let mut bands = self.bands.borrow_mut();
+    self.source.read(&part, &mut bands)?;
+    let value = match bands.as_slice() {
+      [indexes] => PixelValue::Index(indexes[0]),
+      [r, g, b] => PixelValue::Rgb([r[0], g[0], b[0]]),
+      _ => unreachable!(),
+    };
+    Ok(Some(value))
+  }
+}
+
+/// Reads chart images from either a single chart file or two adjacent chart files.
+pub enum ChartSource {
+  Single(ImageSource),
+  Combined(Box<CompositeSource>),
+}
+
+impl ChartSource {
+  /// Open one chart file, or two adjacent chart files as a combined chart.
+  /// - `paths`: chart file paths
+  pub fn open(paths: &[path::PathBuf]) -> Result<(Self, Transform), util::Error> {
+    match paths {
+      [path] => {
+        let (source, transform) = ImageSource::open(path)?;
+        Ok((Self::Single(source), transform))
+      }
+      [a, b] => {
+        let (source, transform) = CompositeSource::open(a, b)?;
+        Ok((Self::Combined(Box::new(source)), transform))
+      }
+      _ => Err("Unable to open chart: expected one or two files".into()),
+    }
+  }
+
+  /// Read part of the chart as an RGBA image.
+  /// - `part`: the area to read from the chart
+  pub fn read_image(
+    &self,
+    part: &ImagePart,
+  ) -> Result<epaint::ColorImage, gdal::errors::GdalError> {
+    match self {
+      Self::Single(source) => source.read_image(part),
+      Self::Combined(source) => source.read_image(part),
+    }
+  }
+
+  /// Read the raw value of a single pixel. Returns `None` if the position is outside the chart.
+  /// - `px`: pixel position (full resolution)
+  pub fn read_pixel(&self, px: util::Pos) -> Result<Option<PixelValue>, gdal::errors::GdalError> {
+    match self {
+      Self::Single(source) => source.read_pixel(px),
+      Self::Combined(source) => source.read_pixel(px),
+    }
+  }
+
+  fn info(&self) -> RasterInfo {
+    // A combined chart reports the properties of its first file.
+    let source = match self {
+      Self::Single(source) => source,
+      Self::Combined(source) => &source.halves[0].0,
+    };
+    RasterInfo {
+      palette: source.palette.clone(),
+      overview_count: source.source.overview_count(),
+      edition: source.source.edition(),
+    }
+  }
+}
+
+/// Two adjacent charts that share a spatial reference and scale, read as one chart.
+pub struct CompositeSource {
+  /// Image sources with their pixel offsets within the combined chart.
+  halves: [(ImageSource, util::Pos); 2],
+}
+
+impl CompositeSource {
+  /// Open two adjacent charts as one.
+  /// - `a`: first chart file path
+  /// - `b`: second chart file path
+  fn open(a: &path::Path, b: &path::Path) -> Result<(Self, Transform), util::Error> {
+    let (source_a, transform_a) = ImageSource::open(a)?;
+    let (source_b, transform_b) = ImageSource::open(b)?;
+    let Some((offsets, transform)) = combine(&transform_a, &transform_b) else {
+      return Err("Unable to combine charts: they are not adjacent".into());
+    };
+
+    let [offset_a, offset_b] = offsets;
+    let halves = [(source_a, offset_a), (source_b, offset_b)];
+    Ok((Self { halves }, transform))
+  }
+
+  /// Read part of the combined chart as an RGBA image. Parts that straddle the boundary are read
+  /// from both charts and composited; areas covered by neither chart are transparent.
+  /// - `part`: the area to read from the combined chart
+  fn read_image(&self, part: &ImagePart) -> Result<epaint::ColorImage, gdal::errors::GdalError> {
+    let (w, h): (usize, usize) = part.rect.size.into();
+    let mut image = epaint::ColorImage::new([w, h], epaint::Color32::TRANSPARENT);
+    let zoom: f32 = part.zoom.into();
+    let (left, top) = (part.rect.pos.x, part.rect.pos.y);
+    let (right, bottom) = (left + w as i32, top + h as i32);
+    for (source, offset) in &self.halves {
+      // This chart's extent in zoomed pixels.
+      let size = source.source.px_size;
+      let x0 = (offset.x as f32 * zoom).round() as i32;
+      let y0 = (offset.y as f32 * zoom).round() as i32;
+      let x1 = ((offset.x + size.w as i32) as f32 * zoom).round() as i32;
+      let y1 = ((offset.y + size.h as i32) as f32 * zoom).round() as i32;
+
+      // The part of the request that falls on this chart.
+      let (ix0, iy0) = (left.max(x0), top.max(y0));
+      let (ix1, iy1) = (right.min(x1), bottom.min(y1));
+      if ix0 >= ix1 || iy0 >= iy1 {
+        continue;
+      }
+
+      let rect = util::Rect {
+        pos: util::Pos {
+          x: ix0 - x0,
+          y: iy0 - y0,
+        },
+        size: util::Size {
+          w: (ix1 - ix0) as u32,
+          h: (iy1 - iy0) as u32,
+        },
+      };
+      let sub = source.read_image(&ImagePart::new(rect, zoom, part.palette))?;
+
+      // Copy it into place.
+      let [sw, _] = sub.size;
+      let dx = (ix0 - left) as usize;
+      for (row, pixels) in sub.pixels.chunks_exact(sw).enumerate() {
+        let start = (iy0 - top) as usize * w + row * w + dx;
+        image.pixels[start..start + sw].copy_from_slice(pixels);
+      }
+    }
+    Ok(image)
+  }
+
+  /// Read the raw value of a single pixel from whichever chart covers it.
+  /// - `px`: pixel position in the combined chart (full resolution)
+  fn read_pixel(&self, px: util::Pos) -> Result<Option<PixelValue>, gdal::errors::GdalError> {
+    for (source, offset) in &self.halves {
+      let pos = util::Pos {
+        x: px.x - offset.x,
+        y: px.y - offset.y,
+      };
+      if let Some(value) = source.read_pixel(pos)? {
+        return Ok(Some(value));
+      }
+    }
+    Ok(None)
+  }
+}
+
+/// Check if two charts can be read as one combined chart.
+/// - `a`: first chart file path
+/// - `b`: second chart file path
+pub fn can_combine(a: &path::Path, b: &path::Path) -> bool {
+  let (Ok((_, a, _)), Ok((_, b, _))) = (RasterSource::open(a), RasterSource::open(b)) else {
+    return false;
+  };
+  combine(&a, &b).is_some()
+}
+
+/// Get the pixel offsets of two charts within their union, along with the union's
+/// transformation. Returns `None` unless the charts share a spatial reference and pixel scale and
+/// sit side by side (or one above the other) without overlapping.
+/// - `a`: first chart transformation
+/// - `b`: second chart transformation
+fn combine(a: &Transform, b: &Transform) -> Option<([util::Pos; 2], Transform)> {
+  if a.get_proj4() != b.get_proj4() {
+    return None;
+  }
+
+  // Neither can be rotated and the pixel sizes must match.
+  let (ga, gb) = (&a.from_px, &b.from_px);
+  if ga[2] != 0.0 || ga[4] != 0.0 || gb[2] != 0.0 || gb[4] != 0.0 {
+    return None;
+  }
+
+  if (ga[1] - gb[1]).abs() > 1e-6 * ga[1].abs() || (ga[5] - gb[5]).abs() > 1e-6 * ga[5].abs() {
+    return None;
+  }
+
+  // Position of the second chart relative to the first, which must be in whole pixels.
+  let dx = (gb[0] - ga[0]) / ga[1];
+  let dy = (gb[3] - ga[3]) / ga[5];
+  if (dx - dx.round()).abs() > 0.01 || (dy - dy.round()).abs() > 0.01 {
+    return None;
+  }
+
+  let (dx, dy) = (dx.round() as i64, dy.round() as i64);
+  let (wa, ha) = (a.px_size.w as i64, a.px_size.h as i64);
+  let (wb, hb) = (b.px_size.w as i64, b.px_size.h as i64);
+
+  // The extents must share an edge.
+  let overlap_x = dx < wa && dx + wb > 0;
+  let overlap_y = dy < ha && dy + hb > 0;
+  let touch_x = dx == wa || dx + wb == 0;
+  let touch_y = dy == ha || dy + hb == 0;
+  if !((touch_x && overlap_y) || (touch_y && overlap_x)) {
+    return None;
+  }
+
+  let (min_x, min_y) = (dx.min(0), dy.min(0));
+  let (max_x, max_y) = ((dx + wb).max(wa), (dy + hb).max(ha));
+  let px_size = util::Size {
+    w: u32::try_from(max_x - min_x).ok()?,
+    h: u32::try_from(max_y - min_y).ok()?,
+  };
+
+  let mut geo_transform = *ga;
+  geo_transform[0] += min_x as f64 * ga[1];
+  geo_transform[3] += min_y as f64 * ga[5];
+  let transform = Transform::new(px_size, a.spatial_ref.clone(), geo_transform).ok()?;
+  let offsets = [
+    util::Pos {
+      x: -min_x as i32,
+      y: -min_y as i32,
+    },
+    util::Pos {
+      x: (dx - min_x) as i32,
+      y: (dy - min_y) as i32,
+    },
+  ];
+  Some((offsets, transform))
+}
+
+/// Maximum number of color table entries (palette indexes are 8 bits).
+const PAL_LEN: usize = 256;
+
+/// Color for palette indexes that are past the end of the color table.
+const PAL_MISSING: epaint::Color32 = epaint::Color32::from_rgb(255, 0, 255);
+
+/// Maximum number of threads used for a single read.
+const MAX_READ_THREADS: usize = 4;
+
+/// Reads that cover fewer source rows than this aren't worth splitting between threads.
+const SPLIT_ROWS: usize = 2048;
+
+/// Convert a color table to a palette with `PAL_LEN` colors. Entries past the end of the color
+/// table are set to `PAL_MISSING`.
+/// - `palette`: color table entries (no more than `PAL_LEN`)
+/// - `convert`: color conversion
+fn convert_palette(
+  palette: &[raster::RgbaEntry],
+  convert: fn(&raster::RgbaEntry) -> epaint::Color32,
+) -> Vec<epaint::Color32> {
+  let mut colors: Vec<epaint::Color32> = palette.iter().take(PAL_LEN).map(convert).collect();
+  colors.resize(PAL_LEN, PAL_MISSING);
+  colors
 }
 
 /// Transformations between pixel, chart (LCC) and NAD83 coordinates.
+/// Parts of a [`Transform`]. Unlike the transformation itself, these can be sent between threads.
+struct TransformParts {
+  px_size: util::Size,
+  wkt: String,
+  axis_mapping: gdal_sys::OSRAxisMappingStrategy::Type,
+  geo_transform: gdal::GeoTransform,
+}
+
+impl TransformParts {
+  /// Rebuild the transformation.
+  fn build(self) -> Result<Transform, util::Error> {
+    let result = spatial_ref::SpatialRef::from_wkt(&self.wkt).and_then(|spatial_ref| {
+      spatial_ref.set_axis_mapping_strategy(self.axis_mapping);
+      Transform::new(self.px_size, spatial_ref, self.geo_transform)
+    });
+
+    match result {
+      Ok(transform) => Ok(transform),
+      Err(err) => Err(format!("Unable to open chart: {err}").into()),
+    }
+  }
+}
+
 pub struct Transform {
   px_size: util::Size,
   spatial_ref: spatial_ref::SpatialRef,
@@ -150,6 +830,19 @@ impl Transform {
     })
   }
 
+  /// Get the parts needed to rebuild this transformation on another thread.
+  fn parts(&self) -> Result<TransformParts, util::Error> {
+    match self.spatial_ref.to_wkt() {
+      Ok(wkt) => Ok(TransformParts {
+        px_size: self.px_size,
+        wkt,
+        axis_mapping: self.spatial_ref.axis_mapping_strategy(),
+        geo_transform: self.from_px,
+      }),
+      Err(err) => Err(format!("Unable to open chart: {err}").into()),
+    }
+  }
+
   /// Get the spatial reference as a proj4 string.
   pub fn get_proj4(&self) -> String {
     self.spatial_ref.to_proj4().unwrap()
@@ -160,6 +853,17 @@ impl Transform {
     self.px_size
   }
 
+  /// Get the geo-transformation from pixel to chart coordinates.
+  pub fn geo_transform(&self) -> gdal::GeoTransform {
+    self.from_px
+  }
+
+  /// Get the number of meters covered by a single pixel at full zoom.
+  pub fn meters_per_pixel(&self) -> f64 {
+    // Chart units are meters, so the length of a one pixel step is the scale.
+    self.from_px[1].hypot(self.from_px[4])
+  }
+
   /// Get the bounds as chart (LCC) coordinates.
   pub fn bounds(&self) -> &util::Bounds {
     &self.bounds
@@ -197,7 +901,6 @@ impl Transform {
 
   /// Convert a pixel coordinate to a NAD83 coordinate.
   /// - `coord`: pixel coordinate
-  #[allow(unused)]
   pub fn px_to_nad83(&self, coord: util::Coord) -> Result<util::Coord, gdal::errors::GdalError> {
     self.chart_to_nad83(self.px_to_chart(coord))
   }
@@ -208,6 +911,48 @@ impl Transform {
     let coord = self.nad83_to_chart(coord);
     coord.map(|coord| self.chart_to_px(coord))
   }
+
+  /// Check that the center of the chart makes it to NAD83 and back.
+  fn check_round_trip(&self) -> bool {
+    let px = util::Coord {
+      x: self.px_size.w as f64 * 0.5,
+      y: self.px_size.h as f64 * 0.5,
+    };
+
+    let coord = self.px_to_chart(px);
+    let Ok(nad83) = self.chart_to_nad83(coord) else {
+      return false;
+    };
+
+    if !(-180.0..=180.0).contains(&nad83.x) || !(-90.0..=90.0).contains(&nad83.y) {
+      return false;
+    }
+
+    let Ok(back) = self.nad83_to_chart(nad83) else {
+      return false;
+    };
+
+    (back.x - coord.x).hypot(back.y - coord.y) < Self::ROUND_TRIP_TOLERANCE
+  }
+
+  /// Maximum round trip error in meters.
+  const ROUND_TRIP_TOLERANCE: f64 = 0.01;
+}
+
+/// Check that a spatial reference is a Lambert conformal conic projection in meters. The datum
+/// doesn't have to be NAD83 (e.g. WGS84 or GRS80 with a null datum shift).
+/// - `sr`: chart spatial reference
+fn check_spatial_ref(sr: &spatial_ref::SpatialRef) -> Result<(), util::Error> {
+  let projection = sr.get_attr_value("PROJECTION", 0).ok().flatten();
+  let lcc = projection.is_some_and(|name| name.starts_with("Lambert_Conformal_Conic"));
+  if !sr.is_projected() || !lcc {
+    return Err("Unable to open chart: not a Lambert conformal conic projection".into());
+  }
+
+  if (sr.linear_units() - 1.0).abs() > 1.0e-9 {
+    return Err("Unable to open chart: spatial reference units are not meters".into());
+  }
+  Ok(())
 }
 
 /// The part of the image needed for display.
@@ -215,17 +960,60 @@ impl Transform {
 pub struct ImagePart {
   pub rect: util::Rect,
   pub zoom: util::Hashable,
-  pub dark: bool,
+  pub palette: Palette,
 }
 
 impl ImagePart {
-  pub fn new(rect: util::Rect, zoom: f32, dark: bool) -> Self {
+  pub fn new(rect: util::Rect, zoom: f32, palette: Palette) -> Self {
     // A zoom value of zero is not valid.
     assert!(zoom > 0.0);
     Self {
       rect,
       zoom: zoom.into(),
-      dark,
+      palette,
+    }
+  }
+}
+
+/// Colors used for the chart image.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Palette {
+  /// Chart colors as published.
+  #[default]
+  Light,
+
+  /// Luminance inverted for night mode.
+  Dark,
+
+  /// More contrast and saturation for bright light or low vision.
+  Contrast,
+}
+
+/// Raw value of a chart pixel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PixelValue {
+  /// Color palette index.
+  Index(u8),
+
+  /// Red, green and blue components.
+  Rgb([u8; 3]),
+}
+
+/// How the chart colors are stored.
+enum ColorMode {
+  /// Single band of color palette indexes.
+  Palette(isize),
+
+  /// Separate red, green and blue bands.
+  Rgb([isize; 3]),
+}
+
+impl ColorMode {
+  /// Raster band indexes in the order they should be read.
+  fn bands(&self) -> &[isize] {
+    match self {
+      Self::Palette(index) => std::slice::from_ref(index),
+      Self::Rgb(indexes) => indexes,
     }
   }
 }
@@ -233,8 +1021,12 @@ impl ImagePart {
 /// Chart raster data source.
 struct RasterSource {
   dataset: gdal::Dataset,
-  band_idx: isize,
+  color_mode: ColorMode,
   px_size: util::Size,
+
+  /// Extra handles to the same file for reading on worker threads (a GDAL dataset can't be used
+  /// by more than one thread at a time).
+  workers: Vec<sync::Mutex<gdal::Dataset>>,
 }
 
 impl RasterSource {
@@ -245,27 +1037,29 @@ impl RasterSource {
     }
   }
 
-  /// Open a chart data source.
+  /// Open a chart data source, with a thread for each available processor (up to
+  /// `MAX_READ_THREADS`) for large reads.
   /// - `path`: raster file path
   fn open(
     path: &path::Path,
+  ) -> Result<(Self, Transform, Vec<gdal::raster::RgbaEntry>), util::Error> {
+    let count = thread::available_parallelism().map_or(1, |count| count.get());
+    Self::open_with_threads(path, count.min(MAX_READ_THREADS))
+  }
+
+  /// Open a chart data source.
+  /// - `path`: raster file path
+  /// - `threads`: number of threads for large reads
+  fn open_with_threads(
+    path: &path::Path,
+    threads: usize,
   ) -> Result<(Self, Transform, Vec<gdal::raster::RgbaEntry>), util::Error> {
     match gdal::Dataset::open_ex(path, Self::open_options()) {
       Ok(dataset) => {
         // Get and check the dataset's spatial reference.
         let spatial_ref = match dataset.spatial_ref() {
           Ok(sr) => {
-            match sr.to_proj4() {
-              Ok(proj4) => {
-                let proj4 = proj4.to_lowercase();
-                for item in ["+proj=lcc", "+datum=nad83", "+units=m"] {
-                  if !proj4.contains(item) {
-                    return Err("Unable to open chart: invalid spatial reference".into());
-                  }
-                }
-              }
-              Err(err) => return Err(format!("Unable to open chart: {err}").into()),
-            }
+            check_spatial_ref(&sr)?;
             sr
           }
           Err(err) => return Err(format!("Unable to open chart: {err}").into()),
@@ -287,45 +1081,80 @@ impl RasterSource {
           Err(err) => return Err(format!("Unable to open chart: {err}").into()),
         };
 
-        let (band_idx, palette) = || -> Result<(isize, Vec<raster::RgbaEntry>), util::Error> {
-          // The raster bands start at index one.
-          for index in 1..=dataset.raster_count() {
-            let rasterband = dataset.rasterband(index).unwrap();
-
-            // The color interpretation for a FAA chart is PaletteIndex.
-            if rasterband.color_interpretation() == raster::ColorInterpretation::PaletteIndex {
-              if let Some(color_table) = rasterband.color_table() {
-                // The color table must have 256 entries.
-                let size = color_table.entry_count();
-                if size != 256 {
-                  return Err("Unable to open chart: invalid color table".into());
-                }
+        if !chart_transform.check_round_trip() {
+          return Err("Unable to open chart: invalid spatial reference".into());
+        }
+
+        let (color_mode, palette) =
+          || -> Result<(ColorMode, Vec<raster::RgbaEntry>), util::Error> {
+            // Check for separate red, green and blue bands.
+            if let Some(bands) = Self::rgb_bands(&dataset) {
+              return Ok((ColorMode::Rgb(bands), Vec::new()));
+            }
+
+            // The raster bands start at index one.
+            for index in 1..=dataset.raster_count() {
+              let rasterband = dataset.rasterband(index).unwrap();
 
-                // Collect the color entries as RGB.
-                let mut palette = Vec::with_capacity(size);
-                for index in 0..size {
-                  if let Some(color) = color_table.entry_as_rgb(index) {
-                    // All components must be in 0..256 range.
-                    if util::check_color(color) {
-                      palette.push(color);
-                      continue;
+              // The color interpretation for a FAA chart is PaletteIndex.
+              if rasterband.color_interpretation() == raster::ColorInterpretation::PaletteIndex {
+                if let Some(color_table) = rasterband.color_table() {
+                  // Re-exported charts may have fewer than 256 entries.
+                  let size = color_table.entry_count();
+                  if size == 0 || size > PAL_LEN {
+                    return Err("Unable to open chart: invalid color table".into());
+                  }
+
+                  // Collect the color entries as RGB.
+                  let mut palette = Vec::with_capacity(size);
+                  for index in 0..size {
+                    if let Some(color) = color_table.entry_as_rgb(index) {
+                      // All components must be in 0..256 range.
+                      if util::check_color(color) {
+                        palette.push(color);
+                        continue;
+                      }
                     }
+                    return Err("Unable to open chart: invalid color table".into());
                   }
-                  return Err("Unable to open chart: invalid color table".into());
+                  return Ok((ColorMode::Palette(index), palette));
                 }
-                return Ok((index, palette));
+                return Err("Unable to open chart: color table not found".into());
               }
-              return Err("Unable to open chart: color table not found".into());
+            }
+            Err("Unable to open chart: raster layer not found".into())
+          }()?;
+
+        // Read a single pixel from each corner so that obviously truncated files are rejected here
+        // rather than failing later.
+        let (w, h) = (px_size.w as isize - 1, px_size.h as isize - 1);
+        for index in color_mode.bands() {
+          let raster = dataset.rasterband(*index).unwrap();
+          for pos in [(0, 0), (w, 0), (0, h), (w, h)] {
+            if raster.read_as::<u8>(pos, (1, 1), (1, 1), None).is_err() {
+              return Err(
+                "Unable to open chart: file appears corrupt or incomplete — re-download the chart"
+                  .into(),
+              );
             }
           }
-          Err("Unable to open chart: raster layer not found".into())
-        }()?;
+        }
+
+        // Open the worker handles. Reads just use fewer threads if any of these fail.
+        let mut workers = Vec::new();
+        for _ in 1..threads {
+          let Ok(dataset) = gdal::Dataset::open_ex(path, Self::open_options()) else {
+            break;
+          };
+          workers.push(sync::Mutex::new(dataset));
+        }
 
         Ok((
           Self {
             dataset,
-            band_idx,
+            color_mode,
             px_size,
+            workers,
           },
           chart_transform,
           palette,
@@ -335,16 +1164,868 @@ impl RasterSource {
     }
   }
 
-  fn read(&self, part: &ImagePart) -> Result<gdal::raster::Buffer<u8>, gdal::errors::GdalError> {
+  /// Find the red, green and blue bands of a non-palettized raster.
+  fn rgb_bands(dataset: &gdal::Dataset) -> Option<[isize; 3]> {
+    let mut bands = [None; 3];
+    for index in 1..=dataset.raster_count() {
+      let rasterband = dataset.rasterband(index).ok()?;
+      let slot = match rasterband.color_interpretation() {
+        raster::ColorInterpretation::RedBand => 0,
+        raster::ColorInterpretation::GreenBand => 1,
+        raster::ColorInterpretation::BlueBand => 2,
+        _ => continue,
+      };
+      bands[slot] = Some(index);
+    }
+    Some([bands[0]?, bands[1]?, bands[2]?])
+  }
+
+  fn overview_count(&self) -> usize {
+    let raster = self.dataset.rasterband(self.color_mode.bands()[0]).unwrap();
+    raster.overview_count().unwrap_or(0) as usize
+  }
+
+  /// Read the edition details from the dataset's metadata.
+  fn edition(&self) -> Option<edition::ChartEdition> {
+    let entries = self.dataset.metadata();
+    edition::ChartEdition::from_metadata(entries.map(|entry| (entry.key, entry.value)))
+  }
+
+  /// Read part of the image. Fills one buffer for palettized charts or separate red, green and
+  /// blue buffers for RGB charts. The buffers are resized to fit but keep their capacity, so they
+  /// can be reused between reads without reallocating.
+  /// - `part`: the area to read from the source image
+  /// - `bands`: band buffers
+  fn read(
+    &self,
+    part: &ImagePart,
+    bands: &mut Vec<Vec<u8>>,
+  ) -> Result<(), gdal::errors::GdalError> {
+    // Only split reads that cover enough source rows.
+    let src_rect = part.rect.scaled(part.zoom.inverse()).fitted(self.px_size);
+    let threads = if src_rect.size.h as usize >= SPLIT_ROWS {
+      self.workers.len() + 1
+    } else {
+      1
+    };
+    self.read_split(part, bands, threads)
+  }
+
+  /// Read part of the image, splitting the rows into bands that are read on separate threads.
+  /// - `part`: the area to read from the source image
+  /// - `bands`: band buffers
+  /// - `threads`: number of threads, limited to the number opened with the source
+  fn read_split(
+    &self,
+    part: &ImagePart,
+    bands: &mut Vec<Vec<u8>>,
+    threads: usize,
+  ) -> Result<(), gdal::errors::GdalError> {
     // Scale and correct the source rectangle (GDAL does not tolerate
     // read requests outside the original raster size).
     let src_rect = part.rect.scaled(part.zoom.inverse()).fitted(self.px_size);
-    let raster = self.dataset.rasterband(self.band_idx).unwrap();
-    raster.read_as::<u8>(
-      src_rect.pos.into(),
-      src_rect.size.into(),
-      part.rect.size.into(),
-      Some(gdal::raster::ResampleAlg::Average),
-    )
+    let size: (usize, usize) = part.rect.size.into();
+    let indexes = self.color_mode.bands();
+    bands.resize_with(indexes.len(), Vec::new);
+    for buffer in bands.iter_mut() {
+      buffer.resize(size.0 * size.1, 0);
+    }
+
+    // Divide each band buffer between the row bands.
+    let count = threads.clamp(1, self.workers.len() + 1);
+    let mut jobs: Vec<_> = split_rows(size.1, count)
+      .into_iter()
+      .map(|rows| (rows, Vec::with_capacity(bands.len())))
+      .collect();
+
+    for buffer in bands.iter_mut() {
+      let mut rest = buffer.as_mut_slice();
+      for (rows, buffers) in &mut jobs {
+        let (head, tail) = mem::take(&mut rest).split_at_mut(rows.len() * size.0);
+        buffers.push(head);
+        rest = tail;
+      }
+    }
+
+    let src_rect = &src_rect;
+    let mut jobs = jobs.into_iter();
+    let (rows, buffers) = jobs.next().unwrap();
+    thread::scope(|scope| {
+      let handles: Vec<_> = jobs
+        .zip(&self.workers)
+        .map(|((rows, buffers), worker)| {
+          scope.spawn(move || {
+            let dataset = worker.lock().unwrap();
+            read_rows(&dataset, indexes, src_rect, size, rows, buffers)
+          })
+        })
+        .collect();
+
+      // Read the first band on this thread while the workers read the rest.
+      let result = read_rows(&self.dataset, indexes, src_rect, size, rows, buffers);
+      handles
+        .into_iter()
+        .fold(result, |result, handle| result.and(handle.join().unwrap()))
+    })
+  }
+}
+
+/// Split rows into contiguous bands of nearly equal size.
+/// - `rows`: number of rows
+/// - `count`: number of bands
+fn split_rows(rows: usize, count: usize) -> Vec<ops::Range<usize>> {
+  let count = count.clamp(1, rows.max(1));
+  let bounds = |index: usize| index * rows / count;
+  (0..count)
+    .map(|index| bounds(index)..bounds(index + 1))
+    .collect()
+}
+
+/// Read a band of destination rows. The source window is computed from the absolute row indexes
+/// (a band can start part way through a source row), so bands join without seams and give the
+/// same pixels as reading all the rows at once.
+/// - `dataset`: dataset to read from
+/// - `indexes`: raster band indexes
+/// - `src_rect`: source rectangle of the whole read
+/// - `size`: destination size of the whole read
+/// - `rows`: destination rows to read
+/// - `buffers`: destination buffers for the rows, one for each raster band
+fn read_rows(
+  dataset: &gdal::Dataset,
+  indexes: &[isize],
+  src_rect: &util::Rect,
+  size: (usize, usize),
+  rows: ops::Range<usize>,
+  buffers: Vec<&mut [u8]>,
+) -> Result<(), gdal::errors::GdalError> {
+  if rows.is_empty() {
+    return Ok(());
+  }
+
+  let src_h = src_rect.size.h as usize;
+  let top = src_rect.pos.y as f64 + (rows.start * src_h) as f64 / size.1 as f64;
+  let bottom = src_rect.pos.y as f64 + (rows.end * src_h) as f64 / size.1 as f64;
+
+  // The whole-pixel window must enclose the fractional one.
+  let (y, h) = (top.floor(), bottom.ceil() - top.floor());
+  for (index, buffer) in indexes.iter().zip(buffers) {
+    let raster = dataset.rasterband(*index).unwrap();
+    let mut extra = gdal_sys::GDALRasterIOExtraArg {
+      nVersion: 1,
+      eResampleAlg: raster::ResampleAlg::Average.to_gdal(),
+      pfnProgress: None,
+      pProgressData: std::ptr::null_mut(),
+      bFloatingPointWindowValidity: 1,
+      dfXOff: src_rect.pos.x as f64,
+      dfYOff: top,
+      dfXSize: src_rect.size.w as f64,
+      dfYSize: bottom - top,
+    };
+
+    // SAFETY: the raster band is valid for the lifetime of `raster` and the buffer holds exactly
+    // `size.0` by `rows.len()` bytes. The `gdal` crate doesn't expose fractional source windows.
+    let result = unsafe {
+      gdal_sys::GDALRasterIOEx(
+        raster.c_rasterband(),
+        gdal_sys::GDALRWFlag::GF_Read,
+        src_rect.pos.x as ffi::c_int,
+        y as ffi::c_int,
+        src_rect.size.w as ffi::c_int,
+        h as ffi::c_int,
+        buffer.as_mut_ptr().cast(),
+        size.0 as ffi::c_int,
+        rows.len() as ffi::c_int,
+        gdal_sys::GDALDataType::GDT_Byte,
+        0,
+        0,
+        &mut extra,
+      )
+    };
+
+    if result != gdal_sys::CPLErr::CE_None {
+      // SAFETY: GDAL keeps the last error for each thread and the message is a C string.
+      let (number, msg) = unsafe {
+        let msg = ffi::CStr::from_ptr(gdal_sys::CPLGetLastErrorMsg());
+        let msg = msg.to_string_lossy().into_owned();
+        let number = gdal_sys::CPLGetLastErrorNo();
+        gdal_sys::CPLErrorReset();
+        (number, msg)
+      };
+      return Err(gdal::errors::GdalError::CplError {
+        class: result,
+        number,
+        msg,
+      });
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+  use gdal::{raster, spatial_ref};
+  use std::{env, path};
+
+  /// Lambert conformal conic projection of the test charts, without the datum and units.
+  const LCC: &str = "+proj=lcc +lat_0=34.1 +lon_0=-118.45 +lat_1=38.6666666666667 \
+                     +lat_2=33.3333333333333 +x_0=0 +y_0=0";
+
+  /// Builder for small GeoTIFF charts in the temp folder, with 250 meter pixels. By default it's
+  /// an 8 x 4 chart with its top left corner at (-1000, 1000) and a gray palette, with every pixel
+  /// set to palette index 7.
+  pub(crate) struct TestChart {
+    size: (usize, usize),
+    origin: (f64, f64),
+    rgb: bool,
+    palette_bits: u32,
+    pixels: Box<dyn Fn(usize, usize) -> u8>,
+  }
+
+  impl TestChart {
+    pub(crate) fn new() -> Self {
+      Self {
+        size: (8, 4),
+        origin: (-1000.0, 1000.0),
+        rgb: false,
+        palette_bits: 8,
+        pixels: Box::new(|_, _| 7),
+      }
+    }
+
+    /// Set the width and height in pixels.
+    pub(crate) fn size(mut self, w: usize, h: usize) -> Self {
+      self.size = (w, h);
+      self
+    }
+
+    /// Set the chart coordinate of the top left corner.
+    fn origin(mut self, x: f64, y: f64) -> Self {
+      self.origin = (x, y);
+      self
+    }
+
+    /// Make an RGB chart instead, with every pixel set to (200, 100, 50).
+    fn rgb(mut self) -> Self {
+      self.rgb = true;
+      self
+    }
+
+    /// Set the bits per palette index. The palette has an entry for every index.
+    fn palette_bits(mut self, bits: u32) -> Self {
+      self.palette_bits = bits;
+      self
+    }
+
+    /// Set every pixel to the same palette index.
+    fn fill(self, val: u8) -> Self {
+      self.pattern(move |_, _| val)
+    }
+
+    /// Set the palette index for each pixel position. The palette is gray, so pixel values read
+    /// back as their palette indexes.
+    fn pattern(mut self, pattern: impl Fn(usize, usize) -> u8 + 'static) -> Self {
+      self.pixels = Box::new(pattern);
+      self
+    }
+
+    /// Write the chart.
+    /// - `name`: file name
+    pub(crate) fn create(&self, name: &str) -> path::PathBuf {
+      let path = env::temp_dir().join(name).with_extension("tif");
+      let driver = gdal::DriverManager::get_driver_by_name("GTiff").unwrap();
+      let (w, h) = self.size;
+      let bands = if self.rgb { 3 } else { 1 };
+      let bits = self.palette_bits.to_string();
+      let options = [raster::RasterCreationOption {
+        key: "NBITS",
+        value: &bits,
+      }];
+      let mut dataset = driver
+        .create_with_band_type_with_options::<u8, _>(&path, w as isize, h as isize, bands, &options)
+        .unwrap();
+
+      let proj4 = format!("{LCC} +datum=NAD83 +units=m +no_defs");
+      let sr = spatial_ref::SpatialRef::from_proj4(&proj4).unwrap();
+      dataset.set_spatial_ref(&sr).unwrap();
+      let (x, y) = self.origin;
+      dataset
+        .set_geo_transform(&[x, 250.0, 0.0, y, 0.0, -250.0])
+        .unwrap();
+
+      if self.rgb {
+        use raster::ColorInterpretation::*;
+        for (index, (interp, val)) in [(RedBand, 200), (GreenBand, 100), (BlueBand, 50)]
+          .into_iter()
+          .enumerate()
+        {
+          let mut band = dataset.rasterband(index as isize + 1).unwrap();
+          band.set_color_interpretation(interp).unwrap();
+          let buffer = raster::Buffer::new((w, h), vec![val; w * h]);
+          band.write((0, 0), (w, h), &buffer).unwrap();
+        }
+        return path;
+      }
+
+      let mut table = raster::ColorTable::default();
+      for index in 0..1 << self.palette_bits {
+        let entry = raster::RgbaEntry {
+          r: index,
+          g: index,
+          b: index,
+          a: 255,
+        };
+        table.set_color_entry(index as u16, &raster::ColorEntry::Rgba(entry));
+      }
+
+      let mut data = Vec::with_capacity(w * h);
+      for y in 0..h {
+        for x in 0..w {
+          data.push((self.pixels)(x, y));
+        }
+      }
+
+      let mut band = dataset.rasterband(1).unwrap();
+      band.set_color_table(&table);
+      band
+        .set_color_interpretation(raster::ColorInterpretation::PaletteIndex)
+        .unwrap();
+      let buffer = raster::Buffer::new((w, h), data);
+      band.write((0, 0), (w, h), &buffer).unwrap();
+      path
+    }
+  }
+
+  /// Create a pattern chart.
+  /// - `name`: file name
+  /// - `pattern`: palette index for each pixel position
+  fn create_pattern_chart(
+    name: &str,
+    pattern: impl Fn(usize, usize) -> u8 + 'static,
+  ) -> path::PathBuf {
+    let size = PATTERN_SIZE;
+    TestChart::new()
+      .size(size, size)
+      .pattern(pattern)
+      .create(name)
+  }
+
+  /// Width and height of the pattern charts.
+  const PATTERN_SIZE: usize = 16;
+
+  /// Read part of a pattern chart.
+  /// - `source`: chart source
+  /// - `pos`: position in zoomed pixels
+  /// - `size`: size in zoomed pixels
+  /// - `zoom`: zoom
+  fn read_pattern(
+    source: &super::RasterSource,
+    pos: (i32, i32),
+    size: (u32, u32),
+    zoom: f32,
+  ) -> Vec<u8> {
+    let rect = crate::util::Rect {
+      pos: crate::util::Pos { x: pos.0, y: pos.1 },
+      size: crate::util::Size {
+        w: size.0,
+        h: size.1,
+      },
+    };
+    let part = super::ImagePart::new(rect, zoom, super::Palette::Light);
+    let mut bands = Vec::new();
+    source.read(&part, &mut bands).unwrap();
+    assert!(bands[0].len() == (size.0 * size.1) as usize);
+    bands.swap_remove(0)
+  }
+
+  /// Read the whole pattern chart at a zoom.
+  fn read_pattern_zoom(source: &super::RasterSource, zoom: f32) -> Vec<u8> {
+    let size = (PATTERN_SIZE as f32 * zoom).round() as u32;
+    read_pattern(source, (0, 0), (size, size), zoom)
+  }
+
+  #[test]
+  fn test_read_checkerboard() {
+    let path = create_pattern_chart("aviate_test_checkerboard", |x, y| {
+      if (x + y) % 2 == 0 {
+        200
+      } else {
+        0
+      }
+    });
+    let (source, _, _) = super::RasterSource::open(&path).unwrap();
+
+    let image = read_pattern_zoom(&source, 1.0);
+    assert!(image[..4] == [200, 0, 200, 0] && image[16..20] == [0, 200, 0, 200]);
+
+    // Every block of source pixels is half on and half off.
+    assert!(read_pattern_zoom(&source, 0.5) == vec![100; 64]);
+    assert!(read_pattern_zoom(&source, 0.125) == vec![100; 4]);
+
+    // Blocks at a fractional zoom are uneven, but close to half on.
+    let image = read_pattern_zoom(&source, 0.37);
+    assert!(image.len() == 36);
+    assert!(image.iter().all(|val| val.abs_diff(100) <= 12));
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_read_gradient() {
+    let path = create_pattern_chart("aviate_test_gradient", |x, _| x as u8 * 16);
+    let (source, _, _) = super::RasterSource::open(&path).unwrap();
+
+    let image = read_pattern_zoom(&source, 1.0);
+    let row: Vec<u8> = (0..16).map(|x| x * 16).collect();
+    assert!(image.chunks(16).all(|chunk| chunk == row));
+
+    let image = read_pattern_zoom(&source, 0.5);
+    let row: Vec<u8> = (0..8).map(|x| x * 32 + 8).collect();
+    assert!(image.chunks(8).all(|chunk| chunk == row));
+
+    let image = read_pattern_zoom(&source, 0.125);
+    assert!(image == [56, 184, 56, 184]);
+
+    // Rows are the same and increase from left to right.
+    let image = read_pattern_zoom(&source, 0.37);
+    let row = &image[..6];
+    assert!(image.chunks(6).all(|chunk| chunk == row));
+    assert!(row.windows(2).all(|pair| pair[0] < pair[1]));
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_read_bright_pixel() {
+    let path = create_pattern_chart("aviate_test_bright_pixel", |x, y| {
+      if (x, y) == (3, 5) {
+        128
+      } else {
+        0
+      }
+    });
+    let (source, _, _) = super::RasterSource::open(&path).unwrap();
+
+    let image = read_pattern_zoom(&source, 1.0);
+    assert!(image[5 * 16 + 3] == 128 && image.iter().filter(|val| **val != 0).count() == 1);
+
+    let image = read_pattern_zoom(&source, 0.5);
+    assert!(image[2 * 8 + 1] == 32 && image.iter().filter(|val| **val != 0).count() == 1);
+
+    let image = read_pattern_zoom(&source, 0.125);
+    assert!(image == [2, 0, 0, 0]);
+
+    // The brightest pixel stays where the source pixel is.
+    let image = read_pattern_zoom(&source, 0.37);
+    let (index, _) = image
+      .iter()
+      .enumerate()
+      .max_by_key(|(_, val)| **val)
+      .unwrap();
+    assert!(index % 6 == 1 && (1..=2).contains(&(index / 6)));
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_read_seams() {
+    let path = create_pattern_chart("aviate_test_seams", |x, y| (x * 13 + y * 7) as u8);
+    let (source, _, _) = super::RasterSource::open(&path).unwrap();
+
+    // Reading two abutting halves gives the same pixels as reading the whole.
+    for zoom in [0.5, 0.25] {
+      let size = (PATTERN_SIZE as f32 * zoom) as u32;
+      let half = size / 2;
+      let whole = read_pattern(&source, (0, 0), (size, size), zoom);
+      let left = read_pattern(&source, (0, 0), (half, size), zoom);
+      let right = read_pattern(&source, (half as i32, 0), (half, size), zoom);
+      for y in 0..size as usize {
+        let row = &whole[y * size as usize..(y + 1) * size as usize];
+        let half = half as usize;
+        assert!(row[..half] == left[y * half..(y + 1) * half]);
+        assert!(row[half..] == right[y * half..(y + 1) * half]);
+      }
+    }
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_read_split() {
+    let path = create_pattern_chart("aviate_test_read_split", |x, y| (x * 29 + y * 11) as u8);
+    let (source, _, _) = super::RasterSource::open_with_threads(&path, 4).unwrap();
+    assert!(source.workers.len() == 3);
+
+    // Reading the rows in bands on separate threads gives the same pixels as reading them all at
+    // once, including where a band starts part way through a source row.
+    for zoom in [1.0, 0.5, 0.37, 0.3, 0.25, 0.125] {
+      let size = (PATTERN_SIZE as f32 * zoom).round() as u32;
+      let rect = crate::util::Rect {
+        pos: crate::util::Pos::default(),
+        size: crate::util::Size { w: size, h: size },
+      };
+      let part = super::ImagePart::new(rect, zoom, super::Palette::Light);
+      let (mut whole, mut split) = (Vec::new(), Vec::new());
+      source.read_split(&part, &mut whole, 1).unwrap();
+      source.read_split(&part, &mut split, 4).unwrap();
+      assert!(whole == split);
+    }
+
+    assert!(super::split_rows(10, 4) == [0..2, 2..5, 5..7, 7..10]);
+    assert!(super::split_rows(2, 4) == [0..1, 1..2]);
+    let rows = super::split_rows(0, 4);
+    assert!(rows.len() == 1 && rows[0].is_empty());
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_combined_chart() {
+    use eframe::epaint::Color32;
+
+    // Two charts side by side.
+    let west = TestChart::new().fill(10).create("aviate_test_west");
+    let east = TestChart::new()
+      .origin(1000.0, 1000.0)
+      .fill(20)
+      .create("aviate_test_east");
+    assert!(super::can_combine(&west, &east));
+
+    let (source, transform) = super::CompositeSource::open(&west, &east).unwrap();
+    assert!(transform.px_size() == crate::util::Size { w: 16, h: 4 });
+    let px = transform.chart_to_px(crate::util::Coord {
+      x: 1500.0,
+      y: 500.0,
+    });
+    assert!((px.x - 10.0).abs() < 1e-6 && (px.y - 2.0).abs() < 1e-6);
+
+    // A read that straddles the boundary gets pixels from both charts.
+    let rect = crate::util::Rect {
+      pos: crate::util::Pos { x: 4, y: 0 },
+      size: crate::util::Size { w: 8, h: 4 },
+    };
+    let image = source
+      .read_image(&super::ImagePart::new(rect, 1.0, super::Palette::Light))
+      .unwrap();
+    for row in image.pixels.chunks(8) {
+      assert!(row[..4]
+        .iter()
+        .all(|color| *color == Color32::from_gray(10)));
+      assert!(row[4..]
+        .iter()
+        .all(|color| *color == Color32::from_gray(20)));
+    }
+
+    // Same at half zoom.
+    let rect = crate::util::Rect {
+      pos: crate::util::Pos { x: 0, y: 0 },
+      size: crate::util::Size { w: 8, h: 2 },
+    };
+    let image = source
+      .read_image(&super::ImagePart::new(rect, 0.5, super::Palette::Light))
+      .unwrap();
+    for row in image.pixels.chunks(8) {
+      assert!(row[..4]
+        .iter()
+        .all(|color| *color == Color32::from_gray(10)));
+      assert!(row[4..]
+        .iter()
+        .all(|color| *color == Color32::from_gray(20)));
+    }
+
+    // Single pixels come from whichever chart covers them.
+    let pixel = |x| source.read_pixel(crate::util::Pos { x, y: 1 }).unwrap();
+    assert!(pixel(7) == Some(super::PixelValue::Index(10)));
+    assert!(pixel(8) == Some(super::PixelValue::Index(20)));
+    assert!(pixel(16).is_none());
+
+    // The order of the files doesn't matter.
+    let (source, transform) = super::CompositeSource::open(&east, &west).unwrap();
+    assert!(transform.px_size() == crate::util::Size { w: 16, h: 4 });
+    let image = source
+      .read_image(&super::ImagePart::new(rect, 0.5, super::Palette::Light))
+      .unwrap();
+    assert!(image.pixels[0] == Color32::from_gray(10));
+    assert!(image.pixels[7] == Color32::from_gray(20));
+
+    // Overlapping charts and charts that only touch at a corner can't be combined.
+    let overlap = TestChart::new().origin(0.0, 1000.0).fill(30);
+    let overlap = overlap.create("aviate_test_overlap");
+    let corner = TestChart::new().origin(1000.0, 0.0).fill(40);
+    let corner = corner.create("aviate_test_corner");
+    assert!(!super::can_combine(&west, &overlap));
+    assert!(!super::can_combine(&west, &corner));
+    assert!(super::CompositeSource::open(&west, &overlap).is_err());
+
+    for path in [west, east, overlap, corner] {
+      std::fs::remove_file(path).ok();
+    }
+  }
+
+  #[test]
+  fn test_rgb_chart() {
+    let path = TestChart::new().rgb().create("aviate_test_rgb");
+    let (source, transform, palette) = super::RasterSource::open(&path).unwrap();
+    assert!(matches!(
+      source.color_mode,
+      super::ColorMode::Rgb([1, 2, 3])
+    ));
+    assert!(palette.is_empty());
+    assert!(transform.px_size() == crate::util::Size { w: 8, h: 4 });
+
+    let rect = crate::util::Rect {
+      pos: crate::util::Pos { x: 0, y: 0 },
+      size: crate::util::Size { w: 4, h: 2 },
+    };
+    let part = super::ImagePart::new(rect, 0.5, super::Palette::Light);
+    let mut bands = Vec::new();
+    source.read(&part, &mut bands).unwrap();
+    assert!(bands.len() == 3);
+    assert!(bands[0].len() == 8);
+    assert!(bands[0].iter().all(|val| *val == 200));
+    assert!(bands[1].iter().all(|val| *val == 100));
+    assert!(bands[2].iter().all(|val| *val == 50));
+
+    // Buffers are reused by later reads.
+    let ptr = bands[0].as_ptr();
+    let part = super::ImagePart::new(rect, 1.0, super::Palette::Light);
+    source.read(&part, &mut bands).unwrap();
+    assert!(bands[0].len() == 8 && bands[0].as_ptr() == ptr);
+
+    // Single pixels read back as their components.
+    let (source, _) = super::ImageSource::open(&path).unwrap();
+    let value = source.read_pixel(crate::util::Pos { x: 7, y: 3 }).unwrap();
+    assert!(value == Some(super::PixelValue::Rgb([200, 100, 50])));
+    let value = source.read_pixel(crate::util::Pos { x: 8, y: 0 }).unwrap();
+    assert!(value.is_none());
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_spatial_ref() {
+    use crate::util::Coord;
+
+    let size = crate::util::Size { w: 8, h: 4 };
+    let geo_transform = [-1000.0, 250.0, 0.0, 1000.0, 0.0, -250.0];
+
+    // NAD83 and equivalent datums.
+    let datums = [
+      "+datum=NAD83",
+      "+datum=WGS84",
+      "+ellps=GRS80 +towgs84=0,0,0",
+    ];
+    for datum in datums {
+      let proj4 = format!("{LCC} {datum} +units=m +no_defs");
+      let sr = spatial_ref::SpatialRef::from_proj4(&proj4).unwrap();
+      assert!(super::check_spatial_ref(&sr).is_ok());
+
+      let transform = super::Transform::new(size, sr, geo_transform).unwrap();
+      assert!(transform.check_round_trip());
+
+      // The projection origin is still reported in NAD83 decimal degrees.
+      let nad83 = transform.chart_to_nad83(Coord { x: 0.0, y: 0.0 }).unwrap();
+      assert!((nad83.x + 118.45).abs() < 1.0e-4);
+      assert!((nad83.y - 34.1).abs() < 1.0e-4);
+    }
+
+    // Not meters.
+    let proj4 = format!("{LCC} +datum=NAD83 +units=ft +no_defs");
+    let sr = spatial_ref::SpatialRef::from_proj4(&proj4).unwrap();
+    assert!(super::check_spatial_ref(&sr).is_err());
+
+    // Not Lambert conformal conic.
+    let proj4 = "+proj=merc +lon_0=0 +k=1 +x_0=0 +y_0=0 +datum=WGS84 +units=m +no_defs";
+    let sr = spatial_ref::SpatialRef::from_proj4(proj4).unwrap();
+    assert!(super::check_spatial_ref(&sr).is_err());
+
+    // Not projected.
+    let sr = spatial_ref::SpatialRef::from_epsg(4269).unwrap();
+    assert!(super::check_spatial_ref(&sr).is_err());
+  }
+
+  #[test]
+  fn test_meters_per_pixel() {
+    let path = TestChart::new().rgb().create("aviate_test_scale");
+    let (_, transform, _) = super::RasterSource::open(&path).unwrap();
+    let a = transform.px_to_chart(crate::util::Coord { x: 1.0, y: 1.0 });
+    let b = transform.px_to_chart(crate::util::Coord { x: 7.0, y: 1.0 });
+    let dist = (b.x - a.x).hypot(b.y - a.y);
+    assert!((transform.meters_per_pixel() * 6.0 - dist).abs() < 1e-6);
+    assert!((transform.meters_per_pixel() - 250.0).abs() < 1e-6);
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_palette_chart() {
+    let path = TestChart::new().create("aviate_test_palette");
+    let (source, _, palette) = super::RasterSource::open(&path).unwrap();
+    assert!(matches!(source.color_mode, super::ColorMode::Palette(1)));
+    assert!(palette.len() == 256);
+
+    let rect = crate::util::Rect {
+      pos: crate::util::Pos { x: 0, y: 0 },
+      size: crate::util::Size { w: 8, h: 4 },
+    };
+    let part = super::ImagePart::new(rect, 1.0, super::Palette::Light);
+    let mut bands = Vec::new();
+    source.read(&part, &mut bands).unwrap();
+    assert!(bands.len() == 1);
+    assert!(bands[0].iter().all(|val| *val == 7));
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_short_palette() {
+    // A 6 bit GeoTIFF has a 64 entry color table.
+    let path = TestChart::new()
+      .palette_bits(6)
+      .create("aviate_test_short_palette");
+    let (source, transform) = super::ImageSource::open(&path).unwrap();
+    assert!(source.palette.len() == 64);
+    assert!(source.light.len() == super::PAL_LEN);
+
+    let rect = crate::util::Rect {
+      pos: crate::util::Pos { x: 0, y: 0 },
+      size: transform.px_size(),
+    };
+    let part = super::ImagePart::new(rect, 1.0, super::Palette::Light);
+    let image = source.read_image(&part).unwrap();
+    assert!(image.size == [8, 4]);
+    assert!(image
+      .pixels
+      .iter()
+      .all(|color| *color == eframe::epaint::Color32::from_gray(7)));
+
+    // The high contrast palette pushes the dark gray to black.
+    let part = super::ImagePart::new(rect, 1.0, super::Palette::Contrast);
+    let image = source.read_image(&part).unwrap();
+    assert!(image
+      .pixels
+      .iter()
+      .all(|color| *color == eframe::epaint::Color32::BLACK));
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_convert_palette() {
+    let entry = raster::RgbaEntry {
+      r: 10,
+      g: 20,
+      b: 30,
+      a: 255,
+    };
+    let colors = super::convert_palette(&[entry; 64], crate::util::color);
+    assert!(colors.len() == super::PAL_LEN);
+    assert!(colors[63] == eframe::epaint::Color32::from_rgb(10, 20, 30));
+    assert!(colors[64] == super::PAL_MISSING && colors[255] == super::PAL_MISSING);
+  }
+
+  #[test]
+  fn test_read_stats() {
+    use super::ReadStats;
+    use std::time::Duration;
+
+    let mut stats = ReadStats::default();
+    assert!(stats.average().is_none() && stats.p95().is_none() && !stats.is_slow());
+
+    // A single slow read isn't enough.
+    stats.add_read(Duration::from_millis(900));
+    assert!(stats.p95() == Some(Duration::from_millis(900)) && !stats.is_slow());
+
+    // One slow read out of twenty doesn't change the 95th percentile much.
+    for _ in 0..19 {
+      stats.add_read(Duration::from_millis(100));
+    }
+    assert!(stats.average() == Some(Duration::from_millis(140)));
+    assert!(stats.p95() == Some(Duration::from_millis(100)) && !stats.is_slow());
+
+    // Consistently slow reads.
+    for _ in 0..10 {
+      stats.add_read(Duration::from_millis(500));
+    }
+    assert!(stats.p95() == Some(Duration::from_millis(500)) && stats.is_slow());
+
+    // Only the recent reads count.
+    for _ in 0..ReadStats::WINDOW {
+      stats.add_read(Duration::from_millis(50));
+    }
+    assert!(stats.read_count == 30 + ReadStats::WINDOW);
+    assert!(stats.average() == Some(Duration::from_millis(50)) && !stats.is_slow());
+  }
+
+  /// Wait for the reply to opening a chart. Returns the error text if it couldn't be opened.
+  fn wait_open(reader: &super::RasterReader) -> Result<(), crate::util::Error> {
+    use std::{thread, time};
+
+    let start = time::Instant::now();
+    while start.elapsed() < time::Duration::from_secs(10) {
+      for reply in reader.get_replies() {
+        match reply {
+          super::RasterReply::Opened => return Ok(()),
+          super::RasterReply::OpenError(err) => return Err(err),
+          _ => (),
+        }
+      }
+      thread::sleep(time::Duration::from_millis(10));
+    }
+    panic!("Timed out waiting for the chart to open");
+  }
+
+  #[test]
+  fn test_reader_open() {
+    let path = TestChart::new().create("aviate_test_reader_open");
+    let ctx = eframe::egui::Context::default();
+    let reader = super::RasterReader::new(&path, &ctx);
+    assert!(wait_open(&reader).is_ok() && reader.opened.get().is_some());
+    assert!(reader.info().palette.len() == 256);
+
+    // The transformation rebuilt from its parts matches one from opening the chart here.
+    let (_, transform) = super::ImageSource::open(&path).unwrap();
+    let rebuilt = reader.transform();
+    assert!(rebuilt.px_size() == transform.px_size());
+    assert!(rebuilt.geo_transform() == transform.geo_transform());
+    assert!(rebuilt.get_proj4() == transform.get_proj4());
+
+    let px = eframe::emath::pos2(3.0, 2.0).into();
+    let lcc = transform.px_to_chart(px);
+    assert!(rebuilt.px_to_chart(px) == lcc);
+    assert!(rebuilt.chart_to_nad83(lcc).unwrap() == transform.chart_to_nad83(lcc).unwrap());
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_reader_open_error() {
+    let ctx = eframe::egui::Context::default();
+
+    // Missing file.
+    let path = env::temp_dir().join("aviate_test_missing_chart.tif");
+    let reader = super::RasterReader::new(&path, &ctx);
+    let err = wait_open(&reader).unwrap_err();
+    assert!(err.starts_with("Unable to open chart"));
+    assert!(reader.opened.get().is_none() && reader.edition().is_none());
+
+    // One of the two halves is missing.
+    let path = TestChart::new().create("aviate_test_open_error_half");
+    let missing = env::temp_dir().join("aviate_test_missing_half.tif");
+    let reader = super::RasterReader::new_combined([path.clone(), missing], &ctx);
+    assert!(wait_open(&reader).is_err() && reader.opened.get().is_none());
+    std::fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_reader_pixel() {
+    use crate::util::Pos;
+    use std::time;
+
+    let path = TestChart::new().create("aviate_test_reader_pixel");
+    let ctx = eframe::egui::Context::default();
+    let reader = super::RasterReader::new(&path, &ctx);
+    assert!(wait_open(&reader).is_ok());
+
+    // Pixels are read on the probe thread and returned as replies.
+    let read_pixel = |px| {
+      reader.read_pixel(px);
+      match reader.wait_reply(time::Duration::from_secs(10)) {
+        Some(super::RasterReply::Pixel(pos, value)) if pos == px => value,
+        _ => panic!("No pixel reply"),
+      }
+    };
+    assert!(read_pixel(Pos { x: 7, y: 3 }) == Some(super::PixelValue::Index(7)));
+    assert!(read_pixel(Pos { x: 8, y: 0 }).is_none());
+    std::fs::remove_file(path).ok();
   }
 }
@@ -0,0 +1,410 @@
+use crate::util;
+use eframe::egui;
+use std::{any, collections, ffi::OsStr, fs, path, sync::mpsc, thread, time};
+
+/// A chart file within a downloaded chart zip file and the area that it covers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CatalogEntry {
+  /// Zip file path.
+  pub path: path::PathBuf,
+
+  /// Chart file within the zip file.
+  pub file: path::PathBuf,
+
+  /// NAD83 coordinates of the chart corners (top left, top right, bottom right, bottom left).
+  pub corners: [util::Coord; 4],
+}
+
+impl CatalogEntry {
+  /// Chart name (the chart file name without the extension).
+  pub fn name(&self) -> &str {
+    util::stem_str(&self.file).unwrap_or_default()
+  }
+
+  /// Returns true if the chart covers a coordinate.
+  /// - `coord`: NAD83 coordinate
+  pub fn contains(&self, coord: util::Coord) -> bool {
+    // Count the edges that a ray going right from the coordinate crosses.
+    let mut inside = false;
+    let mut prev = self.corners[3];
+    for corner in self.corners {
+      if (corner.y > coord.y) != (prev.y > coord.y) {
+        let x = corner.x + (coord.y - corner.y) * (prev.x - corner.x) / (prev.y - corner.y);
+        if coord.x < x {
+          inside = !inside;
+        }
+      }
+      prev = corner;
+    }
+    inside
+  }
+}
+
+/// Catalog of the charts in a folder of downloaded chart zip files. The folder is scanned on a
+/// separate thread, and the results are cached so that unchanged zip files aren't opened again.
+pub struct Catalog {
+  folder: path::PathBuf,
+  rx: mpsc::Receiver<Reply>,
+  entries: Vec<CatalogEntry>,
+  progress: Option<(usize, usize)>,
+}
+
+impl Catalog {
+  /// Start scanning a folder for chart zip files.
+  /// - `folder`: folder containing the zip files
+  /// - `ctx`: egui context for requesting a repaint
+  pub fn scan(folder: path::PathBuf, ctx: &egui::Context) -> Self {
+    let ctx = ctx.clone();
+    let (tx, rx) = mpsc::channel();
+    let scan_folder = folder.clone();
+    thread::Builder::new()
+      .name(any::type_name::<Catalog>().to_owned())
+      .spawn(move || {
+        let cache_path = cache_path();
+        let mut cache = cache_path.as_deref().map(load_cache).unwrap_or_default();
+        let zips = list_zips(&scan_folder);
+        let mut scanned = Cache::new();
+        let mut changed = false;
+        for (index, zip) in zips.iter().enumerate() {
+          if tx.send(Reply::Progress(index, zips.len())).is_err() {
+            return;
+          }
+          ctx.request_repaint();
+
+          let modified = modified_secs(zip);
+          let item = match cache.remove(zip) {
+            Some(item) if item.modified == modified => item,
+            _ => {
+              changed = true;
+              CacheItem {
+                modified,
+                charts: read_zip(zip),
+              }
+            }
+          };
+          scanned.insert(zip.clone(), item);
+        }
+
+        // Zip files that were removed or are in other folders are dropped from the cache.
+        changed |= !cache.is_empty();
+        if changed {
+          if let Some(path) = &cache_path {
+            save_cache(path, &scanned);
+          }
+        }
+
+        let mut entries = Vec::new();
+        for (path, item) in scanned {
+          for (file, corners) in item.charts {
+            let path = path.clone();
+            entries.push(CatalogEntry {
+              path,
+              file,
+              corners,
+            });
+          }
+        }
+
+        entries.sort_by(|a, b| a.name().cmp(b.name()));
+        if tx.send(Reply::Done(entries)).is_ok() {
+          ctx.request_repaint();
+        }
+      })
+      .unwrap();
+
+    Self {
+      folder,
+      rx,
+      entries: Vec::new(),
+      progress: Some((0, 0)),
+    }
+  }
+
+  /// The folder being cataloged.
+  pub fn folder(&self) -> &path::Path {
+    &self.folder
+  }
+
+  /// Process the messages from the scan thread. Call this once per frame.
+  pub fn update(&mut self) {
+    while let Ok(reply) = self.rx.try_recv() {
+      match reply {
+        Reply::Progress(done, total) => self.progress = Some((done, total)),
+        Reply::Done(entries) => {
+          self.entries = entries;
+          self.progress = None;
+        }
+      }
+    }
+  }
+
+  /// Number of zip files scanned and the total, if the scan is still running.
+  pub fn progress(&self) -> Option<(usize, usize)> {
+    self.progress
+  }
+
+  /// Number of charts found.
+  pub fn chart_count(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// Find the charts that cover a coordinate.
+  /// - `coord`: NAD83 coordinate
+  pub fn find_covering(&self, coord: util::Coord) -> Vec<CatalogEntry> {
+    find_covering(&self.entries, coord)
+  }
+}
+
+enum Reply {
+  Progress(usize, usize),
+  Done(Vec<CatalogEntry>),
+}
+
+fn find_covering(entries: &[CatalogEntry], coord: util::Coord) -> Vec<CatalogEntry> {
+  let iter = entries.iter().filter(|entry| entry.contains(coord));
+  iter.cloned().collect()
+}
+
+/// Zip files in a folder, sorted by path.
+fn list_zips(folder: &path::Path) -> Vec<path::PathBuf> {
+  let Ok(entries) = fs::read_dir(folder) else {
+    return Vec::new();
+  };
+
+  let zip_ext = Some(OsStr::new("zip"));
+  let mut zips: Vec<_> = entries
+    .flatten()
+    .map(|entry| entry.path())
+    .filter(|path| path.extension() == zip_ext && path.is_file())
+    .collect();
+  zips.sort();
+  zips
+}
+
+/// File modification time in seconds since the Unix epoch, or zero if it isn't available.
+fn modified_secs(path: &path::Path) -> u64 {
+  let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+  let elapsed = modified
+    .ok()
+    .and_then(|time| time.duration_since(time::UNIX_EPOCH).ok());
+  elapsed.map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Read the chart files in a zip file. Zip files that don't contain charts give an empty list.
+fn read_zip(path: &path::Path) -> Vec<(path::PathBuf, [util::Coord; 4])> {
+  let Ok(util::ZipInfo::Chart(files)) = util::get_zip_info(path) else {
+    return Vec::new();
+  };
+
+  let Ok(vsi_path) = util::vsi_path("/vsizip/", path) else {
+    return Vec::new();
+  };
+
+  let mut charts = Vec::with_capacity(files.len());
+  for file in files {
+    if let Some(corners) = read_corners(&vsi_path.join(&file)) {
+      charts.push((file, corners));
+    }
+  }
+  charts
+}
+
+/// Read a chart file's header (no pixels) and convert its corners to NAD83.
+/// - `path`: VSI path of the chart file
+fn read_corners(path: &path::Path) -> Option<[util::Coord; 4]> {
+  let dataset = gdal::Dataset::open_ex(path, super::RasterSource::open_options()).ok()?;
+  let px_size: util::Size = dataset.raster_size().into();
+  if !px_size.is_valid() {
+    return None;
+  }
+
+  let spatial_ref = dataset.spatial_ref().ok()?;
+  let geo_transform = dataset.geo_transform().ok()?;
+  let transform = super::Transform::new(px_size, spatial_ref, geo_transform).ok()?;
+  let (w, h) = (px_size.w as f64, px_size.h as f64);
+  let mut corners = [util::Coord::default(); 4];
+  let pxs = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+  for (corner, px) in corners.iter_mut().zip(pxs) {
+    *corner = transform.px_to_nad83(px.into()).ok()?;
+  }
+  Some(corners)
+}
+
+/// Cached charts for a zip file.
+#[derive(Debug, PartialEq)]
+struct CacheItem {
+  /// Zip file modification time in seconds since the Unix epoch.
+  modified: u64,
+  charts: Vec<(path::PathBuf, [util::Coord; 4])>,
+}
+
+type Cache = collections::HashMap<path::PathBuf, CacheItem>;
+
+fn cache_path() -> Option<path::PathBuf> {
+  let name = format!("{}_catalog.json", util::APP_NAME);
+  dirs::cache_dir().map(|path| path.join(name))
+}
+
+fn load_cache(path: &path::Path) -> Cache {
+  let text = fs::read_to_string(path).unwrap_or_default();
+  let value = serde_json::from_str(&text).unwrap_or_default();
+  cache_from_value(&value)
+}
+
+fn save_cache(path: &path::Path, cache: &Cache) {
+  if let Err(err) = fs::write(path, cache_to_value(cache).to_string()) {
+    log::error!("Unable to save the chart catalog: {err}");
+  }
+}
+
+fn cache_from_value(value: &serde_json::Value) -> Cache {
+  let mut cache = Cache::new();
+  let Some(items) = value.as_object() else {
+    return cache;
+  };
+
+  for (path, item) in items {
+    let Some(modified) = item.get(MODIFIED_KEY).and_then(|val| val.as_u64()) else {
+      continue;
+    };
+
+    let mut charts = Vec::new();
+    let array = item.get(CHARTS_KEY).and_then(|val| val.as_array());
+    for chart in array.into_iter().flatten() {
+      let Some(file) = chart.get(FILE_KEY).and_then(|val| val.as_str()) else {
+        continue;
+      };
+
+      let Some(corners) = chart.get(CORNERS_KEY).and_then(corners_from_value) else {
+        continue;
+      };
+      charts.push((file.into(), corners));
+    }
+
+    cache.insert(path.into(), CacheItem { modified, charts });
+  }
+  cache
+}
+
+fn cache_to_value(cache: &Cache) -> serde_json::Value {
+  let mut items = serde_json::Map::new();
+  for (path, item) in cache {
+    // Paths that aren't valid unicode can't be opened through GDAL anyway.
+    let Some(path) = path.to_str() else {
+      continue;
+    };
+
+    let charts: Vec<_> = item
+      .charts
+      .iter()
+      .filter_map(|(file, corners)| {
+        let corners = corners.map(|corner| serde_json::json!([corner.x, corner.y]));
+        Some(serde_json::json!({FILE_KEY: file.to_str()?, CORNERS_KEY: corners}))
+      })
+      .collect();
+
+    let item = serde_json::json!({MODIFIED_KEY: item.modified, CHARTS_KEY: charts});
+    items.insert(path.into(), item);
+  }
+  serde_json::Value::Object(items)
+}
+
+fn corners_from_value(value: &serde_json::Value) -> Option<[util::Coord; 4]> {
+  let array = value.as_array().filter(|array| array.len() == 4)?;
+  let mut corners = [util::Coord::default(); 4];
+  for (corner, value) in corners.iter_mut().zip(array) {
+    let x = value.get(0)?.as_f64()?;
+    let y = value.get(1)?.as_f64()?;
+    *corner = util::Coord { x, y };
+  }
+  Some(corners)
+}
+
+const MODIFIED_KEY: &str = "modified";
+const CHARTS_KEY: &str = "charts";
+const FILE_KEY: &str = "file";
+const CORNERS_KEY: &str = "corners";
+
+#[cfg(test)]
+mod test {
+  use crate::util;
+  use std::path;
+
+  fn entry(name: &str, corners: [(f64, f64); 4]) -> super::CatalogEntry {
+    super::CatalogEntry {
+      path: path::PathBuf::from(format!("/charts/{name}.zip")),
+      file: path::PathBuf::from(format!("{name}.tif")),
+      corners: corners.map(util::Coord::from),
+    }
+  }
+
+  #[test]
+  fn test_find_covering() {
+    // Chart edges in NAD83 aren't aligned with the meridians and parallels.
+    let sf_sec = [
+      (-125.6, 40.1),
+      (-118.1, 40.4),
+      (-118.4, 35.6),
+      (-125.2, 35.3),
+    ];
+    let sf_tac = [
+      (-123.4, 38.3),
+      (-121.2, 38.4),
+      (-121.3, 36.9),
+      (-123.3, 36.8),
+    ];
+    let la_sec = [
+      (-122.1, 36.1),
+      (-115.1, 36.3),
+      (-115.4, 31.9),
+      (-121.6, 31.7),
+    ];
+    let entries = [
+      entry("San Francisco SEC", sf_sec),
+      entry("San Francisco TAC", sf_tac),
+      entry("Los Angeles SEC", la_sec),
+    ];
+
+    let names = |coord: (f64, f64)| -> Vec<String> {
+      let found = super::find_covering(&entries, coord.into());
+      found.iter().map(|entry| entry.name().to_owned()).collect()
+    };
+
+    // KSFO is on the sectional and the TAC.
+    assert!(names((-122.375, 37.619)) == ["San Francisco SEC", "San Francisco TAC"]);
+
+    // KSBP is where the two sectionals overlap.
+    assert!(names((-120.642, 35.237)) == ["Los Angeles SEC"]);
+    assert!(names((-120.642, 35.7)) == ["San Francisco SEC", "Los Angeles SEC"]);
+
+    // KSEA isn't covered.
+    assert!(names((-122.309, 47.449)).is_empty());
+  }
+
+  #[test]
+  fn test_cache_value() {
+    let mut cache = super::Cache::new();
+    let corners = [(1.0, 2.0), (3.0, 4.0), (5.0, 6.0), (7.0, 8.0)].map(util::Coord::from);
+    let charts = vec![("Test SEC.tif".into(), corners)];
+    let item = super::CacheItem {
+      modified: 1_700_000_000,
+      charts,
+    };
+    cache.insert("/charts/Test.zip".into(), item);
+
+    // Zip files without charts are kept so that they aren't opened again.
+    let item = super::CacheItem {
+      modified: 1_700_000_001,
+      charts: Vec::new(),
+    };
+    cache.insert("/charts/NASR.zip".into(), item);
+
+    let value = super::cache_to_value(&cache);
+    assert!(super::cache_from_value(&value) == cache);
+
+    // Malformed items are skipped.
+    let value = serde_json::json!({"/charts/A.zip": {"charts": []}, "/charts/B.zip": 7});
+    assert!(super::cache_from_value(&value).is_empty());
+    assert!(super::cache_from_value(&serde_json::json!([])).is_empty());
+  }
+}
@@ -0,0 +1,319 @@
+use crate::util;
+use std::fmt;
+
+/// Chart edition details from a chart file's metadata.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChartEdition {
+  /// Edition number.
+  pub number: Option<u32>,
+
+  /// First day that the edition is in effect.
+  pub effective: Option<util::Date>,
+
+  /// Last day that the edition is in effect.
+  pub expiration: Option<util::Date>,
+}
+
+impl ChartEdition {
+  /// Find the edition details in metadata items. Items with a recognized key (e.g. "EDITION" or
+  /// "EFFECTIVE_DATE") are read as that value, and any other item is searched as free text.
+  /// Returns `None` if nothing is found, which is normal for charts from other sources.
+  /// - `items`: metadata keys and values
+  pub fn from_metadata<K, V, I>(items: I) -> Option<Self>
+  where
+    K: AsRef<str>,
+    V: AsRef<str>,
+    I: IntoIterator<Item = (K, V)>,
+  {
+    let mut edition = Self::default();
+    for (key, value) in items {
+      let key = key.as_ref().to_uppercase();
+      let tokens = tokenize(value.as_ref());
+      let found = if key.contains("EDITION") {
+        Self {
+          number: tokens.iter().find_map(|token| parse_number(token)),
+          ..Default::default()
+        }
+      } else if ["EFFECTIVE", "START", "BEGIN"]
+        .iter()
+        .any(|k| key.contains(k))
+      {
+        Self {
+          effective: find_date(&tokens).map(|(date, _)| date),
+          ..Default::default()
+        }
+      } else if key.contains("EXPIR") || key.contains("END_DATE") || key.ends_with("END") {
+        Self {
+          expiration: find_date(&tokens).map(|(date, _)| date),
+          ..Default::default()
+        }
+      } else {
+        Self::from_tokens(&tokens)
+      };
+      edition.merge(found);
+    }
+
+    (edition != Self::default()).then_some(edition)
+  }
+
+  /// Returns true if the edition has expired.
+  /// - `today`: current date
+  pub fn expired(&self, today: util::Date) -> bool {
+    self.expiration.is_some_and(|expiration| today > expiration)
+  }
+
+  /// Search free text (e.g. "113TH EDITION EFFECTIVE 0901Z 5 SEP 2024 TO 0901Z 20 MAR 2025").
+  fn from_tokens(tokens: &[String]) -> Self {
+    let mut edition = Self::default();
+    for (index, token) in tokens.iter().enumerate() {
+      let rest = &tokens[index + 1..];
+      match token.as_str() {
+        "EDITION" => {
+          // "113TH EDITION", "EDITION 113" or "EDITION NO 113".
+          let before = index
+            .checked_sub(1)
+            .and_then(|prev| parse_number(&tokens[prev]));
+          let after = match rest {
+            [number, ..] if parse_number(number).is_some() => parse_number(number),
+            [label, number, ..] if ["NO", "NR", "NUMBER"].contains(&label.as_str()) => {
+              parse_number(number)
+            }
+            _ => None,
+          };
+          edition.number = edition.number.or(before).or(after);
+        }
+        "EFFECTIVE" => {
+          let Some((date, end)) = find_date(rest) else {
+            continue;
+          };
+
+          edition.effective = edition.effective.or(Some(date));
+
+          // An end date can follow (e.g. "TO 20 MAR 2025").
+          let rest = &rest[end..];
+          if rest
+            .first()
+            .is_some_and(|token| ["TO", "THROUGH", "THRU", "UNTIL"].contains(&token.as_str()))
+          {
+            let expiration = find_date(&rest[1..]).map(|(date, _)| date);
+            edition.expiration = edition.expiration.or(expiration);
+          }
+        }
+        token if token.starts_with("EXPIR") || token == "END" || token == "ENDS" => {
+          let expiration = find_date(rest).map(|(date, _)| date);
+          edition.expiration = edition.expiration.or(expiration);
+        }
+        _ => (),
+      }
+    }
+    edition
+  }
+
+  /// Fill in the missing details from another edition.
+  fn merge(&mut self, other: Self) {
+    self.number = self.number.or(other.number);
+    self.effective = self.effective.or(other.effective);
+    self.expiration = self.expiration.or(other.expiration);
+  }
+}
+
+impl fmt::Display for ChartEdition {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut parts = Vec::new();
+    if let Some(number) = self.number {
+      parts.push(format!("Edition {number}"));
+    }
+
+    if let Some(effective) = self.effective {
+      parts.push(format!("Effective {effective}"));
+    }
+
+    if let Some(expiration) = self.expiration {
+      parts.push(format!("Expires {expiration}"));
+    }
+    write!(f, "{}", parts.join(", "))
+  }
+}
+
+/// Split text into upper case words and numbers.
+fn tokenize(text: &str) -> Vec<String> {
+  let iter = text.split(|ch: char| !ch.is_ascii_alphanumeric());
+  iter
+    .filter(|token| !token.is_empty())
+    .map(str::to_uppercase)
+    .collect()
+}
+
+/// Parse an edition number, which may have an ordinal suffix (e.g. "113TH").
+fn parse_number(token: &str) -> Option<u32> {
+  let digits = token.trim_end_matches(['S', 'T', 'N', 'D', 'R', 'H']);
+  if digits.is_empty() || digits.len() > 3 || !digits.bytes().all(|ch| ch.is_ascii_digit()) {
+    return None;
+  }
+  digits.parse().ok().filter(|number| *number > 0)
+}
+
+/// Find the first date in a list of tokens. Returns the date and the index of the token after it.
+fn find_date(tokens: &[String]) -> Option<(util::Date, usize)> {
+  (0..tokens.len()).find_map(|index| {
+    let (date, len) = date_at(&tokens[index..])?;
+    Some((date, index + len))
+  })
+}
+
+/// Parse a date from the start of a list of tokens. Returns the date and the number of tokens
+/// that it used. These forms are recognized:
+/// - `20240905`
+/// - `2024-09-05`, `2024/9/5` or `2024:09:05`
+/// - `09/05/2024` (falling back to day first if that isn't a valid date)
+/// - `SEP 5, 2024` or `SEPTEMBER 05 2024`
+/// - `5 SEP 2024`
+fn date_at(tokens: &[String]) -> Option<(util::Date, usize)> {
+  let token = |index: usize| tokens.get(index).map_or("", String::as_str);
+  let number = |index: usize, lens: std::ops::RangeInclusive<usize>| {
+    let text = token(index);
+    if !lens.contains(&text.len()) || !text.bytes().all(|ch| ch.is_ascii_digit()) {
+      return None;
+    }
+    text.parse::<u32>().ok()
+  };
+
+  // Compact form.
+  if let Some(value) = number(0, 8..=8) {
+    let date = util::Date::new((value / 10000) as i32, value / 100 % 100, value % 100);
+    return date.map(|date| (date, 1));
+  }
+
+  // Year first.
+  if let (Some(year), Some(month), Some(day)) =
+    (number(0, 4..=4), number(1, 1..=2), number(2, 1..=2))
+  {
+    return util::Date::new(year as i32, month, day).map(|date| (date, 3));
+  }
+
+  // Month first, then day first.
+  if let (Some(a), Some(b), Some(year)) = (number(0, 1..=2), number(1, 1..=2), number(2, 4..=4)) {
+    let date = util::Date::new(year as i32, a, b).or_else(|| util::Date::new(year as i32, b, a));
+    return date.map(|date| (date, 3));
+  }
+
+  // Month name first.
+  if let (Some(month), Some(day), Some(year)) =
+    (month(token(0)), number(1, 1..=2), number(2, 4..=4))
+  {
+    return util::Date::new(year as i32, month, day).map(|date| (date, 3));
+  }
+
+  // Day first with a month name.
+  if let (Some(day), Some(month), Some(year)) =
+    (number(0, 1..=2), month(token(1)), number(2, 4..=4))
+  {
+    return util::Date::new(year as i32, month, day).map(|date| (date, 3));
+  }
+  None
+}
+
+/// Month number for a full or abbreviated (at least three letters) upper case month name.
+fn month(token: &str) -> Option<u32> {
+  const MONTHS: [&str; 12] = [
+    "JANUARY",
+    "FEBRUARY",
+    "MARCH",
+    "APRIL",
+    "MAY",
+    "JUNE",
+    "JULY",
+    "AUGUST",
+    "SEPTEMBER",
+    "OCTOBER",
+    "NOVEMBER",
+    "DECEMBER",
+  ];
+
+  if token.len() < 3 {
+    return None;
+  }
+
+  let index = MONTHS.iter().position(|name| name.starts_with(token))?;
+  Some(index as u32 + 1)
+}
+
+#[cfg(test)]
+mod test {
+  use super::ChartEdition;
+  use crate::util::Date;
+
+  fn date(year: i32, month: u32, day: u32) -> Option<Date> {
+    Date::new(year, month, day)
+  }
+
+  #[test]
+  fn test_keyed_metadata() {
+    let items = [
+      ("AREA_OR_POINT", "Area"),
+      ("EDITION", "113"),
+      ("EFFECTIVE_DATE", "2024-09-05"),
+      ("EXPIRATION_DATE", "03/20/2025"),
+    ];
+    let edition = ChartEdition::from_metadata(items).unwrap();
+    assert!(edition.number == Some(113));
+    assert!(edition.effective == date(2024, 9, 5));
+    assert!(edition.expiration == date(2025, 3, 20));
+    assert!(edition.to_string() == "Edition 113, Effective 2024-09-05, Expires 2025-03-20");
+
+    assert!(!edition.expired(Date::new(2025, 3, 20).unwrap()));
+    assert!(edition.expired(Date::new(2025, 3, 21).unwrap()));
+
+    // Other keys and date forms.
+    let items = [
+      ("CHART_EDITION", "87th"),
+      ("START", "20240905"),
+      ("END", "Mar 20, 2025"),
+    ];
+    let edition = ChartEdition::from_metadata(items).unwrap();
+    assert!(edition.number == Some(87));
+    assert!(edition.effective == date(2024, 9, 5));
+    assert!(edition.expiration == date(2025, 3, 20));
+  }
+
+  #[test]
+  fn test_free_text_metadata() {
+    let text =
+      "SAN FRANCISCO SECTIONAL 113TH EDITION EFFECTIVE 0901Z 5 SEP 2024 TO 0901Z 20 MAR 2025";
+    let edition = ChartEdition::from_metadata([("TIFFTAG_IMAGEDESCRIPTION", text)]).unwrap();
+    assert!(edition.number == Some(113));
+    assert!(edition.effective == date(2024, 9, 5));
+    assert!(edition.expiration == date(2025, 3, 20));
+
+    let text = "Seattle TAC, edition no. 42. Effective September 5, 2024; expires 2025/3/20.";
+    let edition = ChartEdition::from_metadata([("TIFFTAG_DOCUMENTNAME", text)]).unwrap();
+    assert!(edition.number == Some(42));
+    assert!(edition.effective == date(2024, 9, 5));
+    assert!(edition.expiration == date(2025, 3, 20));
+
+    // Day first when month first isn't a valid date.
+    let edition = ChartEdition::from_metadata([("NOTE", "Effective 20/03/2025")]).unwrap();
+    assert!(edition.effective == date(2025, 3, 20));
+    assert!(edition.number.is_none() && edition.expiration.is_none());
+  }
+
+  #[test]
+  fn test_missing_metadata() {
+    let items = [
+      ("AREA_OR_POINT", "Area"),
+      ("TIFFTAG_SOFTWARE", "GDAL 3.8.4"),
+      ("TIFFTAG_DATETIME", "2024:09:05 12:00:00"),
+      ("TIFFTAG_RESOLUTIONUNIT", "2 (pixels/inch)"),
+    ];
+    assert!(ChartEdition::from_metadata(items).is_none());
+    assert!(ChartEdition::from_metadata(Vec::<(String, String)>::new()).is_none());
+
+    // Words that look like months or numbers that look like editions aren't matched alone.
+    let items = [
+      ("EDITION", "first"),
+      ("EFFECTIVE", "MAYBE 2024"),
+      ("NOTE", "EDITION 2024"),
+    ];
+    assert!(ChartEdition::from_metadata(items).is_none());
+  }
+}
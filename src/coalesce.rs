@@ -0,0 +1,123 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Create a single-slot channel where sending replaces any request that hasn't been received yet.
+///
+/// This is useful for a worker thread where only the most recent request matters, such as reading
+/// the part of a chart that is currently visible. No matter how many requests are sent while the
+/// worker is busy, it only ever sees the latest one.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+  let shared = Arc::new(Shared {
+    state: Mutex::new(State {
+      value: None,
+      closed: false,
+    }),
+    ready: Condvar::new(),
+  });
+
+  let sender = Sender {
+    shared: shared.clone(),
+  };
+  (sender, Receiver { shared })
+}
+
+struct State<T> {
+  value: Option<T>,
+  closed: bool,
+}
+
+struct Shared<T> {
+  state: Mutex<State<T>>,
+  ready: Condvar,
+}
+
+/// Sending half of a coalescing channel. Dropping it closes the channel.
+pub struct Sender<T> {
+  shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+  /// Send a request, replacing the pending request (if any).
+  /// - `value`: the request
+  pub fn send(&self, value: T) {
+    let mut state = self.shared.state.lock().unwrap();
+    state.value = Some(value);
+    self.shared.ready.notify_one();
+  }
+}
+
+impl<T> Drop for Sender<T> {
+  fn drop(&mut self) {
+    if let Ok(mut state) = self.shared.state.lock() {
+      state.closed = true;
+    }
+    self.shared.ready.notify_one();
+  }
+}
+
+/// Receiving half of a coalescing channel.
+pub struct Receiver<T> {
+  shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+  /// Wait for a request. Returns `None` once the sender has been dropped.
+  pub fn recv(&self) -> Option<T> {
+    let mut state = self.shared.state.lock().unwrap();
+    loop {
+      // Pending requests are dropped once the channel is closed.
+      if state.closed {
+        return None;
+      }
+
+      if let Some(value) = state.value.take() {
+        return Some(value);
+      }
+
+      state = self.shared.ready.wait(state).unwrap();
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::{sync::mpsc, thread, time};
+
+  #[test]
+  fn test_flood() {
+    const COUNT: usize = 10_000;
+    let (tx, rx) = super::channel();
+    let (done_tx, done_rx) = mpsc::channel();
+    let worker = thread::spawn(move || {
+      let mut reads = 0;
+      while let Some(value) = rx.recv() {
+        // Simulate a slow read.
+        thread::sleep(time::Duration::from_millis(20));
+        reads += 1;
+        if value == COUNT - 1 {
+          done_tx.send(()).unwrap();
+        }
+      }
+      reads
+    });
+
+    for value in 0..COUNT {
+      tx.send(value);
+    }
+
+    // The last request is always read.
+    done_rx.recv_timeout(time::Duration::from_secs(10)).unwrap();
+    drop(tx);
+
+    let reads = worker.join().unwrap();
+    assert!(reads < 10);
+  }
+
+  #[test]
+  fn test_closed() {
+    let (tx, rx) = super::channel::<u32>();
+    tx.send(1);
+    assert!(rx.recv() == Some(1));
+    drop(tx);
+    assert!(rx.recv().is_none());
+  }
+}
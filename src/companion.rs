@@ -0,0 +1,69 @@
+use crate::util;
+use eframe::egui;
+use std::{any, ffi::OsStr, fs, path, sync::mpsc, thread};
+
+/// CompanionFinder looks for a companion zip file (NASR data for a chart or a chart for NASR data)
+/// in the same folder as a zip file that was just opened.
+pub struct CompanionFinder {
+  aero: bool,
+  rx: mpsc::Receiver<path::PathBuf>,
+}
+
+impl CompanionFinder {
+  /// Start looking for a companion zip file.
+  /// - `zip`: path of the zip file that was opened
+  /// - `aero`: look for NASR data rather than a chart
+  /// - `ctx`: egui context for requesting a repaint
+  pub fn new(zip: &path::Path, aero: bool, ctx: &egui::Context) -> Option<Self> {
+    let folder = zip.parent()?.to_owned();
+    let zip = zip.to_owned();
+    let ctx = ctx.clone();
+    let (tx, rx) = mpsc::channel();
+
+    // Each zip file is opened through GDAL, so do the scan on a separate thread.
+    thread::Builder::new()
+      .name(any::type_name::<CompanionFinder>().to_owned())
+      .spawn(move || {
+        let Ok(entries) = fs::read_dir(folder) else {
+          return;
+        };
+
+        let zip_ext = Some(OsStr::new("zip"));
+        let mut found = Vec::new();
+        for entry in entries.flatten() {
+          let path = entry.path();
+          if path == zip || path.extension() != zip_ext || !path.is_file() {
+            continue;
+          }
+
+          let matched = match util::get_zip_info(&path) {
+            Ok(util::ZipInfo::Chart(_)) => !aero,
+            Ok(util::ZipInfo::Aero { .. }) => aero,
+            Err(_) => false,
+          };
+
+          if matched {
+            found.push(path);
+          }
+        }
+
+        // Only offer a companion if there's exactly one candidate.
+        if found.len() == 1 && tx.send(found.pop().unwrap()).is_ok() {
+          ctx.request_repaint();
+        }
+      })
+      .unwrap();
+
+    Some(Self { aero, rx })
+  }
+
+  /// True if looking for NASR data.
+  pub fn aero(&self) -> bool {
+    self.aero
+  }
+
+  /// Get the companion zip file path if one has been found.
+  pub fn get_reply(&self) -> Option<path::PathBuf> {
+    self.rx.try_recv().ok()
+  }
+}
@@ -0,0 +1,71 @@
+use crate::{nasr, util};
+use eframe::{egui, emath};
+
+/// Get a displayable airport field.
+type Field = fn(&nasr::AirportInfo, util::Units) -> String;
+
+/// Side-by-side comparison of several airports.
+pub struct CompareDlg {
+  infos: Vec<nasr::AirportInfo>,
+}
+
+impl CompareDlg {
+  pub fn open(infos: Vec<nasr::AirportInfo>) -> Self {
+    Self { infos }
+  }
+
+  /// Show the comparison. Returns false when the window has been closed.
+  /// - `ctx`: egui context
+  /// - `units`: display units
+  pub fn show(&mut self, ctx: &egui::Context, units: util::Units) -> bool {
+    let mut open = !ctx.input(|state| state.key_pressed(egui::Key::Escape));
+    egui::Window::new(egui::RichText::from("⚖  Compare").strong())
+      .open(&mut open)
+      .collapsible(false)
+      .resizable(false)
+      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0])
+      .show(ctx, |ui| {
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+          egui::Grid::new("compare_grid")
+            .striped(true)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+              let rows: [(&str, Field); 6] = [
+                ("ID", |info, _| info.id.clone()),
+                ("Name", |info, _| info.short_name().into()),
+                ("Type", |info, _| info.airport_type.text().into()),
+                ("Use", |info, _| info.airport_use.text().into()),
+                ("Elevation", |info, units| match info.elevation {
+                  Some(elevation) => util::format_altitude(elevation, units),
+                  None => "—".into(),
+                }),
+                ("Fuel", |info, _| {
+                  if info.fuel_types.is_empty() {
+                    "None".into()
+                  } else {
+                    info.fuel_types.clone()
+                  }
+                }),
+              ];
+
+              for (label, value) in rows {
+                ui.label(egui::RichText::new(label).strong());
+                for info in &self.infos {
+                  ui.label(value(info, units));
+                }
+                ui.end_row();
+              }
+
+              ui.label(egui::RichText::new("Location").strong());
+              for info in &self.infos {
+                let lat = util::format_lat(info.coord.y).unwrap_or_default();
+                let lon = util::format_lon(info.coord.x).unwrap_or_default();
+                ui.label(format!("{lat}\n{lon}"));
+              }
+              ui.end_row();
+            });
+        });
+      });
+    open
+  }
+}
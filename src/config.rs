@@ -1,5 +1,5 @@
-use crate::util;
-use std::{path, sync};
+use crate::{folder_watch, util};
+use std::{ops, path, sync};
 
 /// Storage for configuration items, persisted as JSON.
 #[derive(Clone)]
@@ -26,9 +26,15 @@ impl Storage {
       let value = win_info.to_value();
       let mut items = self.items.write().unwrap();
       items.set(Storage::WIN_INFO_KEY, value);
+      self.thread.persist();
     }
   }
 
+  /// Check if the window size and position are stored.
+  pub fn store_win(&self) -> bool {
+    self.store_win
+  }
+
   pub fn get_win_info(&self) -> util::WinInfo {
     let items = self.items.read().unwrap();
     util::WinInfo::from_value(items.get(Storage::WIN_INFO_KEY))
@@ -46,6 +52,437 @@ impl Storage {
     items.get(Storage::NIGHT_MODE_KEY)?.as_bool()
   }
 
+  pub fn set_high_contrast(&mut self, high_contrast: bool) {
+    let value = serde_json::Value::Bool(high_contrast);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::HIGH_CONTRAST_KEY, value);
+    self.thread.persist();
+  }
+
+  /// True if the chart is shown with more contrast (when not in night mode).
+  pub fn get_high_contrast(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::HIGH_CONTRAST_KEY)?.as_bool()
+  }
+
+  pub fn set_font_scale(&mut self, scale: f32) {
+    let value = serde_json::json!(scale);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::FONT_SCALE_KEY, value);
+    self.thread.persist();
+  }
+
+  /// UI font scale, clamped to `FONT_SCALE`.
+  pub fn get_font_scale(&self) -> Option<f32> {
+    let items = self.items.read().unwrap();
+    let scale = items.get(Storage::FONT_SCALE_KEY)?.as_f64()? as f32;
+    let range = Storage::FONT_SCALE;
+    scale
+      .is_finite()
+      .then(|| scale.clamp(*range.start(), *range.end()))
+  }
+
+  pub fn set_chart_filter(&mut self, filter: util::ChartFilter) {
+    let value = filter.to_value();
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::CHART_FILTER_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_chart_filter(&self) -> Option<util::ChartFilter> {
+    let items = self.items.read().unwrap();
+    util::ChartFilter::from_value(items.get(Storage::CHART_FILTER_KEY)?)
+  }
+
+  pub fn set_magnify_filter(&mut self, filter: util::ChartFilter) {
+    let value = filter.to_value();
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::MAGNIFY_FILTER_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_magnify_filter(&self) -> Option<util::ChartFilter> {
+    let items = self.items.read().unwrap();
+    util::ChartFilter::from_value(items.get(Storage::MAGNIFY_FILTER_KEY)?)
+  }
+
+  pub fn set_units(&mut self, units: util::Units) {
+    let value = units.to_value();
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::UNITS_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_units(&self) -> Option<util::Units> {
+    let items = self.items.read().unwrap();
+    util::Units::from_value(items.get(Storage::UNITS_KEY)?)
+  }
+
+  pub fn set_scale_bar(&mut self, show: bool) {
+    let value = serde_json::Value::Bool(show);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::SCALE_BAR_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_scale_bar(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::SCALE_BAR_KEY)?.as_bool()
+  }
+
+  pub fn set_animate_goto(&mut self, animate: bool) {
+    let value = serde_json::Value::Bool(animate);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::ANIMATE_GOTO_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_animate_goto(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::ANIMATE_GOTO_KEY)?.as_bool()
+  }
+
+  pub fn set_animate_zoom(&mut self, animate: bool) {
+    let value = serde_json::Value::Bool(animate);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::ANIMATE_ZOOM_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_animate_zoom(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::ANIMATE_ZOOM_KEY)?.as_bool()
+  }
+
+  pub fn set_zoom_step(&mut self, step: f32) {
+    let value = serde_json::json!(step);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::ZOOM_STEP_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_zoom_step(&self) -> Option<f32> {
+    let items = self.items.read().unwrap();
+    let step = items.get(Storage::ZOOM_STEP_KEY)?.as_f64()? as f32;
+    (step > 1.0).then_some(step)
+  }
+
+  pub fn set_overview(&mut self, show: bool) {
+    let value = serde_json::Value::Bool(show);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::OVERVIEW_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_overview(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::OVERVIEW_KEY)?.as_bool()
+  }
+
+  pub fn set_invert_zoom(&mut self, invert: bool) {
+    let value = serde_json::Value::Bool(invert);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::INVERT_ZOOM_KEY, value);
+    self.thread.persist();
+  }
+
+  /// True if scroll-wheel and touchpad zoom are reversed.
+  pub fn get_invert_zoom(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::INVERT_ZOOM_KEY)?.as_bool()
+  }
+
+  pub fn set_wheel_zoom(&mut self, sensitivity: f32) {
+    let value = serde_json::json!(sensitivity);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::WHEEL_ZOOM_KEY, value);
+    self.thread.persist();
+  }
+
+  /// Scroll-wheel and touchpad zoom sensitivity, clamped to `ZOOM_SENSITIVITY`.
+  pub fn get_wheel_zoom(&self) -> Option<f32> {
+    let items = self.items.read().unwrap();
+    let sensitivity = items.get(Storage::WHEEL_ZOOM_KEY)?.as_f64()? as f32;
+    Some(Storage::clamp_sensitivity(sensitivity))
+  }
+
+  pub fn set_pinch_zoom(&mut self, sensitivity: f32) {
+    let value = serde_json::json!(sensitivity);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::PINCH_ZOOM_KEY, value);
+    self.thread.persist();
+  }
+
+  /// Touch screen pinch zoom sensitivity, clamped to `ZOOM_SENSITIVITY`.
+  pub fn get_pinch_zoom(&self) -> Option<f32> {
+    let items = self.items.read().unwrap();
+    let sensitivity = items.get(Storage::PINCH_ZOOM_KEY)?.as_f64()? as f32;
+    Some(Storage::clamp_sensitivity(sensitivity))
+  }
+
+  fn clamp_sensitivity(sensitivity: f32) -> f32 {
+    let range = Storage::ZOOM_SENSITIVITY;
+    if sensitivity.is_finite() {
+      return sensitivity.clamp(*range.start(), *range.end());
+    }
+    1.0
+  }
+
+  pub fn set_graticule(&mut self, show: bool) {
+    let value = serde_json::Value::Bool(show);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::GRATICULE_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_graticule(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::GRATICULE_KEY)?.as_bool()
+  }
+
+  pub fn set_show_bounds(&mut self, show: bool) {
+    let value = serde_json::Value::Bool(show);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::SHOW_BOUNDS_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_show_bounds(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::SHOW_BOUNDS_KEY)?.as_bool()
+  }
+
+  pub fn set_runway_ticks(&mut self, show: bool) {
+    let value = serde_json::Value::Bool(show);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::RUNWAY_TICKS_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_runway_ticks(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::RUNWAY_TICKS_KEY)?.as_bool()
+  }
+
+  pub fn set_auto_open(&mut self, auto: bool) {
+    let value = serde_json::Value::Bool(auto);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::AUTO_OPEN_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_auto_open(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::AUTO_OPEN_KEY)?.as_bool()
+  }
+
+  pub fn set_watch_folder(&mut self, watch: bool) {
+    let value = serde_json::Value::Bool(watch);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::WATCH_FOLDER_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_watch_folder(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::WATCH_FOLDER_KEY)?.as_bool()
+  }
+
+  /// Remember the zip files seen in a watched folder.
+  /// - `folder`: watched folder
+  /// - `zips`: zip files now in the folder
+  pub fn set_seen_zips(&mut self, folder: &path::Path, zips: &[folder_watch::SeenZip]) {
+    let zips = zips.iter().map(folder_watch::SeenZip::to_value).collect();
+    let mut items = self.items.write().unwrap();
+    let mut value = items
+      .get(Storage::SEEN_ZIPS_KEY)
+      .filter(|value| value.is_object())
+      .cloned()
+      .unwrap_or_else(|| serde_json::json!({}));
+    value[folder.to_string_lossy().as_ref()] = serde_json::Value::Array(zips);
+    items.set(Storage::SEEN_ZIPS_KEY, value);
+    self.thread.persist();
+  }
+
+  /// Get the zip files seen in a watched folder. An empty list means that the folder was watched
+  /// but had no zip files, while `None` means that it hasn't been watched.
+  /// - `folder`: watched folder
+  pub fn get_seen_zips(&self, folder: &path::Path) -> Option<Vec<folder_watch::SeenZip>> {
+    let items = self.items.read().unwrap();
+    let folder = folder.to_string_lossy();
+    let value = items.get(Storage::SEEN_ZIPS_KEY)?.get(folder.as_ref())?;
+    let zips = value.as_array()?.iter();
+    Some(zips.filter_map(folder_watch::SeenZip::from_value).collect())
+  }
+
+  /// Remember whether the slow chart read hint has been shown.
+  pub fn set_slow_read_hint(&mut self, shown: bool) {
+    let value = serde_json::Value::Bool(shown);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::SLOW_READ_HINT_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_slow_read_hint(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::SLOW_READ_HINT_KEY)?.as_bool()
+  }
+
+  pub fn set_terrain_opacity(&mut self, opacity: f32) {
+    let value = serde_json::json!(opacity);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::TERRAIN_OPACITY_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_terrain_opacity(&self) -> Option<f32> {
+    let items = self.items.read().unwrap();
+    Some(items.get(Storage::TERRAIN_OPACITY_KEY)?.as_f64()? as f32)
+  }
+
+  pub fn set_nearby_radius(&mut self, radius: f32) {
+    let value = serde_json::json!(radius);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::NEARBY_RADIUS_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_nearby_radius(&self) -> Option<f32> {
+    let items = self.items.read().unwrap();
+    Some(items.get(Storage::NEARBY_RADIUS_KEY)?.as_f64()? as f32)
+  }
+
+  /// True if the chart bounds can be edited. There's no UI for this setting.
+  pub fn get_edit_bounds(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::EDIT_BOUNDS_KEY)?.as_bool()
+  }
+
+  /// Maximum texture memory in megabytes. There's no UI for this setting.
+  pub fn get_texture_budget(&self) -> Option<u32> {
+    let items = self.items.read().unwrap();
+    let megabytes = items.get(Storage::TEXTURE_BUDGET_KEY)?.as_u64()?;
+    (megabytes > 0).then_some(megabytes.min(u32::MAX as u64) as u32)
+  }
+
+  /// Override the bounds used for airport searches on a chart.
+  /// - `name`: chart name
+  /// - `rect`: bounds in chart pixels
+  pub fn set_chart_bounds(&mut self, name: &str, rect: util::Rect) {
+    let mut items = self.items.write().unwrap();
+    let mut value = items
+      .get(Storage::CHART_BOUNDS_KEY)
+      .filter(|value| value.is_object())
+      .cloned()
+      .unwrap_or_else(|| serde_json::json!({}));
+    value[name] = rect.to_value();
+    items.set(Storage::CHART_BOUNDS_KEY, value);
+    self.thread.persist();
+  }
+
+  /// Remove the bounds override for a chart.
+  /// - `name`: chart name
+  pub fn remove_chart_bounds(&mut self, name: &str) {
+    let mut items = self.items.write().unwrap();
+    let Some(mut value) = items.get(Storage::CHART_BOUNDS_KEY).cloned() else {
+      return;
+    };
+
+    if let Some(object) = value.as_object_mut() {
+      if object.remove(name).is_some() {
+        items.set(Storage::CHART_BOUNDS_KEY, value);
+        self.thread.persist();
+      }
+    }
+  }
+
+  /// Get the bounds override (in chart pixels) for a chart.
+  /// - `name`: chart name
+  pub fn get_chart_bounds(&self, name: &str) -> Option<util::Rect> {
+    let items = self.items.read().unwrap();
+    let rect = util::Rect::from_value(items.get(Storage::CHART_BOUNDS_KEY)?.get(name)?)?;
+    rect.size.is_valid().then_some(rect)
+  }
+
+  /// Remember the size of a dialog.
+  /// - `name`: dialog name
+  /// - `size`: dialog content size
+  pub fn set_dialog_size(&mut self, name: &str, size: util::Size) {
+    let mut items = self.items.write().unwrap();
+    let mut value = items
+      .get(Storage::DIALOG_SIZES_KEY)
+      .filter(|value| value.is_object())
+      .cloned()
+      .unwrap_or_else(|| serde_json::json!({}));
+    value[name] = size.to_value();
+    items.set(Storage::DIALOG_SIZES_KEY, value);
+    self.thread.persist();
+  }
+
+  /// Get the remembered size of a dialog.
+  /// - `name`: dialog name
+  pub fn get_dialog_size(&self, name: &str) -> Option<util::Size> {
+    let items = self.items.read().unwrap();
+    let size = util::Size::from_value(items.get(Storage::DIALOG_SIZES_KEY)?.get(name)?)?;
+    size.is_valid().then_some(size)
+  }
+
+  pub fn set_bookmarks(&mut self, bookmarks: &[util::Bookmark]) {
+    let value = bookmarks.iter().map(util::Bookmark::to_value).collect();
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::BOOKMARKS_KEY, serde_json::Value::Array(value));
+    self.thread.persist();
+  }
+
+  pub fn get_bookmarks(&self) -> Vec<util::Bookmark> {
+    let items = self.items.read().unwrap();
+    let Some(array) = items.get(Storage::BOOKMARKS_KEY).and_then(|v| v.as_array()) else {
+      return Vec::new();
+    };
+    array
+      .iter()
+      .filter_map(util::Bookmark::from_value)
+      .collect()
+  }
+
+  /// Add a successful search term to the history (most recent first).
+  pub fn push_search_term(&mut self, term: &str) {
+    let term = term.trim();
+    if term.is_empty() {
+      return;
+    }
+
+    let mut terms = self.get_search_terms();
+    terms.retain(|other| !other.eq_ignore_ascii_case(term));
+    terms.insert(0, term.into());
+    terms.truncate(Storage::MAX_SEARCH_TERMS);
+
+    let value = terms.into_iter().map(serde_json::Value::String).collect();
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::SEARCH_TERMS_KEY, serde_json::Value::Array(value));
+    self.thread.persist();
+  }
+
+  pub fn get_search_terms(&self) -> Vec<String> {
+    let items = self.items.read().unwrap();
+    let Some(array) = items
+      .get(Storage::SEARCH_TERMS_KEY)
+      .and_then(|v| v.as_array())
+    else {
+      return Vec::new();
+    };
+    array
+      .iter()
+      .filter_map(|v| Some(v.as_str()?.into()))
+      .collect()
+  }
+
+  pub fn clear_search_terms(&mut self) {
+    let mut items = self.items.write().unwrap();
+    items.remove(Storage::SEARCH_TERMS_KEY);
+    self.thread.persist();
+  }
+
   pub fn set_asset_path(&mut self, path: String) {
     let value = serde_json::Value::String(path);
     let mut items = self.items.write().unwrap();
@@ -58,20 +495,137 @@ impl Storage {
     Some(items.get(Storage::ASSET_PATH_KEY)?.as_str()?.into())
   }
 
+  pub fn set_catalog_path(&mut self, path: String) {
+    let value = serde_json::Value::String(path);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::CATALOG_PATH_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_catalog_path(&self) -> Option<String> {
+    let items = self.items.read().unwrap();
+    Some(items.get(Storage::CATALOG_PATH_KEY)?.as_str()?.into())
+  }
+
+  pub fn set_open_last(&mut self, open: bool) {
+    let value = serde_json::Value::Bool(open);
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::OPEN_LAST_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_open_last(&self) -> Option<bool> {
+    let items = self.items.read().unwrap();
+    items.get(Storage::OPEN_LAST_KEY)?.as_bool()
+  }
+
+  /// Remember the last chart that was opened.
+  /// - `path`: path to the chart zip file
+  /// - `files`: chart files within the zip file (two for a combined chart)
+  pub fn set_last_chart(&mut self, path: &path::Path, files: &[path::PathBuf]) {
+    let Some(strings) = std::iter::once(path)
+      .chain(files.iter().map(|file| file.as_path()))
+      .map(|path| path.to_str().map(serde_json::Value::from))
+      .collect::<Option<Vec<_>>>()
+    else {
+      return;
+    };
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::LAST_CHART_KEY, serde_json::Value::Array(strings));
+    self.thread.persist();
+  }
+
+  pub fn get_last_chart(&self) -> Option<(path::PathBuf, Vec<path::PathBuf>)> {
+    let items = self.items.read().unwrap();
+    let (path, files) = items
+      .get(Storage::LAST_CHART_KEY)?
+      .as_array()?
+      .split_first()?;
+    let path = path.as_str()?.into();
+    let files = files
+      .iter()
+      .map(|file| Some(file.as_str()?.into()))
+      .collect::<Option<Vec<_>>>()?;
+    (!files.is_empty()).then_some((path, files))
+  }
+
+  /// Remember the last NASR zip file that was opened.
+  pub fn set_last_nasr(&mut self, path: &path::Path) {
+    let Some(path) = path.to_str() else {
+      return;
+    };
+    let value = serde_json::Value::String(path.into());
+    let mut items = self.items.write().unwrap();
+    items.set(Storage::LAST_NASR_KEY, value);
+    self.thread.persist();
+  }
+
+  pub fn get_last_nasr(&self) -> Option<path::PathBuf> {
+    let items = self.items.read().unwrap();
+    Some(items.get(Storage::LAST_NASR_KEY)?.as_str()?.into())
+  }
+
   fn path() -> Option<path::PathBuf> {
     dirs::config_dir().map(|path| path.join(util::APP_NAME).with_extension("json"))
   }
 
+  const VERSION_KEY: &'static str = "version";
   const WIN_INFO_KEY: &'static str = "win_info";
   const NIGHT_MODE_KEY: &'static str = "night_mode";
+  const HIGH_CONTRAST_KEY: &'static str = "high_contrast";
+  const FONT_SCALE_KEY: &'static str = "font_scale";
   const ASSET_PATH_KEY: &'static str = "asset_path";
+  const CATALOG_PATH_KEY: &'static str = "catalog_path";
+  const CHART_FILTER_KEY: &'static str = "chart_filter";
+  const MAGNIFY_FILTER_KEY: &'static str = "magnify_filter";
+  const UNITS_KEY: &'static str = "units";
+  const SCALE_BAR_KEY: &'static str = "scale_bar";
+  const ANIMATE_GOTO_KEY: &'static str = "animate_goto";
+  const ANIMATE_ZOOM_KEY: &'static str = "animate_zoom";
+  const ZOOM_STEP_KEY: &'static str = "zoom_step";
+  const GRATICULE_KEY: &'static str = "graticule";
+  const INVERT_ZOOM_KEY: &'static str = "invert_zoom";
+  const WHEEL_ZOOM_KEY: &'static str = "wheel_zoom";
+  const PINCH_ZOOM_KEY: &'static str = "pinch_zoom";
+  const SHOW_BOUNDS_KEY: &'static str = "show_bounds";
+  const RUNWAY_TICKS_KEY: &'static str = "runway_ticks";
+  const AUTO_OPEN_KEY: &'static str = "auto_open";
+  const WATCH_FOLDER_KEY: &'static str = "watch_folder";
+  const SEEN_ZIPS_KEY: &'static str = "seen_zips";
+  const SLOW_READ_HINT_KEY: &'static str = "slow_read_hint";
+  const OVERVIEW_KEY: &'static str = "overview";
+  const TERRAIN_OPACITY_KEY: &'static str = "terrain_opacity";
+  const NEARBY_RADIUS_KEY: &'static str = "nearby_radius";
+  const EDIT_BOUNDS_KEY: &'static str = "edit_bounds";
+  const TEXTURE_BUDGET_KEY: &'static str = "texture_budget";
+  const CHART_BOUNDS_KEY: &'static str = "chart_bounds";
+  const DIALOG_SIZES_KEY: &'static str = "dialog_sizes";
+  const BOOKMARKS_KEY: &'static str = "bookmarks";
+  const SEARCH_TERMS_KEY: &'static str = "search_terms";
+  const OPEN_LAST_KEY: &'static str = "open_last";
+  const LAST_CHART_KEY: &'static str = "last_chart";
+  const LAST_NASR_KEY: &'static str = "last_nasr";
+  const MAX_SEARCH_TERMS: usize = 15;
+
+  /// Current layout of the stored items.
+  const VERSION: u64 = 1;
+
+  /// Allowed range for the zoom sensitivity settings.
+  pub const ZOOM_SENSITIVITY: ops::RangeInclusive<f32> = 0.25..=4.0;
+
+  /// Allowed range for the UI font scale.
+  pub const FONT_SCALE: ops::RangeInclusive<f32> = 0.75..=1.5;
 }
 
 mod inner {
+  use super::Storage;
+  use crate::util;
   use std::{
-    fs, io, path,
+    ffi, fs,
+    io::{self, Write},
+    path,
     sync::{self, atomic, mpsc},
-    thread,
+    thread, time,
   };
 
   pub struct Items {
@@ -82,8 +636,8 @@ mod inner {
 
   impl Items {
     pub fn load(path: path::PathBuf) -> Self {
-      let items = Self::load_items(&path);
-      let changed = atomic::AtomicBool::new(false);
+      let mut items = Self::load_items(&path);
+      let changed = atomic::AtomicBool::new(Self::migrate(&mut items));
       Self {
         path,
         items,
@@ -103,7 +657,6 @@ mod inner {
       self.changed.store(true, atomic::Ordering::Relaxed);
     }
 
-    #[allow(unused)]
     pub fn remove(&mut self, key: &str) {
       if self.items.as_object_mut().unwrap().remove(key).is_some() {
         self.changed.store(true, atomic::Ordering::Relaxed);
@@ -114,37 +667,102 @@ mod inner {
       match fs::File::open(path) {
         Ok(file) => {
           let reader = io::BufReader::new(file);
-          match serde_json::from_reader(reader) {
-            Ok(items) => {
-              let items: serde_json::Value = items;
-              if items.is_object() {
-                return items;
-              }
-            }
-            Err(err) => println!("{path:?}: {err}"),
+          let result: Result<serde_json::Value, _> = serde_json::from_reader(reader);
+          match result {
+            Ok(items) if items.is_object() => return items,
+            Ok(_) => Self::quarantine(path, "not a JSON object"),
+            Err(err) => Self::quarantine(path, &err.to_string()),
           }
         }
-        Err(err) => println!("{path:?}: {err}"),
+        Err(err) => log::warn!("{path:?}: {err}"),
       }
       serde_json::json!({})
     }
 
+    /// Move an unreadable file out of the way so that the settings aren't lost without a trace.
+    /// - `path`: config file path
+    /// - `reason`: why the file can't be read
+    fn quarantine(path: &path::Path, reason: &str) {
+      let bad = path.with_extension("json.bad");
+      match fs::rename(path, &bad) {
+        Ok(()) => log::warn!("{path:?}: {reason} (moved to {bad:?})"),
+        Err(err) => log::warn!("{path:?}: {reason} ({err})"),
+      }
+    }
+
+    /// Upgrade items stored by older versions. Returns true if anything changed.
+    /// - `items`: stored items
+    fn migrate(items: &mut serde_json::Value) -> bool {
+      let object = items.as_object_mut().unwrap();
+      let version = object
+        .get(Storage::VERSION_KEY)
+        .and_then(|value| value.as_u64());
+      let version = version.unwrap_or(0);
+      if version >= Storage::VERSION {
+        return false;
+      }
+
+      if version < 1 {
+        // The asset folder used to be stored as "asset_folder".
+        if let Some(value) = object.remove("asset_folder") {
+          object.entry(Storage::ASSET_PATH_KEY).or_insert(value);
+        }
+
+        // Window info used to be stored as [x, y, width, height].
+        if let Some(value) = object
+          .get(Storage::WIN_INFO_KEY)
+          .filter(|value| !value.is_object())
+        {
+          let get = |index: usize| value.get(index).cloned().unwrap_or_default();
+          let pos = util::Pos::from_value(&serde_json::json!([get(0), get(1)]));
+          let size = util::Size::from_value(&serde_json::json!([get(2), get(3)]));
+          let win_info = util::WinInfo {
+            pos,
+            size,
+            ..Default::default()
+          };
+          object.insert(Storage::WIN_INFO_KEY.into(), win_info.to_value());
+        }
+      }
+
+      object.insert(Storage::VERSION_KEY.into(), Storage::VERSION.into());
+      true
+    }
+
     fn store_items(&self) {
       if self.changed.swap(false, atomic::Ordering::Relaxed) {
-        match fs::File::create(&self.path) {
-          Ok(file) => {
-            let writer = io::BufWriter::new(file);
-            match serde_json::to_writer(writer, &self.items) {
-              Ok(()) => (),
-              Err(err) => println!("{:?}: {err}", self.path),
-            }
-          }
-          Err(err) => println!("{:?}: {err}", self.path),
+        let result = serde_json::to_vec(&self.items).map_err(io::Error::from);
+        if let Err(err) = result.and_then(|data| write_atomic(&self.path, &data)) {
+          log::warn!("{:?}: {err}", self.path);
         }
       }
     }
   }
 
+  /// Write a file by way of a temporary file (e.g. "aviate.json.tmp") that replaces it once the
+  /// data is on disk, so that a crash can't leave a truncated file.
+  /// - `path`: file path
+  /// - `data`: file contents
+  pub fn write_atomic(path: &path::Path, data: &[u8]) -> io::Result<()> {
+    let tmp = tmp_path(path);
+    let result = fs::File::create(&tmp).and_then(|mut file| {
+      file.write_all(data)?;
+      file.sync_all()
+    });
+
+    let result = result.and_then(|()| fs::rename(&tmp, path));
+    if result.is_err() {
+      fs::remove_file(&tmp).ok();
+    }
+    result
+  }
+
+  fn tmp_path(path: &path::Path) -> path::PathBuf {
+    let mut name = ffi::OsString::from(path.as_os_str());
+    name.push(".tmp");
+    name.into()
+  }
+
   impl Drop for Items {
     fn drop(&mut self) {
       self.store_items();
@@ -164,6 +782,16 @@ mod inner {
           move || {
             // Wait for a message. Exit when the connection is closed.
             while rx.recv().is_ok() {
+              // Fold in the requests that follow closely (e.g. while dragging a slider) so that
+              // they're written once.
+              let deadline = time::Instant::now() + PERSIST_DELAY;
+              loop {
+                let timeout = deadline.saturating_duration_since(time::Instant::now());
+                if rx.recv_timeout(timeout).is_err() {
+                  break;
+                }
+              }
+
               // Persist the items.
               items.read().unwrap().store_items();
             }
@@ -189,4 +817,124 @@ mod inner {
       self.join.take().unwrap().join().unwrap();
     }
   }
+
+  /// How long to wait for more changes before writing the items.
+  const PERSIST_DELAY: time::Duration = time::Duration::from_millis(500);
+}
+
+#[cfg(test)]
+mod test {
+  use super::{inner, inner::Items, Storage};
+  use std::{env, fs, path, sync};
+
+  /// Load items from a config file fixture.
+  /// - `name`: file name
+  /// - `text`: file contents
+  fn load(name: &str, text: &str) -> (Items, path::PathBuf) {
+    let path = env::temp_dir().join(name).with_extension("json");
+    fs::write(&path, text).unwrap();
+    (Items::load(path.clone()), path)
+  }
+
+  #[test]
+  fn test_migrate() {
+    let text = r#"{"asset_folder": "/charts", "win_info": [10, 20, 800, 600], "night_mode": true}"#;
+    let (items, path) = load("aviate_test_migrate", text);
+    assert!(items.get("asset_folder").is_none());
+    assert!(items.get(Storage::ASSET_PATH_KEY).unwrap() == "/charts");
+    assert!(items.get(Storage::NIGHT_MODE_KEY).unwrap() == true);
+    assert!(items.get(Storage::VERSION_KEY).unwrap() == Storage::VERSION);
+
+    let win_info = crate::util::WinInfo::from_value(items.get(Storage::WIN_INFO_KEY));
+    assert!(win_info.pos == Some(crate::util::Pos { x: 10, y: 20 }));
+    assert!(win_info.size == Some(crate::util::Size { w: 800, h: 600 }));
+    assert!(!win_info.maxed);
+    drop(items);
+
+    // Current items are left alone.
+    let text = r#"{"version": 1, "asset_path": "/charts", "asset_folder": "/other"}"#;
+    let (items, _) = load("aviate_test_migrate", text);
+    assert!(items.get(Storage::ASSET_PATH_KEY).unwrap() == "/charts");
+    assert!(items.get("asset_folder").unwrap() == "/other");
+
+    drop(items);
+    fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_zoom_step() {
+    let (items, path) = load("aviate_test_zoom_step", "{}");
+    let items = sync::Arc::new(sync::RwLock::new(items));
+    let thread = sync::Arc::new(inner::PersistThread::new(items.clone()));
+    let mut storage = Storage {
+      items,
+      thread,
+      store_win: false,
+    };
+    assert!(storage.get_zoom_step().is_none());
+
+    storage.set_zoom_step(1.5);
+    assert!(storage.get_zoom_step() == Some(1.5));
+
+    // Steps that wouldn't zoom in are ignored.
+    for value in [
+      serde_json::json!(1.0),
+      serde_json::json!(0.5),
+      serde_json::json!("2"),
+    ] {
+      storage
+        .items
+        .write()
+        .unwrap()
+        .set(Storage::ZOOM_STEP_KEY, value);
+      assert!(storage.get_zoom_step().is_none());
+    }
+
+    drop(storage);
+    fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_quarantine() {
+    for (name, text) in [
+      ("aviate_test_bad", "{\"night_mode\": tr"),
+      ("aviate_test_array", "[]"),
+    ] {
+      let (items, path) = load(name, text);
+      let bad = path.with_extension("json.bad");
+      assert!(items.get(Storage::NIGHT_MODE_KEY).is_none());
+      assert!(!path.exists());
+      assert!(fs::read_to_string(&bad).unwrap() == text);
+
+      drop(items);
+      fs::remove_file(path).ok();
+      fs::remove_file(bad).ok();
+    }
+  }
+
+  #[test]
+  fn test_write_atomic() {
+    let path = env::temp_dir().join("aviate_test_atomic.json");
+    let tmp = env::temp_dir().join("aviate_test_atomic.json.tmp");
+    fs::remove_file(&path).ok();
+
+    inner::write_atomic(&path, b"{\"night_mode\": true}").unwrap();
+    assert!(fs::read_to_string(&path).unwrap() == "{\"night_mode\": true}");
+    assert!(!tmp.exists());
+
+    // A temporary file left by a crash is replaced, even if it's longer.
+    fs::write(&tmp, "{\"night_mode\": false, \"units\": \"metric\"}").unwrap();
+    inner::write_atomic(&path, b"{}").unwrap();
+    assert!(fs::read_to_string(&path).unwrap() == "{}");
+    assert!(!tmp.exists());
+
+    // The file is left alone if the write fails.
+    let dir = env::temp_dir().join("aviate_test_atomic_dir");
+    fs::create_dir_all(&dir).unwrap();
+    assert!(inner::write_atomic(&dir, b"{}").is_err());
+    assert!(dir.is_dir());
+
+    fs::remove_dir(dir).ok();
+    fs::remove_file(path).ok();
+  }
 }
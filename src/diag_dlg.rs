@@ -0,0 +1,182 @@
+use crate::{chart, nasr, util};
+use eframe::{egui, emath};
+use std::{mem, path, time};
+
+/// Chart and NASR dataset properties for bug reports.
+#[derive(Default)]
+pub struct DiagnosticsInfo {
+  sections: Vec<(&'static str, Vec<(&'static str, String)>)>,
+}
+
+impl DiagnosticsInfo {
+  /// Add the chart properties.
+  /// - `name`: chart name
+  /// - `reader`: chart raster reader
+  pub fn add_chart(&mut self, name: &str, reader: &chart::RasterReader) {
+    let transform = reader.transform();
+    let size = transform.px_size();
+    let bounds = transform.bounds();
+    let info = reader.info();
+    let edition = reader
+      .edition()
+      .map_or("None".into(), |edition| edition.to_string());
+    let stats = reader.read_stats();
+    self.sections.push((
+      "Chart",
+      vec![
+        ("Name", name.into()),
+        ("Pixel size", format!("{} x {}", size.w, size.h)),
+        ("PROJ4", transform.get_proj4()),
+        (
+          "Bounds",
+          format!(
+            "({:.1}, {:.1}) - ({:.1}, {:.1})",
+            bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y
+          ),
+        ),
+        ("Overview levels", info.overview_count.to_string()),
+        ("Palette size", info.palette.len().to_string()),
+        ("Edition", edition),
+        ("Open time", format_duration(stats.open_time)),
+        ("Image reads", stats.read_count.to_string()),
+        ("Average read time", format_duration(stats.average())),
+        ("95th percentile read time", format_duration(stats.p95())),
+      ],
+    ));
+  }
+
+  /// Add the NASR properties.
+  /// - `reader`: NASR airport reader
+  /// - `airspace`: airspace shape file folder, if the NASR download includes it
+  pub fn add_nasr(&mut self, reader: &nasr::AirportReader, airspace: Option<&path::Path>) {
+    let status = if reader.airport_spatial_idx() {
+      "Spatial"
+    } else if reader.airport_basic_idx() {
+      "Basic"
+    } else {
+      "None"
+    };
+
+    let stats = reader.stats();
+    let mut items = vec![
+      ("Path", reader.path().to_string_lossy().into_owned()),
+      ("Index status", status.into()),
+      ("Airport records", stats.feature_count.to_string()),
+      ("Indexed IDs", stats.id_count.to_string()),
+      ("Indexed names", stats.name_count.to_string()),
+      ("Airports on chart", stats.spatial_count.to_string()),
+      ("Basic index time", format_duration(stats.basic_idx_time)),
+      (
+        "Spatial index time",
+        format_duration(stats.spatial_idx_time),
+      ),
+    ];
+
+    if let Some((effective, expiration)) = reader.effective_range() {
+      items.push(("Effective", format!("{effective} - {expiration}")));
+    }
+
+    let airspace = airspace.map_or(util::NO_AIRSPACE_DATA.into(), |path| {
+      path.to_string_lossy().into_owned()
+    });
+    items.push(("Airspace data", airspace));
+
+    self.sections.push(("NASR", items));
+  }
+
+  /// Add the texture memory use.
+  /// - `used`: bytes used by retained textures
+  /// - `cap`: maximum bytes before textures are evicted
+  pub fn add_textures(&mut self, used: usize, cap: usize) {
+    const MB: f64 = 1024.0 * 1024.0;
+    let used = format!("{:.1} MB", used as f64 / MB);
+    let cap = format!("{:.0} MB", cap as f64 / MB);
+    self.sections.push((
+      "Textures",
+      vec![("Texture memory", used), ("Texture budget", cap)],
+    ));
+  }
+
+  /// The properties as text.
+  pub fn to_text(&self) -> String {
+    let mut text = String::new();
+    for (heading, items) in &self.sections {
+      text.push_str(&format!("[{heading}]\n"));
+      for (label, value) in items {
+        text.push_str(&format!("{label}: {value}\n"));
+      }
+    }
+    text
+  }
+}
+
+fn format_duration(duration: Option<time::Duration>) -> String {
+  match duration {
+    Some(duration) => format!("{:.3} s", duration.as_secs_f64()),
+    None => "-".into(),
+  }
+}
+
+pub struct DiagDlg {
+  info: DiagnosticsInfo,
+  reset: bool,
+}
+
+impl DiagDlg {
+  pub fn open(info: DiagnosticsInfo) -> Self {
+    Self { info, reset: true }
+  }
+
+  pub fn show(&mut self, ctx: &egui::Context) -> bool {
+    let mut open = !ctx.input(|state| state.key_pressed(egui::Key::Escape));
+    let win = egui::Window::new(egui::RichText::from("🛈  Diagnostics").strong())
+      .open(&mut open)
+      .collapsible(false)
+      .resizable(false)
+      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0]);
+
+    // Hack to reset the window size.
+    let win = if mem::take(&mut self.reset) {
+      win.fixed_size([400.0, 20.0])
+    } else {
+      win
+    };
+
+    let mut close = false;
+    win.show(ctx, |ui| {
+      ui.add_space(8.0);
+      if self.info.sections.is_empty() {
+        ui.label("Nothing is loaded");
+      }
+
+      for (heading, items) in &self.info.sections {
+        ui.label(egui::RichText::new(*heading).strong());
+        egui::Grid::new(format!("{}_diag_{heading}", util::APP_NAME))
+          .num_columns(2)
+          .striped(true)
+          .show(ui, |ui| {
+            for (label, value) in items {
+              ui.label(*label);
+              ui.add(egui::Label::new(value.as_str()).wrap(true));
+              ui.end_row();
+            }
+          });
+        ui.add_space(8.0);
+      }
+
+      ui.separator();
+      ui.horizontal(|ui| {
+        if ui.button("Copy to clipboard").clicked() {
+          let text = self.info.to_text();
+          ctx.output_mut(|state| state.copied_text = text);
+        }
+
+        if ui.button("Close").clicked() {
+          close = true;
+        }
+      });
+    });
+
+    open && !close
+  }
+}
@@ -1,5 +1,5 @@
 use crate::util;
-use eframe::{egui, emath, epaint};
+use eframe::{egui, emath};
 use std::mem;
 
 #[derive(Default)]
@@ -41,7 +41,9 @@ impl ErrorDlg {
       ui.add_space(8.0);
       ui.vertical_centered(|ui| {
         let text = egui::RichText::from(self.text.as_ref().unwrap().as_ref());
-        let widget = egui::Label::new(text.color(epaint::Color32::LIGHT_RED)).wrap(false);
+        // Follow the theme so that the text stays readable in both day and night mode.
+        let color = ui.visuals().error_fg_color;
+        let widget = egui::Label::new(text.color(color)).wrap(false);
         ui.add(widget);
       });
       ui.add_space(8.0);
@@ -0,0 +1,119 @@
+use std::{collections, fmt::Write, sync, time};
+
+/// Maximum number of entries kept in the event log.
+const MAX_ENTRIES: usize = 500;
+
+/// Logged message.
+#[derive(Clone)]
+pub struct Entry {
+  pub time: time::SystemTime,
+  pub level: log::Level,
+  pub text: String,
+}
+
+impl Entry {
+  /// Get the time of day (UTC) as text.
+  pub fn time_text(&self) -> String {
+    let secs = self
+      .time
+      .duration_since(time::UNIX_EPOCH)
+      .map_or(0, |duration| duration.as_secs());
+    let secs = secs % 86_400;
+    format!("{:02}:{:02}:{:02}Z", secs / 3600, secs / 60 % 60, secs % 60)
+  }
+}
+
+/// Logger that prints messages and keeps the most recent ones for the event log window. Messages
+/// come from reader threads as well as the UI thread.
+struct EventLog {
+  entries: sync::Mutex<collections::VecDeque<Entry>>,
+}
+
+impl EventLog {
+  const fn new() -> Self {
+    Self {
+      entries: sync::Mutex::new(collections::VecDeque::new()),
+    }
+  }
+
+  fn push(&self, entry: Entry) {
+    let mut entries = self.entries.lock().unwrap();
+    if entries.len() == MAX_ENTRIES {
+      entries.pop_front();
+    }
+    entries.push_back(entry);
+  }
+
+  fn entries(&self) -> Vec<Entry> {
+    self.entries.lock().unwrap().iter().cloned().collect()
+  }
+}
+
+impl log::Log for EventLog {
+  fn enabled(&self, metadata: &log::Metadata) -> bool {
+    // Dependencies have their own (very chatty) logging.
+    metadata.level() <= log::Level::Info && metadata.target().starts_with(env!("CARGO_PKG_NAME"))
+  }
+
+  fn log(&self, record: &log::Record) {
+    if self.enabled(record.metadata()) {
+      let text = record.args().to_string();
+      println!("{text}");
+      self.push(Entry {
+        time: time::SystemTime::now(),
+        level: record.level(),
+        text,
+      });
+    }
+  }
+
+  fn flush(&self) {}
+}
+
+static EVENT_LOG: EventLog = EventLog::new();
+
+/// Install the event log as the logger.
+pub fn init() {
+  if log::set_logger(&EVENT_LOG).is_ok() {
+    log::set_max_level(log::LevelFilter::Info);
+  }
+}
+
+/// Get the logged entries, oldest first.
+pub fn entries() -> Vec<Entry> {
+  EVENT_LOG.entries()
+}
+
+/// Format entries as text.
+pub fn to_text<'a>(entries: impl Iterator<Item = &'a Entry>) -> String {
+  let mut text = String::new();
+  for entry in entries {
+    let time = entry.time_text();
+    let _ = writeln!(text, "{time} [{}] {}", entry.level, entry.text);
+  }
+  text
+}
+
+#[cfg(test)]
+mod test {
+  use std::time;
+
+  #[test]
+  fn test_event_log() {
+    let log = super::EventLog::new();
+    for index in 0..super::MAX_ENTRIES + 10 {
+      log.push(super::Entry {
+        time: time::UNIX_EPOCH + time::Duration::from_secs(3661),
+        level: log::Level::Warn,
+        text: index.to_string(),
+      });
+    }
+
+    // Only the most recent entries are kept.
+    let entries = log.entries();
+    assert!(entries.len() == super::MAX_ENTRIES);
+    assert!(entries[0].text == "10");
+    assert!(entries.last().unwrap().text == (super::MAX_ENTRIES + 9).to_string());
+    assert!(super::to_text(entries.iter().take(1)) == "01:01:01Z [WARN] 10\n");
+  }
+}
@@ -0,0 +1,540 @@
+use crate::{chart, pdf, util};
+use eframe::egui;
+use std::{
+  any, fs, io, path,
+  sync::{atomic, mpsc, Arc},
+  thread,
+};
+
+/// Page margin in points.
+const MARGIN: f64 = 18.0;
+
+/// Height of the footer in points.
+const FOOTER: f64 = 24.0;
+
+/// Footer font size in points.
+const FONT_SIZE: f64 = 7.0;
+
+/// Maximum image resolution in pixels per inch.
+const MAX_DPI: f64 = 300.0;
+
+/// Size of the image tiles that are read and written one at a time.
+const TILE_SIZE: u32 = 1024;
+
+/// JPEG quality (1 - 100).
+const JPEG_QUALITY: u8 = 85;
+
+/// Points per meter.
+const POINTS_PER_METER: f64 = 72.0 / 0.0254;
+
+/// Output scales that can be chosen, as scale denominators.
+pub const SCALES: [u32; 3] = [250_000, 500_000, 1_000_000];
+
+/// Paper sizes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Paper {
+  Letter,
+  Legal,
+  Tabloid,
+  A4,
+  A3,
+}
+
+impl Paper {
+  pub const ALL: [Paper; 5] = [
+    Paper::Letter,
+    Paper::Legal,
+    Paper::Tabloid,
+    Paper::A4,
+    Paper::A3,
+  ];
+
+  /// Get the paper name.
+  pub fn label(self) -> &'static str {
+    match self {
+      Paper::Letter => "Letter",
+      Paper::Legal => "Legal",
+      Paper::Tabloid => "Tabloid",
+      Paper::A4 => "A4",
+      Paper::A3 => "A3",
+    }
+  }
+
+  /// Get the page size in points.
+  /// - `landscape`: true for landscape orientation
+  pub fn size(self, landscape: bool) -> pdf::Size {
+    let (w, h) = match self {
+      Paper::Letter => (612.0, 792.0),
+      Paper::Legal => (612.0, 1008.0),
+      Paper::Tabloid => (792.0, 1224.0),
+      Paper::A4 => (595.0, 842.0),
+      Paper::A3 => (842.0, 1191.0),
+    };
+
+    if landscape {
+      pdf::Size { w: h, h: w }
+    } else {
+      pdf::Size { w, h }
+    }
+  }
+}
+
+/// Export settings.
+#[derive(Clone, Debug)]
+pub struct Settings {
+  /// Region to export in chart pixels.
+  pub region: util::Rect,
+
+  pub paper: Paper,
+  pub landscape: bool,
+
+  /// Output scale denominator (e.g. 500,000 for 1:500,000).
+  pub scale: u32,
+}
+
+/// How an export region is split into pages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+  /// Zoom used to read the chart so that the image resolution is at most `MAX_DPI`.
+  pub zoom: f32,
+
+  /// Size of each page's image area in zoomed pixels.
+  pub page_px: (u32, u32),
+
+  /// Points per zoomed pixel.
+  pub pt_per_px: f64,
+
+  /// Number of page columns and rows.
+  pub cols: u32,
+  pub rows: u32,
+}
+
+impl Layout {
+  /// Lay out a region.
+  /// - `settings`: export settings
+  /// - `meters_per_px`: chart meters per pixel at full zoom
+  pub fn new(settings: &Settings, meters_per_px: f64) -> Self {
+    let page = settings.paper.size(settings.landscape);
+    let area = (page.w - 2.0 * MARGIN, page.h - 2.0 * MARGIN - FOOTER);
+
+    // Chart pixels per point at the output scale.
+    let px_per_pt = settings.scale as f64 / POINTS_PER_METER / meters_per_px;
+
+    // Don't read at a higher resolution than needed.
+    let zoom = (MAX_DPI / 72.0 / px_per_pt).min(1.0);
+    let pt_per_px = 1.0 / (px_per_pt * zoom);
+    // Allow for rounding error so that exact fits aren't a pixel short.
+    let page_px = (
+      ((area.0 / pt_per_px + 1e-6).floor() as u32).max(1),
+      ((area.1 / pt_per_px + 1e-6).floor() as u32).max(1),
+    );
+
+    let region = settings.region.scaled(zoom as f32);
+    Self {
+      zoom: zoom as f32,
+      page_px,
+      pt_per_px,
+      cols: region.size.w.div_ceil(page_px.0).max(1),
+      rows: region.size.h.div_ceil(page_px.1).max(1),
+    }
+  }
+
+  pub fn page_count(&self) -> u32 {
+    self.cols * self.rows
+  }
+}
+
+pub enum ExportReply {
+  /// Fraction of the export that's done.
+  Progress(f32),
+
+  /// The export is complete.
+  Done,
+
+  /// The export failed.
+  Error(util::Error),
+}
+
+/// Exports part of a chart to a PDF on its own thread.
+pub struct PdfExport {
+  cancel: Arc<atomic::AtomicBool>,
+  rx: mpsc::Receiver<ExportReply>,
+}
+
+impl PdfExport {
+  /// Start exporting.
+  /// - `chart_paths`: chart file paths (two for a combined chart)
+  /// - `name`: chart name for the footer
+  /// - `settings`: export settings
+  /// - `output`: PDF file path
+  /// - `ctx`: egui context for requesting a repaint
+  pub fn start(
+    chart_paths: Vec<path::PathBuf>,
+    name: String,
+    settings: Settings,
+    output: path::PathBuf,
+    ctx: &egui::Context,
+  ) -> Self {
+    PdfExport::_start(chart_paths, name, settings, output, ctx.clone(), None)
+  }
+
+  /// Start exporting, calling `hook` each time a tile is done. This lets tests act while the export
+  /// is in progress.
+  /// - `chart_paths`: chart file paths (two for a combined chart)
+  /// - `name`: chart name for the footer
+  /// - `settings`: export settings
+  /// - `output`: PDF file path
+  /// - `ctx`: egui context for requesting a repaint
+  /// - `hook`: called before each progress reply
+  #[cfg(test)]
+  fn with_progress_hook(
+    chart_paths: Vec<path::PathBuf>,
+    name: String,
+    settings: Settings,
+    output: path::PathBuf,
+    ctx: &egui::Context,
+    hook: impl Fn() + Send + 'static,
+  ) -> Self {
+    let hook = Some(Box::new(hook) as Box<dyn Fn() + Send>);
+    PdfExport::_start(chart_paths, name, settings, output, ctx.clone(), hook)
+  }
+
+  fn _start(
+    chart_paths: Vec<path::PathBuf>,
+    name: String,
+    settings: Settings,
+    output: path::PathBuf,
+    ctx: egui::Context,
+    hook: Option<Box<dyn Fn() + Send>>,
+  ) -> Self {
+    let cancel = Arc::new(atomic::AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+
+    thread::Builder::new()
+      .name(any::type_name::<PdfExport>().to_owned())
+      .spawn({
+        let cancel = cancel.clone();
+        move || {
+          // Don't leave a partial file behind if the export is canceled or fails.
+          let mut partial = PartialFile::new(&output);
+          let progress = |fraction: f32| {
+            if let Some(hook) = &hook {
+              hook();
+            }
+            tx.send(ExportReply::Progress(fraction)).ok();
+            ctx.request_repaint();
+          };
+
+          let job = Job {
+            name,
+            settings,
+            cancel: &cancel,
+          };
+
+          let reply = match job.run(&chart_paths, &output, progress) {
+            Ok(true) => {
+              partial.keep();
+              ExportReply::Done
+            }
+            Ok(false) => return,
+            Err(err) => ExportReply::Error(err),
+          };
+
+          drop(partial);
+          tx.send(reply).ok();
+          ctx.request_repaint();
+        }
+      })
+      .unwrap();
+
+    Self { cancel, rx }
+  }
+
+  /// Get all available replies.
+  pub fn get_replies(&self) -> Vec<ExportReply> {
+    self.rx.try_iter().collect()
+  }
+}
+
+impl Drop for PdfExport {
+  fn drop(&mut self) {
+    // Stop as soon as the current tile is done.
+    self.cancel.store(true, atomic::Ordering::Relaxed);
+  }
+}
+
+/// Removes the output file when dropped, unless it's been kept.
+struct PartialFile<'a> {
+  path: &'a path::Path,
+  keep: bool,
+}
+
+impl<'a> PartialFile<'a> {
+  fn new(path: &'a path::Path) -> Self {
+    Self { path, keep: false }
+  }
+
+  /// Keep the file.
+  fn keep(&mut self) {
+    self.keep = true;
+  }
+}
+
+impl Drop for PartialFile<'_> {
+  fn drop(&mut self) {
+    if !self.keep {
+      fs::remove_file(self.path).ok();
+    }
+  }
+}
+
+struct Job<'a> {
+  name: String,
+  settings: Settings,
+  cancel: &'a atomic::AtomicBool,
+}
+
+impl Job<'_> {
+  /// Run the export. Returns false if it was canceled.
+  fn run(
+    &self,
+    chart_paths: &[path::PathBuf],
+    output: &path::Path,
+    mut progress: impl FnMut(f32),
+  ) -> Result<bool, util::Error> {
+    let (source, transform) = chart::ChartSource::open(chart_paths)?;
+    let layout = Layout::new(&self.settings, transform.meters_per_pixel());
+    let page_size = self.settings.paper.size(self.settings.landscape);
+    let region = self.settings.region.scaled(layout.zoom);
+
+    let file = match fs::File::create(output) {
+      Ok(file) => file,
+      Err(err) => return Err(format!("Unable to create {}\n{err}", output.display()).into()),
+    };
+
+    let io_err = |err: io::Error| util::Error::from(format!("Unable to write PDF\n{err}"));
+    let mut doc = pdf::Document::new(io::BufWriter::new(file)).map_err(io_err)?;
+
+    let tiles_x = layout.page_px.0.div_ceil(TILE_SIZE);
+    let tiles_y = layout.page_px.1.div_ceil(TILE_SIZE);
+    let total = (layout.page_count() * tiles_x * tiles_y) as f32;
+    let mut done = 0;
+
+    for row in 0..layout.rows {
+      for col in 0..layout.cols {
+        doc.begin_page(page_size).map_err(io_err)?;
+
+        // This page's part of the region in zoomed pixels.
+        let x0 = col * layout.page_px.0;
+        let y0 = row * layout.page_px.1;
+        let w = layout.page_px.0.min(region.size.w.saturating_sub(x0));
+        let h = layout.page_px.1.min(region.size.h.saturating_sub(y0));
+        let top = page_size.h - MARGIN;
+
+        // Read and write the page one tile at a time.
+        for ty in (0..h).step_by(TILE_SIZE as usize) {
+          for tx in (0..w).step_by(TILE_SIZE as usize) {
+            if self.cancel.load(atomic::Ordering::Relaxed) {
+              return Ok(false);
+            }
+
+            let size = util::Size {
+              w: TILE_SIZE.min(w - tx),
+              h: TILE_SIZE.min(h - ty),
+            };
+
+            let rect = util::Rect {
+              pos: util::Pos {
+                x: region.pos.x + (x0 + tx) as i32,
+                y: region.pos.y + (y0 + ty) as i32,
+              },
+              size,
+            };
+
+            let part = chart::ImagePart::new(rect, layout.zoom, chart::Palette::Light);
+            let image = match source.read_image(&part) {
+              Ok(image) => image,
+              Err(err) => return Err(format!("Unable to read chart image\n{err}").into()),
+            };
+
+            let jpeg = encode_jpeg(&image)?;
+            let place = pdf::Rect {
+              x: MARGIN + tx as f64 * layout.pt_per_px,
+              y: top - (ty + size.h) as f64 * layout.pt_per_px,
+              w: size.w as f64 * layout.pt_per_px,
+              h: size.h as f64 * layout.pt_per_px,
+            };
+            doc
+              .add_jpeg(&jpeg, (image.size[0], image.size[1]), place)
+              .map_err(io_err)?;
+
+            done += 1;
+            if self.cancel.load(atomic::Ordering::Relaxed) {
+              return Ok(false);
+            }
+            progress(done as f32 / total);
+          }
+        }
+
+        // Frame the image.
+        let frame = pdf::Rect {
+          x: MARGIN,
+          y: top - h as f64 * layout.pt_per_px,
+          w: w as f64 * layout.pt_per_px,
+          h: h as f64 * layout.pt_per_px,
+        };
+        doc.add_frame(frame, 0.5);
+
+        // Footer with the chart name, scale, page number and corner coordinates.
+        let page = row * layout.cols + col + 1;
+        let text = format!(
+          "{}  -  {}  -  Page {page} of {}",
+          self.name,
+          scale_label(self.settings.scale),
+          layout.page_count()
+        );
+        doc.add_text(&text, MARGIN, MARGIN + FONT_SIZE + 4.0, FONT_SIZE);
+
+        let zoom = layout.zoom as f64;
+        let nw = (
+          region.pos.x as f64 + x0 as f64,
+          region.pos.y as f64 + y0 as f64,
+        );
+        let se = (nw.0 + w as f64, nw.1 + h as f64);
+        let corner = |(x, y): (f64, f64)| {
+          let px = util::Coord {
+            x: x / zoom,
+            y: y / zoom,
+          };
+          let coord = transform.px_to_nad83(px).ok()?;
+          let lat = util::format_lat(coord.y)?;
+          let lon = util::format_lon(coord.x)?;
+          Some(format!("{lat}, {lon}"))
+        };
+
+        if let (Some(nw), Some(se)) = (corner(nw), corner(se)) {
+          let text = format!("NW {nw}    SE {se}");
+          doc.add_text(&text, MARGIN, MARGIN, FONT_SIZE);
+        }
+      }
+    }
+
+    doc.finish().map_err(io_err)?;
+    Ok(true)
+  }
+}
+
+/// Format a scale for display (e.g. "1:500,000").
+/// - `scale`: scale denominator
+pub fn scale_label(scale: u32) -> String {
+  let digits = scale.to_string();
+  let mut text = String::from("1:");
+  for (index, ch) in digits.chars().enumerate() {
+    if index > 0 && (digits.len() - index).is_multiple_of(3) {
+      text.push(',');
+    }
+    text.push(ch);
+  }
+  text
+}
+
+/// Encode an image as a baseline RGB JPEG.
+fn encode_jpeg(image: &egui::ColorImage) -> Result<Vec<u8>, util::Error> {
+  let rgb: Vec<u8> = image
+    .pixels
+    .iter()
+    .flat_map(|c| [c.r(), c.g(), c.b()])
+    .collect();
+  let mut jpeg = Vec::new();
+  let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY);
+  let (w, h) = (image.size[0] as u32, image.size[1] as u32);
+  if let Err(err) = encoder.encode(&rgb, w, h, image::ExtendedColorType::Rgb8) {
+    return Err(format!("Unable to encode image\n{err}").into());
+  }
+  Ok(jpeg)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn settings(w: u32, h: u32, scale: u32) -> Settings {
+    Settings {
+      region: util::Rect {
+        pos: util::Pos { x: 0, y: 0 },
+        size: util::Size { w, h },
+      },
+      paper: Paper::Letter,
+      landscape: false,
+      scale,
+    }
+  }
+
+  #[test]
+  fn test_layout() {
+    // Sectional charts are about 42.3 meters per pixel, which is 300 DPI at 1:500,000.
+    let meters_per_px = 500_000.0 / POINTS_PER_METER / (MAX_DPI / 72.0);
+    let layout = Layout::new(&settings(10_000, 10_000, 500_000), meters_per_px);
+    assert!((layout.zoom - 1.0).abs() < 1e-6);
+    assert!((layout.pt_per_px - 72.0 / MAX_DPI).abs() < 1e-9);
+
+    // Letter: 576 x 732 points of image area = 2400 x 3050 pixels.
+    assert!(layout.page_px == (2400, 3050));
+    assert!(layout.cols == 5 && layout.rows == 4);
+
+    // At 1:1,000,000 the chart is read at half resolution.
+    let layout = Layout::new(&settings(10_000, 10_000, 1_000_000), meters_per_px);
+    assert!((layout.zoom - 0.5).abs() < 1e-6);
+    assert!(layout.cols == 3 && layout.rows == 2);
+
+    // Enlarging never reads past full resolution.
+    let layout = Layout::new(&settings(1000, 1000, 250_000), meters_per_px);
+    assert!(layout.zoom == 1.0);
+    assert!((layout.pt_per_px - 2.0 * 72.0 / MAX_DPI).abs() < 1e-9);
+    assert!(layout.page_count() == 1);
+  }
+
+  #[test]
+  fn test_cancel() {
+    use std::{process, time};
+
+    // A chart region that's split into six single tile pages.
+    let chart = chart::test::TestChart::new().size(1000, 600);
+    let chart = chart.create(&format!("aviate_test_export_{}", process::id()));
+    let output = chart.with_extension("pdf");
+
+    // Hold the export at its first tile until it's been dropped.
+    const TIMEOUT: time::Duration = time::Duration::from_secs(10);
+    let (started_tx, started_rx) = mpsc::channel();
+    let (resume_tx, resume_rx) = mpsc::channel::<()>();
+    let hook = move || {
+      started_tx.send(()).ok();
+      resume_rx.recv_timeout(TIMEOUT).ok();
+    };
+
+    let ctx = egui::Context::default();
+    let settings = settings(1000, 600, 500_000);
+    let name = String::from("Test");
+    let paths = vec![chart.clone()];
+    let export = PdfExport::with_progress_hook(paths, name, settings, output.clone(), &ctx, hook);
+    started_rx.recv_timeout(TIMEOUT).unwrap();
+    assert!(output.exists());
+
+    // The thread stops at the next cancel check and exits without another tile. The file is
+    // removed before the hook is dropped with the thread.
+    drop(export);
+    drop(resume_tx);
+    let exited = started_rx.recv_timeout(TIMEOUT);
+    assert!(exited == Err(mpsc::RecvTimeoutError::Disconnected));
+    assert!(!output.exists());
+
+    fs::remove_file(chart).ok();
+  }
+
+  #[test]
+  fn test_scale_label() {
+    assert!(scale_label(500_000) == "1:500,000");
+    assert!(scale_label(1_000_000) == "1:1,000,000");
+    assert!(scale_label(250) == "1:250");
+  }
+}
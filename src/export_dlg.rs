@@ -0,0 +1,163 @@
+use crate::{export, util};
+use eframe::{egui, emath};
+
+/// Modal dialog for printing the chart to a PDF.
+pub struct ExportDlg {
+  /// Part of the chart that was showing when the dialog was opened (chart pixels).
+  view: util::Rect,
+
+  /// Region selected on the chart (chart pixels).
+  selected: Option<util::Rect>,
+  use_selected: bool,
+  meters_per_px: f64,
+  paper: export::Paper,
+  landscape: bool,
+  scale: u32,
+
+  /// Export progress, if an export is running.
+  progress: Option<f32>,
+}
+
+pub enum Response {
+  None,
+  Cancel,
+  SelectRegion,
+  Export(export::Settings),
+}
+
+impl ExportDlg {
+  /// Open the export dialog.
+  /// - `view`: the part of the chart that's showing, in chart pixels
+  /// - `meters_per_px`: chart meters per pixel at full zoom
+  pub fn open(view: util::Rect, meters_per_px: f64) -> Self {
+    Self {
+      view,
+      selected: None,
+      use_selected: false,
+      meters_per_px,
+      paper: export::Paper::Letter,
+      landscape: false,
+      scale: 500_000,
+      progress: None,
+    }
+  }
+
+  /// Set the region to export.
+  /// - `region`: region in chart pixels
+  pub fn set_region(&mut self, region: util::Rect) {
+    self.selected = Some(region);
+    self.use_selected = true;
+  }
+
+  /// Set the export progress.
+  /// - `progress`: fraction done, or `None` if no export is running
+  pub fn set_progress(&mut self, progress: Option<f32>) {
+    self.progress = progress;
+  }
+
+  pub fn show(&mut self, ctx: &egui::Context) -> Response {
+    let mut response = Response::None;
+    let mut open = !ctx.input(|state| state.key_pressed(egui::Key::Escape));
+
+    egui::Window::new(egui::RichText::from("📄  Print / Export PDF").strong())
+      .open(&mut open)
+      .collapsible(false)
+      .resizable(false)
+      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0])
+      .default_width(250.0)
+      .show(ctx, |ui| {
+        if let Some(progress) = self.progress {
+          ui.add_space(8.0);
+          let text = format!("Exporting… {:.0}%", progress * 100.0);
+          ui.add(egui::ProgressBar::new(progress).text(text));
+          ui.add_space(8.0);
+          ui.separator();
+          if ui.button("Cancel").clicked() {
+            response = Response::Cancel;
+          }
+          return;
+        }
+
+        ui.add_space(8.0);
+        egui::Grid::new("export_grid")
+          .num_columns(2)
+          .spacing([12.0, 6.0])
+          .show(ui, |ui| {
+            ui.label("Region");
+            ui.horizontal(|ui| {
+              ui.radio_value(&mut self.use_selected, false, "Current view");
+              ui.add_enabled_ui(self.selected.is_some(), |ui| {
+                ui.radio_value(&mut self.use_selected, true, "Selected");
+              });
+              if ui.button("Select…").clicked() {
+                response = Response::SelectRegion;
+              }
+            });
+            ui.end_row();
+
+            ui.label("Paper");
+            egui::ComboBox::from_id_source("export_paper")
+              .selected_text(self.paper.label())
+              .show_ui(ui, |ui| {
+                for paper in export::Paper::ALL {
+                  ui.selectable_value(&mut self.paper, paper, paper.label());
+                }
+              });
+            ui.end_row();
+
+            ui.label("Orientation");
+            ui.horizontal(|ui| {
+              ui.radio_value(&mut self.landscape, false, "Portrait");
+              ui.radio_value(&mut self.landscape, true, "Landscape");
+            });
+            ui.end_row();
+
+            ui.label("Scale");
+            egui::ComboBox::from_id_source("export_scale")
+              .selected_text(export::scale_label(self.scale))
+              .show_ui(ui, |ui| {
+                for scale in export::SCALES {
+                  ui.selectable_value(&mut self.scale, scale, export::scale_label(scale));
+                }
+              });
+            ui.end_row();
+          });
+
+        let settings = self.settings();
+        let pages = export::Layout::new(&settings, self.meters_per_px).page_count();
+        ui.add_space(8.0);
+        ui.label(egui::RichText::new(format!("{pages} page(s)")).weak());
+        ui.add_space(8.0);
+        ui.separator();
+        ui.horizontal(|ui| {
+          if ui.button("Export…").clicked() {
+            response = Response::Export(settings);
+          }
+
+          if ui.button("Cancel").clicked() {
+            response = Response::Cancel;
+          }
+        });
+      });
+
+    if !open {
+      response = Response::Cancel;
+    }
+
+    response
+  }
+
+  fn settings(&self) -> export::Settings {
+    let region = match self.selected {
+      Some(region) if self.use_selected => region,
+      _ => self.view,
+    };
+
+    export::Settings {
+      region,
+      paper: self.paper,
+      landscape: self.landscape,
+      scale: self.scale,
+    }
+  }
+}
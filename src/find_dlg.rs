@@ -1,57 +1,215 @@
+use crate::{nasr, util};
 use eframe::{egui, emath};
-use std::mem;
+use std::{mem, time};
+
+/// Minimum number of characters before searching as the user types.
+const MIN_FIND_CHARS: usize = 2;
+
+/// Time to wait after the last keystroke before searching.
+const DEBOUNCE: time::Duration = time::Duration::from_millis(250);
 
 #[derive(Default)]
 pub struct FindDlg {
   text: String,
   focus: bool,
+
+  /// Time of the last edit that hasn't been searched yet.
+  edited: Option<time::Instant>,
+
+  /// The most recent term sent to the airport reader.
+  query: String,
+
+  /// Results for `query`.
+  results: Vec<nasr::AirportInfo>,
+
+  /// Highlighted result (arrow keys).
+  highlight: Option<usize>,
+
+  /// Previous successful search terms.
+  history: Vec<String>,
+
+  /// Remembered size, used when the dialog is first shown.
+  restore: Option<util::Size>,
+
+  /// Current content size.
+  size: emath::Vec2,
 }
 
-#[derive(Eq, PartialEq)]
 pub enum Response {
   None,
   Cancel,
-  Term(String),
+  Term(String, nasr::SearchIntent),
+  Airport(nasr::AirportInfo),
+  ClearHistory,
 }
 
 impl FindDlg {
-  pub fn open() -> Self {
+  /// Name used to remember the dialog size.
+  pub const NAME: &'static str = "find";
+
+  /// Open the find dialog.
+  /// - `history`: previous search terms, most recent first
+  /// - `size`: remembered dialog size
+  pub fn open(history: Vec<String>, size: Option<util::Size>) -> Self {
     Self {
-      text: String::new(),
       focus: true,
+      history,
+      restore: size,
+      ..Default::default()
     }
   }
 
-  pub fn show(&mut self, ctx: &egui::Context) -> Response {
+  /// Current dialog size.
+  pub fn size(&self) -> util::Size {
+    self.size.into()
+  }
+
+  /// Set the results of a find request. Results for anything other than the most recent term are
+  /// ignored.
+  /// - `term`: the term that was searched for
+  /// - `infos`: matching airports
+  pub fn set_results(&mut self, term: String, infos: Vec<nasr::AirportInfo>) {
+    if term == self.query {
+      self.results = infos;
+      self.highlight = None;
+    }
+  }
+
+  /// Show the find dialog.
+  /// - `ctx`: egui context
+  /// - `find`: called with the current text when it's time to search as the user types
+  pub fn show(&mut self, ctx: &egui::Context, mut find: impl FnMut(String)) -> Response {
     let mut response = Response::None;
     let mut open = !ctx.input(|state| state.key_pressed(egui::Key::Escape));
 
+    // Arrow keys move the highlight through the results.
+    let (up, down) = ctx.input(|state| {
+      (
+        state.key_pressed(egui::Key::ArrowUp),
+        state.key_pressed(egui::Key::ArrowDown),
+      )
+    });
+
+    if !self.results.is_empty() {
+      let last = self.results.len() - 1;
+      if down {
+        self.highlight = Some(self.highlight.map_or(0, |index| (index + 1).min(last)));
+      } else if up {
+        self.highlight = self.highlight.and_then(|index| index.checked_sub(1));
+      }
+    }
+
+    // Search once typing has paused.
+    if let Some(edited) = self.edited {
+      let elapsed = edited.elapsed();
+      if elapsed >= DEBOUNCE {
+        self.edited = None;
+        let term = self.text.trim();
+        if term.chars().count() >= MIN_FIND_CHARS && term != self.query {
+          self.query = term.into();
+          find(self.query.clone());
+        }
+      } else {
+        ctx.request_repaint_after(DEBOUNCE - elapsed);
+      }
+    }
+
+    // Only the width can be changed; the height follows the results.
+    let mut choice = None;
+    let size = util::dialog_size(ctx, self.restore, emath::vec2(250.0, 0.0));
     egui::Window::new(egui::RichText::from("🔎  Find").strong())
       .open(&mut open)
       .collapsible(false)
-      .resizable(false)
+      .resizable([true, false])
       .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0])
-      .default_width(150.0)
+      .default_width(size.x)
       .show(ctx, |ui| {
+        self.size = ui.max_rect().size();
         ui.add_space(8.0);
         ui.horizontal(|ui| {
-          let widget = egui::TextEdit::singleline(&mut self.text).hint_text("Airport ID or name");
+          let widget =
+            egui::TextEdit::singleline(&mut self.text).hint_text("Airport ID, name or lat/lon");
           let edit_response = ui.add_sized(ui.available_size(), widget);
           if mem::take(&mut self.focus) {
-            self.focus = false;
             edit_response.request_focus();
           }
 
+          if edit_response.changed() {
+            self.edited = Some(time::Instant::now());
+            if self.text.trim().chars().count() < MIN_FIND_CHARS {
+              self.query.clear();
+              self.results.clear();
+              self.highlight = None;
+            }
+            ctx.request_repaint_after(DEBOUNCE);
+          }
+
           if edit_response.lost_focus() && ui.input(|state| state.key_pressed(egui::Key::Enter)) {
-            response = Response::Term(mem::take(&mut self.text));
+            if let Some(index) = self.highlight {
+              choice = Some(index);
+            } else {
+              let term = mem::take(&mut self.text);
+              response = Response::Term(term, nasr::SearchIntent::Goto);
+            }
+          }
+
+          // Keep the keyboard focus in the text box while using the arrow keys.
+          if up || down {
+            edit_response.request_focus();
           }
         });
+
+        if self.text.is_empty() && !self.history.is_empty() {
+          ui.add_space(4.0);
+          ui.label(egui::RichText::new("Recent").weak());
+          egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+              for term in &self.history {
+                let widget = egui::SelectableLabel::new(false, term);
+                if ui.add_sized([ui.available_width(), 0.0], widget).clicked() {
+                  response = Response::Term(term.clone(), nasr::SearchIntent::Goto);
+                }
+              }
+            });
+
+          if ui.small_button("Clear History").clicked() {
+            self.history.clear();
+            response = Response::ClearHistory;
+          }
+        } else if !self.results.is_empty() {
+          ui.add_space(4.0);
+          egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+              for (index, info) in self.results.iter().enumerate() {
+                let selected = self.highlight == Some(index);
+                let widget = egui::SelectableLabel::new(selected, &info.desc);
+                let label_response = ui.add_sized([ui.available_width(), 0.0], widget);
+                if selected && (up || down) {
+                  label_response.scroll_to_me(None);
+                }
+
+                if label_response.clicked() {
+                  choice = Some(index);
+                }
+              }
+            });
+        }
+
         ui.add_space(8.0);
         ui.separator();
         ui.horizontal(|ui| {
           ui.add_enabled_ui(!self.text.is_empty(), |ui| {
             if ui.button("Ok").clicked() {
-              response = Response::Term(mem::take(&mut self.text));
+              let term = mem::take(&mut self.text);
+              response = Response::Term(term, nasr::SearchIntent::Goto);
+            }
+
+            let info_response = ui.button("Info").on_hover_text("Show airport details");
+            if info_response.clicked() {
+              let term = mem::take(&mut self.text);
+              response = Response::Term(term, nasr::SearchIntent::Info);
             }
           });
 
@@ -61,6 +219,10 @@ impl FindDlg {
         });
       });
 
+    if let Some(index) = choice {
+      response = Response::Airport(self.results.swap_remove(index));
+    }
+
     if !open {
       response = Response::Cancel;
     }
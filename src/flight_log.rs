@@ -0,0 +1,104 @@
+use std::{collections, time};
+
+/// Session log of the places that the chart was panned to.
+#[derive(Default)]
+pub struct FlightLog {
+  entries: collections::VecDeque<LogEntry>,
+}
+
+impl FlightLog {
+  /// Add an entry using the current time.
+  /// - `text`: entry text
+  pub fn add(&mut self, text: String) {
+    self.add_at(time::SystemTime::now(), text);
+  }
+
+  /// Add an entry.
+  /// - `time`: entry time
+  /// - `text`: entry text
+  pub fn add_at(&mut self, time: time::SystemTime, text: String) {
+    // Don't repeat the last entry.
+    if self.entries.back().map(|entry| entry.text == text) == Some(true) {
+      return;
+    }
+
+    if self.entries.len() >= FlightLog::MAX_ENTRIES {
+      self.entries.pop_front();
+    }
+
+    self.entries.push_back(LogEntry { time, text });
+  }
+
+  pub fn clear(&mut self) {
+    self.entries.clear();
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn entries(&self) -> impl Iterator<Item = &LogEntry> {
+    self.entries.iter()
+  }
+
+  /// The log as text, one entry per line.
+  pub fn to_text(&self) -> String {
+    let mut text = String::new();
+    for entry in &self.entries {
+      text.push_str(&entry.time_text());
+      text.push_str("  ");
+      text.push_str(&entry.text);
+      text.push('\n');
+    }
+    text
+  }
+
+  const MAX_ENTRIES: usize = 300;
+}
+
+pub struct LogEntry {
+  pub time: time::SystemTime,
+  pub text: String,
+}
+
+impl LogEntry {
+  /// The entry time as UTC hours, minutes and seconds.
+  pub fn time_text(&self) -> String {
+    const SECS_PER_DAY: u64 = 24 * 60 * 60;
+    let secs = match self.time.duration_since(time::UNIX_EPOCH) {
+      Ok(duration) => duration.as_secs() % SECS_PER_DAY,
+      Err(_) => 0,
+    };
+    let hours = secs / 3600;
+    let mins = secs % 3600 / 60;
+    let secs = secs % 60;
+    format!("{hours:02}:{mins:02}:{secs:02}Z")
+  }
+}
+
+#[cfg(test)]
+mod test {
+  #[test]
+  fn test_log_dedup_and_cap() {
+    use std::time;
+    let mut log = super::FlightLog::default();
+    let time = time::UNIX_EPOCH + time::Duration::from_secs(3 * 86400 + 13 * 3600 + 5 * 60 + 9);
+    log.add_at(time, "Palo Alto (PAO), A, PUB".into());
+    log.add_at(time, "Palo Alto (PAO), A, PUB".into());
+    log.add_at(time, "San Carlos (SQL), A, PUB".into());
+    log.add_at(time, "Palo Alto (PAO), A, PUB".into());
+    assert!(log.entries().count() == 3);
+    assert!(log
+      .to_text()
+      .starts_with("13:05:09Z  Palo Alto (PAO), A, PUB\n"));
+
+    for index in 0..1000 {
+      log.add_at(time, format!("{index}"));
+    }
+    assert!(log.entries().count() == super::FlightLog::MAX_ENTRIES);
+    assert!(log.entries().last().unwrap().text == "999");
+
+    log.clear();
+    assert!(log.is_empty());
+  }
+}
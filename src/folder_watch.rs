@@ -0,0 +1,207 @@
+use crate::util;
+use eframe::egui;
+use std::{any, collections, fs, path, sync::mpsc, thread, time};
+
+/// Zip file that has been seen in the watched folder. A zip file that's replaced (or finishes
+/// downloading) has a new modification time, so it's seen again.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SeenZip {
+  pub path: path::PathBuf,
+
+  /// Modification time in seconds since the Unix epoch.
+  pub mtime: u64,
+}
+
+impl SeenZip {
+  pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+    let array = value.as_array()?;
+    let [path, mtime] = array.as_slice() else {
+      return None;
+    };
+
+    Some(Self {
+      path: path.as_str()?.into(),
+      mtime: mtime.as_u64()?,
+    })
+  }
+
+  pub fn to_value(&self) -> serde_json::Value {
+    let path = self.path.to_string_lossy().into_owned();
+    serde_json::json!([path, self.mtime])
+  }
+}
+
+/// Kind of FAA data in a zip file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ZipKind {
+  Chart,
+  Aero,
+}
+
+impl ZipKind {
+  pub fn text(self) -> &'static str {
+    match self {
+      Self::Chart => "chart",
+      Self::Aero => "NASR data",
+    }
+  }
+}
+
+pub enum Reply {
+  /// A new zip file with FAA data.
+  Found(path::PathBuf, ZipKind),
+
+  /// All the zip files now in the folder, to be remembered for the next session.
+  Seen(Vec<SeenZip>),
+}
+
+/// FolderWatcher polls a folder for newly downloaded FAA zip files. Zip files that were already
+/// there (or were seen in an earlier session) are ignored, as are zip files without FAA data.
+pub struct FolderWatcher {
+  folder: path::PathBuf,
+  rx: mpsc::Receiver<Reply>,
+
+  /// Dropping this stops the thread.
+  _stop: mpsc::Sender<()>,
+}
+
+impl FolderWatcher {
+  /// How often the folder is checked.
+  const POLL_INTERVAL: time::Duration = time::Duration::from_secs(30);
+
+  /// Start watching a folder.
+  /// - `folder`: folder to watch
+  /// - `seen`: zip files seen in an earlier session, `None` to ignore everything that's there now
+  /// - `ctx`: egui context for requesting a repaint
+  pub fn new(folder: &path::Path, seen: Option<Vec<SeenZip>>, ctx: &egui::Context) -> Self {
+    let (tx, rx) = mpsc::channel();
+    let (stop, stop_rx) = mpsc::channel::<()>();
+    let ctx = ctx.clone();
+    let watched = folder.to_owned();
+
+    // Each new zip file is opened through GDAL, so check the folder on a separate thread.
+    thread::Builder::new()
+      .name(any::type_name::<FolderWatcher>().to_owned())
+      .spawn(move || {
+        let mut seen: Option<collections::HashSet<SeenZip>> =
+          seen.map(|seen| seen.into_iter().collect());
+        loop {
+          let listing = list_zips(&watched);
+          let mut changed = seen.is_none();
+          if let Some(seen) = &seen {
+            for zip in new_zips(seen, &listing) {
+              changed = true;
+              let kind = match util::get_zip_info(&zip.path) {
+                Ok(util::ZipInfo::Chart(_)) => ZipKind::Chart,
+                Ok(util::ZipInfo::Aero { .. }) => ZipKind::Aero,
+                Err(_) => continue,
+              };
+
+              if tx.send(Reply::Found(zip.path.clone(), kind)).is_err() {
+                return;
+              }
+            }
+
+            // Zip files that were removed are forgotten.
+            changed |= seen.len() != listing.len();
+          }
+
+          if changed {
+            if tx.send(Reply::Seen(listing.clone())).is_err() {
+              return;
+            }
+            ctx.request_repaint();
+          }
+
+          seen = Some(listing.into_iter().collect());
+
+          // Wait for the next check, stopping when the watcher is dropped.
+          if let Err(mpsc::RecvTimeoutError::Disconnected) =
+            stop_rx.recv_timeout(Self::POLL_INTERVAL)
+          {
+            return;
+          }
+        }
+      })
+      .unwrap();
+
+    Self {
+      folder: folder.to_owned(),
+      rx,
+      _stop: stop,
+    }
+  }
+
+  /// The folder being watched.
+  pub fn folder(&self) -> &path::Path {
+    &self.folder
+  }
+
+  /// Get the replies from the watcher thread.
+  pub fn get_replies(&self) -> Vec<Reply> {
+    self.rx.try_iter().collect()
+  }
+}
+
+/// List the zip files in a folder (not including sub-folders).
+/// - `folder`: folder to list
+fn list_zips(folder: &path::Path) -> Vec<SeenZip> {
+  let Ok(entries) = fs::read_dir(folder) else {
+    return Vec::new();
+  };
+
+  let mut zips = Vec::new();
+  for entry in entries.flatten() {
+    let path = entry.path();
+    let zip = path
+      .extension()
+      .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    let Some(metadata) = entry
+      .metadata()
+      .ok()
+      .filter(|metadata| zip && metadata.is_file())
+    else {
+      continue;
+    };
+
+    let mtime = metadata.modified().ok();
+    let mtime = mtime.and_then(|mtime| mtime.duration_since(time::UNIX_EPOCH).ok());
+    let mtime = mtime.map_or(0, |mtime| mtime.as_secs());
+    zips.push(SeenZip { path, mtime });
+  }
+  zips
+}
+
+/// Zip files in a folder listing that haven't been seen.
+/// - `seen`: zip files that have been seen
+/// - `listing`: zip files now in the folder
+fn new_zips<'a>(seen: &collections::HashSet<SeenZip>, listing: &'a [SeenZip]) -> Vec<&'a SeenZip> {
+  listing.iter().filter(|zip| !seen.contains(zip)).collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::{new_zips, SeenZip};
+  use std::collections::HashSet;
+
+  #[test]
+  fn test_new_zips() {
+    let zip = |name: &str, mtime| SeenZip {
+      path: name.into(),
+      mtime,
+    };
+
+    let seen: HashSet<SeenZip> = [zip("a.zip", 1), zip("b.zip", 2)].into();
+    let listing = [zip("a.zip", 1), zip("b.zip", 3), zip("c.zip", 1)];
+    let found = new_zips(&seen, &listing);
+
+    // A replaced zip file is new again.
+    assert!(found == [&listing[1], &listing[2]]);
+    assert!(new_zips(&seen, &listing[..1]).is_empty());
+
+    // Seen zip files round trip through the config.
+    let value = listing[1].to_value();
+    assert!(SeenZip::from_value(&value) == Some(zip("b.zip", 3)));
+    assert!(SeenZip::from_value(&serde_json::json!(["a.zip"])).is_none());
+  }
+}
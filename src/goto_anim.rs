@@ -0,0 +1,178 @@
+use eframe::emath;
+
+/// Animated transition of the chart view from one point to another.
+///
+/// The view center moves from the start to the target while the zoom dips out far enough to show
+/// both points, then comes back in. This keeps some spatial context when jumping a long way.
+pub struct GotoAnim {
+  from: emath::Pos2,
+  to: emath::Pos2,
+  from_zoom: f32,
+  to_zoom: f32,
+  arc_zoom: Option<f32>,
+  duration: f64,
+  start: Option<f64>,
+  last_request: f64,
+}
+
+impl GotoAnim {
+  /// Length of the go-to animation in seconds.
+  const DURATION: f64 = 0.4;
+
+  /// Length of the zoom animation in seconds.
+  const ZOOM_DURATION: f64 = 0.15;
+
+  /// Minimum time between image requests while animating, in seconds.
+  const REQUEST_INTERVAL: f64 = 0.1;
+
+  /// Create a new go-to animation.
+  /// - `from`: current view center in chart pixels (full resolution)
+  /// - `from_zoom`: current zoom
+  /// - `to`: target view center in chart pixels (full resolution)
+  /// - `to_zoom`: target zoom
+  /// - `view_size`: size of the view in screen pixels
+  /// - `min_zoom`: lowest allowed zoom
+  ///
+  /// The animation starts on the first call to `step`.
+  pub fn new(
+    from: emath::Pos2,
+    from_zoom: f32,
+    to: emath::Pos2,
+    to_zoom: f32,
+    view_size: emath::Vec2,
+    min_zoom: f32,
+  ) -> Self {
+    // Zoom needed to fit both points in the view.
+    let dist = (to - from).abs();
+    let fit = (view_size.x / dist.x).min(view_size.y / dist.y);
+    let arc_zoom = fit.min(from_zoom).min(to_zoom).max(min_zoom);
+    Self {
+      from,
+      to,
+      from_zoom,
+      to_zoom,
+      arc_zoom: Some(arc_zoom),
+      duration: Self::DURATION,
+      start: None,
+      last_request: 0.0,
+    }
+  }
+
+  /// Create a short animation that zooms the view without the go-to arc.
+  /// - `from`: current view center in chart pixels (full resolution)
+  /// - `from_zoom`: current zoom
+  /// - `to`: target view center in chart pixels (full resolution)
+  /// - `to_zoom`: target zoom
+  pub fn zoom(from: emath::Pos2, from_zoom: f32, to: emath::Pos2, to_zoom: f32) -> Self {
+    Self {
+      from,
+      to,
+      from_zoom,
+      to_zoom,
+      arc_zoom: None,
+      duration: Self::ZOOM_DURATION,
+      start: None,
+      last_request: 0.0,
+    }
+  }
+
+  /// The view center and zoom at the end of the animation.
+  pub fn target(&self) -> (emath::Pos2, f32) {
+    (self.to, self.to_zoom)
+  }
+
+  /// Get the view center and zoom at a point in time.
+  /// - `now`: current time in seconds
+  ///
+  /// Returns the view center in chart pixels (full resolution), the zoom and whether the animation
+  /// is finished.
+  pub fn step(&mut self, now: f64) -> (emath::Pos2, f32, bool) {
+    let start = *self.start.get_or_insert_with(|| {
+      self.last_request = now;
+      now
+    });
+    let t = ((now - start) / self.duration).clamp(0.0, 1.0) as f32;
+    let pos = self.from.lerp(self.to, ease(t));
+    let zoom = match self.arc_zoom {
+      Some(arc_zoom) if t < 0.5 => emath::lerp(self.from_zoom..=arc_zoom, ease(t * 2.0)),
+      Some(arc_zoom) => emath::lerp(arc_zoom..=self.to_zoom, ease(t * 2.0 - 1.0)),
+      None => emath::lerp(self.from_zoom..=self.to_zoom, ease(t)),
+    };
+    (pos, zoom, t >= 1.0)
+  }
+
+  /// Check if a new image should be requested.
+  /// - `now`: current time in seconds
+  pub fn request_due(&mut self, now: f64) -> bool {
+    if now - self.last_request >= Self::REQUEST_INTERVAL {
+      self.last_request = now;
+      return true;
+    }
+    false
+  }
+}
+
+/// Smooth-step easing.
+fn ease(t: f32) -> f32 {
+  t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod test {
+  use super::GotoAnim;
+  use eframe::emath;
+
+  #[test]
+  fn test_goto_anim() {
+    let from = emath::pos2(0.0, 0.0);
+    let to = emath::pos2(4000.0, 0.0);
+    let size = emath::vec2(1000.0, 800.0);
+    let mut anim = GotoAnim::new(from, 1.0, to, 1.0, size, 0.125);
+
+    let (pos, zoom, done) = anim.step(0.0);
+    assert!(pos == from && zoom == 1.0 && !done);
+
+    // Zoomed out to fit both points halfway through.
+    let (pos, zoom, done) = anim.step(0.2);
+    assert!(pos == emath::pos2(2000.0, 0.0));
+    assert!(zoom == 0.25 && !done);
+
+    let (pos, zoom, done) = anim.step(0.5);
+    assert!(pos == to && zoom == 1.0 && done);
+
+    // Nearby targets don't zoom out.
+    let to = emath::pos2(300.0, 200.0);
+    let mut anim = GotoAnim::new(from, 1.0, to, 1.0, size, 0.125);
+    anim.step(0.0);
+    assert!(anim.step(0.2).1 == 1.0);
+  }
+
+  #[test]
+  fn test_zoom_anim() {
+    let center = emath::pos2(500.0, 400.0);
+    let mut anim = GotoAnim::zoom(center, 0.5, center, 1.0);
+    assert!(anim.target() == (center, 1.0));
+
+    let (pos, zoom, done) = anim.step(1.0);
+    assert!(pos == center && zoom == 0.5 && !done);
+
+    // Halfway through the zoom is halfway between.
+    let (_, zoom, done) = anim.step(1.075);
+    assert!((zoom - 0.75).abs() < 1.0e-4 && !done);
+
+    let (pos, zoom, done) = anim.step(1.15);
+    assert!(pos == center && zoom == 1.0 && done);
+  }
+
+  #[test]
+  fn test_request_due() {
+    let from = emath::pos2(0.0, 0.0);
+    let size = emath::vec2(1000.0, 800.0);
+    let mut anim = GotoAnim::new(from, 1.0, from, 1.0, size, 0.125);
+    anim.step(0.0);
+    assert!(!anim.request_due(0.05));
+    assert!(anim.request_due(0.1));
+    assert!(!anim.request_due(0.15));
+    assert!(anim.request_due(0.25));
+  }
+}
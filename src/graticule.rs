@@ -0,0 +1,172 @@
+use crate::{chart, util};
+use eframe::{egui, emath, epaint};
+
+/// Screen distance between samples along a grid line.
+const SAMPLE_SPACING: f32 = 50.0;
+
+/// Minimum screen distance between grid lines.
+const MIN_LINE_SPACING: f64 = 100.0;
+
+/// Grid spacings in decimal degrees: 10', 30' and 1°.
+const GRID_SPACINGS: [f64; 3] = [10.0 / 60.0, 30.0 / 60.0, 1.0];
+
+/// Draw a latitude/longitude grid over the visible part of the chart.
+/// - `ui`: chart view UI
+/// - `rect`: visible chart rectangle on the screen
+/// - `transform`: chart transformation
+/// - `offset`: scroll offset of the chart view
+/// - `zoom`: current chart zoom
+pub fn draw(
+  ui: &egui::Ui,
+  rect: emath::Rect,
+  transform: &chart::Transform,
+  offset: emath::Vec2,
+  zoom: f32,
+) {
+  // Conversions between chart pixels and screen positions.
+  let to_screen = |coord: util::Coord| {
+    let pos = emath::pos2(coord.x as f32, coord.y as f32) * zoom;
+    rect.min + pos.to_vec2() - offset
+  };
+  let to_px = |pos: emath::Pos2| {
+    let pos = (pos - rect.min + offset) / zoom;
+    util::Coord {
+      x: pos.x as f64,
+      y: pos.y as f64,
+    }
+  };
+
+  // Get the latitude/longitude range of the view. Edge midpoints are included because
+  // latitude lines are curved in LCC.
+  let (min, max) = (rect.min, rect.max);
+  let center = rect.center();
+  let points = [
+    min,
+    emath::pos2(center.x, min.y),
+    emath::pos2(max.x, min.y),
+    emath::pos2(max.x, center.y),
+    max,
+    emath::pos2(center.x, max.y),
+    emath::pos2(min.x, max.y),
+    emath::pos2(min.x, center.y),
+  ];
+
+  let mut lat_range = (f64::MAX, f64::MIN);
+  let mut lon_range = (f64::MAX, f64::MIN);
+  for pos in points {
+    let Ok(coord) = transform.px_to_nad83(to_px(pos)) else {
+      return;
+    };
+    lat_range = (lat_range.0.min(coord.y), lat_range.1.max(coord.y));
+    lon_range = (lon_range.0.min(coord.x), lon_range.1.max(coord.x));
+  }
+
+  // Choose the smallest grid spacing that keeps the lines far enough apart.
+  let px_per_deg = rect.height() as f64 / (lat_range.1 - lat_range.0);
+  let Some(spacing) = GRID_SPACINGS
+    .into_iter()
+    .find(|spacing| spacing * px_per_deg >= MIN_LINE_SPACING)
+  else {
+    return;
+  };
+
+  let visuals = ui.visuals();
+  let color = if visuals.dark_mode {
+    epaint::Color32::from_rgba_unmultiplied(100, 160, 255, 160)
+  } else {
+    epaint::Color32::from_rgba_unmultiplied(0, 60, 160, 160)
+  };
+  let stroke = epaint::Stroke::new(1.0, color);
+  let font_id = egui::TextStyle::Small.resolve(ui.style());
+  let painter = ui.painter_at(rect);
+
+  // Number of samples along a line.
+  let count = |len: f32| (len / SAMPLE_SPACING).ceil() as usize + 2;
+
+  // Sample a constant latitude or longitude line.
+  let sample = |from: util::Coord, to: util::Coord, count: usize| {
+    let mut points = Vec::with_capacity(count);
+    for idx in 0..count {
+      let t = idx as f64 / (count - 1) as f64;
+      let coord = util::Coord {
+        x: from.x + (to.x - from.x) * t,
+        y: from.y + (to.y - from.y) * t,
+      };
+      if let Ok(coord) = transform.nad83_to_px(coord) {
+        points.push(to_screen(coord));
+      }
+    }
+    points
+  };
+
+  // Grid line indexes covering the range.
+  let indexes = |range: (f64, f64)| {
+    let first = (range.0 / spacing).floor() as i64;
+    let last = (range.1 / spacing).ceil() as i64;
+    first..=last
+  };
+
+  // Longitude lines, labeled along the top edge.
+  let lat_ext = (lat_range.0 - spacing, lat_range.1 + spacing);
+  for idx in indexes(lon_range) {
+    let lon = idx as f64 * spacing;
+    let from = util::Coord {
+      x: lon,
+      y: lat_ext.0,
+    };
+    let to = util::Coord {
+      x: lon,
+      y: lat_ext.1,
+    };
+    let points = sample(from, to, count(rect.height()) * 2);
+    let label = util::format_lon(lon);
+    let pos = points
+      .iter()
+      .filter(|pos| rect.contains(**pos))
+      .min_by(|a, b| a.y.total_cmp(&b.y))
+      .copied();
+    draw_line(&painter, points, stroke);
+    if let (Some(label), Some(pos)) = (label, pos) {
+      let pos = emath::pos2(pos.x + 2.0, rect.top() + 2.0);
+      painter.text(pos, emath::Align2::LEFT_TOP, label, font_id.clone(), color);
+    }
+  }
+
+  // Latitude lines, labeled along the left edge.
+  let lon_ext = (lon_range.0 - spacing, lon_range.1 + spacing);
+  for idx in indexes(lat_range) {
+    let lat = idx as f64 * spacing;
+    let from = util::Coord {
+      x: lon_ext.0,
+      y: lat,
+    };
+    let to = util::Coord {
+      x: lon_ext.1,
+      y: lat,
+    };
+    let points = sample(from, to, count(rect.width()) * 2);
+    let label = util::format_lat(lat);
+    let pos = points
+      .iter()
+      .filter(|pos| rect.contains(**pos))
+      .min_by(|a, b| a.x.total_cmp(&b.x))
+      .copied();
+    draw_line(&painter, points, stroke);
+    if let (Some(label), Some(pos)) = (label, pos) {
+      let pos = emath::pos2(rect.left() + 2.0, pos.y - 2.0);
+      painter.text(
+        pos,
+        emath::Align2::LEFT_BOTTOM,
+        label,
+        font_id.clone(),
+        color,
+      );
+    }
+  }
+}
+
+fn draw_line(painter: &egui::Painter, points: Vec<emath::Pos2>, stroke: epaint::Stroke) {
+  if points.len() > 1 {
+    painter.add(epaint::Shape::line(points, stroke));
+  }
+}
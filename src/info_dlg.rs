@@ -0,0 +1,278 @@
+use crate::{back, nasr, runway_diagram, text_tagger, util};
+use eframe::{egui, emath};
+
+/// Non-modal window showing the full details for an airport.
+pub struct InfoDlg {
+  title: String,
+  id: String,
+  detail: Option<Box<nasr::AirportDetail>>,
+
+  /// Remarks with their phone numbers and web links.
+  remarks: Vec<text_tagger::TaggedText>,
+
+  /// Runway selected for the diagram.
+  runway: Option<String>,
+
+  /// Remembered size, used when the window is first shown.
+  restore: Option<util::Size>,
+
+  /// Current content size.
+  size: emath::Vec2,
+}
+
+/// Response from the airport info window.
+/// - `None`: nothing to do
+/// - `Close`: the window has been closed
+/// - `ShowOnChart`: pan the chart to the airport's coordinate
+/// - `ZoomOnChart`: zoom the chart in on the airport's coordinate
+pub enum Response {
+  None,
+  Close,
+  ShowOnChart(util::Coord),
+  ZoomOnChart(util::Coord),
+}
+
+impl InfoDlg {
+  /// Name used to remember the window size.
+  pub const NAME: &'static str = "info";
+
+  /// Open the airport info window.
+  /// - `title`: airport description
+  /// - `id`: airport ID
+  /// - `size`: remembered window size
+  pub fn open(title: String, id: String, size: Option<util::Size>) -> Self {
+    Self {
+      title,
+      id,
+      detail: None,
+      remarks: Vec::new(),
+      runway: None,
+      restore: size,
+      size: emath::Vec2::ZERO,
+    }
+  }
+
+  /// Current window size.
+  pub fn size(&self) -> util::Size {
+    self.size.into()
+  }
+
+  /// Airport ID for this window.
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  /// Set the airport details once they're available.
+  pub fn set_detail(&mut self, detail: Box<nasr::AirportDetail>) {
+    let tagger = text_tagger::TextTagger::default();
+    let remarks = detail.remarks.iter().map(|text| tagger.tag(text.clone()));
+    self.remarks = remarks.collect();
+    self.detail = Some(detail);
+  }
+
+  /// Show the window. Returns `Response::Close` when it has been closed.
+  /// - `ctx`: egui context
+  /// - `units`: display units
+  pub fn show(&mut self, ctx: &egui::Context, units: util::Units) -> Response {
+    let mut response = Response::None;
+    let mut open = !back::register(ctx, "info");
+    let win = egui::Window::new(egui::RichText::from("🛈  Airport Info").strong())
+      .open(&mut open)
+      .collapsible(false)
+      .anchor(emath::Align2::RIGHT_TOP, [-8.0, 8.0]);
+
+    let win = match self.restore {
+      Some(size) => win.default_size(util::dialog_size(ctx, Some(size), size.into())),
+      None => win.default_width(360.0),
+    };
+
+    win.show(ctx, |ui| {
+      self.size = ui.max_rect().size();
+      ui.label(egui::RichText::new(&self.title).strong());
+      ui.separator();
+
+      let Some(detail) = &self.detail else {
+        ui.spinner();
+        return;
+      };
+
+      ui.horizontal(|ui| {
+        if ui.button("Show on Chart").clicked() {
+          response = Response::ShowOnChart(detail.info.coord);
+        }
+
+        if ui.button("Zoom to Airport").clicked() {
+          response = Response::ZoomOnChart(detail.info.coord);
+        }
+      });
+      ui.add_space(2.0);
+
+      egui::ScrollArea::vertical()
+        .max_height(400.0)
+        .show(ui, |ui| {
+          egui::CollapsingHeader::new("Summary")
+            .default_open(true)
+            .show(ui, |ui| show_summary(ui, detail, units));
+
+          let text = format!("Frequencies ({})", detail.frequencies.len());
+          egui::CollapsingHeader::new(text)
+            .id_source("info_frequencies")
+            .default_open(true)
+            .show(ui, |ui| show_frequencies(ui, &detail.frequencies));
+
+          let text = format!("Runways ({})", detail.runways.len());
+          egui::CollapsingHeader::new(text)
+            .id_source("info_runways")
+            .default_open(true)
+            .show(ui, |ui| {
+              show_runways(ui, &detail.runways, &mut self.runway, units);
+            });
+
+          let text = format!("Remarks ({})", detail.remarks.len());
+          egui::CollapsingHeader::new(text)
+            .id_source("info_remarks")
+            .show(ui, |ui| {
+              for remark in &self.remarks {
+                remark.show(ui);
+                ui.add_space(2.0);
+              }
+            });
+        });
+    });
+
+    if !open {
+      response = Response::Close;
+    }
+
+    response
+  }
+}
+
+fn show_summary(ui: &mut egui::Ui, detail: &nasr::AirportDetail, units: util::Units) {
+  let info = &detail.info;
+  let lat = util::format_lat(info.coord.y).unwrap_or_default();
+  let lon = util::format_lon(info.coord.x).unwrap_or_default();
+  let mut fields = vec![
+    ("ID", info.id.clone()),
+    ("Name", info.name.clone()),
+    ("Location", detail.location.clone()),
+    ("Coordinates", format!("{lat}, {lon}")),
+    ("Type", info.airport_type.text().into()),
+    ("Use", info.airport_use.text().into()),
+  ];
+
+  if let Some(elevation) = info.elevation {
+    fields.push(("Elevation", util::format_altitude(elevation, units)));
+  }
+
+  if let Some(mag_var) = &detail.mag_var {
+    fields.push(("Mag Var", mag_var.clone()));
+  }
+
+  if !info.fuel_types.is_empty() {
+    fields.push(("Fuel", info.fuel_types.clone()));
+  }
+
+  egui::Grid::new("info_summary_grid")
+    .spacing([16.0, 2.0])
+    .show(ui, |ui| {
+      for (label, value) in fields {
+        ui.label(egui::RichText::new(label).weak());
+        if label == "Coordinates" {
+          let response = ui.button(&value).on_hover_text("Copy to clipboard");
+          if response.clicked() {
+            // Copy both the DMS and decimal formats.
+            let text = format!("{value}\n{:.6}, {:.6}", info.coord.y, info.coord.x);
+            ui.ctx().output_mut(|state| state.copied_text = text);
+          }
+        } else {
+          ui.label(value);
+        }
+        ui.end_row();
+      }
+    });
+}
+
+fn show_frequencies(ui: &mut egui::Ui, frequencies: &[nasr::Frequency]) {
+  if frequencies.is_empty() {
+    ui.label(egui::RichText::new("None").weak());
+    return;
+  }
+
+  egui::Grid::new("info_frequency_grid")
+    .spacing([16.0, 2.0])
+    .show(ui, |ui| {
+      for (group, frequencies) in nasr::Frequency::grouped(frequencies) {
+        ui.label(egui::RichText::new(group.text()).strong());
+        ui.end_row();
+
+        for frequency in frequencies {
+          ui.label(egui::RichText::new(&frequency.usage).weak());
+          ui.label(egui::RichText::new(&frequency.freq).monospace());
+          ui.horizontal(|ui| {
+            ui.label(&frequency.call);
+            if !frequency.sector.is_empty() {
+              ui.label(egui::RichText::new(&frequency.sector).weak());
+            }
+          });
+          ui.end_row();
+        }
+      }
+    });
+}
+
+/// Show the runway list. Clicking a runway toggles its diagram.
+fn show_runways(
+  ui: &mut egui::Ui,
+  runways: &[nasr::Runway],
+  selected: &mut Option<String>,
+  units: util::Units,
+) {
+  if runways.is_empty() {
+    ui.label(egui::RichText::new("None").weak());
+    return;
+  }
+
+  for runway in runways {
+    let mut text = runway.id.clone();
+    if let (Some(length), Some(width)) = (runway.length, runway.width) {
+      let length = util::group_thousands(units.altitude(length).round() as i64);
+      let width = util::group_thousands(units.altitude(width).round() as i64);
+      text += &format!("  {length} x {width} {}", units.altitude_abv());
+    }
+
+    if !runway.surface.is_empty() {
+      text += &format!("  {}", runway.surface);
+    }
+
+    let is_selected = selected.as_deref() == Some(runway.id.as_str());
+    let response = ui.selectable_label(is_selected, egui::RichText::new(text).strong());
+    if response.on_hover_text("Show diagram").clicked() {
+      *selected = (!is_selected).then(|| runway.id.clone());
+    }
+
+    ui.indent(&runway.id, |ui| {
+      for end in &runway.ends {
+        let mut text = end.id.clone();
+        if let Some(alignment) = end.true_alignment {
+          text += &format!("  {alignment:03.0}° true");
+        }
+
+        if let Some(elevation) = end.elevation {
+          text += &format!("  {}", util::format_altitude(elevation, units));
+        }
+
+        if end.right_traffic {
+          text += "  right traffic";
+        }
+
+        ui.label(text);
+      }
+
+      if is_selected {
+        runway_diagram::show(ui, runway, units);
+      }
+    });
+    ui.add_space(2.0);
+  }
+}
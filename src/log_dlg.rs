@@ -0,0 +1,87 @@
+use crate::{event_log, util};
+use eframe::{egui, emath};
+use std::mem;
+
+pub struct LogDlg {
+  /// Least severe level shown.
+  level: log::Level,
+  reset: bool,
+}
+
+impl LogDlg {
+  pub fn open() -> Self {
+    Self {
+      level: log::Level::Info,
+      reset: true,
+    }
+  }
+
+  pub fn show(&mut self, ctx: &egui::Context) -> bool {
+    let mut open = !ctx.input(|state| state.key_pressed(egui::Key::Escape));
+    let win = egui::Window::new(egui::RichText::from("🗒  Event Log").strong())
+      .open(&mut open)
+      .collapsible(false)
+      .resizable(false)
+      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0]);
+
+    // Hack to reset the window size.
+    let win = if mem::take(&mut self.reset) {
+      win.fixed_size([500.0, 20.0])
+    } else {
+      win
+    };
+
+    let mut close = false;
+    win.show(ctx, |ui| {
+      ui.add_space(8.0);
+      ui.horizontal(|ui| {
+        ui.label("Show");
+        ui.selectable_value(&mut self.level, log::Level::Error, "Errors");
+        ui.selectable_value(&mut self.level, log::Level::Warn, "Warnings");
+        ui.selectable_value(&mut self.level, log::Level::Info, "All");
+      });
+
+      let entries = event_log::entries();
+      let level = self.level;
+      let shown = || entries.iter().filter(move |entry| entry.level <= level);
+
+      ui.separator();
+      egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+          let mut empty = true;
+          egui::Grid::new(format!("{}_event_log", util::APP_NAME))
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+              for entry in shown() {
+                ui.label(entry.time_text());
+                ui.label(entry.level.as_str());
+                ui.add(egui::Label::new(entry.text.as_str()).wrap(true));
+                ui.end_row();
+                empty = false;
+              }
+            });
+
+          if empty {
+            ui.label("Nothing has been logged");
+          }
+        });
+
+      ui.separator();
+      ui.horizontal(|ui| {
+        if ui.button("Copy to clipboard").clicked() {
+          let text = event_log::to_text(shown());
+          ctx.output_mut(|state| state.copied_text = text);
+        }
+
+        if ui.button("Close").clicked() {
+          close = true;
+        }
+      });
+    });
+
+    open && !close
+  }
+}
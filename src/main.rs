@@ -4,30 +4,66 @@
 #[macro_use]
 mod util;
 
+mod airport_fmt;
 mod app;
+mod ask_dlg;
+mod back;
+mod benchmark;
 mod chart;
+mod coalesce;
+mod companion;
+mod compare_dlg;
 mod config;
+mod diag_dlg;
 mod error_dlg;
+mod event_log;
+mod export;
+mod export_dlg;
 mod find_dlg;
+mod flight_log;
+mod folder_watch;
+mod goto_anim;
+mod graticule;
+mod info_dlg;
+mod log_dlg;
+mod name_dlg;
 mod nasr;
+mod palette_dlg;
+mod pdf;
+mod remarks_dlg;
+mod runway_diagram;
 mod select_dlg;
 mod select_menu;
+mod solar;
+mod terrain;
+mod text_tagger;
+mod texture_budget;
+mod toast;
 mod touch;
+mod view_history;
+mod wind;
+mod wind_dlg;
+mod wx;
+mod wx_dlg;
 
 use eframe::egui;
-use std::env;
+use std::{env, path, process};
 
 struct Opts {
   native: eframe::NativeOptions,
   theme: Option<egui::Visuals>,
   scale: Option<f32>,
   config: config::Storage,
+
+  /// Zip file to open at startup.
+  path: Option<path::PathBuf>,
 }
 
 fn parse_args() -> Opts {
   let mut sim = false;
   let mut theme = None;
   let mut deco = cfg!(not(feature = "mobile"));
+  let mut path = None;
   let icon = image::load_from_memory(util::APP_ICON).unwrap();
   let icon = egui::IconData {
     width: icon.width(),
@@ -35,7 +71,7 @@ fn parse_args() -> Opts {
     rgba: icon.into_rgba8().into_raw(),
   };
 
-  for arg in env::args() {
+  for arg in env::args().skip(1) {
     match arg.as_str() {
       // Force dark theme as default.
       "--dark" => theme = Some(egui::Visuals::dark()),
@@ -48,7 +84,12 @@ fn parse_args() -> Opts {
 
       // Simulate what it would look like on a device like PinePhone or Librem 5.
       "--sim" => sim = cfg!(not(feature = "mobile")),
-      _ => (),
+
+      // Ignore unknown options.
+      _ if arg.starts_with("--") => (),
+
+      // Zip file to open (e.g. from a file association).
+      _ => path = Some(path::PathBuf::from(arg)),
     }
   }
 
@@ -71,10 +112,15 @@ fn parse_args() -> Opts {
       let mut viewport = egui::ViewportBuilder::default()
         .with_icon(icon)
         .with_min_inner_size(MIN_SIZE)
-        .with_maximized(win_info.maxed);
+        .with_maximized(win_info.maxed)
+        .with_fullscreen(win_info.fullscreen);
       if let Some(size) = win_info.size {
         viewport = viewport.with_inner_size(size);
       }
+
+      if let Some(pos) = win_info.restore_pos() {
+        viewport = viewport.with_position(pos);
+      }
       (viewport, None)
     } else {
       let viewport = egui::ViewportBuilder::default()
@@ -94,15 +140,34 @@ fn parse_args() -> Opts {
     theme,
     scale,
     config,
+    path,
   }
 }
 
 fn main() {
-  let opts = parse_args();
+  event_log::init();
+
+  // Run the benchmark instead of the app if it was asked for.
+  if let Some(paths) = benchmark::parse_args(env::args().skip(1)) {
+    let result = paths.and_then(|(chart, nasr)| benchmark::run(&chart, &nasr));
+    if let Err(err) = result {
+      eprintln!("{err}");
+      process::exit(1);
+    }
+    return;
+  }
+
+  let Opts {
+    native,
+    theme,
+    scale,
+    config,
+    path,
+  } = parse_args();
   eframe::run_native(
     &util::title_case(env!("CARGO_PKG_NAME")),
-    opts.native,
-    Box::new(move |cc| Box::new(app::App::new(cc, opts.theme, opts.scale, opts.config))),
+    native,
+    Box::new(move |cc| Box::new(app::App::new(cc, theme, scale, config, path))),
   )
   .unwrap();
 }
@@ -0,0 +1,77 @@
+use eframe::{egui, emath};
+use std::mem;
+
+/// Modal prompt for a name.
+pub struct NameDlg {
+  title: String,
+  text: String,
+  focus: bool,
+}
+
+#[derive(Eq, PartialEq)]
+pub enum Response {
+  None,
+  Cancel,
+  Name(String),
+}
+
+impl NameDlg {
+  /// Open the name prompt.
+  /// - `title`: window title
+  /// - `text`: initial name
+  pub fn open(title: &str, text: String) -> Self {
+    Self {
+      title: title.into(),
+      text,
+      focus: true,
+    }
+  }
+
+  pub fn show(&mut self, ctx: &egui::Context) -> Response {
+    let mut response = Response::None;
+    let mut open = !ctx.input(|state| state.key_pressed(egui::Key::Escape));
+
+    egui::Window::new(egui::RichText::from(format!("✏  {}", self.title)).strong())
+      .open(&mut open)
+      .collapsible(false)
+      .resizable(false)
+      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0])
+      .default_width(200.0)
+      .show(ctx, |ui| {
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+          let widget = egui::TextEdit::singleline(&mut self.text).hint_text("Name");
+          let edit_response = ui.add_sized(ui.available_size(), widget);
+          if mem::take(&mut self.focus) {
+            edit_response.request_focus();
+          }
+
+          if edit_response.lost_focus()
+            && ui.input(|state| state.key_pressed(egui::Key::Enter))
+            && !self.text.trim().is_empty()
+          {
+            response = Response::Name(self.text.trim().into());
+          }
+        });
+        ui.add_space(8.0);
+        ui.separator();
+        ui.horizontal(|ui| {
+          ui.add_enabled_ui(!self.text.trim().is_empty(), |ui| {
+            if ui.button("Ok").clicked() {
+              response = Response::Name(self.text.trim().into());
+            }
+          });
+
+          if ui.button("Cancel").clicked() {
+            response = Response::Cancel;
+          }
+        });
+      });
+
+    if !open {
+      response = Response::Cancel;
+    }
+
+    response
+  }
+}
@@ -1,7 +1,7 @@
 use crate::util;
 use eframe::egui;
 use gdal::{errors, spatial_ref, vector};
-use std::{any, collections, path, sync, thread};
+use std::{any, collections, fmt, path, sync, thread, time};
 use sync::{atomic, mpsc};
 
 // NASR = National Airspace System Resources
@@ -11,56 +11,121 @@ use sync::{atomic, mpsc};
 pub struct AirportReader {
   request_count: sync::Arc<atomic::AtomicI64>,
   airport_status: AirportStatusSync,
+  cancel: sync::Arc<atomic::AtomicBool>,
+  spatial_gen: sync::Arc<atomic::AtomicU64>,
+  effective: sync::Arc<sync::OnceLock<util::Date>>,
+  path_date: Option<util::Date>,
+  stats: sync::Arc<sync::Mutex<AirportStats>>,
+  path: path::PathBuf,
   ctx: egui::Context,
   tx: mpsc::Sender<AirportRequest>,
   rx: mpsc::Receiver<AirportReply>,
+  thread: Option<thread::JoinHandle<()>>,
 }
 
 impl AirportReader {
-  /// Create a new NASR airport reader.
+  /// Create a new NASR airport reader. The airport data is opened on the reader's thread, and the
+  /// result is returned as either `AirportReply::Opened` or `AirportReply::OpenError`.
   /// - `path`: path to the airport CSV file.
   /// - `ctx`: egui context for requesting a repaint
-  pub fn new<P: AsRef<path::Path>>(path: P, ctx: &egui::Context) -> Result<Self, util::Error> {
-    AirportReader::_new(path.as_ref(), ctx.clone())
+  pub fn new<P: AsRef<path::Path>>(path: P, ctx: &egui::Context) -> Self {
+    AirportReader::_new(path.as_ref(), ctx.clone(), None)
   }
 
-  fn _new(path: &path::Path, ctx: egui::Context) -> Result<Self, util::Error> {
-    let mut source = match AirportSource::open(path) {
-      Ok(source) => source,
-      Err(err) => {
-        let err = format!("Unable to open airport data source: {err}");
-        return Err(err.into());
-      }
-    };
+  /// Create a NASR airport reader that calls `hook` each time the basic indexing checks whether
+  /// it should stop. This lets tests act while indexing is in progress.
+  /// - `path`: path to the airport CSV file.
+  /// - `ctx`: egui context for requesting a repaint
+  /// - `hook`: called before each check
+  #[cfg(test)]
+  fn with_abandon_hook<P: AsRef<path::Path>>(
+    path: P,
+    ctx: &egui::Context,
+    hook: impl Fn() + Send + 'static,
+  ) -> Self {
+    AirportReader::_new(path.as_ref(), ctx.clone(), Some(Box::new(hook)))
+  }
 
+  fn _new(path: &path::Path, ctx: egui::Context, hook: Option<Box<dyn Fn() + Send>>) -> Self {
     let airport_status = AirportStatusSync::new();
     let request_count = sync::Arc::new(atomic::AtomicI64::new(0));
+    let cancel = sync::Arc::new(atomic::AtomicBool::new(false));
+    let spatial_gen = sync::Arc::new(atomic::AtomicU64::new(0));
+    let effective = sync::Arc::new(sync::OnceLock::new());
+    let stats = sync::Arc::new(sync::Mutex::new(AirportStats::default()));
     let (tx, trx) = mpsc::channel();
     let (ttx, rx) = mpsc::channel();
 
     // Create the thread.
-    thread::Builder::new()
+    let thread = thread::Builder::new()
       .name(any::type_name::<AirportSource>().into())
       .spawn({
         let mut airport_status = airport_status.clone();
         let request_count = request_count.clone();
+        let cancel = cancel.clone();
+        let spatial_gen = spatial_gen.clone();
+        let effective = effective.clone();
+        let stats = stats.clone();
         let ctx = ctx.clone();
+        let path = path.to_owned();
         move || {
+          // The airport data is opened on this thread so that GDAL datasets are only used from the
+          // thread that opened them. The UI shows it as indexing in the meantime.
+          airport_status.set_indexing(true);
+          let source = match Database::open_source(&path) {
+            Ok(source) => source,
+            Err(err) => {
+              airport_status.set_indexing(false);
+
+              // Stop taking requests before replying. The reader may have been dropped while
+              // this was being opened.
+              drop(trx);
+              ttx.send(AirportReply::OpenError(err)).ok();
+              ctx.request_repaint();
+              return;
+            }
+          };
+
+          if let Ok(mut stats) = stats.lock() {
+            stats.feature_count = source.count;
+          }
+
+          ttx.send(AirportReply::Opened).ok();
+          ctx.request_repaint();
+
+          // The database isn't `Send` once it has a chart, so it's created on this thread.
+          let mut database = Database::with_source(source, &path);
+
           // Create the name and ID indexes.
-          if source.create_basic_indexes() {
+          let start = time::Instant::now();
+          let abandon = || {
+            if let Some(hook) = &hook {
+              hook();
+            }
+            cancel.load(atomic::Ordering::Relaxed)
+          };
+          let basic_idx = database.create_basic_indexes(&abandon);
+          airport_status.set_indexing(false);
+          if basic_idx {
             airport_status.set_has_basic_idx();
-          }
 
-          let nad83 = spatial_ref::SpatialRef::from_epsg(4269).unwrap();
-          nad83.set_axis_mapping_strategy(0);
+            if let Ok(mut stats) = stats.lock() {
+              stats.id_count = database.airports.id_map.len();
+              stats.name_count = database.airports.name_vec.len();
+              stats.basic_idx_time = Some(start.elapsed());
+            }
+          }
 
-          // Chart transformation.
-          let mut to_chart = None;
+          if let Some(date) = database.airports.eff_date {
+            effective.set(date).unwrap();
+          }
 
           let send = {
             let ctx = ctx.clone();
+            let request_count = request_count.clone();
             move |reply: AirportReply, dec: bool| {
-              ttx.send(reply).unwrap();
+              // The reader may have been shut down.
+              ttx.send(reply).ok();
               ctx.request_repaint();
               if dec {
                 assert!(request_count.fetch_sub(1, atomic::Ordering::Relaxed) > 0);
@@ -71,88 +136,145 @@ impl AirportReader {
           // Request a repaint so that the UI knows the basic indexes are ready.
           ctx.request_repaint();
 
-          // Wait for a message. Exit when the connection is closed.
-          while let Ok(request) = trx.recv() {
+          // Wait for a message. Exit when the connection is closed or the reader is canceled.
+          let mut next = None;
+          while let Some(request) = next.take().or_else(|| trx.recv().ok()) {
+            if cancel.load(atomic::Ordering::Relaxed) {
+              break;
+            }
+
             match request {
-              AirportRequest::SpatialRef(spatial_info) => {
+              AirportRequest::SpatialRef(generation, spatial_info) => {
+                // Skip requests that have been superseded; the newest one is still queued.
+                if generation != spatial_gen.load(atomic::Ordering::Relaxed) {
+                  continue;
+                }
+
                 if airport_status.get() >= AirportStatus::BasicIdx {
+                  // Start from a fully reset state so that a failed or abandoned build never
+                  // leaves the old chart's indexes behind.
                   airport_status.set_has_basic_idx();
-                  to_chart = None;
+                  database.clear_chart();
 
                   // Request a repaint so the UI knows that the spatial index has been cleared.
                   ctx.request_repaint();
 
                   if let Some((proj4, bounds)) = spatial_info {
-                    match spatial_ref::SpatialRef::from_proj4(&proj4) {
-                      Ok(sr) => {
-                        match spatial_ref::CoordTransform::new(&nad83, &sr) {
-                          Ok(trans) => {
-                            let trans_info = ToChart { trans, bounds };
-                            // Create the airport spatial index.
-                            if source.create_spatial_index(&trans_info) {
-                              airport_status.set_has_spatial_idx();
-                              to_chart = Some(trans_info);
-
-                              // Request a repaint so the UI knows that the spatial index is ready.
-                              ctx.request_repaint();
-                            }
-                          }
-                          Err(err) => {
-                            let err = format!("Unable to create coordinate transformation: {err}");
-                            send(AirportReply::Error(err.into()), false);
-                          }
+                    // Unusually small bounds leave few airports in the index.
+                    log::info!("Chart bounds area: {:.0} km²", bounds.area() * 1.0e-6);
+
+                    // Abandon the build if the reader is canceled or a newer spatial reference
+                    // arrives.
+                    let abandon = || {
+                      cancel.load(atomic::Ordering::Relaxed)
+                        || spatial_gen.load(atomic::Ordering::Relaxed) != generation
+                    };
+
+                    airport_status.set_indexing(true);
+                    let start = time::Instant::now();
+                    match database.set_chart_with(&proj4, bounds, &abandon) {
+                      Ok(true) => {
+                        if let Ok(mut stats) = stats.lock() {
+                          stats.spatial_count = database.airports.sp_idx.size();
+                          stats.spatial_idx_time = Some(start.elapsed());
+                        }
+
+                        if database.has_chart() {
+                          airport_status.set_has_spatial_idx();
                         }
-                      }
 
-                      Err(err) => {
-                        let err = format!("Unable to create spatial reference: {err}");
-                        send(AirportReply::Error(err.into()), false);
+                        let count = database.airports.sp_idx.size();
+                        send(AirportReply::Indexed(count), false);
                       }
+                      Ok(false) => (),
+                      Err(err) => send(AirportReply::Error(err), false),
                     }
+                    airport_status.set_indexing(false);
+
+                    // Request a repaint so the UI knows that the spatial index is ready.
+                    ctx.request_repaint();
                   }
                 }
               }
               AirportRequest::Airport(id) => {
-                let id = id.trim().to_uppercase();
-                let reply = if let Some(info) = source.airport(&id) {
-                  AirportReply::Airport(info)
-                } else {
-                  let err = format!("No airport IDs match\n'{id}'");
-                  AirportReply::Error(err.into())
+                let reply = match database.airport(&id) {
+                  Ok(info) => AirportReply::Airport(info),
+                  Err(err) => AirportReply::Error(err),
                 };
                 send(reply, true);
               }
-              AirportRequest::Nearby(coord, dist, nph) => {
-                let infos = source.nearby(coord, dist, nph);
-                send(AirportReply::Nearby(infos), true);
+              AirportRequest::Navaid(id) => {
+                let reply = match database.navaid(&id) {
+                  Ok(info) => AirportReply::Navaid(info),
+                  Err(err) => AirportReply::Error(err),
+                };
+                send(reply, true);
               }
-              AirportRequest::Search(term, nph) => {
-                if let Some(to_chart) = to_chart.as_ref() {
-                  let term = term.trim().to_uppercase();
-
-                  // Search for an airport ID first.
-                  let reply = if let Some(info) = source.airport(&term) {
-                    if to_chart.contains(info.coord) {
-                      AirportReply::Airport(info)
-                    } else {
-                      let err = format!("{}\nis not on this chart", info.desc);
-                      AirportReply::Error(err.into())
-                    }
+              AirportRequest::Nearby(mut generation, mut coord, mut dist) => {
+                // Nearby requests can pile up while panning. Skip to the most recent one of a run.
+                while let Ok(request) = trx.try_recv() {
+                  if let AirportRequest::Nearby(g, c, d) = request {
+                    (generation, coord, dist) = (g, c, d);
+                    assert!(request_count.fetch_sub(1, atomic::Ordering::Relaxed) > 0);
                   } else {
-                    // Airport ID not found, search the airport names.
-                    let infos = source.search(&term, to_chart, nph);
-                    if infos.is_empty() {
-                      let err = format!("Nothing on this chart matches\n'{term}'");
-                      AirportReply::Error(err.into())
-                    } else {
-                      AirportReply::Search(infos)
-                    }
-                  };
-                  send(reply, true);
-                } else {
-                  let err = "Chart transformation is needed for search\n";
-                  send(AirportReply::Error(err.into()), true);
+                    next = Some(request);
+                    break;
+                  }
+                }
+
+                let infos = database.nearby(coord, dist);
+                send(AirportReply::Nearby(generation, infos), true);
+              }
+              AirportRequest::Nearest(coord) => {
+                send(AirportReply::Nearest(database.nearest(coord)), true);
+              }
+              AirportRequest::RunwayTicks(mut generation, mut bounds) => {
+                // Like nearby requests, these follow the view. Skip to the most recent one of a run.
+                while let Ok(request) = trx.try_recv() {
+                  if let AirportRequest::RunwayTicks(g, b) = request {
+                    (generation, bounds) = (g, b);
+                    assert!(request_count.fetch_sub(1, atomic::Ordering::Relaxed) > 0);
+                  } else {
+                    next = Some(request);
+                    break;
+                  }
                 }
+
+                let ticks = database.runway_ticks(&bounds);
+                send(AirportReply::RunwayTicks(generation, ticks), true);
+              }
+              AirportRequest::Search(generation, term, nph, unlimited, intent) => {
+                let limit = (!unlimited).then_some(util::MAX_SEARCH_RESULTS);
+                let reply = match database.search(&term, nph, limit) {
+                  Ok(result) => AirportReply::Search(generation, intent, result),
+                  Err(err) => AirportReply::Error(err),
+                };
+                send(reply, true);
+              }
+              AirportRequest::Runways(id) => {
+                let reply = match database.runways(&id) {
+                  Ok(ends) => AirportReply::Runways(id, ends),
+                  Err(err) => AirportReply::Error(err),
+                };
+                send(reply, true);
+              }
+              AirportRequest::Detail(id) => {
+                let reply = match database.detail(&id) {
+                  Ok(detail) => AirportReply::Detail(Box::new(detail)),
+                  Err(err) => AirportReply::Error(err),
+                };
+                send(reply, true);
+              }
+              AirportRequest::Remarks(id) => {
+                let reply = match database.remarks(&id) {
+                  Ok(remarks) => AirportReply::Remarks(id, remarks),
+                  Err(err) => AirportReply::Error(err),
+                };
+                send(reply, true);
+              }
+              AirportRequest::Find(term, nph) => {
+                let infos = database.find(&term, nph);
+                send(AirportReply::Find(term, infos), true);
               }
             }
           }
@@ -160,13 +282,23 @@ impl AirportReader {
       })
       .unwrap();
 
-    Ok(Self {
+    // The NASR zip file name usually contains the effective date.
+    let path_date = path.to_str().and_then(util::Date::find);
+
+    Self {
       request_count,
       airport_status,
+      cancel,
+      spatial_gen,
+      effective,
+      path_date,
+      stats,
+      path: path.to_owned(),
       ctx,
       tx,
       rx,
-    })
+      thread: Some(thread),
+    }
   }
 
   /// True if the airport source has ID and name indexes.
@@ -179,58 +311,205 @@ impl AirportReader {
     self.airport_status.get() >= AirportStatus::SpatialIdx
   }
 
+  /// The effective and expiration dates of the NASR data.
+  /// > **NOTE**: the effective date from the airport data is preferred over the one from the file
+  /// > name, but it's only available once the basic indexes are ready.
+  pub fn effective_range(&self) -> Option<(util::Date, util::Date)> {
+    let effective = *self.effective.get().or(self.path_date.as_ref())?;
+    Some((effective, effective.add_days(AirportReader::CYCLE_DAYS)))
+  }
+
+  /// Path to the airport data source.
+  pub fn path(&self) -> &path::Path {
+    &self.path
+  }
+
+  /// Airport source statistics collected when the source was opened and indexed.
+  pub fn stats(&self) -> AirportStats {
+    self.stats.lock().unwrap().clone()
+  }
+
+  /// True if an airport index is being created.
+  pub fn is_indexing(&self) -> bool {
+    self.airport_status.is_indexing()
+  }
+
+  /// Cancel any indexing in progress and stop processing requests.
+  /// > **NOTE**: the reader is no longer usable after this is called.
+  pub fn cancel(&self) {
+    self.cancel.store(true, atomic::Ordering::Relaxed);
+  }
+
+  /// Stop the reader's thread so that the airport data is released before a replacement reader
+  /// opens its own. This cancels the reader and closes its request channel, then waits for the
+  /// thread on a detached helper thread so that the caller isn't blocked.
+  /// - `timeout`: how long to wait for the thread to exit
+  ///
+  /// The returned receiver gets true if the thread exited within the timeout.
+  pub fn shutdown(mut self, timeout: time::Duration) -> mpsc::Receiver<bool> {
+    let (tx, rx) = mpsc::channel();
+    let Some(handle) = self.thread.take() else {
+      tx.send(true).ok();
+      return rx;
+    };
+
+    // Dropping the reader cancels it and closes the request channel.
+    drop(self);
+
+    thread::Builder::new()
+      .name(format!("{} shutdown", any::type_name::<AirportReader>()))
+      .spawn(move || {
+        const POLL: time::Duration = time::Duration::from_millis(10);
+        let start = time::Instant::now();
+        while !handle.is_finished() {
+          if start.elapsed() >= timeout {
+            log::warn!("Airport reader thread didn't exit within {timeout:?}");
+            tx.send(false).ok();
+            return;
+          }
+          thread::sleep(POLL);
+        }
+
+        handle.join().ok();
+        tx.send(true).ok();
+      })
+      .unwrap();
+    rx
+  }
+
   /// Set the chart spatial reference using a PROJ4 string.
   /// > **NOTE**: this is required for all queries other than `airport`.
   /// - `proj4`: PROJ4 text
   /// - `bounds`: Chart bounds in LCC coordinates.
   pub fn set_spatial_ref(&self, proj4: String, bounds: util::Bounds) {
-    let request = AirportRequest::SpatialRef(Some((proj4, bounds)));
-    self.tx.send(request).unwrap();
+    let generation = self.next_spatial_gen();
+    let request = AirportRequest::SpatialRef(generation, Some((proj4, bounds)));
+    self.tx.send(request).ok();
   }
 
   /// Clear the chart spatial reference.
   pub fn clear_spatial_ref(&self) {
-    let request = AirportRequest::SpatialRef(None);
-    self.tx.send(request).unwrap();
+    let generation = self.next_spatial_gen();
+    let request = AirportRequest::SpatialRef(generation, None);
+    self.tx.send(request).ok();
+  }
+
+  /// Send a request that has a reply. Nothing is sent if the reader's thread has exited (e.g. the
+  /// airport data couldn't be opened).
+  fn send_request(&self, request: AirportRequest) {
+    if self.tx.send(request).is_ok() {
+      self.request_count.fetch_add(1, atomic::Ordering::Relaxed);
+      self.ctx.request_repaint();
+    }
+  }
+
+  /// Supersede any spatial reference request that's pending or being indexed.
+  fn next_spatial_gen(&self) -> u64 {
+    self.spatial_gen.fetch_add(1, atomic::Ordering::Relaxed) + 1
   }
 
   /// Lookup airport information using it's identifier.
   /// > **NOTE**: Ignores chart boundaries and does not require a chart spatial reference.
   /// - `id`: airport id
-  #[allow(unused)]
   pub fn airport(&self, id: String) {
     if !id.is_empty() {
-      self.tx.send(AirportRequest::Airport(id)).unwrap();
-      self.request_count.fetch_add(1, atomic::Ordering::Relaxed);
-      self.ctx.request_repaint();
+      self.send_request(AirportRequest::Airport(id));
+    }
+  }
+
+  /// Lookup navaid information using it's identifier.
+  /// > **NOTE**: Ignores chart boundaries and does not require a chart spatial reference.
+  /// - `id`: navaid id
+  #[allow(unused)]
+  pub fn navaid(&self, id: String) {
+    if !id.is_empty() {
+      self.send_request(AirportRequest::Navaid(id));
     }
   }
 
-  /// Request nearby airports.
+  /// Request nearby airports and navaids. Non-public heliports are always included so that they
+  /// can be shown or hidden without searching again (see `Facility::included`).
   /// > **NOTE**: requires a chart spatial reference.
+  /// - `generation`: tag returned with the reply so that stale results can be dropped
   /// - `coord`: chart coordinate (LCC)
   /// - `dist`: search distance in meters
-  /// - `nph`: include non-public heliports
-  pub fn nearby(&self, coord: util::Coord, dist: f64, nph: bool) {
+  pub fn nearby(&self, generation: u64, coord: util::Coord, dist: f64) {
     if dist >= 0.0 {
-      self
-        .tx
-        .send(AirportRequest::Nearby(coord, dist, nph))
-        .unwrap();
-      self.request_count.fetch_add(1, atomic::Ordering::Relaxed);
-      self.ctx.request_repaint();
+      let request = AirportRequest::Nearby(generation, coord, dist);
+      self.send_request(request);
     }
   }
 
-  /// Find an airport by ID or airport(s) by (partial) name match.
+  /// Request the closest public use airports.
+  /// > **NOTE**: requires a chart spatial reference.
+  /// - `coord`: chart coordinate (LCC)
+  pub fn nearest(&self, coord: util::Coord) {
+    self.send_request(AirportRequest::Nearest(coord));
+  }
+
+  /// Request the runway orientation ticks for the airports within a rectangle.
+  /// > **NOTE**: requires a chart spatial reference.
+  /// - `generation`: tag returned with the reply so that stale results can be dropped
+  /// - `bounds`: rectangle in chart coordinates (LCC)
+  pub fn runway_ticks(&self, generation: u64, bounds: util::Bounds) {
+    self.send_request(AirportRequest::RunwayTicks(generation, bounds));
+  }
+
+  /// Find an airport or navaid by ID and airport(s) and navaid(s) by (partial) name match.
   /// > **NOTE**: requires a chart spatial reference.
+  /// - `generation`: tag returned with the reply so that stale results can be dropped
   /// - `term`: search term
   /// - `nph`: include non-public heliports
-  pub fn search(&self, term: String, nph: bool) {
+  /// - `unlimited`: return all the name matches instead of the best `util::MAX_SEARCH_RESULTS`
+  /// - `intent`: what the search is for, returned with the reply
+  pub fn search(
+    &self,
+    generation: u64,
+    term: String,
+    nph: bool,
+    unlimited: bool,
+    intent: SearchIntent,
+  ) {
     if !term.is_empty() {
-      self.tx.send(AirportRequest::Search(term, nph)).unwrap();
-      self.request_count.fetch_add(1, atomic::Ordering::Relaxed);
-      self.ctx.request_repaint();
+      let request = AirportRequest::Search(generation, term, nph, unlimited, intent);
+      self.send_request(request);
+    }
+  }
+
+  /// Request the runway ends for an airport.
+  /// - `id`: airport ID
+  pub fn runways(&self, id: String) {
+    if !id.is_empty() {
+      self.send_request(AirportRequest::Runways(id));
+    }
+  }
+
+  /// Request the full details for an airport (runways, frequencies and remarks).
+  /// > **NOTE**: Ignores chart boundaries and does not require a chart spatial reference.
+  /// - `id`: airport ID
+  pub fn detail(&self, id: String) {
+    if !id.is_empty() {
+      self.send_request(AirportRequest::Detail(id));
+    }
+  }
+
+  /// Request only the remarks for an airport. Much lighter than `detail`.
+  /// > **NOTE**: Ignores chart boundaries and does not require a chart spatial reference.
+  /// - `id`: airport ID
+  pub fn remarks(&self, id: String) {
+    if !id.is_empty() {
+      self.send_request(AirportRequest::Remarks(id));
+    }
+  }
+
+  /// Find airports as the user types. Unlike `search`, the reply is always `AirportReply::Find`
+  /// tagged with the term so that replies for superseded terms can be dropped.
+  /// > **NOTE**: requires a chart spatial reference.
+  /// - `term`: partial airport ID or name
+  /// - `nph`: include non-public heliports
+  pub fn find(&self, term: String, nph: bool) {
+    if !term.is_empty() {
+      self.send_request(AirportRequest::Find(term, nph));
     }
   }
 
@@ -239,176 +518,1695 @@ impl AirportReader {
     self.request_count.load(atomic::Ordering::Relaxed)
   }
 
-  /// Get all available replies.
-  pub fn get_replies(&self) -> Vec<AirportReply> {
-    self.rx.try_iter().collect()
+  /// Get at most `max` of the available replies, oldest first. The rest stay queued for the next
+  /// call, so that a burst of large replies can be spread over several frames.
+  /// - `max`: maximum number of replies
+  pub fn get_replies_budgeted(&self, max: usize) -> Vec<AirportReply> {
+    take_replies(&self.rx, max)
   }
-}
 
-enum AirportRequest {
-  SpatialRef(Option<(String, util::Bounds)>),
-  Airport(String),
-  Nearby(util::Coord, f64, bool),
-  Search(String, bool),
-}
-
-pub enum AirportReply {
-  /// Airport info from ID search.
-  Airport(AirportInfo),
+  /// Wait for the next reply. This is for driving the reader without a UI (e.g. benchmarking).
+  /// - `timeout`: how long to wait
+  pub fn wait_reply(&self, timeout: time::Duration) -> Option<AirportReply> {
+    self.rx.recv_timeout(timeout).ok()
+  }
 
-  /// Airport infos from a nearby search.
-  Nearby(Vec<AirportInfo>),
+  /// NASR subscriptions are on a 28 day cycle.
+  const CYCLE_DAYS: i64 = 28;
 
-  /// Airport infos matching a name search.
-  Search(Vec<AirportInfo>),
+  /// Maximum number of find-as-you-type results.
+  const MAX_FIND_RESULTS: usize = 20;
 
-  /// Request resulted in an error.
-  Error(util::Error),
+  /// How long to wait for the thread to exit when the reader is replaced.
+  pub const SHUTDOWN_TIMEOUT: time::Duration = time::Duration::from_secs(5);
 }
 
-struct ToChart {
-  /// Coordinate transformation from NAD83 to LCC.
-  trans: spatial_ref::CoordTransform,
+impl Drop for AirportReader {
+  fn drop(&mut self) {
+    // Stop the thread as soon as possible. Dropping the sender closes the connection.
+    self.cancel();
+  }
+}
 
-  /// Chart bounds in LCC coordinates.
-  bounds: util::Bounds,
+/// Synchronous access to NASR airport and navaid data.
+///
+/// `AirportReader` runs one of these on a worker thread. It can also be used directly from tests
+/// and scripts, where blocking is fine.
+pub struct Database {
+  airports: AirportSource,
+  navaids: Option<NavaidSource>,
+  related: RelatedSources,
+  to_chart: Option<ToChart>,
+
+  /// Longest runway for each airport, if the runway data has been read.
+  runway_summaries: Option<collections::HashMap<String, RunwaySummary>>,
 }
 
-impl ToChart {
-  /// Test if a NAD83 coordinate is contained within the chart bounds.
-  fn contains(&self, nad83: util::Coord) -> bool {
-    use util::Transform;
-    match self.trans.transform(nad83) {
-      Ok(lcc) => return self.bounds.contains(lcc),
-      Err(err) => println!("{err}"),
+impl Database {
+  /// Open the NASR airport data and create the ID and name indexes.
+  /// - `path`: path to the airport CSV file
+  #[allow(unused)]
+  pub fn open<P: AsRef<path::Path>>(path: P) -> Result<Self, util::Error> {
+    let path = path.as_ref();
+    let mut database = Self::with_source(Self::open_source(path)?, path);
+    if !database.create_basic_indexes(&|| false) {
+      return Err("Unable to index the airport data".into());
     }
-    false
+    Ok(database)
   }
-}
-
-#[derive(Eq, Ord, PartialEq, PartialOrd)]
-enum AirportStatus {
-  None,
-
-  /// ID and name indexes are ready.
-  BasicIdx,
 
-  /// Spatial index is ready.
-  SpatialIdx,
-}
+  /// Set the chart spatial reference. This is required for `nearby`, `search` and `find`.
+  /// - `proj4`: PROJ4 text
+  /// - `bounds`: chart bounds in LCC coordinates
+  #[allow(unused)]
+  pub fn set_chart(&mut self, proj4: &str, bounds: util::Bounds) -> Result<(), util::Error> {
+    self.clear_chart();
+    self.set_chart_with(proj4, bounds, &|| false)?;
+    Ok(())
+  }
 
-impl From<u8> for AirportStatus {
-  fn from(value: u8) -> Self {
-    const NONE: u8 = AirportStatus::None as u8;
-    const BASIC: u8 = AirportStatus::BasicIdx as u8;
-    const SPATIAL: u8 = AirportStatus::SpatialIdx as u8;
-    match value {
-      NONE => AirportStatus::None,
-      BASIC => AirportStatus::BasicIdx,
-      SPATIAL => AirportStatus::SpatialIdx,
-      _ => unreachable!(),
+  /// Clear the chart spatial reference and the spatial indexes.
+  pub fn clear_chart(&mut self) {
+    self.to_chart = None;
+    self.airports.clear_spatial_index();
+    if let Some(navaids) = &mut self.navaids {
+      navaids.clear_spatial_index();
     }
   }
-}
 
-#[derive(Clone)]
-struct AirportStatusSync {
-  status: sync::Arc<atomic::AtomicU8>,
-}
+  /// True if a chart is set and it contains airports.
+  pub fn has_chart(&self) -> bool {
+    self.to_chart.is_some()
+  }
 
-impl AirportStatusSync {
-  fn new() -> Self {
-    let status = atomic::AtomicU8::new(AirportStatus::None as u8);
-    Self {
-      status: sync::Arc::new(status),
+  /// Lookup airport information using it's identifier. Ignores chart boundaries.
+  /// - `id`: airport ID
+  pub fn airport(&self, id: &str) -> Result<AirportInfo, util::Error> {
+    let id = id.trim().to_uppercase();
+    match self.airports.airport(&id) {
+      Some(info) => Ok(info),
+      None => Err(format!("No airport IDs match\n'{id}'").into()),
     }
   }
 
-  fn set_has_basic_idx(&mut self) {
-    self.set(AirportStatus::BasicIdx);
+  /// Lookup navaid information using it's identifier. Ignores chart boundaries.
+  /// - `id`: navaid ID
+  pub fn navaid(&self, id: &str) -> Result<NavaidInfo, util::Error> {
+    let id = id.trim().to_uppercase();
+    match self
+      .navaids
+      .as_ref()
+      .and_then(|navaids| navaids.navaid(&id))
+    {
+      Some(info) => Ok(info),
+      None => Err(format!("No navaid IDs match\n'{id}'").into()),
+    }
   }
 
-  fn set_has_spatial_idx(&mut self) {
-    self.set(AirportStatus::SpatialIdx);
+  /// Find airports and navaids within a search radius, including non-public heliports.
+  /// - `coord`: chart coordinate (LCC)
+  /// - `dist`: search distance in meters
+  pub fn nearby(&self, coord: util::Coord, dist: f64) -> Vec<Facility> {
+    let mut airports = self.airports.nearby(coord, dist);
+    self.add_runway_summaries(&mut airports);
+    let mut infos: Vec<Facility> = airports.into_iter().map(Facility::Airport).collect();
+    if let Some(navaids) = &self.navaids {
+      let navaids = navaids.nearby(coord, dist);
+      infos.extend(navaids.into_iter().map(Facility::Navaid));
+    }
+    infos
   }
 
-  fn set(&mut self, status: AirportStatus) {
-    self.status.store(status as u8, atomic::Ordering::Relaxed);
+  /// Find the closest public use airports, searching outward up to 40 NM.
+  /// - `coord`: chart coordinate (LCC)
+  ///
+  /// Returns up to five airports with their distances in meters, nearest first.
+  pub fn nearest(&self, coord: util::Coord) -> Vec<(AirportInfo, f64)> {
+    const MAX_DIST: f64 = 40.0 * util::METERS_PER_NM;
+    const COUNT: usize = 5;
+    self.airports.nearest(coord, MAX_DIST, COUNT)
   }
 
-  fn get(&self) -> AirportStatus {
-    self.status.load(atomic::Ordering::Relaxed).into()
+  /// Get the runway orientation ticks for the airports within a rectangle. Airports without a
+  /// numbered or compass runway are left out.
+  /// - `bounds`: rectangle in chart coordinates (LCC)
+  pub fn runway_ticks(&self, bounds: &util::Bounds) -> Vec<RunwayTick> {
+    let (Some(to_chart), Some(summaries)) = (&self.to_chart, &self.runway_summaries) else {
+      return Vec::new();
+    };
+    self.airports.runway_ticks(bounds, summaries, to_chart)
   }
-}
 
-struct AirportSource {
-  dataset: gdal::Dataset,
-  count: u64,
-  name_vec: Vec<(String, u64)>,
-  id_map: collections::HashMap<String, u64>,
-  sp_idx: rstar::RTree<LocIdx>,
-}
+  /// Find an airport or navaid by ID and airports and navaids by (partial) name match. Airports
+  /// also match by city, and the term can be narrowed with "city:" and "state:" prefixes or a
+  /// trailing state code (see `SearchQuery`). Both are returned, so a term that's an ID and also
+  /// part of some names (e.g. "RNO") finds all of them.
+  /// - `term`: search term
+  /// - `nph`: include non-public heliports
+  /// - `limit`: maximum number of name matches
+  pub fn search(
+    &self,
+    term: &str,
+    nph: bool,
+    limit: Option<usize>,
+  ) -> Result<SearchResult, util::Error> {
+    let Some(to_chart) = &self.to_chart else {
+      return Err("Chart transformation is needed for search\n".into());
+    };
 
-impl AirportSource {
-  fn open_options<'a>() -> gdal::DatasetOptions<'a> {
-    gdal::DatasetOptions {
-      open_flags: gdal::GdalOpenFlags::GDAL_OF_READONLY | gdal::GdalOpenFlags::GDAL_OF_VECTOR,
-      ..Default::default()
+    // Look for an airport ID first, then a navaid ID.
+    let term = term.trim().to_uppercase();
+    let mut exact = match self.airports.airport_or_icao(&term) {
+      Some(info) => Some(Facility::Airport(info)),
+      None => {
+        let navaids = self.navaids.as_ref();
+        let navaid = navaids.and_then(|navaids| navaids.navaid(&term));
+        navaid.map(Facility::Navaid)
+      }
+    };
+
+    // An ID match that's off the chart is only reported if nothing else matches.
+    let mut off_chart = None;
+    if let Some(info) = &exact {
+      if !to_chart.contains(info.coord()) {
+        off_chart = Some(format!("{}\nis not on this chart", info.desc()));
+        exact = None;
+      }
     }
-  }
 
-  /// Open an airport data source.
-  /// - `path`: NASR airport CSV file path
-  fn open(path: &path::Path) -> Result<Self, errors::GdalError> {
-    use gdal::vector::LayerAccess;
+    // Search the airport names and cities, then the navaid names.
+    let query = SearchQuery::parse(&term);
+    let mut airports = self.airports.search(&query, to_chart, nph);
+    self.add_runway_summaries(&mut airports);
+    let mut infos: Vec<Facility> = airports.into_iter().map(Facility::Airport).collect();
+    if let (Some(navaids), false) = (&self.navaids, query.is_filtered()) {
+      let navaids = navaids.search(&query.text, to_chart);
+      infos.extend(navaids.into_iter().map(Facility::Navaid));
+    }
 
-    // Open the dataset and get the layer.
-    let dataset = gdal::Dataset::open_ex(path, Self::open_options())?;
-    let layer = dataset.layer(0)?;
-    let count = layer.feature_count();
+    // The ID match isn't repeated in the name matches.
+    if let Some(exact) = &exact {
+      infos.retain(|info| !info.is_same(exact));
+    }
 
-    Ok(Self {
-      dataset,
-      count,
-      name_vec: Vec::new(),
-      id_map: collections::HashMap::new(),
-      sp_idx: rstar::RTree::new(),
+    if exact.is_none() && infos.is_empty() {
+      let err = off_chart.unwrap_or_else(|| format!("Nothing on this chart matches\n'{term}'"));
+      return Err(err.into());
+    }
+
+    let total = rank_matches(&mut infos, &query.text, limit);
+    Ok(SearchResult {
+      exact,
+      matches: infos,
+      total,
     })
   }
 
-  // Create the name and ID indexes.
-  fn create_basic_indexes(&mut self) -> bool {
-    use vector::LayerAccess;
+  /// Find airports on the chart by ID or (partial) name. An ID match goes first.
+  /// - `term`: partial airport ID or name
+  /// - `nph`: include non-public heliports
+  pub fn find(&self, term: &str, nph: bool) -> Vec<AirportInfo> {
+    let mut infos = Vec::new();
+    if let Some(to_chart) = &self.to_chart {
+      let upper = term.trim().to_uppercase();
+      if let Some(info) = self.airports.airport(&upper) {
+        if to_chart.contains(info.coord) {
+          infos.push(info);
+        }
+      }
 
-    let count = self.count as usize;
-    let mut name_vec = Vec::with_capacity(count);
+      let query = SearchQuery::parse(&upper);
+      for info in self.airports.search(&query, to_chart, nph) {
+        if infos.len() == AirportReader::MAX_FIND_RESULTS {
+          break;
+        }
+
+        if !infos.iter().any(|other| other.id == info.id) {
+          infos.push(info);
+        }
+      }
+    }
+    infos
+  }
+
+  /// Get the runway ends for an airport.
+  /// - `id`: airport ID
+  pub fn runways(&mut self, id: &str) -> Result<Vec<RunwayEnd>, util::Error> {
+    let source = self.related.get(RunwayEnd::FILE_NAME)?;
+    Ok(RunwayEnd::read(source, id))
+  }
+
+  /// Get the full details for an airport (runways, frequencies and remarks).
+  /// - `id`: airport ID
+  pub fn detail(&mut self, id: &str) -> Result<AirportDetail, util::Error> {
+    let id = id.trim().to_uppercase();
+    match self.airports.detail(&id, &mut self.related) {
+      Some(detail) => Ok(detail),
+      None => Err(format!("No airport IDs match\n'{id}'").into()),
+    }
+  }
+
+  /// Get the remarks for an airport. An airport without remarks is not an error.
+  /// - `id`: airport ID
+  pub fn remarks(&mut self, id: &str) -> Result<Vec<String>, util::Error> {
+    let id = id.trim().to_uppercase();
+    if !self.airports.id_map.contains_key(&id) {
+      return Err(format!("No airport IDs match\n'{id}'").into());
+    }
+
+    let source = self.related.get(AirportDetail::REMARK_FILE_NAME)?;
+    Ok(AirportDetail::read_remarks(source, &id))
+  }
+
+  /// Open the airport data source.
+  /// - `path`: path to the airport CSV file
+  fn open_source(path: &path::Path) -> Result<AirportSource, util::Error> {
+    match AirportSource::open(path) {
+      Ok(source) => Ok(source),
+      Err(err) => {
+        let err = format!("Unable to open airport data source: {err}");
+        Err(err.into())
+      }
+    }
+  }
+
+  /// Create a database from an airport data source without creating any indexes.
+  /// - `airports`: airport data source
+  /// - `path`: path to the airport CSV file
+  fn with_source(airports: AirportSource, path: &path::Path) -> Self {
+    // Runway, frequency, remark and navaid data are in the same folder as the airport data.
+    let folder = path
+      .parent()
+      .map(|path| path.to_owned())
+      .unwrap_or_default();
+
+    Self {
+      airports,
+      navaids: None,
+      related: RelatedSources::new(folder),
+      to_chart: None,
+      runway_summaries: None,
+    }
+  }
+
+  /// Create the airport name and ID indexes, then open the navaid data. Returns false if
+  /// abandoned or the airport data has no records.
+  /// - `abandon`: returns true if the indexes are no longer needed
+  fn create_basic_indexes(&mut self, abandon: &dyn Fn() -> bool) -> bool {
+    if !self.airports.create_basic_indexes(abandon) {
+      return false;
+    }
+
+    // Check between the builds so that shutting down during indexing is quick.
+    if abandon() {
+      return false;
+    }
+
+    // Navaid data is optional (older NASR zips may not have it).
+    let path = self.related.folder.join(NavaidSource::FILE_NAME);
+    self.navaids = match NavaidSource::open(&path) {
+      Ok(mut navaids) => navaids.create_basic_indexes(abandon).then_some(navaids),
+      Err(err) => {
+        log::warn!("Navaids are not available: {err}");
+        None
+      }
+    };
+
+    if abandon() {
+      return false;
+    }
+
+    // Runway summaries are also optional. Reading them only needs two fields per runway.
+    self.runway_summaries = match self.related.get(Runway::FILE_NAME) {
+      Ok(source) => match RunwaySummary::read_all(source, abandon) {
+        Some(summaries) => Some(summaries),
+        None => return false,
+      },
+      Err(err) => {
+        log::warn!("Runway summaries are not available: {err}");
+        None
+      }
+    };
+    true
+  }
+
+  /// Add the runway summaries to airport search results, if they've been read.
+  /// - `infos`: airport search results
+  fn add_runway_summaries(&self, infos: &mut [AirportInfo]) {
+    let Some(summaries) = &self.runway_summaries else {
+      return;
+    };
+
+    for info in infos {
+      if let Some(summary) = summaries.get(&info.id) {
+        info.set_runway(*summary);
+      }
+    }
+  }
+
+  /// Set the chart spatial reference, building the spatial indexes. The new indexes are only
+  /// swapped in once they're all complete. Returns false if abandoned.
+  /// - `proj4`: PROJ4 text
+  /// - `bounds`: chart bounds in LCC coordinates
+  /// - `abandon`: returns true if the indexes are no longer needed
+  fn set_chart_with(
+    &mut self,
+    proj4: &str,
+    bounds: util::Bounds,
+    abandon: &dyn Fn() -> bool,
+  ) -> Result<bool, util::Error> {
+    let sr = match spatial_ref::SpatialRef::from_proj4(proj4) {
+      Ok(sr) => sr,
+      Err(err) => return Err(format!("Unable to create spatial reference: {err}").into()),
+    };
+
+    let nad83 = spatial_ref::SpatialRef::from_epsg(4269).unwrap();
+    nad83.set_axis_mapping_strategy(0);
+
+    let trans = match spatial_ref::CoordTransform::new(&nad83, &sr) {
+      Ok(trans) => trans,
+      Err(err) => {
+        let err = format!("Unable to create coordinate transformation: {err}");
+        return Err(err.into());
+      }
+    };
+
+    // Create the airport and navaid spatial indexes.
+    let to_chart = ToChart { trans, bounds };
+    let Some(airport_idx) = self.airports.create_spatial_index(&to_chart, abandon) else {
+      return Ok(false);
+    };
+
+    if abandon() {
+      return Ok(false);
+    }
+
+    let navaid_idx = match &self.navaids {
+      Some(navaids) => match navaids.create_spatial_index(&to_chart, abandon) {
+        Some(navaid_idx) => Some(navaid_idx),
+        None => return Ok(false),
+      },
+      None => None,
+    };
+
+    // Swap in the new indexes all at once.
+    let has_airports = airport_idx.size() > 0;
+    self.airports.set_spatial_index(airport_idx);
+    if let (Some(navaids), Some(navaid_idx)) = (&mut self.navaids, navaid_idx) {
+      navaids.set_spatial_index(navaid_idx);
+    }
+
+    if has_airports {
+      self.to_chart = Some(to_chart);
+    }
+    Ok(true)
+  }
+}
+
+/// Result of a `Database::search`.
+#[derive(Debug)]
+pub struct SearchResult {
+  /// Airport or navaid ID match, which goes above the name matches.
+  pub exact: Option<Facility>,
+
+  /// Airports and navaids matching by name.
+  pub matches: Vec<Facility>,
+
+  /// Total number of name matches, which is more than `matches` holds if they were limited.
+  pub total: usize,
+}
+
+/// What a search is for, which decides what happens with a single result.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchIntent {
+  /// Go to the facility on the chart.
+  Goto,
+
+  /// Show the airport's details.
+  Info,
+}
+
+/// Airport source statistics.
+#[derive(Clone, Debug, Default)]
+pub struct AirportStats {
+  /// Number of airport records.
+  pub feature_count: u64,
+
+  /// Number of airport IDs in the ID index.
+  pub id_count: usize,
+
+  /// Number of airport names in the name index.
+  pub name_count: usize,
+
+  /// Number of airports in the spatial index (within the chart bounds).
+  pub spatial_count: usize,
+
+  /// Time taken to create the ID and name indexes.
+  pub basic_idx_time: Option<time::Duration>,
+
+  /// Time taken to create the spatial index.
+  pub spatial_idx_time: Option<time::Duration>,
+}
+
+enum AirportRequest {
+  /// Spatial reference request tagged with its generation.
+  SpatialRef(u64, Option<(String, util::Bounds)>),
+  Airport(String),
+  Navaid(String),
+  /// Nearby request tagged with the caller's generation.
+  Nearby(u64, util::Coord, f64),
+  Nearest(util::Coord),
+
+  /// Runway tick request tagged with the caller's generation.
+  RunwayTicks(u64, util::Bounds),
+
+  /// Search request tagged with the caller's generation and intent.
+  Search(u64, String, bool, bool, SearchIntent),
+  Find(String, bool),
+  Runways(String),
+  Detail(String),
+  Remarks(String),
+}
+
+pub enum AirportReply {
+  /// Airport data source was opened. Indexing continues on the reader's thread.
+  Opened,
+
+  /// Airport data source couldn't be opened. The reader is no longer usable.
+  OpenError(util::Error),
+
+  /// Airport info from an ID request.
+  Airport(AirportInfo),
+
+  /// Navaid info from an ID request.
+  Navaid(NavaidInfo),
+
+  /// Airports and navaids from a nearby search, tagged with the request's generation.
+  Nearby(u64, Vec<Facility>),
+
+  /// Closest public use airports with their distances in meters, nearest first.
+  Nearest(Vec<(AirportInfo, f64)>),
+
+  /// Runway orientation ticks for part of the chart, tagged with the request's generation.
+  RunwayTicks(u64, Vec<RunwayTick>),
+
+  /// ID and name matches from a search, tagged with the request's generation and intent.
+  Search(u64, SearchIntent, SearchResult),
+
+  /// Airport infos matching a find-as-you-type term (the term is included).
+  Find(String, Vec<AirportInfo>),
+
+  /// Runway ends for an airport (the airport ID is included).
+  Runways(String, Vec<RunwayEnd>),
+
+  /// Full airport details.
+  Detail(Box<AirportDetail>),
+
+  /// Remarks for an airport (the airport ID is included).
+  Remarks(String, Vec<String>),
+
+  /// Spatial index is complete (the number of airports within the chart bounds is included).
+  Indexed(usize),
+
+  /// Request resulted in an error.
+  Error(util::Error),
+}
+
+/// Take at most `max` replies from a channel without waiting.
+/// - `rx`: reply receiver
+/// - `max`: maximum number of replies
+fn take_replies<T>(rx: &mpsc::Receiver<T>, max: usize) -> Vec<T> {
+  rx.try_iter().take(max).collect()
+}
+
+struct ToChart {
+  /// Coordinate transformation from NAD83 to LCC.
+  trans: spatial_ref::CoordTransform,
+
+  /// Chart bounds in LCC coordinates.
+  bounds: util::Bounds,
+}
+
+impl ToChart {
+  /// Test if a NAD83 coordinate is contained within the chart bounds.
+  fn contains(&self, nad83: util::Coord) -> bool {
+    use util::Transform;
+    match self.trans.transform(nad83) {
+      Ok(lcc) => return self.bounds.contains(lcc),
+      Err(err) => log::warn!("{err}"),
+    }
+    false
+  }
+}
+
+#[derive(Eq, Ord, PartialEq, PartialOrd)]
+enum AirportStatus {
+  None,
+
+  /// ID and name indexes are ready.
+  BasicIdx,
+
+  /// Spatial index is ready.
+  SpatialIdx,
+}
+
+impl From<u8> for AirportStatus {
+  fn from(value: u8) -> Self {
+    const NONE: u8 = AirportStatus::None as u8;
+    const BASIC: u8 = AirportStatus::BasicIdx as u8;
+    const SPATIAL: u8 = AirportStatus::SpatialIdx as u8;
+    match value {
+      NONE => AirportStatus::None,
+      BASIC => AirportStatus::BasicIdx,
+      SPATIAL => AirportStatus::SpatialIdx,
+      _ => unreachable!(),
+    }
+  }
+}
+
+#[derive(Clone)]
+struct AirportStatusSync {
+  status: sync::Arc<atomic::AtomicU8>,
+  indexing: sync::Arc<atomic::AtomicBool>,
+}
+
+impl AirportStatusSync {
+  fn new() -> Self {
+    let status = atomic::AtomicU8::new(AirportStatus::None as u8);
+    Self {
+      status: sync::Arc::new(status),
+      indexing: sync::Arc::new(atomic::AtomicBool::new(false)),
+    }
+  }
+
+  fn set_indexing(&mut self, indexing: bool) {
+    self.indexing.store(indexing, atomic::Ordering::Relaxed);
+  }
+
+  fn is_indexing(&self) -> bool {
+    self.indexing.load(atomic::Ordering::Relaxed)
+  }
+
+  fn set_has_basic_idx(&mut self) {
+    self.set(AirportStatus::BasicIdx);
+  }
+
+  fn set_has_spatial_idx(&mut self) {
+    self.set(AirportStatus::SpatialIdx);
+  }
+
+  fn set(&mut self, status: AirportStatus) {
+    self.status.store(status as u8, atomic::Ordering::Relaxed);
+  }
+
+  fn get(&self) -> AirportStatus {
+    self.status.load(atomic::Ordering::Relaxed).into()
+  }
+}
+
+struct AirportSource {
+  dataset: gdal::Dataset,
+  count: u64,
+  eff_date: Option<util::Date>,
+  name_vec: Vec<AirportName>,
+  id_map: collections::HashMap<String, u64>,
+  sp_idx: rstar::RTree<LocIdx>,
+}
+
+/// Airport name index entry.
+struct AirportName {
+  name: String,
+  city: String,
+  state: String,
+  fid: u64,
+}
+
+impl AirportName {
+  /// City and state (e.g. "SAN JOSE, CA").
+  fn location(&self) -> String {
+    join_location(&self.city, &self.state)
+  }
+}
+
+impl AirportSource {
+  fn open_options<'a>() -> gdal::DatasetOptions<'a> {
+    gdal::DatasetOptions {
+      open_flags: gdal::GdalOpenFlags::GDAL_OF_READONLY | gdal::GdalOpenFlags::GDAL_OF_VECTOR,
+      ..Default::default()
+    }
+  }
+
+  /// Open an airport data source.
+  /// - `path`: NASR airport CSV file path
+  fn open(path: &path::Path) -> Result<Self, errors::GdalError> {
+    use gdal::vector::LayerAccess;
+
+    // Open the dataset and get the layer.
+    let dataset = gdal::Dataset::open_ex(path, Self::open_options())?;
+    let layer = dataset.layer(0)?;
+    let count = layer.feature_count();
+
+    Ok(Self {
+      dataset,
+      count,
+      eff_date: None,
+      name_vec: Vec::new(),
+      id_map: collections::HashMap::new(),
+      sp_idx: rstar::RTree::new(),
+    })
+  }
+
+  /// Create the name and ID indexes.
+  /// - `abandon`: returns true if the indexes are no longer needed
+  fn create_basic_indexes(&mut self, abandon: &dyn Fn() -> bool) -> bool {
+    use vector::LayerAccess;
+
+    let count = self.count as usize;
+    let mut name_vec = Vec::with_capacity(count);
+    let mut id_map = collections::HashMap::with_capacity(count);
+    let mut eff_date = None;
+    for feature in self.layer().features() {
+      if abandon() {
+        return false;
+      }
+
+      if let Some(fid) = feature.fid() {
+        // Add the airport name, city and state to the name vector.
+        if let Some(name) = feature.get_string(AirportInfo::AIRPORT_NAME) {
+          let city = feature.get_string(AirportDetail::CITY).unwrap_or_default();
+          let state = feature
+            .get_string(AirportDetail::STATE_CODE)
+            .unwrap_or_default();
+          name_vec.push(AirportName {
+            name,
+            city,
+            state,
+            fid,
+          });
+        }
+
+        // Add the airport IDs to the ID index.
+        if let Some(id) = feature.get_string(AirportInfo::AIRPORT_ID) {
+          id_map.insert(id, fid);
+        }
+
+        // All the records should have the same effective date.
+        if eff_date.is_none() {
+          if let Some(date) = feature.get_string(AirportInfo::EFFECTIVE_DATE) {
+            eff_date = util::Date::parse(&date);
+          }
+        }
+      }
+    }
+
+    self.eff_date = eff_date;
+    self.name_vec = name_vec;
+    self.id_map = id_map;
+    !self.name_vec.is_empty() && !self.id_map.is_empty()
+  }
+
+  /// Create a spatial index. Returns `None` if abandoned.
+  /// - `to_chart`: coordinate transformation and chart bounds
+  /// - `abandon`: returns true if the index is no longer needed
+  fn create_spatial_index(
+    &self,
+    to_chart: &ToChart,
+    abandon: &dyn Fn() -> bool,
+  ) -> Option<rstar::RTree<LocIdx>> {
+    LocIdx::create_index(self.layer(), self.count, to_chart, abandon)
+  }
+
+  fn set_spatial_index(&mut self, sp_idx: rstar::RTree<LocIdx>) {
+    self.sp_idx = sp_idx;
+  }
+
+  fn clear_spatial_index(&mut self) {
+    self.sp_idx = rstar::RTree::new();
+  }
+
+  /// Get `AirportInfo` for the specified airport ID.
+  /// - `id`: airport ID
+  fn airport(&self, id: &str) -> Option<AirportInfo> {
+    use vector::LayerAccess;
+    let layer = self.layer();
+    if let Some(fid) = self.id_map.get(id) {
+      return layer.feature(*fid).and_then(AirportInfo::new);
+    }
+    None
+  }
+
+  /// Get `AirportInfo` for the specified airport ID, falling back to the ICAO form of the ID
+  /// (e.g. "KPAO" for "PAO"). The description notes the ICAO form when it's used.
+  /// - `id`: airport ID or ICAO code
+  fn airport_or_icao(&self, id: &str) -> Option<AirportInfo> {
+    let local_id = local_airport_id(id, |id| self.id_map.contains_key(id))?;
+    let mut info = self.airport(local_id)?;
+    if local_id != id {
+      info.desc = info.make_desc(Some(id));
+    }
+    Some(info)
+  }
+
+  /// Find airports within a search radius.
+  /// > **NOTE**: requires spatial index.
+  /// - `coord`: chart coordinate (LCC)
+  /// - `dist`: search distance in meters
+  fn nearby(&self, coord: util::Coord, dist: f64) -> Vec<AirportInfo> {
+    use vector::LayerAccess;
+    let layer = self.layer();
+    let coord = [coord.x, coord.y];
+    let dsq = dist * dist;
+
+    // Collect the feature IDs.
+    let mut fids = Vec::new();
+    for item in self.sp_idx.locate_within_distance(coord, dsq) {
+      fids.push(item.fid);
+    }
+
+    // Sort the feature IDs so that lookups are sequential.
+    fids.sort_unstable();
+
+    let mut airports = Vec::with_capacity(fids.len());
+    for fid in fids {
+      if let Some(info) = layer.feature(fid).and_then(AirportInfo::new) {
+        airports.push(info);
+      }
+    }
+
+    airports.sort_unstable_by(|a, b| a.desc.cmp(&b.desc));
+    airports
+  }
+
+  /// Find the airports within a rectangle.
+  /// > **NOTE**: requires spatial index.
+  /// - `bounds`: rectangle in chart coordinates (LCC)
+  ///
+  /// Returns the feature IDs in ascending order so that lookups are sequential.
+  fn within_rect(&self, bounds: &util::Bounds) -> Vec<u64> {
+    let min = [bounds.min.x, bounds.min.y];
+    let max = [bounds.max.x, bounds.max.y];
+    let envelope = rstar::AABB::from_corners(min, max);
+    let mut fids: Vec<u64> = self
+      .sp_idx
+      .locate_in_envelope(&envelope)
+      .map(|item| item.fid)
+      .collect();
+    fids.sort_unstable();
+    fids
+  }
+
+  /// Get the runway ticks for the airports within a rectangle.
+  /// > **NOTE**: requires spatial index.
+  /// - `bounds`: rectangle in chart coordinates (LCC)
+  /// - `summaries`: runway summaries
+  /// - `to_chart`: coordinate transformation
+  fn runway_ticks(
+    &self,
+    bounds: &util::Bounds,
+    summaries: &collections::HashMap<String, RunwaySummary>,
+    to_chart: &ToChart,
+  ) -> Vec<RunwayTick> {
+    use vector::LayerAccess;
+    let layer = self.layer();
+    let mut ticks = Vec::new();
+    for fid in self.within_rect(bounds) {
+      let Some(feature) = layer.feature(fid) else {
+        continue;
+      };
+
+      let Some(id) = feature.get_string(AirportInfo::AIRPORT_ID) else {
+        continue;
+      };
+
+      let (Some(summary), Some(nad83)) = (summaries.get(&id), feature.get_coord()) else {
+        continue;
+      };
+
+      // Runway numbers are magnetic.
+      let mag_var = feature.get_mag_var().unwrap_or(0.0);
+
+      if let Some(tick) = RunwayTick::new(id, nad83, mag_var, summary, to_chart) {
+        ticks.push(tick);
+      }
+    }
+    ticks
+  }
+
+  /// Find the closest public use airports, nearest first.
+  /// > **NOTE**: requires spatial index.
+  /// - `coord`: chart coordinate (LCC)
+  /// - `dist`: maximum distance in meters
+  /// - `count`: maximum number of airports
+  fn nearest(&self, coord: util::Coord, dist: f64, count: usize) -> Vec<(AirportInfo, f64)> {
+    use vector::LayerAccess;
+    let layer = self.layer();
+    let coord = [coord.x, coord.y];
+    let dsq = dist * dist;
+
+    let mut airports = Vec::with_capacity(count);
+    for (item, item_dsq) in self.sp_idx.nearest_neighbor_iter_with_distance_2(&coord) {
+      if item_dsq > dsq || airports.len() == count {
+        break;
+      }
+
+      if let Some(info) = layer.feature(item.fid).and_then(AirportInfo::new) {
+        if info.airport_use == AirportUse::Public {
+          airports.push((info, item_dsq.sqrt()));
+        }
+      }
+    }
+    airports
+  }
+
+  /// Search for airports with names or cities that contain the query text. The city and state
+  /// are added to the descriptions so that the results can be told apart.
+  /// - `query`: parsed search term
+  /// - `to_chart`: coordinate transformation and chart bounds
+  /// - `nph`: include non-public heliports
+  fn search(&self, query: &SearchQuery, to_chart: &ToChart, nph: bool) -> Vec<AirportInfo> {
+    use vector::LayerAccess;
+    let layer = self.layer();
+    let mut airports = Vec::new();
+    for entry in &self.name_vec {
+      if query.matches(&entry.name, &entry.city, &entry.state) {
+        if let Some(mut info) = layer.feature(entry.fid).and_then(AirportInfo::new) {
+          // Make sure the coordinate (NAD83) is within the chart bounds.
+          if info.included(nph) && to_chart.contains(info.coord) {
+            info.add_location(&entry.location());
+            info.add_fuel();
+            airports.push(info);
+          }
+        }
+      }
+    }
+
+    airports.sort_unstable_by(|a, b| a.desc.cmp(&b.desc));
+    airports
+  }
+
+  /// Get the full details for the specified airport ID.
+  /// - `id`: airport ID
+  /// - `related`: runway, frequency and remark data sources
+  fn detail(&self, id: &str, related: &mut RelatedSources) -> Option<AirportDetail> {
+    use vector::LayerAccess;
+    let layer = self.layer();
+    let feature = layer.feature(*self.id_map.get(id)?)?;
+    let city = feature.get_string(AirportDetail::CITY).unwrap_or_default();
+    let state = feature
+      .get_string(AirportDetail::STATE_CODE)
+      .unwrap_or_default();
+    let location = join_location(&city, &state);
+
+    let mag_var = feature.get_string(AirportDetail::MAG_VARN).and_then(|var| {
+      let hemis = feature
+        .get_string(AirportDetail::MAG_HEMIS)
+        .unwrap_or_default();
+      (!var.is_empty()).then(|| format!("{var}°{hemis}"))
+    });
+
+    let info = AirportInfo::new(feature)?;
+
+    // Missing related data leaves that section empty.
+    let ends = related.read(RunwayEnd::FILE_NAME, |source| RunwayEnd::read(source, id));
+    let runways = related.read(Runway::FILE_NAME, |source| Runway::read(source, id, ends));
+    let frequencies = related.read(Frequency::FILE_NAME, |source| Frequency::read(source, id));
+    let remarks = related.read(AirportDetail::REMARK_FILE_NAME, |source| {
+      AirportDetail::read_remarks(source, id)
+    });
+
+    Some(AirportDetail {
+      info,
+      location,
+      mag_var,
+      runways,
+      frequencies,
+      remarks,
+    })
+  }
+
+  fn layer(&self) -> vector::Layer<'_> {
+    self.dataset.layer(0).unwrap()
+  }
+}
+
+/// NASR CSV data source with records that refer to airports by ID (runways, frequencies, etc).
+struct RelatedSource {
+  dataset: gdal::Dataset,
+}
+
+impl RelatedSource {
+  /// Open a related data source.
+  /// - `path`: NASR CSV file path
+  fn open(path: &path::Path) -> Result<Self, errors::GdalError> {
+    let dataset = gdal::Dataset::open_ex(path, AirportSource::open_options())?;
+    dataset.layer(0)?;
+    Ok(Self { dataset })
+  }
+
+  /// Call a function for each record that refers to an airport.
+  /// - `field`: name of the airport ID field
+  /// - `id`: airport ID
+  /// - `f`: function to call with each record
+  fn for_each(&self, field: &str, id: &str, mut f: impl FnMut(&vector::Feature)) {
+    use vector::LayerAccess;
+    let mut layer = self.dataset.layer(0).unwrap();
+    let id = id.trim().to_uppercase().replace('\'', "''");
+    let filter = format!("{field} = '{id}'");
+    if let Err(err) = layer.set_attribute_filter(&filter) {
+      log::warn!("{err}");
+      return;
+    }
+
+    for feature in layer.features() {
+      f(&feature);
+    }
+
+    layer.clear_attribute_filter();
+  }
+
+  /// Call a function for every record. Returns false if abandoned.
+  /// - `abandon`: returns true if the records are no longer needed
+  /// - `f`: function to call with each record
+  fn for_all(&self, abandon: &dyn Fn() -> bool, mut f: impl FnMut(&vector::Feature)) -> bool {
+    use vector::LayerAccess;
+    let mut layer = self.dataset.layer(0).unwrap();
+    for feature in layer.features() {
+      if abandon() {
+        return false;
+      }
+      f(&feature);
+    }
+    true
+  }
+}
+
+/// Related data sources, opened as they're needed.
+struct RelatedSources {
+  folder: path::PathBuf,
+  sources: collections::HashMap<&'static str, Result<RelatedSource, String>>,
+}
+
+impl RelatedSources {
+  /// Create the related data sources.
+  /// - `folder`: folder containing the NASR CSV files
+  fn new(folder: path::PathBuf) -> Self {
+    Self {
+      folder,
+      sources: collections::HashMap::new(),
+    }
+  }
+
+  /// Get a related data source, opening it if needed.
+  /// - `file_name`: NASR CSV file name
+  fn get(&mut self, file_name: &'static str) -> Result<&RelatedSource, util::Error> {
+    let folder = &self.folder;
+    let source = self.sources.entry(file_name).or_insert_with(|| {
+      RelatedSource::open(&folder.join(file_name))
+        .map_err(|err| format!("Unable to open {file_name}: {err}"))
+    });
+
+    match source {
+      Ok(source) => Ok(source),
+      Err(err) => Err(err.clone().into()),
+    }
+  }
+
+  /// Read from a related data source. Returns the default value if the source can't be opened.
+  /// - `file_name`: NASR CSV file name
+  /// - `f`: function that reads from the source
+  fn read<T: Default>(
+    &mut self,
+    file_name: &'static str,
+    f: impl FnOnce(&RelatedSource) -> T,
+  ) -> T {
+    match self.get(file_name) {
+      Ok(source) => f(source),
+      Err(err) => {
+        log::warn!("{err}");
+        T::default()
+      }
+    }
+  }
+}
+
+/// Navaid (VOR, NDB, etc) data source.
+struct NavaidSource {
+  dataset: gdal::Dataset,
+  count: u64,
+  name_vec: Vec<(String, u64)>,
+  id_map: collections::HashMap<String, u64>,
+  sp_idx: rstar::RTree<LocIdx>,
+}
+
+impl NavaidSource {
+  /// Open a navaid data source.
+  /// - `path`: NASR navaid CSV file path
+  fn open(path: &path::Path) -> Result<Self, errors::GdalError> {
+    use gdal::vector::LayerAccess;
+
+    let dataset = gdal::Dataset::open_ex(path, AirportSource::open_options())?;
+    let count = dataset.layer(0)?.feature_count();
+    Ok(Self {
+      dataset,
+      count,
+      name_vec: Vec::new(),
+      id_map: collections::HashMap::new(),
+      sp_idx: rstar::RTree::new(),
+    })
+  }
+
+  /// Create the name and ID indexes.
+  /// - `abandon`: returns true if the indexes are no longer needed
+  fn create_basic_indexes(&mut self, abandon: &dyn Fn() -> bool) -> bool {
+    use vector::LayerAccess;
+
+    let count = self.count as usize;
+    let mut name_vec = Vec::with_capacity(count);
     let mut id_map = collections::HashMap::with_capacity(count);
     for feature in self.layer().features() {
+      if abandon() {
+        return false;
+      }
+
       if let Some(fid) = feature.fid() {
-        // Add the airport name to the name vector.
-        if let Some(name) = feature.get_string(AirportInfo::AIRPORT_NAME) {
+        if let Some(name) = feature.get_string(NavaidInfo::NAME) {
           name_vec.push((name, fid));
         }
 
-        // Add the airport IDs to the ID index.
-        if let Some(id) = feature.get_string(AirportInfo::AIRPORT_ID) {
-          id_map.insert(id, fid);
-        }
+        // Different types of navaid can share an ID. Keep the first one.
+        if let Some(id) = feature.get_string(NavaidInfo::NAV_ID) {
+          id_map.entry(id).or_insert(fid);
+        }
+      }
+    }
+
+    self.name_vec = name_vec;
+    self.id_map = id_map;
+    !self.id_map.is_empty()
+  }
+
+  /// Create a spatial index. Returns `None` if abandoned.
+  /// - `to_chart`: coordinate transformation and chart bounds
+  /// - `abandon`: returns true if the index is no longer needed
+  fn create_spatial_index(
+    &self,
+    to_chart: &ToChart,
+    abandon: &dyn Fn() -> bool,
+  ) -> Option<rstar::RTree<LocIdx>> {
+    LocIdx::create_index(self.layer(), self.count, to_chart, abandon)
+  }
+
+  fn set_spatial_index(&mut self, sp_idx: rstar::RTree<LocIdx>) {
+    self.sp_idx = sp_idx;
+  }
+
+  fn clear_spatial_index(&mut self) {
+    self.sp_idx = rstar::RTree::new();
+  }
+
+  /// Get `NavaidInfo` for the specified navaid ID.
+  /// - `id`: navaid ID
+  fn navaid(&self, id: &str) -> Option<NavaidInfo> {
+    use vector::LayerAccess;
+    let fid = self.id_map.get(id)?;
+    self.layer().feature(*fid).and_then(NavaidInfo::new)
+  }
+
+  /// Find navaids within a search radius.
+  /// - `coord`: chart coordinate (LCC)
+  /// - `dist`: search distance in meters
+  fn nearby(&self, coord: util::Coord, dist: f64) -> Vec<NavaidInfo> {
+    use vector::LayerAccess;
+    let layer = self.layer();
+    let coord = [coord.x, coord.y];
+    let mut fids: Vec<u64> = (self.sp_idx.locate_within_distance(coord, dist * dist))
+      .map(|item| item.fid)
+      .collect();
+
+    // Sort the feature IDs so that lookups are sequential.
+    fids.sort_unstable();
+
+    let mut navaids: Vec<NavaidInfo> = (fids.into_iter())
+      .filter_map(|fid| layer.feature(fid).and_then(NavaidInfo::new))
+      .collect();
+    navaids.sort_unstable_by(|a, b| a.desc.cmp(&b.desc));
+    navaids
+  }
+
+  /// Search for navaids with names that contain the specified text.
+  /// - `term`: search text
+  /// - `to_chart`: coordinate transformation and chart bounds
+  fn search(&self, term: &str, to_chart: &ToChart) -> Vec<NavaidInfo> {
+    use vector::LayerAccess;
+    let layer = self.layer();
+    let mut navaids = Vec::new();
+    for (name, fid) in &self.name_vec {
+      if name.contains(term) {
+        if let Some(info) = layer.feature(*fid).and_then(NavaidInfo::new) {
+          if to_chart.contains(info.coord) {
+            navaids.push(info);
+          }
+        }
+      }
+    }
+
+    navaids.sort_unstable_by(|a, b| a.desc.cmp(&b.desc));
+    navaids
+  }
+
+  fn layer(&self) -> vector::Layer<'_> {
+    self.dataset.layer(0).unwrap()
+  }
+
+  const FILE_NAME: &'static str = "NAV_BASE.csv";
+}
+
+/// Runway end information.
+#[derive(Clone, Debug)]
+pub struct RunwayEnd {
+  /// Runway ID (e.g. "13R/31L").
+  pub runway: String,
+
+  /// Runway end ID (e.g. "31L").
+  pub id: String,
+
+  /// True alignment in degrees, if known.
+  pub true_alignment: Option<f64>,
+
+  /// Elevation in feet (MSL), if known.
+  pub elevation: Option<f64>,
+
+  /// True if the traffic pattern is right-hand.
+  pub right_traffic: bool,
+
+  /// Displaced threshold length in feet, if any.
+  pub displaced_threshold: Option<f64>,
+
+  /// Controlling obstacle on the approach, if any.
+  pub obstacle: Option<Obstacle>,
+}
+
+impl RunwayEnd {
+  /// Read the runway ends for an airport.
+  /// - `source`: runway end data source
+  /// - `id`: airport ID
+  fn read(source: &RelatedSource, id: &str) -> Vec<RunwayEnd> {
+    let mut ends = Vec::new();
+    source.for_each(AirportInfo::AIRPORT_ID, id, |feature| {
+      let Some(id) = feature.get_string(RunwayEnd::RUNWAY_END_ID) else {
+        return;
+      };
+
+      // The alignment is often blank.
+      let true_alignment = feature
+        .field_as_double_by_name(RunwayEnd::TRUE_ALIGNMENT)
+        .ok()
+        .flatten();
+
+      let elevation = feature
+        .field_as_double_by_name(RunwayEnd::ELEVATION)
+        .ok()
+        .flatten();
+
+      let right_traffic = feature.get_string(RunwayEnd::RIGHT_TRAFFIC).as_deref() == Some("Y");
+      let displaced_threshold = feature
+        .get_number(RunwayEnd::DISPLACED_THRESHOLD)
+        .filter(|length| *length > 0.0);
+
+      ends.push(RunwayEnd {
+        runway: feature.get_string(Runway::RUNWAY_ID).unwrap_or_default(),
+        id,
+        true_alignment,
+        elevation,
+        right_traffic,
+        displaced_threshold,
+        obstacle: Obstacle::new(feature),
+      });
+    });
+    ends
+  }
+
+  const FILE_NAME: &'static str = "APT_RWY_END.csv";
+  const RUNWAY_END_ID: &'static str = "RWY_END_ID";
+  const TRUE_ALIGNMENT: &'static str = "TRUE_ALIGNMENT";
+  const ELEVATION: &'static str = "RWY_END_ELEV";
+  const RIGHT_TRAFFIC: &'static str = "RIGHT_HAND_TRAFFIC_PAT_FLAG";
+  const DISPLACED_THRESHOLD: &'static str = "DISPLACED_THR_LEN";
+}
+
+/// Controlling obstacle on a runway end approach.
+#[derive(Clone, Debug)]
+pub struct Obstacle {
+  /// Obstacle type (e.g. "TREES").
+  pub kind: String,
+
+  /// Height in feet above the runway end, if known.
+  pub height: Option<f64>,
+
+  /// Distance in feet from the runway end, if known.
+  pub distance: Option<f64>,
+
+  /// Offset in feet from the extended centerline, if known. Positive values are to the right
+  /// when looking along the approach.
+  pub offset: Option<f64>,
+
+  /// True if the offset applies to both sides of the centerline.
+  pub both_sides: bool,
+}
+
+impl Obstacle {
+  fn new(feature: &vector::Feature) -> Option<Self> {
+    let kind = feature.get_string(Obstacle::KIND).unwrap_or_default();
+    let height = feature.get_number(Obstacle::HEIGHT);
+    let distance = feature.get_number(Obstacle::DISTANCE);
+    if kind.is_empty() && height.is_none() && distance.is_none() {
+      return None;
+    }
+
+    let side = feature.get_string(Obstacle::SIDE).unwrap_or_default();
+    let offset = feature.get_number(Obstacle::OFFSET);
+    let offset = offset.map(|offset| if side == "L" { -offset } else { offset });
+
+    Some(Self {
+      kind,
+      height,
+      distance,
+      offset,
+      both_sides: side == "B",
+    })
+  }
+
+  const KIND: &'static str = "OBSTN_TYPE";
+  const HEIGHT: &'static str = "OBSTN_HGT";
+  const DISTANCE: &'static str = "DIST_FROM_THR";
+  const OFFSET: &'static str = "CNTRLN_OFFSET";
+  const SIDE: &'static str = "CNTRLN_DIR_CODE";
+}
+
+/// Runway information.
+#[derive(Clone, Debug)]
+pub struct Runway {
+  /// Runway ID (e.g. "13R/31L").
+  pub id: String,
+
+  /// Length in feet, if known.
+  pub length: Option<f64>,
+
+  /// Width in feet, if known.
+  pub width: Option<f64>,
+
+  /// Surface type and condition (e.g. "ASPH-G").
+  pub surface: String,
+
+  /// Runway ends.
+  pub ends: Vec<RunwayEnd>,
+}
+
+impl Runway {
+  /// Read the runways for an airport.
+  /// - `source`: runway data source
+  /// - `id`: airport ID
+  /// - `ends`: runway ends for the airport
+  fn read(source: &RelatedSource, id: &str, mut ends: Vec<RunwayEnd>) -> Vec<Runway> {
+    let mut runways = Vec::new();
+    source.for_each(AirportInfo::AIRPORT_ID, id, |feature| {
+      let Some(id) = feature.get_string(Runway::RUNWAY_ID) else {
+        return;
+      };
+
+      let surface = feature.get_string(Runway::SURFACE).unwrap_or_default();
+      let surface = match feature.get_string(Runway::CONDITION) {
+        Some(cond) if !cond.is_empty() => format!("{surface}-{cond}"),
+        _ => surface,
+      };
+
+      runways.push(Runway {
+        id,
+        length: feature.get_f64(Runway::LENGTH),
+        width: feature.get_f64(Runway::WIDTH),
+        surface,
+        ends: Vec::new(),
+      });
+    });
+
+    for runway in &mut runways {
+      let (own, rest) = ends.into_iter().partition(|end| end.runway == runway.id);
+      runway.ends = own;
+      ends = rest;
+    }
+    runways
+  }
+
+  const FILE_NAME: &'static str = "APT_RWY.csv";
+  const RUNWAY_ID: &'static str = "RWY_ID";
+  const LENGTH: &'static str = "RWY_LEN";
+  const WIDTH: &'static str = "RWY_WIDTH";
+  const SURFACE: &'static str = "SURFACE_TYPE_CODE";
+  const CONDITION: &'static str = "COND";
+}
+
+/// Longest runway at an airport, shown in search result lists.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RunwaySummary {
+  /// Length in feet.
+  pub length: u32,
+
+  /// True if the runway has a hard surface (e.g. asphalt or concrete).
+  pub hard: bool,
+
+  /// Magnetic heading of the runway in degrees (0 to 180), taken from the runway ID. Helipads and
+  /// other runways without a numbered or compass ID don't have one.
+  pub heading: Option<f32>,
+}
+
+impl RunwaySummary {
+  /// Read the longest runway for every airport. Returns `None` if abandoned.
+  /// - `source`: runway data source
+  /// - `abandon`: returns true if the summaries are no longer needed
+  fn read_all(
+    source: &RelatedSource,
+    abandon: &dyn Fn() -> bool,
+  ) -> Option<collections::HashMap<String, RunwaySummary>> {
+    let mut summaries = collections::HashMap::new();
+    let complete = source.for_all(abandon, |feature| {
+      let Some(id) = feature.get_string(AirportInfo::AIRPORT_ID) else {
+        return;
+      };
+
+      let Some(length) = feature.get_number(Runway::LENGTH).filter(|len| *len > 0.0) else {
+        return;
+      };
+
+      let surface = feature.get_string(Runway::SURFACE).unwrap_or_default();
+      let runway_id = feature.get_string(Runway::RUNWAY_ID).unwrap_or_default();
+      let summary = RunwaySummary {
+        length: length.round() as u32,
+        hard: RunwaySummary::hard_surface(&surface),
+        heading: RunwaySummary::heading(&runway_id),
+      };
+
+      // Keep the longest runway, preferring a hard surface when they're the same length.
+      let entry = summaries.entry(id).or_insert(summary);
+      if (summary.length, summary.hard) > (entry.length, entry.hard) {
+        *entry = summary;
+      }
+    });
+    complete.then_some(summaries)
+  }
+
+  /// Returns true if a surface type code (e.g. "ASPH" or "TURF-GRVL") includes a hard surface.
+  /// - `surface`: surface type code
+  fn hard_surface(surface: &str) -> bool {
+    let mut parts = surface.split(['-', '/']).map(str::trim);
+    parts.any(|part| RunwaySummary::HARD_SURFACES.contains(&part))
+  }
+
+  /// Get the magnetic heading from a runway ID (e.g. "16R/34L" or "NE/SW"), folded into the range
+  /// 0 to 180 degrees.
+  /// - `runway_id`: runway ID
+  fn heading(runway_id: &str) -> Option<f32> {
+    let end = runway_id.split('/').next()?.trim();
+    let number = end.trim_end_matches(['L', 'C', 'R']);
+    let heading = if let Ok(number) = number.parse::<u16>() {
+      if !(1..=36).contains(&number) {
+        return None;
+      }
+      number as f32 * 10.0
+    } else {
+      let compass_ids = RunwaySummary::COMPASS_IDS;
+      let index = compass_ids.iter().position(|id| *id == end)?;
+      index as f32 * 45.0
+    };
+    Some(heading % 180.0)
+  }
+
+  /// Surface type codes for asphalt, concrete and partially asphalt or concrete.
+  const HARD_SURFACES: [&'static str; 3] = ["ASPH", "CONC", "PEM"];
+
+  /// Runway IDs that are compass directions instead of numbers, clockwise from north.
+  const COMPASS_IDS: [&'static str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+}
+
+/// Runway orientation tick for drawing over an airport on the chart.
+#[derive(Clone, Debug)]
+pub struct RunwayTick {
+  /// Airport ID.
+  #[allow(unused)]
+  pub id: String,
+
+  /// Ends of the airport's longest runway in chart coordinates (LCC), centered on the airport
+  /// reference point.
+  pub ends: [util::Coord; 2],
+
+  /// True if the runway has a hard surface.
+  pub hard: bool,
+}
+
+impl RunwayTick {
+  /// Create a runway tick from a runway summary. Returns `None` if the runway has no heading.
+  /// - `id`: airport ID
+  /// - `nad83`: airport reference point (NAD83)
+  /// - `mag_var`: magnetic variation in degrees (east is positive)
+  /// - `summary`: runway summary
+  /// - `to_chart`: coordinate transformation
+  fn new(
+    id: String,
+    nad83: util::Coord,
+    mag_var: f64,
+    summary: &RunwaySummary,
+    to_chart: &ToChart,
+  ) -> Option<Self> {
+    use util::Transform;
+
+    // Offset the ends from the reference point along the true heading. The transformation then
+    // takes care of the chart's convergence angle.
+    let heading = (summary.heading? as f64 + mag_var).to_radians();
+    let half = summary.length as f64 * util::METERS_PER_FOOT * 0.5;
+    let dy = half * heading.cos() / util::METERS_PER_DEGREE;
+    let dx = half * heading.sin() / (util::METERS_PER_DEGREE * nad83.y.to_radians().cos());
+    let end = |sign: f64| {
+      let coord = util::Coord {
+        x: nad83.x + sign * dx,
+        y: nad83.y + sign * dy,
+      };
+      to_chart.trans.transform(coord).ok()
+    };
+
+    Some(Self {
+      id,
+      ends: [end(-1.0)?, end(1.0)?],
+      hard: summary.hard,
+    })
+  }
+}
+
+impl fmt::Display for RunwaySummary {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let length = util::group_thousands(self.length as i64);
+    let surface = if self.hard { "hard" } else { "soft" };
+    write!(f, "{length} ft {surface}")
+  }
+}
+
+/// Radio frequency information.
+#[derive(Clone, Debug)]
+pub struct Frequency {
+  /// Frequency (e.g. "118.3").
+  pub freq: String,
+
+  /// What the frequency is used for (e.g. "CTAF").
+  pub usage: String,
+
+  /// Call sign, if any.
+  pub call: String,
+
+  /// Sectorization (e.g. "180-359"), if any.
+  pub sector: String,
+}
+
+impl Frequency {
+  /// Read the frequencies for an airport.
+  /// - `source`: frequency data source
+  /// - `id`: airport ID
+  fn read(source: &RelatedSource, id: &str) -> Vec<Frequency> {
+    let mut frequencies = Vec::new();
+    source.for_each(Frequency::SERVICED_FACILITY, id, |feature| {
+      let Some(freq) = feature.get_string(Frequency::FREQ) else {
+        return;
+      };
+
+      frequencies.push(Frequency {
+        freq,
+        usage: feature.get_string(Frequency::FREQ_USE).unwrap_or_default(),
+        call: feature.get_string(Frequency::CALL).unwrap_or_default(),
+        sector: feature.get_string(Frequency::SECTOR).unwrap_or_default(),
+      });
+    });
+    frequencies
+  }
+
+  /// What the frequency is used for, as a group for sorting.
+  pub fn group(&self) -> FrequencyGroup {
+    FrequencyGroup::from_usage(&self.usage)
+  }
+
+  /// Group frequencies by use, in `FrequencyGroup` order. Frequencies within a group are sorted
+  /// numerically.
+  /// - `frequencies`: frequencies in any order
+  pub fn grouped(frequencies: &[Frequency]) -> Vec<(FrequencyGroup, Vec<&Frequency>)> {
+    let mut sorted: Vec<&Frequency> = frequencies.iter().collect();
+    sorted.sort_by(|a, b| {
+      let a_mhz = parse_number(&a.freq).unwrap_or(f64::MAX);
+      let b_mhz = parse_number(&b.freq).unwrap_or(f64::MAX);
+      a.group()
+        .cmp(&b.group())
+        .then(a_mhz.total_cmp(&b_mhz))
+        .then_with(|| a.freq.cmp(&b.freq))
+    });
+
+    let mut groups: Vec<(FrequencyGroup, Vec<&Frequency>)> = Vec::new();
+    for frequency in sorted {
+      let group = frequency.group();
+      match groups.last_mut() {
+        Some((last, list)) if *last == group => list.push(frequency),
+        _ => groups.push((group, vec![frequency])),
       }
     }
+    groups
+  }
 
-    self.name_vec = name_vec;
-    self.id_map = id_map;
-    !self.name_vec.is_empty() && !self.id_map.is_empty()
+  const FILE_NAME: &'static str = "FRQ.csv";
+  const SERVICED_FACILITY: &'static str = "SERVICED_FACILITY";
+  const FREQ: &'static str = "FREQ";
+  const FREQ_USE: &'static str = "FREQ_USE";
+  const CALL: &'static str = "TOWER_OR_COMM_CALL";
+  const SECTOR: &'static str = "SECTORIZATION";
+}
+
+/// Frequency use groups, in the order that they're shown.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum FrequencyGroup {
+  Weather,
+  Ctaf,
+  Tower,
+  Ground,
+  Clearance,
+  Approach,
+  Other,
+}
+
+impl FrequencyGroup {
+  /// Get the group from a NASR frequency use (e.g. "LCL/P" or "APCH/P DEP/P").
+  /// - `usage`: frequency use
+  pub fn from_usage(usage: &str) -> Self {
+    let usage = usage.to_uppercase();
+    let words: Vec<&str> = usage
+      .split(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+      .filter(|word| !word.is_empty())
+      .collect();
+    let has = |check: &dyn Fn(&str) -> bool| words.iter().any(|word| check(word));
+
+    // Weather broadcasts include "D-ATIS" and "AWOS-3".
+    let weather =
+      |word: &str| word.ends_with("ATIS") || word.starts_with("AWOS") || word.starts_with("ASOS");
+
+    if has(&weather) {
+      Self::Weather
+    } else if has(&|word| word == "CTAF" || word == "UNICOM") {
+      Self::Ctaf
+    } else if has(&|word| word == "LCL" || word == "TWR") {
+      Self::Tower
+    } else if has(&|word| word == "GND") {
+      Self::Ground
+    } else if has(&|word| word == "CD" || word == "CLNC") {
+      Self::Clearance
+    } else if has(&|word| word == "APCH" || word == "APP" || word == "DEP") {
+      Self::Approach
+    } else {
+      Self::Other
+    }
+  }
+
+  /// Frequency group heading.
+  pub fn text(&self) -> &'static str {
+    match *self {
+      Self::Weather => "ATIS/AWOS",
+      Self::Ctaf => "CTAF/UNICOM",
+      Self::Tower => "Tower",
+      Self::Ground => "Ground",
+      Self::Clearance => "Clearance",
+      Self::Approach => "Approach/Departure",
+      Self::Other => "Other",
+    }
+  }
+}
+
+/// Full airport details.
+#[derive(Debug)]
+pub struct AirportDetail {
+  /// Basic airport information.
+  pub info: AirportInfo,
+
+  /// City and state.
+  pub location: String,
+
+  /// Magnetic variation (e.g. "14°E"), if known.
+  pub mag_var: Option<String>,
+
+  pub runways: Vec<Runway>,
+  pub frequencies: Vec<Frequency>,
+  pub remarks: Vec<String>,
+}
+
+impl AirportDetail {
+  /// Read the remarks for an airport.
+  /// - `source`: remark data source
+  /// - `id`: airport ID
+  fn read_remarks(source: &RelatedSource, id: &str) -> Vec<String> {
+    let mut remarks = Vec::new();
+    source.for_each(AirportInfo::AIRPORT_ID, id, |feature| {
+      if let Some(remark) = feature.get_string(AirportDetail::REMARK) {
+        remarks.push(remark);
+      }
+    });
+    remarks
   }
 
-  /// Create the spatial index.
+  const REMARK_FILE_NAME: &'static str = "APT_RMK.csv";
+  const REMARK: &'static str = "REMARK";
+  const CITY: &'static str = "CITY";
+  const STATE_CODE: &'static str = "STATE_CODE";
+  const MAG_VARN: &'static str = "MAG_VARN";
+  const MAG_HEMIS: &'static str = "MAG_HEMIS";
+}
+
+/// Location spatial index item.
+struct LocIdx {
+  coord: util::Coord,
+  fid: u64,
+}
+
+impl LocIdx {
+  /// Create a spatial index of the features that are within the chart bounds. Returns `None` if
+  /// abandoned.
+  /// - `layer`: NASR layer with `LAT_DECIMAL` and `LONG_DECIMAL` fields
+  /// - `count`: number of features in the layer
   /// - `to_chart`: coordinate transformation and chart bounds
-  fn create_spatial_index(&mut self, to_chart: &ToChart) -> bool {
+  /// - `abandon`: returns true if the index is no longer needed
+  fn create_index(
+    mut layer: vector::Layer,
+    count: u64,
+    to_chart: &ToChart,
+    abandon: &dyn Fn() -> bool,
+  ) -> Option<rstar::RTree<LocIdx>> {
     use vector::LayerAccess;
 
-    let mut loc_vec = Vec::with_capacity(self.count as usize);
-    for feature in self.layer().features() {
+    let mut loc_vec = Vec::with_capacity(count as usize);
+    for feature in layer.features() {
+      if abandon() {
+        return None;
+      }
+
       if let Some(fid) = feature.fid() {
         use util::Transform;
         if let Some(coord) = feature
@@ -421,172 +2219,486 @@ impl AirportSource {
         }
       }
     }
-    self.sp_idx = rstar::RTree::bulk_load(loc_vec);
-    self.sp_idx.size() > 0
+    Some(rstar::RTree::bulk_load(loc_vec))
   }
+}
 
-  /// Get `AirportInfo` for the specified airport ID.
-  /// - `id`: airport ID
-  fn airport(&self, id: &str) -> Option<AirportInfo> {
-    use vector::LayerAccess;
-    let layer = self.layer();
-    if let Some(fid) = self.id_map.get(id) {
-      return layer.feature(*fid).and_then(AirportInfo::new);
+impl rstar::RTreeObject for LocIdx {
+  type Envelope = rstar::AABB<[f64; 2]>;
+
+  fn envelope(&self) -> Self::Envelope {
+    Self::Envelope::from_point([self.coord.x, self.coord.y])
+  }
+}
+
+impl rstar::PointDistance for LocIdx {
+  fn distance_2(
+    &self,
+    point: &<Self::Envelope as rstar::Envelope>::Point,
+  ) -> <<Self::Envelope as rstar::Envelope>::Point as rstar::Point>::Scalar {
+    let dx = point[0] - self.coord.x;
+    let dy = point[1] - self.coord.y;
+    dx * dx + dy * dy
+  }
+}
+
+/// Airport information.
+#[derive(Debug)]
+pub struct AirportInfo {
+  /// Feature record ID.
+  pub fid: u64,
+
+  /// Airport ID.
+  pub id: String,
+
+  /// ICAO ID, if the airport has one (e.g. "KPAO" or "PANC").
+  pub icao_id: Option<String>,
+
+  /// State or territory code (e.g. "CA").
+  pub state: String,
+
+  /// Airport name.
+  pub name: String,
+
+  /// Coordinate in decimal degrees (NAD 83).
+  pub coord: util::Coord,
+
+  /// Airport type.
+  pub airport_type: AirportType,
+
+  /// Airport usage.
+  pub airport_use: AirportUse,
+
+  /// Elevation in feet (MSL).
+  pub elevation: Option<f64>,
+
+  /// Magnetic variation in degrees (east is positive), if known.
+  pub mag_var: Option<f64>,
+
+  /// Available fuel types (comma separated).
+  pub fuel_types: String,
+
+  /// Fuel types that can be filtered on, from `fuel_types`.
+  pub fuel: FuelTypes,
+
+  /// Longest runway, once the runway summaries are ready (only set for nearby and name search
+  /// results).
+  pub runway: Option<RunwaySummary>,
+
+  /// Short description for UI lists.
+  pub desc: String,
+}
+
+impl AirportInfo {
+  fn new(feature: vector::Feature) -> Option<Self> {
+    let mut info = Self {
+      fid: feature.fid()?,
+      id: feature.get_string(AirportInfo::AIRPORT_ID)?,
+      icao_id: feature
+        .get_string(AirportInfo::ICAO_ID)
+        .filter(|id| !id.is_empty()),
+      state: feature
+        .get_string(AirportDetail::STATE_CODE)
+        .unwrap_or_default(),
+      name: feature.get_string(AirportInfo::AIRPORT_NAME)?,
+      coord: feature.get_coord()?,
+      airport_type: feature.get_airport_type()?,
+      airport_use: feature.get_airport_use()?,
+      elevation: feature.get_number(AirportInfo::ELEVATION),
+      mag_var: feature.get_mag_var(),
+      fuel_types: feature
+        .get_string(AirportInfo::FUEL_TYPES)
+        .unwrap_or_default(),
+      fuel: FuelTypes::default(),
+      runway: None,
+      desc: String::new(),
+    };
+
+    info.fuel = FuelTypes::parse(&info.fuel_types);
+    info.desc = info.make_desc(None);
+    Some(info)
+  }
+
+  /// Make the short description for UI lists.
+  /// - `icao`: ICAO form of the ID that was matched, if any
+  fn make_desc(&self, icao: Option<&str>) -> String {
+    let id = match icao {
+      Some(icao) => format!("{} / {icao}", self.id),
+      None => self.id.clone(),
+    };
+
+    format!(
+      "{} ({id}), {}, {}",
+      self.short_name(),
+      self.airport_type.abv(),
+      self.airport_use.abv()
+    )
+  }
+
+  /// Add the city and state to the short description (e.g. "• SAN JOSE, CA").
+  /// - `location`: city and state
+  fn add_location(&mut self, location: &str) {
+    if !location.is_empty() {
+      self.desc = format!("{} • {location}", self.desc);
     }
-    None
   }
 
-  /// Find airports within a search radius.
-  /// > **NOTE**: requires spatial index.
-  /// - `coord`: chart coordinate (LCC)
-  /// - `dist`: search distance in meters
+  /// Add the fuel types to the short description (e.g. "• 100LL, JET A").
+  fn add_fuel(&mut self) {
+    if !self.fuel.is_empty() {
+      self.desc = format!("{} • {}", self.desc, self.fuel.text());
+    }
+  }
+
+  /// Add the longest runway to the short description (e.g. "• 2,700 ft soft").
+  /// - `runway`: runway summary
+  fn set_runway(&mut self, runway: RunwaySummary) {
+    self.desc = format!("{} • {runway}", self.desc);
+    self.runway = Some(runway);
+  }
+
+  /// Returns a potentially shortened airport name.
+  pub fn short_name(&self) -> &str {
+    // Attempt to shorten the name by removing extra stuff.
+    if let Some(name) = self.name.split(['/', '(']).next() {
+      return name.trim_end();
+    }
+    &self.name
+  }
+
+  /// Elevation text for UI lists (e.g. "EL 1,250 ft").
+  /// - `units`: display units
+  pub fn elevation_text(&self, units: util::Units) -> Option<String> {
+    let elevation = self.elevation?;
+    Some(format!("EL {}", util::format_altitude(elevation, units)))
+  }
+
+  /// Returns true if this is a heliport.
+  pub fn heliport(&self) -> bool {
+    self.airport_type == AirportType::Helicopter
+  }
+
+  /// Returns true if this is a non-public heliport.
+  pub fn non_public_heliport(&self) -> bool {
+    self.heliport() && self.airport_use != AirportUse::Public
+  }
+
+  /// Returns true if this airport belongs in search results.
   /// - `nph`: include non-public heliports
-  fn nearby(&self, coord: util::Coord, dist: f64, nph: bool) -> Vec<AirportInfo> {
-    use vector::LayerAccess;
-    let layer = self.layer();
-    let coord = [coord.x, coord.y];
-    let dsq = dist * dist;
+  pub fn included(&self, nph: bool) -> bool {
+    nph || !self.non_public_heliport()
+  }
 
-    // Collect the feature IDs.
-    let mut fids = Vec::new();
-    for item in self.sp_idx.locate_within_distance(coord, dsq) {
-      fids.push(item.fid);
+  const AIRPORT_ID: &'static str = "ARPT_ID";
+  const ICAO_ID: &'static str = "ICAO_ID";
+  const AIRPORT_NAME: &'static str = "ARPT_NAME";
+  const ELEVATION: &'static str = "ELEV";
+  const FUEL_TYPES: &'static str = "FUEL_TYPES";
+  const EFFECTIVE_DATE: &'static str = "EFF_DATE";
+}
+
+/// Navaid information.
+#[derive(Debug)]
+pub struct NavaidInfo {
+  /// Navaid ID.
+  pub id: String,
+
+  /// Navaid name.
+  pub name: String,
+
+  /// Navaid type (e.g. "VORTAC").
+  pub nav_type: String,
+
+  /// Frequency (e.g. "113.1"), if any.
+  pub freq: Option<String>,
+
+  /// Coordinate in decimal degrees (NAD 83).
+  pub coord: util::Coord,
+
+  /// Short description for UI lists.
+  pub desc: String,
+}
+
+impl NavaidInfo {
+  fn new(feature: vector::Feature) -> Option<Self> {
+    let mut info = Self {
+      id: feature.get_string(NavaidInfo::NAV_ID)?,
+      name: feature.get_string(NavaidInfo::NAME)?,
+      nav_type: feature.get_string(NavaidInfo::NAV_TYPE)?,
+      freq: feature
+        .get_string(NavaidInfo::FREQ)
+        .filter(|freq| !freq.is_empty()),
+      coord: feature.get_coord()?,
+      desc: String::new(),
+    };
+
+    info.desc = if let Some(freq) = &info.freq {
+      format!("{} ({}), {} {freq}", info.name, info.id, info.nav_type)
+    } else {
+      format!("{} ({}), {}", info.name, info.id, info.nav_type)
+    };
+
+    Some(info)
+  }
+
+  const NAV_ID: &'static str = "NAV_ID";
+  const NAME: &'static str = "NAME";
+  const NAV_TYPE: &'static str = "NAV_TYPE";
+  const FREQ: &'static str = "FREQ";
+}
+
+/// Nearby or name search result.
+#[derive(Debug)]
+pub enum Facility {
+  Airport(AirportInfo),
+  Navaid(NavaidInfo),
+}
+
+impl Facility {
+  /// Short description for UI lists.
+  pub fn desc(&self) -> &str {
+    match self {
+      Self::Airport(info) => &info.desc,
+      Self::Navaid(info) => &info.desc,
     }
+  }
 
-    // Sort the feature IDs so that lookups are sequential.
-    fids.sort_unstable();
+  /// Airport or navaid name.
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Airport(info) => &info.name,
+      Self::Navaid(info) => &info.name,
+    }
+  }
 
-    let mut airports = Vec::with_capacity(fids.len());
-    for fid in fids {
-      if let Some(info) = layer.feature(fid).and_then(AirportInfo::new) {
-        if nph || !info.non_public_heliport() {
-          airports.push(info);
-        }
-      }
+  /// Coordinate in decimal degrees (NAD 83).
+  pub fn coord(&self) -> util::Coord {
+    match self {
+      Self::Airport(info) => info.coord,
+      Self::Navaid(info) => info.coord,
     }
+  }
 
-    airports.sort_unstable_by(|a, b| a.desc.cmp(&b.desc));
-    airports
+  /// Returns true if both are the same airport or navaid.
+  /// - `other`: facility to compare with
+  fn is_same(&self, other: &Facility) -> bool {
+    match (self, other) {
+      (Self::Airport(a), Self::Airport(b)) => a.fid == b.fid,
+      (Self::Navaid(a), Self::Navaid(b)) => a.id == b.id && a.nav_type == b.nav_type,
+      _ => false,
+    }
   }
 
-  /// Search for airports with names that contain the specified text.
-  /// - `term`: search text
-  /// - `to_chart`: coordinate transformation and chart bounds
+  /// Elevation text for UI lists, if this is an airport with a known elevation.
+  /// - `units`: display units
+  pub fn elevation_text(&self, units: util::Units) -> Option<String> {
+    self.airport()?.elevation_text(units)
+  }
+
+  /// Returns true if this is a heliport.
+  pub fn heliport(&self) -> bool {
+    self.airport().is_some_and(AirportInfo::heliport)
+  }
+
+  /// Returns true if this is a non-public heliport.
+  pub fn non_public_heliport(&self) -> bool {
+    self.airport().is_some_and(AirportInfo::non_public_heliport)
+  }
+
+  /// Returns true if this facility belongs in search results. Navaids are always included.
   /// - `nph`: include non-public heliports
-  fn search(&self, term: &str, to_chart: &ToChart, nph: bool) -> Vec<AirportInfo> {
-    use vector::LayerAccess;
-    let layer = self.layer();
-    let mut airports = Vec::new();
-    for (name, fid) in &self.name_vec {
-      if name.contains(term) {
-        if let Some(info) = layer.feature(*fid).and_then(AirportInfo::new) {
-          // Make sure the coordinate (NAD83) is within the chart bounds.
-          if (nph || !info.non_public_heliport()) && to_chart.contains(info.coord) {
-            airports.push(info);
-          }
-        }
-      }
-    }
+  pub fn included(&self, nph: bool) -> bool {
+    nph || !self.non_public_heliport()
+  }
 
-    airports.sort_unstable_by(|a, b| a.desc.cmp(&b.desc));
-    airports
+  /// The airport information if this is an airport.
+  pub fn airport(&self) -> Option<&AirportInfo> {
+    match self {
+      Self::Airport(info) => Some(info),
+      Self::Navaid(_) => None,
+    }
   }
 
-  fn layer(&self) -> vector::Layer {
-    self.dataset.layer(0).unwrap()
+  /// Convert into the airport information if this is an airport.
+  pub fn into_airport(self) -> Option<AirportInfo> {
+    match self {
+      Self::Airport(info) => Some(info),
+      Self::Navaid(_) => None,
+    }
   }
 }
 
-/// Location spatial index item.
-struct LocIdx {
-  coord: util::Coord,
-  fid: u64,
-}
+/// Resolve an airport ID that may be in ICAO form (e.g. "KPAO") to the NASR ID ("PAO"). An exact
+/// match is preferred. Four character IDs starting with 'K' (contiguous US) or 'P' (Alaska, Hawaii
+/// and the Pacific) are only stripped if the stripped ID exists.
+/// - `id`: upper case airport ID
+/// - `exists`: returns true if an ID is in the airport data
+fn local_airport_id(id: &str, exists: impl Fn(&str) -> bool) -> Option<&str> {
+  if exists(id) {
+    return Some(id);
+  }
 
-impl rstar::RTreeObject for LocIdx {
-  type Envelope = rstar::AABB<[f64; 2]>;
+  if id.len() == 4 && id.is_ascii() {
+    if let Some(local_id) = id.strip_prefix(['K', 'P']) {
+      if exists(local_id) {
+        return Some(local_id);
+      }
+    }
+  }
+  None
+}
 
-  fn envelope(&self) -> Self::Envelope {
-    Self::Envelope::from_point([self.coord.x, self.coord.y])
+/// Put the facilities with names that start with the search term first, then the other name
+/// matches, then the city matches. Keep only the best matches. Returns the total number of
+/// matches.
+/// - `infos`: facilities matching the search term
+/// - `term`: upper case search term
+/// - `limit`: maximum number of matches to keep
+fn rank_matches(infos: &mut Vec<Facility>, term: &str, limit: Option<usize>) -> usize {
+  // The sort is stable, so the matches keep their order within each group.
+  infos.sort_by_cached_key(|info| {
+    let name = info.name().to_uppercase();
+    if name.starts_with(term) {
+      0
+    } else if name.contains(term) {
+      1
+    } else {
+      2
+    }
+  });
+  let total = infos.len();
+  if let Some(limit) = limit {
+    infos.truncate(limit);
   }
+  total
 }
 
-impl rstar::PointDistance for LocIdx {
-  fn distance_2(
-    &self,
-    point: &<Self::Envelope as rstar::Envelope>::Point,
-  ) -> <<Self::Envelope as rstar::Envelope>::Point as rstar::Point>::Scalar {
-    let dx = point[0] - self.coord.x;
-    let dy = point[1] - self.coord.y;
-    dx * dx + dy * dy
-  }
+/// Join a city and state (e.g. "SAN JOSE, CA"), leaving out the blank parts.
+fn join_location(city: &str, state: &str) -> String {
+  [city, state]
+    .into_iter()
+    .filter(|text| !text.is_empty())
+    .collect::<Vec<_>>()
+    .join(", ")
 }
 
-/// Airport information.
-#[derive(Debug)]
-pub struct AirportInfo {
-  /// Feature record ID.
-  #[allow(unused)]
-  pub fid: u64,
+/// US state, district and territory codes.
+const STATE_CODES: [&str; 56] = [
+  "AK", "AL", "AR", "AS", "AZ", "CA", "CO", "CT", "DC", "DE", "FL", "GA", "GU", "HI", "IA", "ID",
+  "IL", "IN", "KS", "KY", "LA", "MA", "MD", "ME", "MI", "MN", "MO", "MP", "MS", "MT", "NC", "ND",
+  "NE", "NH", "NJ", "NM", "NV", "NY", "OH", "OK", "OR", "PA", "PR", "RI", "SC", "SD", "TN", "TX",
+  "UT", "VA", "VI", "VT", "WA", "WI", "WV", "WY",
+];
+
+/// Airport search term split into its parts, in upper case.
+///
+/// - "city:" starts the city, which runs to the end of the term or the next prefix
+/// - "state:" is followed by a state code
+/// - a trailing state code after other words is the same as "state:"
+///
+/// For example, "reid state:CA", "city:san jose" or "santa monica ca".
+#[derive(Debug, Default, PartialEq)]
+struct SearchQuery {
+  /// Text to find in an airport's name or city.
+  text: String,
+
+  /// Text to find in an airport's city.
+  city: Option<String>,
+
+  /// Airport's state code.
+  state: Option<String>,
+}
 
-  /// Airport ID.
-  pub id: String,
+impl SearchQuery {
+  const CITY_PREFIX: &'static str = "CITY:";
+  const STATE_PREFIX: &'static str = "STATE:";
 
-  /// Airport name.
-  pub name: String,
+  /// Parse a search term. Prefixes are case insensitive.
+  /// - `term`: search term
+  fn parse(term: &str) -> Self {
+    #[derive(PartialEq)]
+    enum Part {
+      Text,
+      City,
+      State,
+    }
 
-  /// Coordinate in decimal degrees (NAD 83).
-  pub coord: util::Coord,
+    let term = term.to_uppercase();
+    let mut text = Vec::new();
+    let mut city = Vec::new();
+    let mut state = None;
+    let mut part = Part::Text;
+    for token in term.split_whitespace() {
+      let token = if let Some(rest) = token.strip_prefix(Self::CITY_PREFIX) {
+        part = Part::City;
+        rest
+      } else if let Some(rest) = token.strip_prefix(Self::STATE_PREFIX) {
+        part = Part::State;
+        rest
+      } else {
+        token
+      };
 
-  /// Airport type.
-  pub airport_type: AirportType,
+      if token.is_empty() {
+        continue;
+      }
 
-  /// Airport usage.
-  pub airport_use: AirportUse,
+      match part {
+        Part::Text => text.push(token),
+        Part::City => city.push(token),
+        Part::State => {
+          state = Some(token.to_owned());
+          part = Part::Text;
+        }
+      }
+    }
 
-  /// Short description for UI lists.
-  pub desc: String,
-}
+    // A trailing state code needs something before it, otherwise it's part of a name.
+    if state.is_none() {
+      let words = if part == Part::City {
+        &mut city
+      } else {
+        &mut text
+      };
 
-impl AirportInfo {
-  fn new(feature: vector::Feature) -> Option<Self> {
-    let mut info = Self {
-      fid: feature.fid()?,
-      id: feature.get_string(AirportInfo::AIRPORT_ID)?,
-      name: feature.get_string(AirportInfo::AIRPORT_NAME)?,
-      coord: feature.get_coord()?,
-      airport_type: feature.get_airport_type()?,
-      airport_use: feature.get_airport_use()?,
-      desc: String::new(),
-    };
+      if words.len() > 1 && words.last().is_some_and(|word| STATE_CODES.contains(word)) {
+        state = words.pop().map(str::to_owned);
+      }
+    }
 
-    info.desc = format!(
-      "{} ({}), {}, {}",
-      info.short_name(),
-      info.id,
-      info.airport_type.abv(),
-      info.airport_use.abv()
-    );
+    Self {
+      text: text.join(" "),
+      city: (!city.is_empty()).then(|| city.join(" ")),
+      state,
+    }
+  }
 
-    Some(info)
+  /// True if the query has a city or state filter.
+  fn is_filtered(&self) -> bool {
+    self.city.is_some() || self.state.is_some()
   }
 
-  /// Returns a potentially shortened airport name.
-  pub fn short_name(&self) -> &str {
-    // Attempt to shorten the name by removing extra stuff.
-    if let Some(name) = self.name.split(['/', '(']).next() {
-      return name.trim_end();
+  /// Check if an airport matches the query.
+  /// - `name`: airport name
+  /// - `city`: airport city
+  /// - `state`: airport state code
+  fn matches(&self, name: &str, city: &str, state: &str) -> bool {
+    if self.text.is_empty() && !self.is_filtered() {
+      return false;
     }
-    &self.name
-  }
 
-  /// Returns true if this is a non-public heliport.
-  pub fn non_public_heliport(&self) -> bool {
-    self.airport_type == AirportType::Helicopter && self.airport_use != AirportUse::Public
-  }
+    if self.state.as_ref().is_some_and(|code| code != state) {
+      return false;
+    }
 
-  const AIRPORT_ID: &'static str = "ARPT_ID";
-  const AIRPORT_NAME: &'static str = "ARPT_NAME";
+    if let Some(text) = &self.city {
+      if !city.contains(text.as_str()) {
+        return false;
+      }
+    }
+
+    name.contains(&self.text) || city.contains(&self.text)
+  }
 }
 
 trait GetF64 {
@@ -598,13 +2710,46 @@ impl GetF64 for vector::Feature<'_> {
     match self.field_as_double_by_name(field) {
       Ok(val) => val,
       Err(err) => {
-        println!("{err}");
+        log::warn!("{err}");
         None
       }
     }
   }
 }
 
+trait GetNumber {
+  fn get_number(&self, field: &str) -> Option<f64>;
+}
+
+impl GetNumber for vector::Feature<'_> {
+  fn get_number(&self, field: &str) -> Option<f64> {
+    // Numeric fields are sometimes blank, which shouldn't be read as zero.
+    parse_number(&self.get_string(field)?)
+  }
+}
+
+/// Parse a numeric field, ignoring blank text.
+fn parse_number(text: &str) -> Option<f64> {
+  text.trim().parse().ok().filter(|val: &f64| val.is_finite())
+}
+
+trait GetMagVar {
+  fn get_mag_var(&self) -> Option<f64>;
+}
+
+impl GetMagVar for vector::Feature<'_> {
+  /// Get the magnetic variation in degrees, with east positive.
+  fn get_mag_var(&self) -> Option<f64> {
+    let mag_var = self.get_number(AirportDetail::MAG_VARN)?;
+    let hemis = self.get_string(AirportDetail::MAG_HEMIS);
+    Some(if hemis.as_deref() == Some("W") {
+      -mag_var
+    } else {
+      mag_var
+    })
+  }
+}
+
 trait GetString {
   fn get_string(&self, field: &str) -> Option<String>;
 }
@@ -614,13 +2759,89 @@ impl GetString for vector::Feature<'_> {
     match self.field_as_string_by_name(field) {
       Ok(val) => val,
       Err(err) => {
-        println!("{err}");
+        log::warn!("{err}");
         None
       }
     }
   }
 }
 
+/// Set of the common fuel types, which search results can be filtered on.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FuelTypes(u8);
+
+impl FuelTypes {
+  pub const AVGAS: Self = Self(1 << 0);
+  pub const JET_A: Self = Self(1 << 1);
+  pub const MOGAS: Self = Self(1 << 2);
+
+  /// Each fuel type with its label.
+  pub const ALL: [(Self, &'static str); 3] = [
+    (Self::AVGAS, "100LL"),
+    (Self::JET_A, "JET A"),
+    (Self::MOGAS, "MOGAS"),
+  ];
+
+  /// Parse the NASR fuel types (e.g. "100LL,A"). The lists are loosely formatted, with stray
+  /// spaces and run together codes such as "100LLA". Other fuels (e.g. "UL94") are left out.
+  /// - `text`: fuel types text
+  pub fn parse(text: &str) -> Self {
+    let mut fuel = Self::default();
+    for code in text.split([',', ' ']).map(str::trim) {
+      let code = code.to_uppercase();
+      let code = match code.strip_prefix("100LL") {
+        Some(rest) => {
+          fuel.insert(Self::AVGAS);
+          rest
+        }
+        None => &code,
+      };
+
+      // Jet A variants include "A1", "A+" and "A1+".
+      if matches!(code, "A" | "A1" | "A+" | "A++" | "A1+" | "JETA") {
+        fuel.insert(Self::JET_A);
+      } else if matches!(code, "MOGAS" | "MOGS" | "MOG") {
+        fuel.insert(Self::MOGAS);
+      }
+    }
+    fuel
+  }
+
+  /// True if there are no fuel types.
+  pub fn is_empty(self) -> bool {
+    self.0 == 0
+  }
+
+  /// True if all the fuel types in `other` are included.
+  /// - `other`: fuel types to check
+  pub fn contains(self, other: Self) -> bool {
+    self.0 & other.0 == other.0
+  }
+
+  /// Add fuel types.
+  /// - `other`: fuel types to add
+  pub fn insert(&mut self, other: Self) {
+    self.0 |= other.0;
+  }
+
+  /// Add fuel types if they're not all included, otherwise remove them.
+  /// - `other`: fuel types to toggle
+  pub fn toggle(&mut self, other: Self) {
+    if self.contains(other) {
+      self.0 &= !other.0;
+    } else {
+      self.0 |= other.0;
+    }
+  }
+
+  /// Fuel type labels (e.g. "100LL, JET A").
+  pub fn text(self) -> String {
+    let labels = Self::ALL.iter().filter(|(fuel, _)| self.contains(*fuel));
+    let labels: Vec<&str> = labels.map(|(_, label)| *label).collect();
+    labels.join(", ")
+  }
+}
+
 #[derive(Eq, Debug, PartialEq)]
 pub enum AirportType {
   Airport,
@@ -643,6 +2864,18 @@ impl AirportType {
       Self::Ultralight => "U",
     }
   }
+
+  /// Airport type description.
+  pub fn text(&self) -> &'static str {
+    match *self {
+      Self::Airport => "Airport",
+      Self::Balloon => "Balloonport",
+      Self::Glider => "Gliderport",
+      Self::Helicopter => "Heliport",
+      Self::Seaplane => "Seaplane Base",
+      Self::Ultralight => "Ultralight",
+    }
+  }
 }
 
 trait GetAirportType {
@@ -685,6 +2918,18 @@ impl AirportUse {
       Self::Public => "PUB",
     }
   }
+
+  /// Airport use description.
+  pub fn text(&self) -> &'static str {
+    match *self {
+      Self::AirForce => "Air Force",
+      Self::Army => "Army",
+      Self::CoastGuard => "Coast Guard",
+      Self::Navy => "Navy",
+      Self::Private => "Private",
+      Self::Public => "Public",
+    }
+  }
 }
 
 trait GetAirportUse {
@@ -720,3 +2965,965 @@ impl GetCoord for vector::Feature<'_> {
     })
   }
 }
+
+#[cfg(test)]
+mod test {
+  use crate::util;
+  use eframe::egui;
+  use std::{env, ffi, fs, path, thread, time};
+
+  /// Wait for a reply that matches.
+  fn wait_reply<T>(
+    reader: &super::AirportReader,
+    mut f: impl FnMut(super::AirportReply) -> Option<T>,
+  ) -> T {
+    let start = time::Instant::now();
+    while start.elapsed() < time::Duration::from_secs(10) {
+      for reply in reader.get_replies_budgeted(usize::MAX) {
+        if let Some(val) = f(reply) {
+          return val;
+        }
+      }
+      thread::sleep(time::Duration::from_millis(10));
+    }
+    panic!("Timed out waiting for a reply");
+  }
+
+  /// Open the airport data in the NASR zip file named by the `AVIATE_NASR_ZIP` environment
+  /// variable.
+  fn open_nasr_zip() -> super::Database {
+    let zip = env::var("AVIATE_NASR_ZIP").expect("AVIATE_NASR_ZIP is not set");
+    open_zip(path::Path::new(&zip))
+  }
+
+  /// Open the airport data in a NASR zip file.
+  /// - `zip`: NASR zip file path
+  fn open_zip(zip: &path::Path) -> super::Database {
+    let util::ZipInfo::Aero { csv, .. } = util::get_zip_info(zip).unwrap() else {
+      panic!("{} is not a NASR zip file", zip.display());
+    };
+
+    super::Database::open(csv.vsi_file(zip, "APT_BASE.csv").unwrap()).unwrap()
+  }
+
+  /// Write a file through GDAL's virtual file system, which can add files to a zip file.
+  /// - `path`: VSI file path
+  /// - `data`: file contents
+  fn write_vsi_file(path: &path::Path, data: &[u8]) {
+    let path = ffi::CString::new(path.to_str().unwrap()).unwrap();
+    let mode = ffi::CString::new("wb").unwrap();
+    unsafe {
+      let handle = gdal_sys::VSIFOpenL(path.as_ptr(), mode.as_ptr());
+      assert!(!handle.is_null());
+      let count = gdal_sys::VSIFWriteL(data.as_ptr().cast(), 1, data.len(), handle);
+      gdal_sys::VSIFCloseL(handle);
+      assert!(count == data.len());
+    }
+  }
+
+  /// Create a miniature NASR zip file in the temp folder. It has the same layout as the FAA
+  /// download, with the CSV files in a zip file within the NASR zip file. The data is a handful of
+  /// facilities around Los Angeles (see `MINI_NASR_FILES`), two of which are off the
+  /// `MINI_NASR_PROJ4` chart.
+  /// - `name`: file name
+  fn create_mini_nasr(name: &str) -> path::PathBuf {
+    let folder = env::temp_dir();
+    let csv_zip = folder.join(format!("{name}_CSV.zip"));
+    let nasr_zip = folder.join(name).with_extension("zip");
+    fs::remove_file(&csv_zip).ok();
+    fs::remove_file(&nasr_zip).ok();
+
+    let vsi = util::vsi_path("/vsizip/", &csv_zip).unwrap();
+    for (file, text) in MINI_NASR_FILES {
+      write_vsi_file(&vsi.join(file), text.as_bytes());
+    }
+
+    let data = fs::read(&csv_zip).unwrap();
+    let vsi = util::vsi_path("/vsizip/", &nasr_zip).unwrap();
+    write_vsi_file(&vsi.join("CSV_Data/16_May_2024_CSV.zip"), &data);
+    fs::remove_file(csv_zip).ok();
+    nasr_zip
+  }
+
+  /// Create and open a miniature NASR zip file with the chart set. Returns the database and the
+  /// zip file path, which should be removed when the test is done.
+  /// - `name`: file name
+  fn open_mini_nasr(name: &str) -> (super::Database, path::PathBuf) {
+    let path = create_mini_nasr(name);
+    let mut database = open_zip(&path);
+    let bounds = util::Bounds {
+      min: util::Coord {
+        x: -1.0e5,
+        y: -1.0e5,
+      },
+      max: util::Coord { x: 1.0e5, y: 1.0e5 },
+    };
+    database.set_chart(MINI_NASR_PROJ4, bounds).unwrap();
+    (database, path)
+  }
+
+  /// Chart spatial reference for the miniature NASR data, centered near Santa Monica.
+  const MINI_NASR_PROJ4: &str = "+proj=lcc +lat_0=34.1 +lon_0=-118.45 +lat_1=38.6666666666667 \
+                                 +lat_2=33.3333333333333 +x_0=0 +y_0=0 +datum=NAD83 +units=m \
+                                 +no_defs";
+
+  /// Miniature NASR CSV files, using the FAA column names.
+  const MINI_NASR_FILES: [(&str, &str); 6] = [
+    (
+      "APT_BASE.csv",
+      "EFF_DATE,SITE_NO,SITE_TYPE_CODE,STATE_CODE,ARPT_ID,CITY,ARPT_NAME,OWNERSHIP_TYPE_CODE,\
+       FACILITY_USE_CODE,LAT_DECIMAL,LONG_DECIMAL,ELEV,MAG_VARN,MAG_HEMIS,FUEL_TYPES,ICAO_ID\n\
+       2024/05/16,02366.*A,A,CA,SMO,SANTA MONICA,SANTA MONICA MUNI,PU,PU,34.0158,-118.4513,177,\
+       12,E,\"100LL,A\",KSMO\n\
+       2024/05/16,02352.*A,A,CA,VNY,VAN NUYS,VAN NUYS,PU,PU,34.2098,-118.4900,802,12,E,\
+       \"100LL,A\",KVNY\n\
+       2024/05/16,01972.*A,A,CA,BUR,BURBANK,BOB HOPE,PU,PU,34.2007,-118.3587,778,12,E,A,KBUR\n\
+       2024/05/16,01818.*A,A,CA,LAX,LOS ANGELES,LOS ANGELES INTL,PU,PU,33.9425,-118.4081,128,12,\
+       E,A,KLAX\n\
+       2024/05/16,02017.*A,A,CA,HHR,HAWTHORNE,JACK NORTHROP FIELD/HAWTHORNE MUNI,PU,PU,33.9228,\
+       -118.3352,66,12,E,100LL,KHHR\n\
+       2024/05/16,02370.*A,A,CA,WHP,LOS ANGELES,WHITEMAN,PU,PU,34.2593,-118.4134,1003,12,E,100LL,\
+       KWHP\n\
+       2024/05/16,25143.*H,H,CA,CA11,LOS ANGELES,SUNSET HELIPORT,PR,PR,34.0900,-118.4000,410,,,,\n\
+       2024/05/16,02361.*A,A,CA,SZP,SANTA PAULA,SANTA PAULA,PR,PU,34.3472,-119.0610,250,13,E,\
+       100LL,KSZP\n\
+       2024/05/16,02290.*A,A,CA,SBA,SANTA BARBARA,SANTA BARBARA MUNI,PU,PU,34.4262,-119.8404,13,\
+       13,E,\"100LL,A\",KSBA\n\
+       2024/05/16,02093.*A,A,CA,PSP,PALM SPRINGS,PALM SPRINGS INTL,PU,PU,33.8297,-116.5067,477,\
+       12,E,\"100LL,A\",KPSP\n",
+    ),
+    (
+      "APT_RWY.csv",
+      "EFF_DATE,SITE_NO,ARPT_ID,RWY_ID,RWY_LEN,RWY_WIDTH,SURFACE_TYPE_CODE,COND\n\
+       2024/05/16,02366.*A,SMO,03/21,3500,150,ASPH,G\n\
+       2024/05/16,02352.*A,VNY,16R/34L,8001,150,CONC,E\n\
+       2024/05/16,02352.*A,VNY,16L/34R,4011,75,ASPH,G\n\
+       2024/05/16,02361.*A,SZP,04/22,2650,60,ASPH,F\n\
+       2024/05/16,25143.*H,CA11,H1,40,40,ROOF-TOP,\n",
+    ),
+    (
+      "APT_RWY_END.csv",
+      "EFF_DATE,SITE_NO,ARPT_ID,RWY_ID,RWY_END_ID,TRUE_ALIGNMENT,RIGHT_HAND_TRAFFIC_PAT_FLAG,\
+       RWY_END_ELEV,DISPLACED_THR_LEN,OBSTN_TYPE,OBSTN_HGT,DIST_FROM_THR,CNTRLN_OFFSET,\
+       CNTRLN_DIR_CODE\n\
+       2024/05/16,02366.*A,SMO,03/21,03,47,N,175.4,,,,,,\n\
+       2024/05/16,02366.*A,SMO,03/21,21,227,Y,153.2,300,TREES,40,1200,100,L\n\
+       2024/05/16,02352.*A,VNY,16R/34L,16R,163,N,802.1,,,,,,\n\
+       2024/05/16,02352.*A,VNY,16R/34L,34L,343,N,770.9,,,,,,\n\
+       2024/05/16,02352.*A,VNY,16L/34R,16L,163,Y,795.3,,,,,,\n\
+       2024/05/16,02352.*A,VNY,16L/34R,34R,343,N,771.8,,POLE,25,500,0,B\n",
+    ),
+    (
+      "FRQ.csv",
+      "EFF_DATE,FACILITY,SERVICED_FACILITY,TOWER_OR_COMM_CALL,FREQ,SECTORIZATION,FREQ_USE\n\
+       2024/05/16,SMO,SMO,SANTA MONICA,120.1,,LCL/P\n\
+       2024/05/16,SMO,SMO,SANTA MONICA,132.65,,GND/P\n\
+       2024/05/16,VNY,VNY,VAN NUYS,119.3,,LCL/P\n",
+    ),
+    (
+      "APT_RMK.csv",
+      "EFF_DATE,SITE_NO,ARPT_ID,REF_COL_NAME,REMARK\n\
+       2024/05/16,02366.*A,SMO,GENERAL_REMARK,\"NOISE ABATEMENT PROCEDURES IN EFFECT, CALL \
+       AIRPORT FOR DETAILS.\"\n\
+       2024/05/16,02366.*A,SMO,RWY_END_ID,RWY 21 DSPLCD THR 300 FT.\n",
+    ),
+    (
+      "NAV_BASE.csv",
+      "EFF_DATE,NAV_ID,NAV_TYPE,STATE_CODE,CITY,NAME,LAT_DECIMAL,LONG_DECIMAL,FREQ\n\
+       2024/05/16,SMO,VOR/DME,CA,SANTA MONICA,SANTA MONICA,34.0103,-118.4569,110.8\n\
+       2024/05/16,LAX,VORTAC,CA,LOS ANGELES,LOS ANGELES,33.9331,-118.4320,113.6\n\
+       2024/05/16,SLI,VORTAC,CA,LOS ALAMITOS,SEAL BEACH,33.7834,-118.0533,115.7\n\
+       2024/05/16,VAN,NDB,CA,VAN NUYS,VAN NUYS,34.2200,-118.4900,379\n",
+    ),
+  ];
+
+  #[test]
+  #[ignore = "requires a NASR zip file (AVIATE_NASR_ZIP)"]
+  fn test_database_detail() {
+    let mut database = open_nasr_zip();
+    assert!(database.airport(" pao").unwrap().id == "PAO");
+    assert!(database.airport("NOT AN ID").is_err());
+
+    let detail = database.detail("PAO").unwrap();
+    let runway = detail.runways.iter().find(|runway| runway.id == "13/31");
+    assert!(runway.unwrap().length == Some(2443.0));
+    assert!(!detail.frequencies.is_empty());
+  }
+
+  #[test]
+  #[ignore = "requires a NASR zip file (AVIATE_NASR_ZIP)"]
+  fn test_database_search() {
+    use super::Facility;
+
+    let mut database = open_nasr_zip();
+
+    // Searching needs a chart.
+    assert!(database.search("PAO", false, None).is_err());
+    assert!(database.find("PALO ALTO", false).is_empty());
+
+    let proj4 = "+proj=lcc +lat_0=38.0 +lon_0=-122.0 +lat_1=33.3333333333333 \
+                 +lat_2=38.6666666666667 +x_0=0 +y_0=0 +datum=NAD83 +units=m +no_defs";
+    let bounds = util::Bounds {
+      min: util::Coord {
+        x: -4.0e5,
+        y: -4.0e5,
+      },
+      max: util::Coord { x: 4.0e5, y: 4.0e5 },
+    };
+    database.set_chart(proj4, bounds).unwrap();
+    assert!(database.has_chart());
+
+    let result = database.search("pao", false, None).unwrap();
+    let exact = result.exact.and_then(Facility::into_airport);
+    assert!(exact.is_some_and(|info| info.id == "PAO"));
+
+    let infos = database.find("PALO ALTO", false);
+    assert!(infos.iter().any(|info| info.id == "PAO"));
+
+    // Name matches are limited.
+    let result = database.search("COUNTY", false, Some(3)).unwrap();
+    assert!(result.matches.len() == 3 && result.total > 3);
+
+    // Nothing in Los Angeles is on this chart.
+    assert!(database.search("LAX", false, None).is_err());
+
+    database.clear_chart();
+    assert!(!database.has_chart());
+  }
+
+  #[test]
+  fn test_mini_nasr_lookup() {
+    use super::{AirportType, AirportUse};
+
+    let (database, path) = open_mini_nasr("aviate_mini_nasr_lookup");
+    assert!(database.airports.eff_date == util::Date::new(2024, 5, 16));
+
+    let info = database.airport(" smo").unwrap();
+    assert!(info.name == "SANTA MONICA MUNI" && info.fuel_types == "100LL,A");
+    assert!(info.airport_type == AirportType::Airport && info.airport_use == AirportUse::Public);
+    assert!(info.desc == "SANTA MONICA MUNI (SMO), A, PUB");
+    assert!(info.elevation_text(util::Units::Aviation).as_deref() == Some("EL 177 ft"));
+    assert!(info.runway.is_none());
+
+    // Names are shortened for lists.
+    let info = database.airport("HHR").unwrap();
+    assert!(info.short_name() == "JACK NORTHROP FIELD");
+
+    // Private heliport with the type and use text.
+    let info = database.airport("CA11").unwrap();
+    assert!(info.non_public_heliport() && !info.included(false));
+    assert!(info.airport_type.text() == "Heliport" && info.airport_use.text() == "Private");
+    assert!(info.desc == "SUNSET HELIPORT (CA11), H, PVT");
+
+    let info = database.airport("VNY").unwrap();
+    assert!(info.elevation_text(util::Units::Metric).as_deref() == Some("EL 244 m"));
+
+    // Off the chart, but ID lookups ignore the chart bounds.
+    assert!(database.airport("PSP").is_ok());
+    assert!(database.airport("KSMO").is_err());
+
+    let info = database.navaid("smo").unwrap();
+    assert!(info.desc == "SANTA MONICA (SMO), VOR/DME 110.8");
+    assert!(database.navaid("VNY").is_err());
+
+    drop(database);
+    fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_mini_nasr_search() {
+    use super::Facility;
+
+    let (database, path) = open_mini_nasr("aviate_mini_nasr_search");
+    let search = |term: &str, nph: bool| database.search(term, nph, None).unwrap();
+    fn names(infos: &[Facility]) -> Vec<&str> {
+      infos.iter().map(Facility::name).collect()
+    }
+
+    // ID matches, including the ICAO form.
+    let result = search("KSMO", false);
+    let Some(Facility::Airport(info)) = result.exact else {
+      panic!("KSMO not found");
+    };
+    assert!(info.id == "SMO" && info.desc == "SANTA MONICA MUNI (SMO / KSMO), A, PUB");
+    assert!(result.matches.is_empty() && result.total == 0);
+
+    // Airport IDs take precedence over navaid IDs.
+    let Some(Facility::Airport(info)) = search("LAX", false).exact else {
+      panic!("LAX not found");
+    };
+    assert!(info.id == "LAX");
+
+    let Some(Facility::Navaid(info)) = search("sli", false).exact else {
+      panic!("SLI not found");
+    };
+    assert!(info.id == "SLI" && info.nav_type == "VORTAC");
+
+    // An ID that's also part of a name finds both, without repeating the ID match.
+    let result = search("VAN", false);
+    let Some(Facility::Navaid(info)) = &result.exact else {
+      panic!("VAN not found");
+    };
+    assert!(info.id == "VAN" && info.nav_type == "NDB");
+    assert!(result.total == 1 && result.matches[0].airport().is_some());
+    assert!(names(&result.matches) == ["VAN NUYS"]);
+
+    // Santa Barbara is off the chart.
+    let result = search("santa", false);
+    let found = names(&result.matches);
+    assert!(result.exact.is_none() && result.total == 3);
+    assert!(found.contains(&"SANTA PAULA") && !found.contains(&"SANTA BARBARA MUNI"));
+    assert!(found.iter().filter(|name| **name == "SANTA MONICA").count() == 1);
+    assert!(database.search("SBA", false, None).is_err());
+
+    // City matches follow the name matches.
+    let result = search("los angeles", false);
+    let expected = ["LOS ANGELES INTL", "LOS ANGELES", "WHITEMAN"];
+    assert!(result.total == 3 && names(&result.matches) == expected);
+
+    // City and state filters leave out the navaids.
+    let result = search("city:Los Angeles", false);
+    assert!(names(&result.matches) == ["LOS ANGELES INTL", "WHITEMAN"]);
+
+    // The description includes the city and state.
+    let result = search("santa monica ca", false);
+    let desc = "SANTA MONICA MUNI (SMO), A, PUB • SANTA MONICA, CA • 100LL, JET A • 3,500 ft hard";
+    assert!(result.matches.len() == 1 && result.matches[0].desc() == desc);
+    assert!(database.search("Van State:ca", false, None).is_ok());
+    assert!(database.search("van state:NV", false, None).is_err());
+
+    // Non-public heliports are only found when asked for.
+    assert!(database.search("SUNSET", false, None).is_err());
+    let result = search("SUNSET", true);
+    assert!(result.matches.len() == 1 && result.matches[0].heliport());
+
+    // Find as you type puts the ID match first.
+    let infos = database.find("smo", false);
+    assert!(infos.first().is_some_and(|info| info.id == "SMO"));
+    let infos = database.find("VAN", false);
+    assert!(infos.len() == 1 && infos[0].id == "VNY");
+
+    drop(database);
+    fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_mini_nasr_nearby() {
+    use super::Facility;
+
+    let (database, path) = open_mini_nasr("aviate_mini_nasr_nearby");
+
+    // Near Santa Monica. Airports are sorted by description, followed by the navaids.
+    let coord = util::Coord { x: 0.0, y: -9300.0 };
+    let infos = database.nearby(coord, 12000.0);
+    let ids: Vec<&str> = infos
+      .iter()
+      .map(|info| match info {
+        Facility::Airport(info) => info.id.as_str(),
+        Facility::Navaid(info) => info.id.as_str(),
+      })
+      .collect();
+    assert!(ids == ["LAX", "SMO", "CA11", "LAX", "SMO"]);
+    assert!(infos[2].heliport() && infos[3].airport().is_none());
+
+    // Runway summaries are added to the descriptions.
+    let smo = infos[1].airport().unwrap();
+    let runway = smo.runway.unwrap();
+    assert!(runway.length == 3500 && runway.hard);
+    assert!(smo.desc == "SANTA MONICA MUNI (SMO), A, PUB • 3,500 ft hard");
+    assert!(infos[0].airport().unwrap().runway.is_none());
+
+    // Nearest public airports, closest first.
+    let infos = database.nearest(coord);
+    let ids: Vec<&str> = infos.iter().map(|(info, _)| info.id.as_str()).collect();
+    assert!(ids.len() == 5 && ids[..3] == ["SMO", "LAX", "HHR"]);
+    assert!(!ids.contains(&"CA11"));
+    assert!(infos.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+
+    drop(database);
+    fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_mini_nasr_runway_ticks() {
+    let (database, path) = open_mini_nasr("aviate_mini_nasr_runway_ticks");
+
+    // Only airports with a numbered runway get a tick.
+    let bounds = util::Bounds {
+      min: util::Coord {
+        x: -1.0e5,
+        y: -1.0e5,
+      },
+      max: util::Coord { x: 1.0e5, y: 1.0e5 },
+    };
+    let ticks = database.runway_ticks(&bounds);
+    let ids: Vec<&str> = ticks.iter().map(|tick| tick.id.as_str()).collect();
+    assert!(ids == ["SMO", "VNY", "SZP"]);
+
+    // Santa Monica's runway 03/21 is 3,500 feet long with 12° east variation.
+    let bounds = util::Bounds {
+      min: util::Coord {
+        x: -3000.0,
+        y: -12300.0,
+      },
+      max: util::Coord {
+        x: 3000.0,
+        y: -6300.0,
+      },
+    };
+    let ticks = database.runway_ticks(&bounds);
+    assert!(ticks.len() == 1 && ticks[0].id == "SMO" && ticks[0].hard);
+
+    let [a, b] = ticks[0].ends;
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length = dx.hypot(dy) / util::METERS_PER_FOOT;
+    assert!((length - 3500.0).abs() < 20.0);
+    assert!((dx.atan2(dy).to_degrees() - 42.0).abs() < 0.5);
+
+    drop(database);
+    fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_mini_nasr_detail() {
+    let (mut database, path) = open_mini_nasr("aviate_mini_nasr_detail");
+
+    let detail = database.detail("smo").unwrap();
+    assert!(detail.info.id == "SMO");
+    assert!(detail.location == "SANTA MONICA, CA");
+    assert!(detail.mag_var.as_deref() == Some("12°E"));
+
+    // Runway ends are joined to their runway.
+    assert!(detail.runways.len() == 1);
+    let runway = &detail.runways[0];
+    assert!(runway.id == "03/21" && runway.surface == "ASPH-G");
+    assert!(runway.length == Some(3500.0) && runway.width == Some(150.0));
+    let ends: Vec<&str> = runway.ends.iter().map(|end| end.id.as_str()).collect();
+    assert!(ends == ["03", "21"]);
+
+    let end = &runway.ends[1];
+    assert!(end.true_alignment == Some(227.0) && end.right_traffic);
+    assert!(end.displaced_threshold == Some(300.0));
+    let obstacle = end.obstacle.as_ref().unwrap();
+    assert!(obstacle.kind == "TREES" && obstacle.height == Some(40.0));
+    assert!(obstacle.offset == Some(-100.0) && !obstacle.both_sides);
+
+    let end = &runway.ends[0];
+    assert!(!end.right_traffic && end.displaced_threshold.is_none() && end.obstacle.is_none());
+
+    let freqs: Vec<&str> = detail.frequencies.iter().map(|f| f.freq.as_str()).collect();
+    assert!(freqs == ["120.1", "132.65"]);
+    assert!(detail.frequencies[0].usage == "LCL/P");
+    assert!(detail.frequencies[0].call == "SANTA MONICA");
+
+    // Quoted fields keep their commas.
+    assert!(detail.remarks.len() == 2);
+    assert!(detail.remarks[0] == "NOISE ABATEMENT PROCEDURES IN EFFECT, CALL AIRPORT FOR DETAILS.");
+
+    // Each runway gets only its own ends.
+    let detail = database.detail("VNY").unwrap();
+    for runway in &detail.runways {
+      assert!(runway.ends.len() == 2);
+      assert!(runway.ends.iter().all(|end| runway.id.contains(&end.id)));
+    }
+    let end = detail.runways[1].ends.iter().find(|end| end.id == "34R");
+    assert!(end.unwrap().obstacle.as_ref().unwrap().both_sides);
+
+    // Airports without related records have empty sections.
+    let detail = database.detail("BUR").unwrap();
+    assert!(detail.runways.is_empty() && detail.frequencies.is_empty());
+    assert!(database.remarks("BUR").unwrap().is_empty());
+    assert!(database.remarks("NOPE").is_err());
+    assert!(database.detail("NOPE").is_err());
+
+    drop(database);
+    fs::remove_file(path).ok();
+  }
+
+  #[test]
+  fn test_spatial_ref_stress() {
+    use super::{AirportReader, AirportReply, Facility, SearchIntent};
+
+    // Two airports on either side of the chart's central meridian.
+    let folder = env::temp_dir().join("aviate_spatial_ref_stress");
+    fs::create_dir_all(&folder).unwrap();
+    let path = folder.join("APT_BASE.csv");
+    let csv = "ARPT_ID,ARPT_NAME,LAT_DECIMAL,LONG_DECIMAL,SITE_TYPE_CODE,OWNERSHIP_TYPE_CODE,\
+               FACILITY_USE_CODE,EFF_DATE\n\
+               WST,WEST FIELD,34.1,-119.0,A,PU,PU,2024/05/16\n\
+               EST,EAST FIELD,34.1,-118.0,A,PU,PU,2024/05/16\n";
+    fs::write(&path, csv).unwrap();
+
+    let proj4 = MINI_NASR_PROJ4;
+    let west = util::Bounds {
+      min: util::Coord {
+        x: -1.0e6,
+        y: -1.0e6,
+      },
+      max: util::Coord { x: 0.0, y: 1.0e6 },
+    };
+    let east = util::Bounds {
+      min: util::Coord { x: 0.0, y: -1.0e6 },
+      max: util::Coord { x: 1.0e6, y: 1.0e6 },
+    };
+
+    let ctx = egui::Context::default();
+    let reader = AirportReader::new(&path, &ctx);
+    let opened = wait_reply(&reader, |reply| match reply {
+      AirportReply::Opened => Some(true),
+      AirportReply::OpenError(_) => Some(false),
+      _ => None,
+    });
+    assert!(opened);
+
+    // Rapidly alternate between the two charts, ending on the west one.
+    for index in 0..50 {
+      let bounds = if index % 2 == 0 { &east } else { &west };
+      reader.set_spatial_ref(proj4.into(), bounds.clone());
+    }
+
+    // Each chart contains one of the airports.
+    let count = wait_reply(&reader, |reply| match reply {
+      AirportReply::Indexed(count) => Some(count),
+      _ => None,
+    });
+    assert!(count == 1);
+
+    // Requests are handled in order, so these see the last spatial reference.
+    reader.find("FIELD".into(), false);
+    let ids: Vec<String> = wait_reply(&reader, |reply| match reply {
+      AirportReply::Find(_, infos) => Some(infos.into_iter().map(|info| info.id).collect()),
+      _ => None,
+    });
+    assert!(ids == vec![String::from("WST")]);
+    assert!(reader.airport_spatial_idx() && !reader.is_indexing());
+
+    // Searches reply with the ID match and the name matches, along with the intent.
+    reader.search(1, "WST".into(), false, false, SearchIntent::Info);
+    let (intent, result) = wait_reply(&reader, |reply| match reply {
+      AirportReply::Search(1, intent, result) => Some((intent, result)),
+      _ => None,
+    });
+    let exact = result.exact.and_then(Facility::into_airport);
+    assert!(intent == SearchIntent::Info && exact.is_some_and(|info| info.id == "WST"));
+    assert!(result.matches.is_empty());
+
+    let coord = util::Coord { x: 0.0, y: 0.0 };
+    reader.nearby(1, coord, 1.0e6);
+    let ids: Vec<String> = wait_reply(&reader, |reply| match reply {
+      AirportReply::Nearby(1, infos) => Some(
+        infos
+          .into_iter()
+          .filter_map(Facility::into_airport)
+          .map(|info| info.id)
+          .collect(),
+      ),
+      _ => None,
+    });
+    assert!(ids == vec![String::from("WST")]);
+
+    // Clearing the spatial reference leaves no spatial index.
+    reader.clear_spatial_ref();
+    reader.nearby(2, coord, 1.0e6);
+    let count = wait_reply(&reader, |reply| match reply {
+      AirportReply::Nearby(2, infos) => Some(infos.len()),
+      _ => None,
+    });
+    assert!(count == 0 && !reader.airport_spatial_idx());
+  }
+
+  #[test]
+  fn test_shutdown() {
+    use super::AirportReader;
+    use std::{process, sync::mpsc};
+
+    let folder = env::temp_dir().join(format!("aviate_shutdown_{}", process::id()));
+    fs::create_dir_all(&folder).unwrap();
+    let path = folder.join("APT_BASE.csv");
+    let mut csv = String::from(
+      "ARPT_ID,ARPT_NAME,LAT_DECIMAL,LONG_DECIMAL,SITE_TYPE_CODE,OWNERSHIP_TYPE_CODE,\
+       FACILITY_USE_CODE,EFF_DATE\n",
+    );
+    for index in 0..100 {
+      csv += &format!("X{index},FIELD {index},34.1,-118.0,A,PU,PU,2024/05/16\n");
+    }
+    fs::write(&path, csv).unwrap();
+
+    // Hold the indexing at its first record until the reader has been shut down.
+    const TIMEOUT: time::Duration = time::Duration::from_secs(10);
+    let (started_tx, started_rx) = mpsc::channel();
+    let (resume_tx, resume_rx) = mpsc::channel::<()>();
+    let hook = move || {
+      started_tx.send(()).ok();
+      resume_rx.recv_timeout(TIMEOUT).ok();
+    };
+
+    let ctx = egui::Context::default();
+    let reader = AirportReader::with_abandon_hook(&path, &ctx, hook);
+    started_rx.recv_timeout(TIMEOUT).unwrap();
+    assert!(reader.is_indexing());
+
+    // The thread checks the cancel flag as it goes, so it exits without finishing the indexes.
+    let exited = reader.shutdown(TIMEOUT);
+    drop(resume_tx);
+    assert!(exited.recv().unwrap());
+
+    // It stopped at the first check after the shutdown.
+    assert!(started_rx.try_recv().is_err());
+
+    fs::remove_dir_all(&folder).ok();
+  }
+
+  #[test]
+  fn test_open_error() {
+    use super::{AirportReader, AirportReply};
+
+    let folder = env::temp_dir().join("aviate_open_error");
+    let path = folder.join("APT_BASE.csv");
+    let ctx = egui::Context::default();
+    let reader = AirportReader::new(&path, &ctx);
+    let err = wait_reply(&reader, |reply| match reply {
+      AirportReply::OpenError(err) => Some(err),
+      _ => None,
+    });
+    assert!(err.starts_with("Unable to open airport data source"));
+    assert!(!reader.is_indexing());
+
+    // Requests made after the reader's thread has exited are dropped.
+    reader.find("FIELD".into(), false);
+    reader.set_spatial_ref(String::new(), util::Bounds::default());
+    assert!(reader.request_count() == 0);
+  }
+
+  #[test]
+  fn test_parse_number() {
+    use super::parse_number;
+
+    assert!(parse_number("1250") == Some(1250.0));
+    assert!(parse_number(" 6269.4 ") == Some(6269.4));
+    assert!(parse_number("-210") == Some(-210.0));
+    assert!(parse_number("").is_none());
+    assert!(parse_number("   ").is_none());
+    assert!(parse_number("NaN").is_none());
+  }
+
+  #[test]
+  fn test_fuel_types() {
+    use super::FuelTypes;
+
+    let avgas_jet = FuelTypes::parse("100LL,A");
+    assert!(avgas_jet.contains(FuelTypes::AVGAS) && avgas_jet.contains(FuelTypes::JET_A));
+    assert!(!avgas_jet.contains(FuelTypes::MOGAS));
+    assert!(avgas_jet.text() == "100LL, JET A");
+
+    // Run together, spaced out and lower case lists.
+    assert!(FuelTypes::parse("100LLA") == avgas_jet);
+    assert!(FuelTypes::parse(" 100LL , A1+ ") == avgas_jet);
+    assert!(FuelTypes::parse("a,100ll") == avgas_jet);
+    assert!(FuelTypes::parse("100LL") == FuelTypes::AVGAS);
+    assert!(FuelTypes::parse("A") == FuelTypes::JET_A);
+    assert!(FuelTypes::parse("A++") == FuelTypes::JET_A);
+    assert!(FuelTypes::parse("MOGAS") == FuelTypes::MOGAS);
+    assert!(FuelTypes::parse("100LL,,MOGAS").text() == "100LL, MOGAS");
+
+    // Blank and other fuels.
+    assert!(FuelTypes::parse("").is_empty());
+    assert!(FuelTypes::parse("UL94, 80").is_empty());
+    assert!(FuelTypes::parse("").text().is_empty());
+
+    let mut fuel = FuelTypes::default();
+    fuel.toggle(FuelTypes::JET_A);
+    assert!(fuel == FuelTypes::JET_A);
+    fuel.toggle(FuelTypes::JET_A);
+    assert!(fuel.is_empty());
+    assert!(fuel.contains(FuelTypes::default()));
+  }
+
+  #[test]
+  fn test_local_airport_id() {
+    use super::local_airport_id;
+    use std::collections::HashSet;
+
+    let ids: HashSet<&str> = ["PAO", "ANC", "HNL", "PAQ", "KPAQ", "0Q5", "K00"].into();
+    let exists = |id: &str| ids.contains(id);
+
+    // Exact and K-prefixed IDs.
+    assert!(local_airport_id("PAO", exists) == Some("PAO"));
+    assert!(local_airport_id("KPAO", exists) == Some("PAO"));
+
+    // Alaska and Hawaii.
+    assert!(local_airport_id("PANC", exists) == Some("ANC"));
+    assert!(local_airport_id("PHNL", exists) == Some("HNL"));
+
+    // Both forms exist, so the exact match wins.
+    assert!(local_airport_id("KPAQ", exists) == Some("KPAQ"));
+    assert!(local_airport_id("PAQ", exists) == Some("PAQ"));
+
+    // Only four character IDs are stripped, and only if the stripped form exists.
+    assert!(local_airport_id("KAO", exists).is_none());
+    assert!(local_airport_id("K00", exists) == Some("K00"));
+    assert!(local_airport_id("KSFO", exists).is_none());
+    assert!(local_airport_id("XPAO", exists).is_none());
+  }
+
+  #[test]
+  fn test_rank_matches() {
+    use super::{Facility, NavaidInfo};
+
+    let navaid = |name: &str| {
+      Facility::Navaid(NavaidInfo {
+        id: String::new(),
+        name: name.into(),
+        nav_type: "VOR".into(),
+        freq: None,
+        coord: util::Coord::default(),
+        desc: name.into(),
+      })
+    };
+
+    let names = ["LOS BANOS", "OAKLAND", "OAKDALE", "SAN JOSE", "Oakey"];
+    let mut infos: Vec<_> = names.into_iter().map(navaid).collect();
+    assert!(super::rank_matches(&mut infos, "OAK", None) == 5);
+    let names: Vec<_> = infos.iter().map(Facility::name).collect();
+    assert!(names == ["OAKLAND", "OAKDALE", "Oakey", "LOS BANOS", "SAN JOSE"]);
+
+    let mut infos: Vec<_> = ["LOS BANOS", "OAKLAND", "OAKDALE"].map(navaid).into();
+    assert!(super::rank_matches(&mut infos, "OAK", Some(2)) == 3);
+    let names: Vec<_> = infos.iter().map(Facility::name).collect();
+    assert!(names == ["OAKLAND", "OAKDALE"]);
+  }
+
+  #[test]
+  fn test_search_query() {
+    use super::SearchQuery;
+
+    let query = |text: &str, city: Option<&str>, state: Option<&str>| SearchQuery {
+      text: text.into(),
+      city: city.map(Into::into),
+      state: state.map(Into::into),
+    };
+
+    // Prefixes are case insensitive and can be combined.
+    assert!(SearchQuery::parse("reid") == query("REID", None, None));
+    assert!(SearchQuery::parse("reid state:CA") == query("REID", None, Some("CA")));
+    assert!(SearchQuery::parse("City:san jose") == query("", Some("SAN JOSE"), None));
+    let parsed = SearchQuery::parse("reid city:San Jose STATE: ca");
+    assert!(parsed == query("REID", Some("SAN JOSE"), Some("CA")));
+
+    // A trailing state code filters by state, but not on its own.
+    assert!(SearchQuery::parse("santa monica ca") == query("SANTA MONICA", None, Some("CA")));
+    assert!(SearchQuery::parse("city:san jose ca") == query("", Some("SAN JOSE"), Some("CA")));
+    assert!(SearchQuery::parse("ca") == query("CA", None, None));
+    assert!(SearchQuery::parse("lake xx") == query("LAKE XX", None, None));
+
+    let reid = ("REID-HILLVIEW OF SANTA CLARA COUNTY", "SAN JOSE", "CA");
+    let parsed = SearchQuery::parse("reid state:ca");
+    assert!(parsed.matches(reid.0, reid.1, reid.2));
+    assert!(!parsed.matches(reid.0, reid.1, "NV"));
+
+    // The text also matches the city.
+    let parsed = SearchQuery::parse("san jose");
+    assert!(parsed.matches(reid.0, reid.1, reid.2));
+    assert!(!SearchQuery::parse("city:santa clara").matches(reid.0, reid.1, reid.2));
+    assert!(SearchQuery::parse("state:CA").matches(reid.0, reid.1, reid.2));
+
+    // Nothing to search for.
+    assert!(!SearchQuery::parse("state:").matches(reid.0, reid.1, reid.2));
+  }
+
+  #[test]
+  fn test_take_replies() {
+    use super::{
+      take_replies, AirportDetail, AirportInfo, AirportReply, AirportType, AirportUse, FuelTypes,
+    };
+    use std::sync::mpsc;
+
+    let detail = AirportDetail {
+      info: AirportInfo {
+        fid: 0,
+        id: "PAO".into(),
+        icao_id: Some("KPAO".into()),
+        state: "CA".into(),
+        name: "PALO ALTO".into(),
+        coord: util::Coord::default(),
+        airport_type: AirportType::Airport,
+        airport_use: AirportUse::Public,
+        elevation: None,
+        mag_var: None,
+        fuel_types: String::new(),
+        fuel: FuelTypes::default(),
+        runway: None,
+        desc: "PAO".into(),
+      },
+      location: String::new(),
+      mag_var: None,
+      runways: Vec::new(),
+      frequencies: Vec::new(),
+      remarks: Vec::new(),
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let err = "Unable to find PAX";
+    tx.send(AirportReply::Error(err.into())).unwrap();
+    tx.send(AirportReply::Detail(Box::new(detail))).unwrap();
+    tx.send(AirportReply::Indexed(1)).unwrap();
+
+    // An error followed by a detail both come through, and the rest waits for the next call.
+    let replies = take_replies(&rx, 2);
+    assert!(replies.len() == 2);
+    assert!(matches!(&replies[0], AirportReply::Error(text) if text == err));
+    assert!(matches!(&replies[1], AirportReply::Detail(detail) if detail.info.id == "PAO"));
+
+    let replies = take_replies(&rx, 2);
+    assert!(matches!(replies.as_slice(), [AirportReply::Indexed(1)]));
+    assert!(take_replies(&rx, 2).is_empty());
+  }
+
+  #[test]
+  fn test_runway_summary() {
+    use super::{RelatedSource, RunwaySummary};
+
+    let folder = env::temp_dir().join("aviate_runway_summary");
+    fs::create_dir_all(&folder).unwrap();
+    let path = folder.join("APT_RWY.csv");
+    let csv = "ARPT_ID,RWY_ID,RWY_LEN,SURFACE_TYPE_CODE\n\
+               PAO,13/31,2443,ASPH\n\
+               O88,08/26,2700,TURF\n\
+               O88,17/35,1200,ASPH-CONC\n\
+               CA11,H1,,\n\
+               TIE,09/27,3000,TURF-GRVL\n\
+               TIE,18/36,3000,PEM\n";
+    fs::write(&path, csv).unwrap();
+
+    let source = RelatedSource::open(&path).unwrap();
+    let summaries = RunwaySummary::read_all(&source, &|| false).unwrap();
+    let summary = |length, hard, heading| {
+      let heading = Some(heading);
+      Some(RunwaySummary {
+        length,
+        hard,
+        heading,
+      })
+    };
+    assert!(summaries.get("PAO").copied() == summary(2443, true, 130.0));
+    assert!(summaries.get("O88").copied() == summary(2700, false, 80.0));
+    assert!(summaries.get("TIE").copied() == summary(3000, true, 0.0));
+    assert!(!summaries.contains_key("CA11"));
+    assert!(summaries["O88"].to_string() == "2,700 ft soft");
+
+    // Headings are folded into 0 to 180 degrees.
+    assert!(RunwaySummary::heading("16R/34L") == Some(160.0));
+    assert!(RunwaySummary::heading("31/13") == Some(130.0));
+    assert!(RunwaySummary::heading("NE/SW") == Some(45.0));
+    assert!(RunwaySummary::heading("W/E") == Some(90.0));
+    assert!(RunwaySummary::heading("H1").is_none());
+    assert!(RunwaySummary::heading("37/19").is_none());
+
+    // Reading can be abandoned.
+    assert!(RunwaySummary::read_all(&source, &|| true).is_none());
+  }
+
+  #[test]
+  fn test_frequency_groups() {
+    use super::{Frequency, FrequencyGroup};
+
+    let frequency = |freq: &str, usage: &str, sector: &str| Frequency {
+      freq: freq.into(),
+      usage: usage.into(),
+      call: String::new(),
+      sector: sector.into(),
+    };
+
+    let frequencies = [
+      frequency("124.3", "APCH/P DEP/P", "180-359"),
+      frequency("121.9", "GND/P", ""),
+      frequency("135.65", "D-ATIS", ""),
+      frequency("118.3", "LCL/P", ""),
+      frequency("122.95", "UNICOM", ""),
+      frequency("121.5", "EMERG", ""),
+      frequency("120.35", "APCH/P DEP/P", "360-179"),
+      frequency("121.65", "CD/P", ""),
+      frequency("119.5", "LCL/P", ""),
+      frequency("121.8", "GND/P", ""),
+    ];
+
+    let groups = Frequency::grouped(&frequencies);
+    let order: Vec<(FrequencyGroup, Vec<&str>)> = groups
+      .iter()
+      .map(|(group, list)| (*group, list.iter().map(|f| f.freq.as_str()).collect()))
+      .collect();
+    assert!(
+      order
+        == [
+          (FrequencyGroup::Weather, vec!["135.65"]),
+          (FrequencyGroup::Ctaf, vec!["122.95"]),
+          (FrequencyGroup::Tower, vec!["118.3", "119.5"]),
+          (FrequencyGroup::Ground, vec!["121.8", "121.9"]),
+          (FrequencyGroup::Clearance, vec!["121.65"]),
+          (FrequencyGroup::Approach, vec!["120.35", "124.3"]),
+          (FrequencyGroup::Other, vec!["121.5"]),
+        ]
+    );
+
+    // Sectorized frequencies stay in their group.
+    let (_, approach) = &groups[5];
+    assert!(approach[0].sector == "360-179" && approach[1].sector == "180-359");
+
+    assert!(FrequencyGroup::from_usage("awos-3") == FrequencyGroup::Weather);
+    assert!(FrequencyGroup::from_usage("CTAF") == FrequencyGroup::Ctaf);
+    assert!(FrequencyGroup::from_usage("") == FrequencyGroup::Other);
+  }
+
+  #[test]
+  fn test_included() {
+    use super::{AirportInfo, AirportType, AirportUse, Facility, FuelTypes, NavaidInfo};
+
+    let airport = |id: &str, airport_type, airport_use| {
+      Facility::Airport(AirportInfo {
+        fid: 0,
+        id: id.into(),
+        icao_id: None,
+        state: "CA".into(),
+        name: id.into(),
+        coord: util::Coord::default(),
+        airport_type,
+        airport_use,
+        elevation: None,
+        mag_var: None,
+        fuel_types: String::new(),
+        fuel: FuelTypes::default(),
+        runway: None,
+        desc: id.into(),
+      })
+    };
+
+    let infos = [
+      airport("PAO", AirportType::Airport, AirportUse::Public),
+      airport("CA11", AirportType::Helicopter, AirportUse::Private),
+      airport("SJC", AirportType::Airport, AirportUse::Public),
+      airport("CA22", AirportType::Helicopter, AirportUse::Public),
+      airport("CA33", AirportType::Helicopter, AirportUse::Navy),
+      Facility::Navaid(NavaidInfo {
+        id: "OSI".into(),
+        name: "WOODSIDE".into(),
+        nav_type: "VORTAC".into(),
+        freq: None,
+        coord: util::Coord::default(),
+        desc: "WOODSIDE".into(),
+      }),
+    ];
+
+    let ids = |nph: bool| -> Vec<&str> {
+      let infos = infos.iter().filter(|info| info.included(nph));
+      infos.map(Facility::desc).collect()
+    };
+
+    // Filtering an unfiltered list matches filtering as the airports are read.
+    assert!(ids(false) == ["PAO", "SJC", "CA22", "WOODSIDE"]);
+    assert!(ids(true) == ["PAO", "CA11", "SJC", "CA22", "CA33", "WOODSIDE"]);
+    for info in &infos {
+      let expected = !info.non_public_heliport();
+      assert!(info.included(false) == expected);
+    }
+  }
+}
@@ -0,0 +1,178 @@
+use crate::{back, chart, util};
+use eframe::{egui, emath, epaint};
+use gdal::raster;
+
+/// Chart palette inspector for tracking down rendering problems (such as colors that don't
+/// survive the night mode inversion).
+pub struct PaletteDlg {
+  palette: Vec<raster::RgbaEntry>,
+
+  /// Chart pixel under the pointer.
+  pointer: Option<util::Pos>,
+
+  /// Chart pixel that was last read and its raw value.
+  pixel: Option<(util::Pos, Option<chart::PixelValue>)>,
+}
+
+impl PaletteDlg {
+  /// Open the palette inspector.
+  /// - `palette`: chart color table entries (empty for RGB charts)
+  pub fn open(palette: &[raster::RgbaEntry]) -> Self {
+    Self {
+      palette: palette.to_vec(),
+      pointer: None,
+      pixel: None,
+    }
+  }
+
+  /// Set the chart pixel under the pointer. Returns true if it changed and needs to be read.
+  /// - `pos`: pixel position (full resolution)
+  pub fn set_pointer(&mut self, pos: util::Pos) -> bool {
+    self.pointer.replace(pos) != Some(pos)
+  }
+
+  /// Set the raw value of a chart pixel that was read. Values for pixels that are no longer under
+  /// the pointer are ignored.
+  /// - `pos`: pixel position (full resolution)
+  /// - `value`: raw pixel value, if it's on the chart
+  pub fn set_pixel(&mut self, pos: util::Pos, value: Option<chart::PixelValue>) {
+    if self.pointer == Some(pos) {
+      self.pixel = Some((pos, value));
+    }
+  }
+
+  pub fn show(&mut self, ctx: &egui::Context) -> bool {
+    let mut open = !back::register(ctx, "palette");
+    let win = egui::Window::new(egui::RichText::from("🎨  Palette").strong())
+      .open(&mut open)
+      .collapsible(false)
+      .resizable(false)
+      .anchor(emath::Align2::RIGHT_BOTTOM, [-8.0, -8.0]);
+
+    let mut close = false;
+    win.show(ctx, |ui| {
+      let index = match self.pixel {
+        Some((_, Some(chart::PixelValue::Index(index)))) => Some(index as usize),
+        _ => None,
+      };
+
+      if self.palette.is_empty() {
+        ui.label("This chart has no color palette (RGB)");
+      } else {
+        ui.horizontal(|ui| {
+          ui.vertical(|ui| {
+            ui.label("Light");
+            show_swatches(ui, &self.palette, util::color, index);
+          });
+
+          ui.vertical(|ui| {
+            ui.label("Dark");
+            show_swatches(ui, &self.palette, util::inverted_color, index);
+          });
+        });
+      }
+
+      ui.separator();
+      ui.label(self.pixel_text());
+
+      ui.separator();
+      ui.horizontal(|ui| {
+        if ui.button("Copy to clipboard").clicked() {
+          let text = format!("{}\n{}", self.pixel_text(), palette_text(&self.palette));
+          ctx.output_mut(|state| state.copied_text = text);
+        }
+
+        if ui.button("Close").clicked() {
+          close = true;
+        }
+      });
+    });
+
+    open && !close
+  }
+
+  /// Description of the chart pixel under the pointer.
+  fn pixel_text(&self) -> String {
+    let Some((pos, value)) = self.pixel else {
+      return "Point at the chart to inspect a pixel".into();
+    };
+
+    let pos = format!("Pixel {}, {}", pos.x, pos.y);
+    match value {
+      Some(chart::PixelValue::Index(index)) => match self.palette.get(index as usize) {
+        Some(entry) => {
+          let light = hex_color(util::color(entry));
+          let dark = hex_color(util::inverted_color(entry));
+          format!("{pos}: index {index}, light {light}, dark {dark}")
+        }
+        None => format!("{pos}: index {index} (not in the color table)"),
+      },
+      Some(chart::PixelValue::Rgb([r, g, b])) => {
+        let light = hex_color(epaint::Color32::from_rgb(r, g, b));
+        let dark = hex_color(util::inverted_rgb(r, g, b));
+        format!("{pos}: light {light}, dark {dark}")
+      }
+      None => format!("{pos}: not on the chart"),
+    }
+  }
+}
+
+/// Draw the palette as a grid of swatches, 16 to a row.
+/// - `ui`: egui UI
+/// - `palette`: color table entries
+/// - `convert`: color conversion
+/// - `selected`: palette index to outline
+fn show_swatches(
+  ui: &mut egui::Ui,
+  palette: &[raster::RgbaEntry],
+  convert: fn(&raster::RgbaEntry) -> epaint::Color32,
+  selected: Option<usize>,
+) {
+  let rows = palette.len().div_ceil(COLUMNS);
+  let size = emath::vec2(COLUMNS as f32, rows as f32) * SWATCH_SIZE;
+  let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+  let origin = response.rect.min;
+  let swatch_rect = |index: usize| {
+    let pos = emath::vec2((index % COLUMNS) as f32, (index / COLUMNS) as f32) * SWATCH_SIZE;
+    emath::Rect::from_min_size(origin + pos, emath::Vec2::splat(SWATCH_SIZE))
+  };
+
+  for (index, entry) in palette.iter().enumerate() {
+    painter.rect_filled(swatch_rect(index), 0.0, convert(entry));
+  }
+
+  if let Some(index) = selected.filter(|index| *index < palette.len()) {
+    let stroke = epaint::Stroke::new(2.0, ui.visuals().strong_text_color());
+    painter.rect_stroke(swatch_rect(index), 0.0, stroke);
+  }
+
+  // Show the entry under the pointer.
+  if let Some(pos) = response.hover_pos() {
+    let offset = (pos - origin) / SWATCH_SIZE;
+    let index = offset.y as usize * COLUMNS + offset.x as usize;
+    if let Some(entry) = palette.get(index) {
+      let text = format!("{index}: {}", hex_color(convert(entry)));
+      response.on_hover_text_at_pointer(text);
+    }
+  }
+}
+
+/// Text dump of the palette with one line per entry.
+/// - `palette`: color table entries
+fn palette_text(palette: &[raster::RgbaEntry]) -> String {
+  let mut text = String::from("Index  Light    Dark\n");
+  for (index, entry) in palette.iter().enumerate() {
+    let light = hex_color(util::color(entry));
+    let dark = hex_color(util::inverted_color(entry));
+    text.push_str(&format!("{index:5}  {light}  {dark}\n"));
+  }
+  text
+}
+
+/// Format a color as `#RRGGBB`.
+fn hex_color(color: epaint::Color32) -> String {
+  format!("#{:02X}{:02X}{:02X}", color.r(), color.g(), color.b())
+}
+
+const COLUMNS: usize = 16;
+const SWATCH_SIZE: f32 = 12.0;
@@ -0,0 +1,318 @@
+use std::io::{self, Write};
+
+/// Minimal PDF writer that streams each object to the output as soon as it's complete.
+///
+/// Only what's needed for printing charts is supported: JPEG images and single line text in the
+/// standard Helvetica font. Image data is written as it's added, so a document can be much larger
+/// than the memory available.
+pub struct Document<W: Write> {
+  out: W,
+  pos: usize,
+
+  /// Byte offset of each object, indexed by object number - 1.
+  offsets: Vec<usize>,
+
+  /// Object number of each page.
+  pages: Vec<usize>,
+
+  /// The page being built.
+  page: Option<Page>,
+}
+
+/// Page size in points (1/72 inch).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Size {
+  pub w: f64,
+  pub h: f64,
+}
+
+/// Rectangle in points with the origin at the bottom-left of the page.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+  pub x: f64,
+  pub y: f64,
+  pub w: f64,
+  pub h: f64,
+}
+
+struct Page {
+  size: Size,
+  images: Vec<usize>,
+  content: String,
+}
+
+impl<W: Write> Document<W> {
+  /// Object number of the page tree, which is written last.
+  const PAGES_ID: usize = 1;
+
+  /// Object number of the catalog.
+  const CATALOG_ID: usize = 2;
+
+  /// Object number of the font.
+  const FONT_ID: usize = 3;
+
+  /// Start a new document.
+  /// - `out`: output for the document
+  pub fn new(out: W) -> io::Result<Self> {
+    let mut doc = Self {
+      out,
+      pos: 0,
+      offsets: vec![0; 3],
+      pages: Vec::new(),
+      page: None,
+    };
+
+    // The binary comment marks the file as containing binary data.
+    doc.write(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n")?;
+
+    let font = b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>";
+    doc.write_object(Self::FONT_ID, font)?;
+    Ok(doc)
+  }
+
+  /// Start a new page. Any page that's in progress is finished first.
+  /// - `size`: page size in points
+  pub fn begin_page(&mut self, size: Size) -> io::Result<()> {
+    self.end_page()?;
+    self.page = Some(Page {
+      size,
+      images: Vec::new(),
+      content: String::new(),
+    });
+    Ok(())
+  }
+
+  /// Add a JPEG image to the current page. The image data is written immediately.
+  /// - `jpeg`: baseline JPEG data with three (RGB) components
+  /// - `px_size`: image width and height in pixels
+  /// - `rect`: where to place the image on the page
+  pub fn add_jpeg(&mut self, jpeg: &[u8], px_size: (usize, usize), rect: Rect) -> io::Result<()> {
+    assert!(self.page.is_some());
+    let id = self.new_id();
+    let head = format!(
+      "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+       /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+      px_size.0,
+      px_size.1,
+      jpeg.len()
+    );
+
+    self.offsets[id - 1] = self.pos;
+    self.write(format!("{id} 0 obj\n").as_bytes())?;
+    self.write(head.as_bytes())?;
+    self.write(jpeg)?;
+    self.write(b"\nendstream\nendobj\n")?;
+
+    let page = self.page.as_mut().unwrap();
+    let index = page.images.len();
+    page.images.push(id);
+    page.content.push_str(&format!(
+      "q {} 0 0 {} {} {} cm /Im{index} Do Q\n",
+      num(rect.w),
+      num(rect.h),
+      num(rect.x),
+      num(rect.y)
+    ));
+    Ok(())
+  }
+
+  /// Add a line of text to the current page.
+  /// - `text`: the text (characters that Helvetica doesn't have are replaced with '?')
+  /// - `x`, `y`: position of the start of the baseline in points
+  /// - `size`: font size in points
+  pub fn add_text(&mut self, text: &str, x: f64, y: f64, size: f64) {
+    let page = self.page.as_mut().unwrap();
+    page.content.push_str(&format!(
+      "BT /F1 {} Tf {} {} Td ({}) Tj ET\n",
+      num(size),
+      num(x),
+      num(y),
+      escape(text)
+    ));
+  }
+
+  /// Draw a rectangle outline on the current page.
+  /// - `rect`: the rectangle
+  /// - `width`: line width in points
+  pub fn add_frame(&mut self, rect: Rect, width: f64) {
+    let page = self.page.as_mut().unwrap();
+    page.content.push_str(&format!(
+      "q {} w {} {} {} {} re S Q\n",
+      num(width),
+      num(rect.x),
+      num(rect.y),
+      num(rect.w),
+      num(rect.h)
+    ));
+  }
+
+  /// Finish the document and return the output.
+  pub fn finish(mut self) -> io::Result<W> {
+    self.end_page()?;
+
+    let kids: Vec<String> = self.pages.iter().map(|id| format!("{id} 0 R")).collect();
+    let pages = format!(
+      "<< /Type /Pages /Kids [{}] /Count {} >>",
+      kids.join(" "),
+      self.pages.len()
+    );
+    self.write_object(Self::PAGES_ID, pages.as_bytes())?;
+
+    let catalog = format!("<< /Type /Catalog /Pages {} 0 R >>", Self::PAGES_ID);
+    self.write_object(Self::CATALOG_ID, catalog.as_bytes())?;
+
+    // Cross-reference table. Each entry must be exactly 20 bytes.
+    let xref = self.pos;
+    let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+    for offset in &self.offsets {
+      table.push_str(&format!("{offset:010} 00000 n \n"));
+    }
+
+    table.push_str(&format!(
+      "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+      self.offsets.len() + 1,
+      Self::CATALOG_ID
+    ));
+    self.write(table.as_bytes())?;
+    self.out.flush()?;
+    Ok(self.out)
+  }
+
+  fn end_page(&mut self) -> io::Result<()> {
+    let Some(page) = self.page.take() else {
+      return Ok(());
+    };
+
+    let content_id = self.new_id();
+    let content = format!(
+      "<< /Length {} >>\nstream\n{}endstream",
+      page.content.len(),
+      page.content
+    );
+    self.write_object(content_id, content.as_bytes())?;
+
+    let images: Vec<String> = (page.images.iter().enumerate())
+      .map(|(index, id)| format!("/Im{index} {id} 0 R"))
+      .collect();
+
+    let page_id = self.new_id();
+    let dict = format!(
+      "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Contents {content_id} 0 R \
+       /Resources << /Font << /F1 {} 0 R >> /XObject << {} >> >> >>",
+      Self::PAGES_ID,
+      num(page.size.w),
+      num(page.size.h),
+      Self::FONT_ID,
+      images.join(" ")
+    );
+    self.write_object(page_id, dict.as_bytes())?;
+    self.pages.push(page_id);
+    Ok(())
+  }
+
+  fn new_id(&mut self) -> usize {
+    self.offsets.push(0);
+    self.offsets.len()
+  }
+
+  fn write_object(&mut self, id: usize, data: &[u8]) -> io::Result<()> {
+    self.offsets[id - 1] = self.pos;
+    self.write(format!("{id} 0 obj\n").as_bytes())?;
+    self.write(data)?;
+    self.write(b"\nendobj\n")
+  }
+
+  fn write(&mut self, data: &[u8]) -> io::Result<()> {
+    self.out.write_all(data)?;
+    self.pos += data.len();
+    Ok(())
+  }
+}
+
+/// Format a number without unnecessary digits.
+fn num(val: f64) -> String {
+  let text = format!("{val:.3}");
+  let text = text.trim_end_matches('0').trim_end_matches('.');
+  if text == "-0" {
+    return "0".into();
+  }
+  text.into()
+}
+
+/// Escape text for a PDF string using WinAnsi encoding.
+fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for ch in text.chars() {
+    match ch {
+      '(' | ')' | '\\' => {
+        escaped.push('\\');
+        escaped.push(ch);
+      }
+      ' '..='~' => escaped.push(ch),
+      '°' => escaped.push_str("\\260"),
+      _ => escaped.push('?'),
+    }
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_document() {
+    let mut doc = Document::new(Vec::new()).unwrap();
+    let size = Size { w: 612.0, h: 792.0 };
+    for _ in 0..2 {
+      doc.begin_page(size).unwrap();
+      let rect = Rect {
+        x: 18.0,
+        y: 36.0,
+        w: 576.0,
+        h: 720.0,
+      };
+      doc.add_jpeg(b"\xFF\xD8fake\xFF\xD9", (4, 5), rect).unwrap();
+      doc.add_text("TVL (38°53'N)", 18.0, 18.0, 8.0);
+    }
+
+    let data = doc.finish().unwrap();
+    // Keep the byte offsets intact.
+    let text: String = data
+      .iter()
+      .map(|b| if b.is_ascii() { *b as char } else { '?' })
+      .collect();
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.contains("/Count 2"));
+    assert!(text.contains("(TVL \\(38\\26053'N\\)) Tj"));
+
+    // Every cross-reference entry points at its object.
+    let xref = text.rfind("\nxref\n").unwrap() + 1;
+    let startxref: usize = text[text.rfind("startxref\n").unwrap() + 10..]
+      .lines()
+      .next()
+      .unwrap()
+      .parse()
+      .unwrap();
+    assert!(startxref == xref);
+
+    let entries: Vec<&str> = text[xref..]
+      .lines()
+      .skip(3)
+      .take_while(|l| l.len() == 19)
+      .collect();
+    assert!(entries.len() == 9);
+    for (index, entry) in entries.iter().enumerate() {
+      let offset: usize = entry[..10].parse().unwrap();
+      assert!(text[offset..].starts_with(&format!("{} 0 obj\n", index + 1)));
+    }
+  }
+
+  #[test]
+  fn test_escape() {
+    assert!(escape("a(b)c\\") == "a\\(b\\)c\\\\");
+    assert!(escape("45°N") == "45\\260N");
+    assert!(escape("日本") == "??");
+    assert!(num(1.5) == "1.5" && num(2.0) == "2" && num(-0.0001) == "0");
+  }
+}
@@ -0,0 +1,69 @@
+use crate::{back, text_tagger};
+use eframe::{egui, emath};
+
+/// Non-modal window showing only the remarks for an airport.
+pub struct RemarksDlg {
+  title: String,
+  id: String,
+  remarks: Option<Vec<text_tagger::TaggedText>>,
+}
+
+impl RemarksDlg {
+  /// Open the airport remarks window.
+  /// - `title`: airport description
+  /// - `id`: airport ID
+  pub fn open(title: String, id: String) -> Self {
+    Self {
+      title,
+      id,
+      remarks: None,
+    }
+  }
+
+  /// Airport ID for this window.
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  /// Set the remarks once they're available.
+  pub fn set_remarks(&mut self, remarks: Vec<String>) {
+    let tagger = text_tagger::TextTagger::default();
+    self.remarks = Some(remarks.into_iter().map(|text| tagger.tag(text)).collect());
+  }
+
+  /// Show the window. Returns false when it has been closed.
+  pub fn show(&mut self, ctx: &egui::Context) -> bool {
+    let mut open = !back::register(ctx, "remarks");
+    egui::Window::new(egui::RichText::from("🗊  Remarks").strong())
+      .open(&mut open)
+      .collapsible(false)
+      .default_width(320.0)
+      .anchor(emath::Align2::RIGHT_TOP, [-8.0, 8.0])
+      .show(ctx, |ui| {
+        ui.label(egui::RichText::new(&self.title).strong());
+        ui.separator();
+
+        let Some(remarks) = &self.remarks else {
+          ui.spinner();
+          return;
+        };
+
+        if remarks.is_empty() {
+          ui.label(egui::RichText::new("No remarks").weak());
+          return;
+        }
+
+        // Remarks can be long.
+        egui::ScrollArea::vertical()
+          .max_height(300.0)
+          .show(ui, |ui| {
+            for remark in remarks {
+              remark.show(ui);
+              ui.add_space(2.0);
+            }
+          });
+      });
+
+    open
+  }
+}
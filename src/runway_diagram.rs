@@ -0,0 +1,199 @@
+use crate::{nasr, util};
+use eframe::{egui, emath, epaint};
+
+/// Draw a runway to scale with its displaced thresholds and approach obstacles.
+/// > **NOTE**: nothing is drawn if the runway length is unknown.
+/// - `ui`: egui UI
+/// - `runway`: runway to draw
+/// - `units`: display units for the obstacle heights
+pub fn show(ui: &mut egui::Ui, runway: &nasr::Runway, units: util::Units) {
+  let Some((before, length, after)) = extent(runway) else {
+    return;
+  };
+
+  let size = emath::vec2(ui.available_width().max(MIN_WIDTH), HEIGHT);
+  let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+  let rect = response.rect.shrink2(emath::vec2(MARGIN, 0.0));
+  let scale = rect.width() as f64 / (before + length + after);
+  let center = rect.center().y;
+
+  // Horizontal position of a distance (in feet) from the left runway end.
+  let x = |feet: f64| rect.left() + ((before + feet) * scale) as f32;
+
+  // Runway surface.
+  let thickness = runway
+    .width
+    .map_or(MIN_THICKNESS, |width| (width * scale) as f32)
+    .clamp(MIN_THICKNESS, MAX_THICKNESS);
+
+  let half = thickness * 0.5;
+  let surface = emath::Rect::from_x_y_ranges(x(0.0)..=x(length), center - half..=center + half);
+  painter.rect_filled(surface, 0.0, RUNWAY_COLOR);
+
+  let text_color = ui.visuals().text_color();
+  let font = epaint::FontId::proportional(11.0);
+  let ends = [runway.ends.first(), runway.ends.get(1)];
+  for (index, end) in ends.into_iter().enumerate() {
+    let Some(end) = end else {
+      continue;
+    };
+
+    // The first end is on the left with its approach from the left.
+    let left = index == 0;
+    let (edge, dir) = if left { (0.0, 1.0) } else { (length, -1.0) };
+
+    if let Some(displaced) = end.displaced_threshold.filter(|len| *len < length) {
+      let threshold = x(edge + dir * displaced);
+      let range = if left {
+        x(edge)..=threshold
+      } else {
+        threshold..=x(edge)
+      };
+
+      let area = emath::Rect::from_x_y_ranges(range, surface.y_range());
+      painter.rect_filled(area, 0.0, DISPLACED_COLOR);
+
+      // Threshold bar.
+      let stroke = epaint::Stroke::new(2.0, THRESHOLD_COLOR);
+      painter.line_segment(
+        [
+          emath::pos2(threshold, surface.top()),
+          emath::pos2(threshold, surface.bottom()),
+        ],
+        stroke,
+      );
+    }
+
+    // Runway end ID under the end of the runway.
+    let pos = emath::pos2(x(edge), surface.bottom() + 2.0);
+    let align = if left {
+      emath::Align2::LEFT_TOP
+    } else {
+      emath::Align2::RIGHT_TOP
+    };
+    painter.text(pos, align, &end.id, font.clone(), text_color);
+
+    // Omit the obstacle if its distance is unknown.
+    let Some(obstacle) = &end.obstacle else {
+      continue;
+    };
+
+    let Some(distance) = obstacle.distance else {
+      continue;
+    };
+
+    // A missing offset is drawn on the extended centerline. Right of the approach is down for the
+    // left end and up for the right end.
+    let limit = rect.height() * 0.5 - 16.0;
+    let offset = (obstacle.offset.unwrap_or_default() * scale) as f32 * dir as f32;
+    let offset = offset.clamp(-limit, limit);
+    let ox = x(edge - dir * distance);
+    let mut offsets = vec![offset];
+    if obstacle.both_sides && offset != 0.0 {
+      offsets.push(-offset);
+    }
+
+    let label = obstacle_label(obstacle, units);
+    for offset in offsets {
+      let pos = emath::pos2(ox, center + offset);
+      draw_marker(&painter, pos);
+      if !label.is_empty() {
+        let pos = pos - emath::vec2(0.0, MARKER_SIZE + 1.0);
+        let align = emath::Align2::CENTER_BOTTOM;
+        painter.text(pos, align, &label, font.clone(), text_color);
+      }
+    }
+  }
+}
+
+/// Distances in feet covered by the diagram: before the left end, the runway length and after
+/// the right end. Returns `None` if the runway length is unknown.
+fn extent(runway: &nasr::Runway) -> Option<(f64, f64, f64)> {
+  let length = runway.length.filter(|length| *length > 0.0)?;
+  let approach = |end: Option<&nasr::RunwayEnd>| {
+    end
+      .and_then(|end| end.obstacle.as_ref())
+      .and_then(|obstacle| obstacle.distance)
+      .map_or(0.0, |distance| distance.max(0.0))
+  };
+  let (first, last) = (runway.ends.first(), runway.ends.get(1));
+  Some((approach(first), length, approach(last)))
+}
+
+/// Obstacle label text (e.g. "120 ft TREES").
+fn obstacle_label(obstacle: &nasr::Obstacle, units: util::Units) -> String {
+  let mut parts = Vec::new();
+  if let Some(height) = obstacle.height {
+    parts.push(util::format_altitude(height, units));
+  }
+
+  if !obstacle.kind.is_empty() {
+    parts.push(obstacle.kind.clone());
+  }
+  parts.join(" ")
+}
+
+fn draw_marker(painter: &egui::Painter, pos: emath::Pos2) {
+  let points = vec![
+    emath::pos2(pos.x, pos.y - MARKER_SIZE),
+    emath::pos2(pos.x + MARKER_SIZE, pos.y + MARKER_SIZE * 0.7),
+    emath::pos2(pos.x - MARKER_SIZE, pos.y + MARKER_SIZE * 0.7),
+  ];
+  let shape = epaint::PathShape::convex_polygon(points, OBSTACLE_COLOR, epaint::Stroke::NONE);
+  painter.add(shape);
+}
+
+const MIN_WIDTH: f32 = 200.0;
+const HEIGHT: f32 = 96.0;
+const MARGIN: f32 = 24.0;
+const MIN_THICKNESS: f32 = 4.0;
+const MAX_THICKNESS: f32 = 24.0;
+const MARKER_SIZE: f32 = 5.0;
+const RUNWAY_COLOR: epaint::Color32 = epaint::Color32::from_gray(110);
+const DISPLACED_COLOR: epaint::Color32 = epaint::Color32::from_rgb(0xD8, 0xB0, 0x30);
+const THRESHOLD_COLOR: epaint::Color32 = epaint::Color32::WHITE;
+const OBSTACLE_COLOR: epaint::Color32 = epaint::Color32::from_rgb(0xE0, 0x40, 0x40);
+
+#[cfg(test)]
+mod test {
+  use crate::nasr;
+
+  fn end(id: &str, distance: Option<f64>) -> nasr::RunwayEnd {
+    nasr::RunwayEnd {
+      runway: "13/31".into(),
+      id: id.into(),
+      true_alignment: None,
+      elevation: None,
+      right_traffic: false,
+      displaced_threshold: None,
+      obstacle: distance.map(|distance| nasr::Obstacle {
+        kind: "TREES".into(),
+        height: Some(50.0),
+        distance: Some(distance),
+        offset: None,
+        both_sides: false,
+      }),
+    }
+  }
+
+  #[test]
+  fn test_extent() {
+    let mut runway = nasr::Runway {
+      id: "13/31".into(),
+      length: Some(5000.0),
+      width: Some(100.0),
+      surface: String::new(),
+      ends: vec![end("13", Some(1200.0)), end("31", None)],
+    };
+    assert!(super::extent(&runway) == Some((1200.0, 5000.0, 0.0)));
+
+    runway.ends.reverse();
+    assert!(super::extent(&runway) == Some((0.0, 5000.0, 1200.0)));
+
+    runway.ends.clear();
+    assert!(super::extent(&runway) == Some((0.0, 5000.0, 0.0)));
+
+    runway.length = None;
+    assert!(super::extent(&runway).is_none());
+  }
+}
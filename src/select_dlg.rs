@@ -1,61 +1,291 @@
-use eframe::{egui, emath};
-use std::mem;
+use crate::{nasr, util};
+use eframe::{egui, emath, epaint};
+use std::collections;
 
 pub struct SelectDlg {
-  reset: bool,
+  /// Remembered size, used when the dialog is first shown.
+  restore: Option<util::Size>,
+
+  /// Current content size.
+  size: emath::Vec2,
+  selected: collections::BTreeSet<usize>,
+
+  /// Fuel types that the listed choices must have.
+  fuel: nasr::FuelTypes,
 }
 
 impl SelectDlg {
-  pub fn new() -> Self {
-    Self { reset: true }
+  /// Name used to remember the dialog size.
+  pub const NAME: &'static str = "select";
+
+  /// Create the selection dialog.
+  /// - `size`: remembered dialog size
+  pub fn new(size: Option<util::Size>) -> Self {
+    Self {
+      restore: size,
+      size: emath::Vec2::ZERO,
+      selected: collections::BTreeSet::new(),
+      fuel: nasr::FuelTypes::default(),
+    }
+  }
+
+  /// Current dialog size.
+  pub fn size(&self) -> util::Size {
+    self.size.into()
   }
 
-  pub fn show<'a, I: Iterator<Item = &'a str>>(
+  /// Show the selection dialog.
+  /// - `ctx`: egui context
+  /// - `choices`: text and optional detail for each choice
+  /// - `multi`: allow several choices to be selected and compared
+  /// - `total`: total number of choices, if only some of them are provided
+  pub fn show<'a, C: Into<Choice<'a>>, I: Iterator<Item = C>>(
     &mut self,
     ctx: &egui::Context,
     choices: I,
+    multi: bool,
+    total: Option<usize>,
   ) -> Option<Response> {
+    let choices: Vec<Choice> = choices.map(Into::into).collect();
     let mut selection = None;
     let mut open = true;
-    let win = egui::Window::new(egui::RichText::from("👉  Select").strong())
+    let size = util::dialog_size(ctx, self.restore, emath::vec2(320.0, 500.0));
+    egui::Window::new(egui::RichText::from("👉  Select").strong())
       .open(&mut open)
       .collapsible(false)
-      .resizable(false)
-      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0]);
-
-    // Hack to reset the window size.
-    let win = if mem::take(&mut self.reset) {
-      win.fixed_size([200.0, 500.0])
-    } else {
-      win
-    };
-
-    win.show(ctx, |ui| {
-      ui.add_space(8.0);
-      ui.vertical_centered(|ui| {
-        egui::ScrollArea::vertical().show(ui, |ui| {
-          for (index, text) in choices.enumerate() {
-            ui.horizontal(|ui| {
-              let widget = egui::SelectableLabel::new(false, text);
-              if ui.add_sized(ui.available_size(), widget).clicked() {
-                selection = Some(Response::Index(index));
+      .default_size(size)
+      .anchor(emath::Align2::CENTER_CENTER, [0.0, 0.0])
+      .show(ctx, |ui| {
+        self.size = ui.max_rect().size();
+        ui.add_space(8.0);
+        if let Some(total) = total.filter(|total| *total > choices.len()) {
+          ui.horizontal(|ui| {
+            ui.label(format!("Showing {} of {total} matches", choices.len()));
+            if ui.button("Show All").clicked() {
+              selection = Some(Response::ShowAll);
+            }
+          });
+          ui.separator();
+        }
+
+        // Fuel filter chips, if any of the choices have fuel.
+        let has_fuel = |choice: &Choice| choice.fuel.is_some_and(|fuel| !fuel.is_empty());
+        if choices.iter().any(has_fuel) {
+          ui.horizontal(|ui| {
+            ui.label("Fuel:");
+            for (fuel, label) in nasr::FuelTypes::ALL {
+              let widget = egui::SelectableLabel::new(self.fuel.contains(fuel), label);
+              if ui.add(widget).clicked() {
+                self.fuel.toggle(fuel);
               }
+            }
+          });
+          ui.separator();
+        } else {
+          self.fuel = nasr::FuelTypes::default();
+        }
+
+        // Pinned choices stay above the list and aren't filtered. The others are only listed if
+        // they have the chosen fuel types.
+        let pinned = choices.iter().take_while(|choice| choice.pinned).count();
+        let fuel = self.fuel;
+        let listed: Vec<usize> = (pinned..choices.len())
+          .filter(|index| choices[*index].listed(fuel))
+          .collect();
+
+        // Hidden choices can't stay selected.
+        self
+          .selected
+          .retain(|index| *index < pinned || listed.binary_search(index).is_ok());
+
+        if multi {
+          // Leave room for the buttons at the bottom.
+          egui::TopBottomPanel::bottom("select_dlg_buttons")
+            .frame(egui::Frame::none())
+            .show_inside(ui, |ui| {
+              ui.separator();
+              ui.horizontal(|ui| {
+                ui.add_enabled_ui(self.selected.len() == 1, |ui| {
+                  if ui.button("Go To").clicked() {
+                    let index = *self.selected.first().unwrap();
+                    selection = Some(Response::Index(index));
+                  }
+                });
+
+                ui.add_enabled_ui(self.selected.len() == 1, |ui| {
+                  if ui.button("Zoom To").clicked() {
+                    let index = *self.selected.first().unwrap();
+                    selection = Some(Response::Zoom(index));
+                  }
+                });
+
+                ui.add_enabled_ui(self.selected.len() == 1, |ui| {
+                  if ui.button("Info").clicked() {
+                    let index = *self.selected.first().unwrap();
+                    selection = Some(Response::Info(index));
+                  }
+                });
+
+                ui.add_enabled_ui(self.selected.len() == 1, |ui| {
+                  if ui.button("Remarks").clicked() {
+                    let index = *self.selected.first().unwrap();
+                    selection = Some(Response::Remarks(index));
+                  }
+                });
+
+                ui.add_enabled_ui(self.selected.len() > 1, |ui| {
+                  if ui.button("Compare").clicked() {
+                    let indexes = self.selected.iter().copied().collect();
+                    selection = Some(Response::Indexes(indexes));
+                  }
+                });
+              });
             });
+        }
+
+        ui.vertical_centered(|ui| {
+          for (index, choice) in choices[..pinned].iter().enumerate() {
+            self.add_row(ui, index, choice, multi, &mut selection);
+          }
+
+          if pinned > 0 {
+            ui.separator();
+          }
+
+          if listed.is_empty() && pinned < choices.len() {
+            ui.label(egui::RichText::new("None have the chosen fuel").weak());
           }
+
+          // Only the visible rows are laid out, which keeps long lists responsive.
+          let row_height = ui.spacing().interact_size.y;
+          egui::ScrollArea::vertical().show_rows(ui, row_height, listed.len(), |ui, range| {
+            for index in &listed[range] {
+              self.add_row(ui, *index, &choices[*index], multi, &mut selection);
+            }
+          });
         });
       });
-    });
 
     if !open || ctx.input(|state| state.key_pressed(egui::Key::Escape)) {
       selection = Some(Response::Close);
     }
 
-    self.reset = selection.is_some();
+    if selection.is_some() {
+      self.selected.clear();
+      self.fuel = nasr::FuelTypes::default();
+    }
     selection
   }
+
+  /// Add the row for a choice.
+  /// - `ui`: egui UI
+  /// - `index`: choice index
+  /// - `choice`: the choice
+  /// - `multi`: allow several choices to be selected
+  /// - `selection`: set to the response if the choice is picked
+  fn add_row(
+    &mut self,
+    ui: &mut egui::Ui,
+    index: usize,
+    choice: &Choice,
+    multi: bool,
+    selection: &mut Option<Response>,
+  ) {
+    let size = emath::vec2(ui.available_width(), ui.spacing().interact_size.y);
+    let layout = egui::Layout::right_to_left(emath::Align::Center);
+    ui.allocate_ui_with_layout(size, layout, |ui| {
+      // The detail and tag go in a column to the right of the choice text.
+      if let Some(detail) = &choice.detail {
+        ui.label(egui::RichText::new(detail).weak());
+      }
+
+      if choice.heliport {
+        let text = egui::RichText::new("HELI")
+          .small()
+          .strong()
+          .color(epaint::Color32::WHITE)
+          .background_color(HELI_COLOR);
+        ui.label(text);
+      }
+
+      let selected = self.selected.contains(&index);
+      let widget = egui::SelectableLabel::new(selected, choice.text);
+      let response = ui.add_sized(ui.available_size(), widget);
+      if response.clicked() {
+        if !multi {
+          *selection = Some(Response::Index(index));
+        } else if !self.selected.remove(&index) {
+          self.selected.insert(index);
+        }
+      }
+    });
+  }
 }
 
 pub enum Response {
   Close,
   Index(usize),
+
+  /// Go to the choice and zoom in to see its surroundings.
+  Zoom(usize),
+  Info(usize),
+  Remarks(usize),
+  Indexes(Vec<usize>),
+
+  /// Show all the choices instead of only some of them.
+  ShowAll,
+}
+
+/// Tag color for heliports.
+const HELI_COLOR: epaint::Color32 = epaint::Color32::from_rgb(0x80, 0x40, 0xC0);
+
+/// A choice in the selection dialog.
+pub struct Choice<'a> {
+  /// Choice text.
+  pub text: &'a str,
+
+  /// Detail shown in the second column (e.g. airport elevation).
+  pub detail: Option<String>,
+
+  /// Tag the choice as a heliport.
+  pub heliport: bool,
+
+  /// Keep the choice above the scrolling list (e.g. an ID match). Pinned choices go first.
+  pub pinned: bool,
+
+  /// Fuel types for an airport, which the choices can be filtered on.
+  pub fuel: Option<nasr::FuelTypes>,
+}
+
+impl<'a> From<&'a str> for Choice<'a> {
+  fn from(text: &'a str) -> Self {
+    Self {
+      text,
+      detail: None,
+      heliport: false,
+      pinned: false,
+      fuel: None,
+    }
+  }
+}
+
+impl<'a> Choice<'a> {
+  /// True if the choice is listed with a fuel filter.
+  /// - `fuel`: fuel types that the choice must have (none for all choices)
+  fn listed(&self, fuel: nasr::FuelTypes) -> bool {
+    fuel.is_empty() || self.fuel.is_some_and(|has| has.contains(fuel))
+  }
+
+  /// Choice for a facility, with its elevation as the detail.
+  /// - `facility`: airport or navaid
+  /// - `units`: display units
+  pub fn facility(facility: &'a nasr::Facility, units: util::Units) -> Self {
+    Self {
+      text: facility.desc(),
+      detail: facility.elevation_text(units),
+      heliport: facility.heliport(),
+      pinned: false,
+      fuel: facility.airport().map(|info| info.fuel),
+    }
+  }
 }
@@ -1,4 +1,4 @@
-use crate::util;
+use crate::{airport_fmt, util};
 use eframe::{egui, emath, epaint};
 
 #[derive(Default)]
@@ -6,10 +6,17 @@ pub struct SelectMenu {
   pos: emath::Pos2,
   org: emath::Pos2,
   width: f32,
+
+  /// Choice highlighted with the keyboard.
+  highlight: Option<usize>,
 }
 
 impl SelectMenu {
   fn add_btn(&mut self, ui: &mut egui::Ui, text: &str) -> egui::Response {
+    self.add_choice(ui, text, false)
+  }
+
+  fn add_choice(&mut self, ui: &mut egui::Ui, text: &str, selected: bool) -> egui::Response {
     let layout = egui::Layout::left_to_right(emath::Align::Center);
     ui.allocate_ui_with_layout(emath::vec2(0.0, 0.0), layout, |ui| {
       let style = ui.style_mut();
@@ -20,7 +27,7 @@ impl SelectMenu {
       style.visuals.widgets.inactive.bg_stroke = epaint::Stroke::NONE;
 
       // Make all the buttons the same width.
-      let widget = egui::Button::new(text);
+      let widget = egui::Button::new(text).selected(selected);
       let size = emath::vec2(self.width, style.spacing.interact_size.y);
       let response = ui.add_sized(size, widget);
       self.width = response.rect.width();
@@ -36,15 +43,60 @@ impl SelectMenu {
     self.width = 210.0;
     self.pos = emath::pos2(pos.x - self.width * 0.5, pos.y);
     self.org = pos;
+    self.highlight = None;
   }
 
-  pub fn show<'a, I: Iterator<Item = &'a str>>(
+  /// Move the keyboard highlight with the arrow keys. Returns the choice if Enter was pressed.
+  /// - `ctx`: egui context
+  /// - `count`: number of choices
+  fn process_keys(&mut self, ctx: &egui::Context, count: usize) -> Option<usize> {
+    if count == 0 {
+      self.highlight = None;
+      return None;
+    }
+
+    let (down, up, enter) = ctx.input(|state| {
+      (
+        state.key_pressed(egui::Key::ArrowDown),
+        state.key_pressed(egui::Key::ArrowUp),
+        state.key_pressed(egui::Key::Enter),
+      )
+    });
+
+    let last = count - 1;
+    if down {
+      self.highlight = Some(self.highlight.map_or(0, |index| (index + 1).min(last)));
+    } else if up {
+      self.highlight = Some(self.highlight.map_or(last, |index| index.saturating_sub(1)));
+    }
+
+    self.highlight = self.highlight.map(|index| index.min(last));
+    self.highlight.filter(|_| enter)
+  }
+
+  /// Show the menu.
+  /// - `ctx`: egui context
+  /// - `lat_lon`: text for the clicked location
+  /// - `choices`: text for each choice and whether it's an airport (airports have a context menu)
+  /// - `nph`: whether non-public heliports are shown, if there are any nearby
+  pub fn show<'a, I: Iterator<Item = (&'a str, bool)>>(
     &mut self,
     ctx: &egui::Context,
     lat_lon: &str,
     choices: Option<I>,
+    nph: Option<bool>,
   ) -> Option<Response> {
-    let mut selection = None;
+    let choices: Option<Vec<_>> = choices.map(|choices| choices.collect());
+    let count = choices.as_ref().map_or(0, |choices| choices.len());
+    let mut selection = self.process_keys(ctx, count).map(Response::Index);
+    let moved = ctx.input(|state| {
+      state.key_pressed(egui::Key::ArrowDown) || state.key_pressed(egui::Key::ArrowUp)
+    });
+
+    // Limit the height of the choices to what fits above or below the clicked location.
+    let available = ctx.available_rect();
+    let room = (available.max.y - self.org.y).max(self.org.y - available.min.y);
+
     let response = egui::Area::new("select_menu".into())
       .order(egui::Order::Foreground)
       .fixed_pos(self.pos)
@@ -54,21 +106,100 @@ impl SelectMenu {
             selection = Some(Response::LatLon);
           }
 
+          if self.add_btn(ui, "Add bookmark here…").clicked() {
+            selection = Some(Response::Bookmark);
+          }
+
+          if self.add_btn(ui, "Copy position report").clicked() {
+            selection = Some(Response::PositionReport);
+          }
+
+          if let Some(show) = nph {
+            let text = if show {
+              "Hide private heliports"
+            } else {
+              "Show private heliports"
+            };
+
+            if self.add_btn(ui, text).clicked() {
+              selection = Some(Response::Heliports);
+            }
+          }
+
           if let Some(choices) = choices {
             ui.add_sized([self.width, 1.0], egui::Separator::default().spacing(2.0));
-            for (index, choice) in choices.enumerate() {
-              if self.add_btn(ui, choice).clicked() {
-                selection = Some(Response::Index(index));
+            if choices.is_empty() {
+              ui.label(egui::RichText::new("Nothing found").weak());
+              if self.add_btn(ui, "Expand search").clicked() {
+                selection = Some(Response::Expand);
               }
             }
+
+            // The header stays put while the choices scroll.
+            let margin = ui.spacing().window_margin.sum().y;
+            let max_height = (room - ui.min_rect().height() - margin).max(100.0);
+            egui::ScrollArea::vertical()
+              .max_height(max_height)
+              .show(ui, |ui| {
+                for (index, (choice, airport)) in choices.into_iter().enumerate() {
+                  let highlighted = self.highlight == Some(index);
+                  let response = self.add_choice(ui, choice, highlighted);
+                  if response.clicked() {
+                    selection = Some(Response::Index(index));
+                  }
+
+                  if highlighted && moved {
+                    response.scroll_to_me(None);
+                  }
+
+                  if !airport {
+                    continue;
+                  }
+
+                  // Offer to copy the airport information or show its weather.
+                  response.context_menu(|ui| {
+                    let choices = [
+                      ("Copy as Text", airport_fmt::Format::Text),
+                      ("Copy as Markdown", airport_fmt::Format::Markdown),
+                    ];
+                    for (text, format) in choices {
+                      if ui.button(text).clicked() {
+                        selection = Some(Response::Copy(index, format));
+                        ui.close_menu();
+                      }
+                    }
+
+                    if ui.button("Info").clicked() {
+                      selection = Some(Response::Info(index));
+                      ui.close_menu();
+                    }
+
+                    if ui.button("Remarks").clicked() {
+                      selection = Some(Response::Remarks(index));
+                      ui.close_menu();
+                    }
+
+                    if ui.button("Weather").clicked() {
+                      selection = Some(Response::Weather(index));
+                      ui.close_menu();
+                    }
+
+                    if ui.button("Runway Winds").clicked() {
+                      selection = Some(Response::Winds(index));
+                      ui.close_menu();
+                    }
+                  });
+                }
+              });
           }
         });
       })
       .response;
 
-    // If the user clicked off then return Response::Close.
+    // If the user clicked off then return Response::Close (clicking a context menu item also
+    // counts as clicking elsewhere).
     if response.clicked_elsewhere() {
-      selection = Some(Response::Close);
+      selection = selection.or(Some(Response::Close));
     } else {
       // Center the popup and make sure it doesn't go past the window's edges.
       let available = ctx.available_rect();
@@ -110,6 +241,16 @@ impl SelectMenu {
 pub enum Response {
   Close,
   LatLon,
-  #[allow(dead_code)]
+  Bookmark,
+
+  /// Copy the clicked location with the nearest airport and chart name.
+  PositionReport,
+  Expand,
+  Heliports,
   Index(usize),
+  Copy(usize, airport_fmt::Format),
+  Info(usize),
+  Remarks(usize),
+  Weather(usize),
+  Winds(usize),
 }
@@ -0,0 +1,165 @@
+use crate::util;
+
+/// Solar zenith angle at sunrise and sunset (includes refraction and the sun's radius).
+const SUNRISE_ZENITH: f64 = 90.833;
+
+/// Solar zenith angle at the start and end of civil twilight.
+const CIVIL_ZENITH: f64 = 96.0;
+
+/// Sunrise, sunset and civil twilight for a single date.
+///
+/// Times are minutes from 00:00 UTC of the date, so they can be negative or past 24 hours depending
+/// on the longitude. `None` means that the event doesn't happen on that date (polar day or night).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SunTimes {
+  pub dawn: Option<f64>,
+  pub sunrise: Option<f64>,
+  pub sunset: Option<f64>,
+  pub dusk: Option<f64>,
+}
+
+impl SunTimes {
+  /// Calculate the sun times using the NOAA solar position algorithm.
+  /// - `coord`: location in decimal degrees
+  /// - `date`: UTC date
+  pub fn new(coord: util::Coord, date: util::Date) -> Self {
+    let (lat, lon) = (coord.y, coord.x);
+    let (decl, eot) = declination_and_eot(date);
+
+    // Solar noon in minutes from 00:00 UTC.
+    let noon = 720.0 - 4.0 * lon - eot;
+    let event = |zenith: f64, sign: f64| {
+      let ha = hour_angle(lat, decl, zenith)?;
+      Some(noon + sign * 4.0 * ha)
+    };
+
+    Self {
+      dawn: event(CIVIL_ZENITH, -1.0),
+      sunrise: event(SUNRISE_ZENITH, -1.0),
+      sunset: event(SUNRISE_ZENITH, 1.0),
+      dusk: event(CIVIL_ZENITH, 1.0),
+    }
+  }
+}
+
+/// Format minutes from 00:00 UTC as a time of day.
+/// - `minutes`: minutes from 00:00 UTC (`None` is formatted as "none")
+/// - `offset`: offset from UTC in minutes (use zero for UTC)
+pub fn format_time(minutes: Option<f64>, offset: i64) -> String {
+  let Some(minutes) = minutes else {
+    return "none".into();
+  };
+
+  let minutes = (minutes.round() as i64 + offset).rem_euclid(24 * 60);
+  format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Get the solar declination (radians) and the equation of time (minutes) at noon UTC.
+fn declination_and_eot(date: util::Date) -> (f64, f64) {
+  // Julian centuries since J2000.0.
+  let jd = date.to_days() as f64 + 2440588.0;
+  let t = (jd - 2451545.0) / 36525.0;
+
+  let mean_long = (280.46646 + t * (36000.76983 + t * 0.0003032)).rem_euclid(360.0);
+  let mean_anom = 357.52911 + t * (35999.05029 - 0.0001537 * t);
+  let ecc = 0.016708634 - t * (0.000042037 + 0.0000001267 * t);
+
+  let m = mean_anom.to_radians();
+  let center = m.sin() * (1.914602 - t * (0.004817 + 0.000014 * t))
+    + (2.0 * m).sin() * (0.019993 - 0.000101 * t)
+    + (3.0 * m).sin() * 0.000289;
+
+  let omega = (125.04 - 1934.136 * t).to_radians();
+  let app_long = (mean_long + center - 0.00569 - 0.00478 * omega.sin()).to_radians();
+  let mean_obliq =
+    23.0 + (26.0 + (21.448 - t * (46.815 + t * (0.00059 - t * 0.001813))) / 60.0) / 60.0;
+  let obliq = (mean_obliq + 0.00256 * omega.cos()).to_radians();
+  let decl = (obliq.sin() * app_long.sin()).asin();
+
+  let y = (obliq / 2.0).tan().powi(2);
+  let l = mean_long.to_radians();
+  let eot = y * (2.0 * l).sin() - 2.0 * ecc * m.sin() + 4.0 * ecc * y * m.sin() * (2.0 * l).cos()
+    - 0.5 * y * y * (4.0 * l).sin()
+    - 1.25 * ecc * ecc * (2.0 * m).sin();
+
+  (decl, 4.0 * eot.to_degrees())
+}
+
+/// Get the hour angle (degrees) at which the sun reaches a zenith angle.
+fn hour_angle(lat: f64, decl: f64, zenith: f64) -> Option<f64> {
+  let lat = lat.to_radians();
+  let cos_ha = zenith.to_radians().cos() / (lat.cos() * decl.cos()) - lat.tan() * decl.tan();
+  if !(-1.0..=1.0).contains(&cos_ha) {
+    return None;
+  }
+  Some(cos_ha.acos().to_degrees())
+}
+
+#[cfg(test)]
+mod test {
+  use super::SunTimes;
+  use crate::util::{Coord, Date};
+
+  fn near(minutes: Option<f64>, expected: &str) -> bool {
+    let (hour, min) = expected.split_once(':').unwrap();
+    let expected = hour.parse::<f64>().unwrap() * 60.0 + min.parse::<f64>().unwrap();
+    let minutes = minutes.unwrap().rem_euclid(24.0 * 60.0);
+    (minutes - expected).abs() <= 2.0
+  }
+
+  #[test]
+  fn test_sun_times() {
+    // San Francisco (KSFO) on the March equinox.
+    let ksfo = Coord {
+      x: -122.375,
+      y: 37.6189,
+    };
+    let times = SunTimes::new(ksfo, Date::new(2024, 3, 20).unwrap());
+    assert!(near(times.sunrise, "14:12"));
+    assert!(near(times.sunset, "02:21"));
+
+    // Denver (KDEN area) on the December solstice.
+    let denver = Coord {
+      x: -104.99,
+      y: 39.74,
+    };
+    let times = SunTimes::new(denver, Date::new(2024, 12, 21).unwrap());
+    assert!(near(times.dawn, "13:47"));
+    assert!(near(times.sunrise, "14:18"));
+    assert!(near(times.sunset, "23:38"));
+    assert!(near(times.dusk, "00:09"));
+
+    // London on the June solstice.
+    let london = Coord {
+      x: -0.1278,
+      y: 51.5074,
+    };
+    let times = SunTimes::new(london, Date::new(2024, 6, 21).unwrap());
+    assert!(near(times.sunrise, "03:43"));
+    assert!(near(times.sunset, "20:21"));
+  }
+
+  #[test]
+  fn test_polar() {
+    // Utqiagvik (PABR).
+    let pabr = Coord {
+      x: -156.79,
+      y: 71.29,
+    };
+    let times = SunTimes::new(pabr, Date::new(2024, 12, 21).unwrap());
+    assert!(times.sunrise.is_none() && times.sunset.is_none());
+    assert!(times.dawn.is_some());
+
+    let times = SunTimes::new(pabr, Date::new(2024, 6, 21).unwrap());
+    assert!(times.sunrise.is_none() && times.sunset.is_none());
+    assert!(super::format_time(times.sunrise, 0) == "none");
+  }
+
+  #[test]
+  fn test_format_time() {
+    assert!(super::format_time(Some(852.1), 0) == "14:12");
+    assert!(super::format_time(Some(1581.4), 0) == "02:21");
+    assert!(super::format_time(Some(852.1), -7 * 60) == "07:12");
+    assert!(super::format_time(Some(-30.0), 0) == "23:30");
+  }
+}
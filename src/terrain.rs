@@ -0,0 +1,427 @@
+use crate::{chart, coalesce, util};
+use eframe::{egui, epaint};
+use gdal::{raster, spatial_ref};
+use std::{any, path, sync::mpsc, thread};
+
+/// TerrainReader reads a hillshade from an elevation (DEM) raster that lines up with a chart.
+pub struct TerrainReader {
+  name: String,
+  tx: coalesce::Sender<chart::ImagePart>,
+  rx: mpsc::Receiver<TerrainReply>,
+}
+
+impl TerrainReader {
+  /// Open an elevation raster for a chart.
+  /// - `path`: elevation raster file path (usually a GeoTIFF)
+  /// - `chart`: transformation of the chart that the terrain is shown with
+  /// - `ctx`: egui context for requesting a repaint
+  pub fn new<P: AsRef<path::Path>>(
+    path: P,
+    chart: &chart::Transform,
+    ctx: &egui::Context,
+  ) -> Result<Self, util::Error> {
+    TerrainReader::_new(path.as_ref(), chart, ctx.clone())
+  }
+
+  fn _new(
+    path: &path::Path,
+    chart: &chart::Transform,
+    ctx: egui::Context,
+  ) -> Result<Self, util::Error> {
+    let source = match TerrainSource::open(path, chart) {
+      Ok(source) => source,
+      Err(err) => return Err(format!("Unable to open terrain file: {err}").into()),
+    };
+
+    let name = util::stem_string(path).unwrap_or_default();
+    let (tx, trx) = coalesce::channel();
+    let (ttx, rx) = mpsc::channel();
+    let chart_proj4 = chart.get_proj4();
+
+    // Create the thread.
+    thread::Builder::new()
+      .name(any::type_name::<TerrainReader>().to_owned())
+      .spawn(move || {
+        // The coordinate transformation is created here because it can't be sent between threads.
+        let to_dem = match source.to_dem(&chart_proj4) {
+          Ok(trans) => trans,
+          Err(err) => {
+            let text = format!("Unable to create coordinate transformation: {err}");
+            ttx.send(TerrainReply::Error(text.into())).unwrap();
+            ctx.request_repaint();
+            return;
+          }
+        };
+
+        // Wait for a message. Exit when the connection is closed.
+        while let Some(part) = trx.recv() {
+          let reply = match source.read(&part, &to_dem) {
+            Ok(image) => TerrainReply::Image(part, image),
+            Err(err) => TerrainReply::Error(format!("{err}").into()),
+          };
+          ttx.send(reply).unwrap();
+
+          // Request a repaint here so that the main thread will wake up and get the message.
+          ctx.request_repaint();
+        }
+      })
+      .unwrap();
+
+    Ok(Self { name, tx, rx })
+  }
+
+  /// Name of the terrain file.
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// Kick-off a hillshade read for the same part of the chart that's being read.
+  /// - `part`: the chart image part
+  pub fn read_image(&self, part: chart::ImagePart) {
+    self.tx.send(part);
+  }
+
+  /// Get all available replies.
+  pub fn get_replies(&self) -> Vec<TerrainReply> {
+    self.rx.try_iter().collect()
+  }
+}
+
+pub enum TerrainReply {
+  /// Hillshade image for a chart image part.
+  Image(chart::ImagePart, epaint::ColorImage),
+
+  /// Error message from a read operation.
+  Error(util::Error),
+}
+
+/// Elevation raster data source.
+struct TerrainSource {
+  dataset: gdal::Dataset,
+  dem_wkt: String,
+  geographic: bool,
+  to_px: gdal::GeoTransform,
+  from_px: gdal::GeoTransform,
+  px_size: util::Size,
+  no_data: Option<f64>,
+  chart_from_px: gdal::GeoTransform,
+  chart_size: util::Size,
+}
+
+impl TerrainSource {
+  /// Output pixels between points that are transformed exactly. Points in between are interpolated.
+  const GRID_STEP: usize = 16;
+
+  /// Sun azimuth and altitude for the hillshade in degrees.
+  const SUN_AZIMUTH: f64 = 315.0;
+  const SUN_ALTITUDE: f64 = 45.0;
+
+  /// Open an elevation raster and make sure that it covers part of the chart.
+  /// - `path`: raster file path
+  /// - `chart`: chart transformation
+  fn open(path: &path::Path, chart: &chart::Transform) -> Result<Self, util::Error> {
+    let options = gdal::DatasetOptions {
+      open_flags: gdal::GdalOpenFlags::GDAL_OF_READONLY | gdal::GdalOpenFlags::GDAL_OF_RASTER,
+      ..Default::default()
+    };
+
+    let err = |err: gdal::errors::GdalError| util::Error::from(err.to_string());
+    let dataset = gdal::Dataset::open_ex(path, options).map_err(err)?;
+    if dataset.raster_count() < 1 {
+      return Err("raster layer not found".into());
+    }
+
+    let spatial_ref = match dataset.spatial_ref() {
+      Ok(sr) => sr,
+      Err(_) => return Err("spatial reference not found".into()),
+    };
+
+    let from_px = dataset.geo_transform().map_err(err)?;
+    let to_px = gdal::GeoTransformEx::invert(&from_px).map_err(err)?;
+    let px_size: util::Size = dataset.raster_size().into();
+    if !px_size.is_valid() {
+      return Err("invalid pixel size".into());
+    }
+
+    let no_data = dataset.rasterband(1).map_err(err)?.no_data_value();
+    let source = Self {
+      dem_wkt: spatial_ref.to_wkt().map_err(err)?,
+      geographic: spatial_ref.is_geographic(),
+      dataset,
+      to_px,
+      from_px,
+      px_size,
+      no_data,
+      chart_from_px: chart.geo_transform(),
+      chart_size: chart.px_size(),
+    };
+
+    // Make sure the spatial references are compatible and that the elevation data overlaps the
+    // chart.
+    let to_dem = source.to_dem(&chart.get_proj4()).map_err(err)?;
+    let (w, h) = (source.chart_size.w as f64, source.chart_size.h as f64);
+    let mut x = Vec::new();
+    let mut y = Vec::new();
+    for step in 0..=8 {
+      let t = step as f64 / 8.0;
+      for (px, py) in [
+        (t * w, 0.0),
+        (t * w, h),
+        (0.0, t * h),
+        (w, t * h),
+        (t * w, t * h),
+      ] {
+        let coord = gdal::GeoTransformEx::apply(&source.chart_from_px, px, py);
+        x.push(coord.0);
+        y.push(coord.1);
+      }
+    }
+
+    if to_dem.transform_coords(&mut x, &mut y, &mut []).is_err() {
+      return Err("spatial reference is not compatible with the chart".into());
+    }
+
+    let overlaps = x.iter().zip(&y).any(|(x, y)| {
+      let (px, py) = gdal::GeoTransformEx::apply(&source.to_px, *x, *y);
+      (0.0..source.px_size.w as f64).contains(&px) && (0.0..source.px_size.h as f64).contains(&py)
+    });
+
+    if !overlaps {
+      return Err("the elevation data doesn't cover this chart".into());
+    }
+
+    Ok(source)
+  }
+
+  /// Create the transformation from chart coordinates to elevation raster coordinates.
+  /// - `chart_proj4`: chart spatial reference as a PROJ4 string
+  fn to_dem(
+    &self,
+    chart_proj4: &str,
+  ) -> Result<spatial_ref::CoordTransform, gdal::errors::GdalError> {
+    let chart_sr = spatial_ref::SpatialRef::from_proj4(chart_proj4)?;
+    let dem_sr = spatial_ref::SpatialRef::from_wkt(&self.dem_wkt)?;
+
+    // Respect X/Y order.
+    chart_sr.set_axis_mapping_strategy(0);
+    dem_sr.set_axis_mapping_strategy(0);
+    spatial_ref::CoordTransform::new(&chart_sr, &dem_sr)
+  }
+
+  /// Read the hillshade for a chart image part. The result is the same size as the chart image so
+  /// that the two can be drawn on top of each other.
+  fn read(
+    &self,
+    part: &chart::ImagePart,
+    to_dem: &spatial_ref::CoordTransform,
+  ) -> Result<epaint::ColorImage, gdal::errors::GdalError> {
+    let (w, h) = (part.rect.size.w as usize, part.rect.size.h as usize);
+    let mut image = epaint::ColorImage::new([w, h], epaint::Color32::TRANSPARENT);
+    if w == 0 || h == 0 {
+      return Ok(image);
+    }
+
+    // Map the output pixels to chart pixels the same way that the chart reader does.
+    let src_rect = part
+      .rect
+      .scaled(part.zoom.inverse())
+      .fitted(self.chart_size);
+    let sx = src_rect.size.w as f64 / w as f64;
+    let sy = src_rect.size.h as f64 / h as f64;
+
+    // Transform a grid of points into elevation raster pixels.
+    let gw = w.div_ceil(Self::GRID_STEP) + 1;
+    let gh = h.div_ceil(Self::GRID_STEP) + 1;
+    let mut x = Vec::with_capacity(gw * gh);
+    let mut y = Vec::with_capacity(gw * gh);
+    for gy in 0..gh {
+      let py = (gy * Self::GRID_STEP).min(h) as f64;
+      for gx in 0..gw {
+        let px = (gx * Self::GRID_STEP).min(w) as f64;
+        let cx = src_rect.pos.x as f64 + px * sx;
+        let cy = src_rect.pos.y as f64 + py * sy;
+        let coord = gdal::GeoTransformEx::apply(&self.chart_from_px, cx, cy);
+        x.push(coord.0);
+        y.push(coord.1);
+      }
+    }
+
+    to_dem.transform_coords(&mut x, &mut y, &mut [])?;
+
+    // Center latitude, used to get the cell size of geographic rasters.
+    let lat = y[y.len() / 2];
+    let grid: Vec<(f64, f64)> = x
+      .iter()
+      .zip(&y)
+      .map(|(x, y)| gdal::GeoTransformEx::apply(&self.to_px, *x, *y))
+      .collect();
+
+    // Elevation raster window that covers the grid (plus a cell for the edges of the hillshade).
+    let (dw, dh) = (self.px_size.w as f64, self.px_size.h as f64);
+    let min_x = grid.iter().map(|p| p.0).fold(f64::MAX, f64::min).floor() - 1.0;
+    let min_y = grid.iter().map(|p| p.1).fold(f64::MAX, f64::min).floor() - 1.0;
+    let max_x = grid.iter().map(|p| p.0).fold(f64::MIN, f64::max).ceil() + 1.0;
+    let max_y = grid.iter().map(|p| p.1).fold(f64::MIN, f64::max).ceil() + 1.0;
+    let (min_x, min_y) = (min_x.max(0.0), min_y.max(0.0));
+    let (max_x, max_y) = (max_x.min(dw), max_y.min(dh));
+    if min_x >= max_x || min_y >= max_y {
+      return Ok(image);
+    }
+
+    // Don't read the elevation data at a higher resolution than the output.
+    let win_size = ((max_x - min_x) as usize, (max_y - min_y) as usize);
+    let buf_size = (win_size.0.min(w + 2), win_size.1.min(h + 2));
+    let bx = buf_size.0 as f64 / win_size.0 as f64;
+    let by = buf_size.1 as f64 / win_size.1 as f64;
+
+    let raster = self.dataset.rasterband(1)?;
+    let buffer = raster.read_as::<f32>(
+      (min_x as isize, min_y as isize),
+      win_size,
+      buf_size,
+      Some(raster::ResampleAlg::Average),
+    )?;
+
+    let mut heights = buffer.data;
+    if let Some(no_data) = self.no_data {
+      for height in &mut heights {
+        if *height as f64 == no_data {
+          *height = f32::NAN;
+        }
+      }
+    }
+
+    // Cell size in meters.
+    let mut cell = (
+      self.from_px[1].hypot(self.from_px[2]) / bx,
+      self.from_px[4].hypot(self.from_px[5]) / by,
+    );
+    if self.geographic {
+      cell.0 *= util::METERS_PER_DEGREE * lat.to_radians().cos();
+      cell.1 *= util::METERS_PER_DEGREE;
+    }
+
+    let shade = hillshade(
+      &heights,
+      buf_size,
+      cell,
+      Self::SUN_AZIMUTH,
+      Self::SUN_ALTITUDE,
+    );
+
+    // Sample the hillshade for each output pixel.
+    for oy in 0..h {
+      let gy = (oy / Self::GRID_STEP).min(gh - 2);
+      let ty = (oy - gy * Self::GRID_STEP) as f64 / Self::GRID_STEP as f64;
+      for ox in 0..w {
+        let gx = (ox / Self::GRID_STEP).min(gw - 2);
+        let tx = (ox - gx * Self::GRID_STEP) as f64 / Self::GRID_STEP as f64;
+        let lerp =
+          |a: (f64, f64), b: (f64, f64), t: f64| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+        let top = lerp(grid[gy * gw + gx], grid[gy * gw + gx + 1], tx);
+        let bottom = lerp(grid[(gy + 1) * gw + gx], grid[(gy + 1) * gw + gx + 1], tx);
+        let (dx, dy) = lerp(top, bottom, ty);
+
+        let cx = ((dx - min_x) * bx).floor();
+        let cy = ((dy - min_y) * by).floor();
+        if cx < 0.0 || cy < 0.0 || cx >= buf_size.0 as f64 || cy >= buf_size.1 as f64 {
+          continue;
+        }
+
+        let value = shade[cy as usize * buf_size.0 + cx as usize];
+        if !value.is_nan() {
+          image.pixels[oy * w + ox] = epaint::Color32::from_gray((value * 255.0) as u8);
+        }
+      }
+    }
+
+    Ok(image)
+  }
+}
+
+/// Compute a hillshade using Horn's method. Returns the illumination for each cell in the range
+/// 0.0 (dark) to 1.0 (fully lit), or NaN where there's no elevation data.
+/// - `heights`: elevations in meters, row by row
+/// - `size`: width and height in cells
+/// - `cell`: cell width and height in meters
+/// - `azimuth`: direction of the light source in degrees clockwise from north
+/// - `altitude`: angle of the light source above the horizon in degrees
+fn hillshade(
+  heights: &[f32],
+  size: (usize, usize),
+  cell: (f64, f64),
+  azimuth: f64,
+  altitude: f64,
+) -> Vec<f32> {
+  let (w, h) = size;
+  let zenith = (90.0 - altitude).to_radians();
+  let azimuth = (360.0 - azimuth + 90.0).to_radians();
+  let get = |x: isize, y: isize| {
+    let x = x.clamp(0, w as isize - 1) as usize;
+    let y = y.clamp(0, h as isize - 1) as usize;
+    heights[y * w + x] as f64
+  };
+
+  let mut shade = Vec::with_capacity(w * h);
+  for y in 0..h as isize {
+    for x in 0..w as isize {
+      if get(x, y).is_nan() {
+        shade.push(f32::NAN);
+        continue;
+      }
+
+      // a b c
+      // d e f
+      // g h i
+      let (a, b, c) = (get(x - 1, y - 1), get(x, y - 1), get(x + 1, y - 1));
+      let (d, f) = (get(x - 1, y), get(x + 1, y));
+      let (g, h, i) = (get(x - 1, y + 1), get(x, y + 1), get(x + 1, y + 1));
+      let dzdx = ((c + 2.0 * f + i) - (a + 2.0 * d + g)) / (8.0 * cell.0);
+      let dzdy = ((g + 2.0 * h + i) - (a + 2.0 * b + c)) / (8.0 * cell.1);
+      let slope = dzdx.hypot(dzdy).atan();
+      let aspect = dzdy.atan2(-dzdx);
+      let value =
+        zenith.cos() * slope.cos() + zenith.sin() * slope.sin() * (azimuth - aspect).cos();
+
+      // NaN from missing neighboring elevation data passes through.
+      shade.push(value.clamp(0.0, 1.0) as f32);
+    }
+  }
+  shade
+}
+
+#[cfg(test)]
+mod test {
+  fn plane(size: usize, dx: f32, dy: f32) -> Vec<f32> {
+    let mut heights = Vec::with_capacity(size * size);
+    for y in 0..size {
+      for x in 0..size {
+        heights.push(x as f32 * dx + y as f32 * dy);
+      }
+    }
+    heights
+  }
+
+  #[test]
+  fn test_hillshade() {
+    let size = (8, 8);
+    let cell = (30.0, 30.0);
+
+    // Flat ground is lit by the sun's altitude.
+    let shade = super::hillshade(&plane(8, 0.0, 0.0), size, cell, 315.0, 45.0);
+    assert!(shade
+      .iter()
+      .all(|v| (*v as f64 - 45f64.to_radians().sin()).abs() < 1e-6));
+
+    // A slope facing north-west (towards the sun) is brighter than one facing south-east.
+    let toward = super::hillshade(&plane(8, 10.0, 10.0), size, cell, 315.0, 45.0);
+    let away = super::hillshade(&plane(8, -10.0, -10.0), size, cell, 315.0, 45.0);
+    assert!(toward[27] > 0.9 && away[27] < 0.5);
+
+    // Missing data stays missing.
+    let mut heights = plane(8, 0.0, 0.0);
+    heights[27] = f32::NAN;
+    let shade = super::hillshade(&heights, size, cell, 315.0, 45.0);
+    assert!(shade[27].is_nan() && shade[0] >= 0.0);
+  }
+}
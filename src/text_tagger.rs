@@ -0,0 +1,207 @@
+use eframe::egui;
+use std::ops;
+
+/// Finds phone numbers and web links in text (e.g. NASR remarks).
+pub struct TextTagger {
+  phone: regex::Regex,
+  url: regex::Regex,
+}
+
+impl Default for TextTagger {
+  fn default() -> Self {
+    // North American numbers: "907-555-1234", "(907) 555-1234", "1-800-992-7433", etc.
+    const PHONE: &str = r"(?:\b1[-. ])?(?:\(\d{3}\) ?|\b\d{3}[-. ])\d{3}[-. ]\d{4}\b";
+    const URL: &str = r"(?i)\bhttps?://[^\s<>\x22]+";
+    Self {
+      phone: regex::Regex::new(PHONE).unwrap(),
+      url: regex::Regex::new(URL).unwrap(),
+    }
+  }
+}
+
+impl TextTagger {
+  /// Find the links in some text, in order.
+  /// - `text`: text to search
+  pub fn links(&self, text: &str) -> Vec<Link> {
+    let mut links: Vec<Link> = self
+      .url
+      .find_iter(text)
+      .map(|found| Link {
+        range: found.start()..found.start() + trim_url(found.as_str()).len(),
+        kind: LinkKind::Url,
+      })
+      .collect();
+
+    // Skip numbers that are part of a web link.
+    for found in self.phone.find_iter(text) {
+      let range = found.range();
+      if !links.iter().any(|link| overlaps(&link.range, &range)) {
+        links.push(Link {
+          range,
+          kind: LinkKind::Phone,
+        });
+      }
+    }
+
+    links.sort_unstable_by_key(|link| link.range.start);
+    links
+  }
+
+  /// Find the links in some text and keep them with it.
+  /// - `text`: text to tag
+  pub fn tag(&self, text: String) -> TaggedText {
+    let links = self.links(&text);
+    TaggedText { text, links }
+  }
+}
+
+/// What a link opens.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LinkKind {
+  Phone,
+  Url,
+}
+
+/// A link within some text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Link {
+  /// Byte range of the link text.
+  pub range: ops::Range<usize>,
+  pub kind: LinkKind,
+}
+
+impl Link {
+  /// Address to open for this link (a web address or `tel:` URI).
+  /// - `text`: the text that the link was found in
+  pub fn target(&self, text: &str) -> String {
+    let link = &text[self.range.clone()];
+    match self.kind {
+      LinkKind::Phone => {
+        let digits: String = link.chars().filter(char::is_ascii_digit).collect();
+        format!("tel:{digits}")
+      }
+      LinkKind::Url => link.into(),
+    }
+  }
+}
+
+/// Text with its links.
+pub struct TaggedText {
+  text: String,
+  links: Vec<Link>,
+}
+
+impl TaggedText {
+  /// Show the text with each link as a clickable hyperlink.
+  pub fn show(&self, ui: &mut egui::Ui) {
+    if self.links.is_empty() {
+      ui.add(egui::Label::new(&self.text).wrap(true));
+      return;
+    }
+
+    ui.horizontal_wrapped(|ui| {
+      ui.spacing_mut().item_spacing.x = 0.0;
+      let mut pos = 0;
+      for link in &self.links {
+        if link.range.start > pos {
+          ui.label(&self.text[pos..link.range.start]);
+        }
+
+        let text = &self.text[link.range.clone()];
+        ui.hyperlink_to(text, link.target(&self.text));
+        pos = link.range.end;
+      }
+
+      if pos < self.text.len() {
+        ui.label(&self.text[pos..]);
+      }
+    });
+  }
+}
+
+/// Remove trailing punctuation that's more likely part of the sentence than the web link.
+fn trim_url(url: &str) -> &str {
+  let mut url = url;
+  loop {
+    let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'']);
+    let trimmed = match trimmed.strip_suffix(')') {
+      // Keep a closing parenthesis that has a match in the link.
+      Some(rest) if trimmed.matches('(').count() < trimmed.matches(')').count() => rest,
+      _ => trimmed,
+    };
+
+    if trimmed.len() == url.len() {
+      return url;
+    }
+    url = trimmed;
+  }
+}
+
+fn overlaps(a: &ops::Range<usize>, b: &ops::Range<usize>) -> bool {
+  a.start < b.end && b.start < a.end
+}
+
+#[cfg(test)]
+mod test {
+  use super::{LinkKind, TextTagger};
+
+  /// Link text and kind for each link found.
+  fn find(tagger: &TextTagger, text: &str) -> Vec<(String, LinkKind)> {
+    let links = tagger.links(text).into_iter();
+    links
+      .map(|link| (text[link.range].to_owned(), link.kind))
+      .collect()
+  }
+
+  #[test]
+  fn test_phone_numbers() {
+    let tagger = TextTagger::default();
+    let text = "FOR FUEL CALL 907-555-1234, AFTER HRS (907) 555-4321.";
+    let links = find(&tagger, text);
+    assert!(links.len() == 2);
+    assert!(links[0] == ("907-555-1234".into(), LinkKind::Phone));
+    assert!(links[1] == ("(907) 555-4321".into(), LinkKind::Phone));
+
+    let links = find(&tagger, "CTC FSS 1-800-992-7433;PPR.");
+    assert!(links == vec![("1-800-992-7433".into(), LinkKind::Phone)]);
+
+    let links = find(&tagger, "(CALL 907.555.1234)");
+    assert!(links == vec![("907.555.1234".into(), LinkKind::Phone)]);
+
+    // Not phone numbers.
+    assert!(find(&tagger, "RWY 13/31 5000 X 100").is_empty());
+    assert!(find(&tagger, "REF 12907-555-12345").is_empty());
+    assert!(find(&tagger, "NOTAM 3-555-1234").is_empty());
+  }
+
+  #[test]
+  fn test_urls() {
+    let tagger = TextTagger::default();
+    let text = "SEE HTTPS://WWW.EXAMPLE.GOV/AIRPORT.";
+    let links = find(&tagger, text);
+    assert!(links == vec![("HTTPS://WWW.EXAMPLE.GOV/AIRPORT".into(), LinkKind::Url)]);
+
+    let text = "INFO (http://example.com/a_(b)), OR http://example.com/x?y=1.";
+    let links = find(&tagger, text);
+    assert!(links.len() == 2);
+    assert!(links[0].0 == "http://example.com/a_(b)");
+    assert!(links[1].0 == "http://example.com/x?y=1");
+
+    // Numbers within a web link aren't tagged separately.
+    let text = "http://example.com/907-555-1234 OR 907-555-4321";
+    let links = find(&tagger, text);
+    assert!(links.len() == 2);
+    assert!(links[0] == ("http://example.com/907-555-1234".into(), LinkKind::Url));
+    assert!(links[1] == ("907-555-4321".into(), LinkKind::Phone));
+  }
+
+  #[test]
+  fn test_targets() {
+    let tagger = TextTagger::default();
+    let text = "CALL (907) 555-1234 OR SEE https://example.com.";
+    let links = tagger.links(text);
+    assert!(links.len() == 2);
+    assert!(links[0].target(text) == "tel:9075551234");
+    assert!(links[1].target(text) == "https://example.com");
+  }
+}
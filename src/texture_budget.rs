@@ -0,0 +1,155 @@
+use std::collections;
+
+/// Retained textures, in eviction order (first evicted first).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TextureSlot {
+  /// Chart image in the other palette for the palette preview.
+  AltPalette,
+
+  /// Reduced image of the whole chart for the overview inset.
+  Thumbnail,
+
+  /// Terrain shading over the visible chart.
+  Terrain,
+
+  /// Visible chart image.
+  Chart,
+}
+
+impl TextureSlot {
+  /// True if the texture can be dropped to free memory while it isn't shown. The chart and terrain
+  /// textures are always shown, so they're never evicted.
+  pub fn evictable(self) -> bool {
+    matches!(self, Self::AltPalette | Self::Thumbnail)
+  }
+
+  pub fn text(self) -> &'static str {
+    match self {
+      Self::AltPalette => "palette preview",
+      Self::Thumbnail => "overview",
+      Self::Terrain => "terrain",
+      Self::Chart => "chart",
+    }
+  }
+}
+
+/// Tracks the memory used by retained textures and decides which ones to drop when there's too
+/// much.
+pub struct TextureBudget {
+  /// Maximum number of bytes before textures are evicted.
+  cap: usize,
+
+  /// Size in bytes of each retained texture.
+  sizes: collections::BTreeMap<TextureSlot, usize>,
+}
+
+impl TextureBudget {
+  /// Default cap in megabytes.
+  pub const DEFAULT_CAP_MB: u32 = 256;
+
+  /// Create a texture budget.
+  /// - `cap_mb`: maximum texture memory in megabytes
+  pub fn new(cap_mb: u32) -> Self {
+    Self {
+      cap: cap_mb as usize * 1024 * 1024,
+      sizes: collections::BTreeMap::new(),
+    }
+  }
+
+  /// Record the size of a texture.
+  /// - `slot`: texture slot
+  /// - `size`: texture size in pixels, `None` if the slot is empty
+  pub fn record(&mut self, slot: TextureSlot, size: Option<[usize; 2]>) {
+    match size {
+      Some([w, h]) => self.sizes.insert(slot, w * h * 4),
+      None => self.sizes.remove(&slot),
+    };
+  }
+
+  /// Total bytes used by the recorded textures.
+  pub fn total(&self) -> usize {
+    self.sizes.values().sum()
+  }
+
+  /// Maximum number of bytes before textures are evicted.
+  pub fn cap(&self) -> usize {
+    self.cap
+  }
+
+  /// Choose textures to drop until the total is within the cap. The chosen slots are forgotten,
+  /// so the caller must empty them.
+  /// - `shown`: slots whose textures are currently on screen, which are kept
+  ///
+  /// Dropping a texture that's on screen would only have it read and stored again, so the total
+  /// can stay over the cap while those textures are shown.
+  pub fn evict(&mut self, shown: &[TextureSlot]) -> Vec<(TextureSlot, usize)> {
+    let mut evicted = Vec::new();
+    let mut total = self.total();
+    while total > self.cap {
+      let mut sizes = self.sizes.iter();
+      let Some((slot, bytes)) = sizes.find(|(slot, _)| slot.evictable() && !shown.contains(slot))
+      else {
+        break;
+      };
+
+      let (slot, bytes) = (*slot, *bytes);
+      self.sizes.remove(&slot);
+      evicted.push((slot, bytes));
+      total -= bytes;
+    }
+    evicted
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{TextureBudget, TextureSlot};
+
+  #[test]
+  fn test_eviction_order() {
+    // 1 MB holds 262144 pixels.
+    let mut budget = TextureBudget::new(1);
+    budget.record(TextureSlot::Chart, Some([256, 512]));
+    budget.record(TextureSlot::Terrain, Some([256, 256]));
+    budget.record(TextureSlot::Thumbnail, Some([128, 128]));
+    budget.record(TextureSlot::AltPalette, Some([256, 512]));
+    assert!(budget.total() == (131072 + 65536 + 16384 + 131072) * 4);
+
+    // The palette preview goes first and is enough.
+    let evicted = budget.evict(&[]);
+    assert!(evicted == vec![(TextureSlot::AltPalette, 131072 * 4)]);
+    assert!(budget.total() <= budget.cap());
+    assert!(budget.evict(&[]).is_empty());
+
+    // Then the overview.
+    budget.record(TextureSlot::Chart, Some([512, 384]));
+    let evicted = budget.evict(&[]);
+    assert!(evicted == vec![(TextureSlot::Thumbnail, 16384 * 4)]);
+
+    // Visible textures are never evicted, even when over the cap.
+    budget.record(TextureSlot::Chart, Some([1024, 1024]));
+    budget.record(TextureSlot::AltPalette, Some([16, 16]));
+    let evicted = budget.evict(&[]);
+    assert!(evicted == vec![(TextureSlot::AltPalette, 1024)]);
+    assert!(budget.total() > budget.cap());
+
+    // Textures that are on screen are kept, even when the visible textures alone are over the cap.
+    budget.record(TextureSlot::Thumbnail, Some([128, 128]));
+    budget.record(TextureSlot::AltPalette, Some([1024, 1024]));
+    let shown = [TextureSlot::Thumbnail, TextureSlot::AltPalette];
+    assert!(budget.evict(&shown).is_empty());
+    assert!(budget.total() == (1048576 + 65536 + 16384 + 1048576) * 4);
+
+    // The overview is still kept while only the palette preview is hidden.
+    let evicted = budget.evict(&[TextureSlot::Thumbnail]);
+    assert!(evicted == vec![(TextureSlot::AltPalette, 1048576 * 4)]);
+
+    // Once it's hidden the overview goes too.
+    let evicted = budget.evict(&[]);
+    assert!(evicted == vec![(TextureSlot::Thumbnail, 16384 * 4)]);
+
+    // Emptied slots aren't counted.
+    budget.record(TextureSlot::Chart, None);
+    assert!(budget.total() == 65536 * 4);
+  }
+}
@@ -0,0 +1,152 @@
+use eframe::{egui, emath};
+use std::{collections, time};
+
+/// Toasts are non-modal notifications in the corner of the window. A toast either offers an action
+/// or is a short message that goes away by itself. They're shown one at a time so that they never
+/// overlap, with a message ahead of any queued actions.
+pub struct Toasts<T> {
+  /// Short message and the time when it's removed.
+  message: Option<(String, f64)>,
+  queue: collections::VecDeque<Toast<T>>,
+}
+
+struct Toast<T> {
+  text: String,
+
+  /// Data for the action, returned when the action is chosen.
+  data: T,
+}
+
+impl<T> Default for Toasts<T> {
+  fn default() -> Self {
+    Self {
+      message: None,
+      queue: collections::VecDeque::new(),
+    }
+  }
+}
+
+impl<T: PartialEq> Toasts<T> {
+  /// How long a message is shown, in seconds.
+  const MESSAGE_DURATION: f64 = 5.0;
+
+  /// Show a short message, replacing any message that's already showing.
+  /// - `ctx`: egui context
+  /// - `text`: message text
+  pub fn message(&mut self, ctx: &egui::Context, text: String) {
+    let until = ctx.input(|state| state.time) + Self::MESSAGE_DURATION;
+    self.message = Some((text, until));
+  }
+
+  /// Add a toast to the end of the queue. A toast for the same data replaces the queued one.
+  /// - `text`: notification text
+  /// - `data`: data for the action
+  pub fn push(&mut self, text: String, data: T) {
+    self.queue.retain(|toast| toast.data != data);
+    self.queue.push_back(Toast { text, data });
+  }
+
+  /// Show the message until it expires, otherwise the first toast in the queue. Choosing the
+  /// action or dismissing the toast removes it.
+  /// - `ctx`: egui context
+  /// - `action`: action button text (e.g. "Open")
+  /// - `enabled`: whether the buttons can be used
+  pub fn show(&mut self, ctx: &egui::Context, action: &str, enabled: bool) -> Option<Response<T>> {
+    if let Some((text, until)) = &self.message {
+      let remaining = until - ctx.input(|state| state.time);
+      if remaining > 0.0 {
+        show_area(ctx, |ui| {
+          ui.label(text);
+        });
+        ctx.request_repaint_after(time::Duration::from_secs_f64(remaining));
+        return None;
+      }
+      self.message = None;
+    }
+
+    let toast = self.queue.front()?;
+    let more = self.queue.len() - 1;
+    let mut response = None;
+    show_area(ctx, |ui| {
+      ui.label(&toast.text);
+      ui.horizontal(|ui| {
+        ui.set_enabled(enabled);
+        if ui.button(action).clicked() {
+          response = Some(true);
+        }
+
+        if ui.button("Dismiss").clicked() {
+          response = Some(false);
+        }
+
+        if more > 0 {
+          ui.label(egui::RichText::new(format!("+{more} more")).weak());
+        }
+      });
+    });
+
+    let action = response?;
+    let toast = self.queue.pop_front()?;
+    Some(if action {
+      Response::Action(toast.data)
+    } else {
+      Response::Dismiss
+    })
+  }
+}
+
+/// Show a toast's contents in the corner of the window.
+/// - `ctx`: egui context
+/// - `add_contents`: toast contents
+fn show_area(ctx: &egui::Context, add_contents: impl FnOnce(&mut egui::Ui)) {
+  egui::Area::new("toast".into())
+    .order(egui::Order::Foreground)
+    .anchor(emath::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+    .show(ctx, |ui| {
+      egui::Frame::popup(ui.style()).show(ui, |ui| {
+        ui.set_max_width(360.0);
+        add_contents(ui);
+      });
+    });
+}
+
+pub enum Response<T> {
+  /// The action was chosen for the toast's data.
+  Action(T),
+  Dismiss,
+}
+
+#[cfg(test)]
+mod test {
+  use super::Toasts;
+
+  #[test]
+  fn test_queue() {
+    let mut toasts = Toasts::default();
+    toasts.push("first".into(), 1);
+    toasts.push("second".into(), 2);
+    toasts.push("first again".into(), 1);
+
+    // Toasts are shown in order, and a repeat replaces the queued one.
+    let texts: Vec<&str> = toasts
+      .queue
+      .iter()
+      .map(|toast| toast.text.as_str())
+      .collect();
+    assert!(texts == ["second", "first again"]);
+  }
+
+  #[test]
+  fn test_message() {
+    let ctx = eframe::egui::Context::default();
+    let mut toasts = Toasts::default();
+    toasts.push("open".into(), 1);
+    toasts.message(&ctx, "first".into());
+    toasts.message(&ctx, "second".into());
+
+    // A message replaces the one that's showing, and doesn't touch the queue.
+    let (text, until) = toasts.message.as_ref().unwrap();
+    assert!(text == "second" && *until == Toasts::<i32>::MESSAGE_DURATION);
+    assert!(toasts.queue.len() == 1);
+  }
+}
@@ -80,7 +80,12 @@ impl LongPressTracker {
         self.ids.insert(id.0);
       }
       egui::TouchPhase::Move => {
-        self.remove_info();
+        // Allow a little finger jitter before canceling.
+        if let Some(info) = &self.info {
+          if info.pos.distance(pos) > LongPressTracker::SLOP_RADIUS {
+            self.remove_info();
+          }
+        }
       }
       egui::TouchPhase::End | egui::TouchPhase::Cancel => {
         self.ids.remove(&id.0);
@@ -101,6 +106,14 @@ impl LongPressTracker {
     None
   }
 
+  /// Convert the pending touch position after a display scale change.
+  /// - `ratio`: old pixels per point divided by the new pixels per point
+  pub fn rescale(&mut self, ratio: f32) {
+    if let Some(info) = &mut self.info {
+      info.pos = (info.pos.to_vec2() * ratio).to_pos2();
+    }
+  }
+
   fn remove_info(&mut self) {
     if self.info.take().is_some() {
       self.send(Request::Cancel);
@@ -112,6 +125,9 @@ impl LongPressTracker {
   }
 
   const LONG_PRESS_DUR: time::Duration = time::Duration::from_secs(1);
+
+  /// How far (in points) a touch can move and still be a long press.
+  const SLOP_RADIUS: f32 = 8.0;
 }
 
 impl Drop for LongPressTracker {
@@ -134,3 +150,307 @@ fn check_time(time: Option<time::SystemTime>) -> bool {
   }
   false
 }
+
+/// Pan and zoom values from a two-finger gesture.
+#[derive(Debug, PartialEq)]
+pub struct Gesture {
+  /// Zoom factor.
+  pub zoom: f32,
+
+  /// Position that the zoom is anchored to (touch centroid before the change).
+  pub anchor: emath::Pos2,
+
+  /// Translation of the touch centroid.
+  pub pan: emath::Vec2,
+
+  /// Translation of the primary touch, which egui also reports as pointer movement.
+  pub drag: emath::Vec2,
+
+  /// Current touch centroid.
+  pub center: emath::Pos2,
+
+  /// True if this is the first part of a new gesture (`anchor` is where it started).
+  pub begin: bool,
+}
+
+/// A point on the chart that stays under the touch centroid for the duration of a pinch gesture.
+///
+/// The scroll offset is recomputed from this fixed point for every event rather than from the
+/// previous (rounded) offset, so rounding errors don't accumulate during a long pinch.
+#[derive(Clone, Copy, Debug)]
+pub struct PinchAnchor {
+  /// Chart position at a zoom of 1.0.
+  point: emath::Vec2,
+}
+
+impl PinchAnchor {
+  /// Create an anchor at the start of a gesture.
+  /// - `offset`: scroll offset when the gesture started
+  /// - `start`: touch centroid relative to the chart view when the gesture started
+  /// - `zoom`: chart zoom when the gesture started
+  pub fn new(offset: emath::Vec2, start: emath::Vec2, zoom: f32) -> Self {
+    Self {
+      point: (offset + start) / zoom,
+    }
+  }
+
+  /// Get the unrounded scroll offset that places the anchor under the touch centroid.
+  /// - `center`: current touch centroid relative to the chart view
+  /// - `zoom`: current chart zoom
+  pub fn offset(&self, center: emath::Vec2, zoom: f32) -> emath::Vec2 {
+    self.point * zoom - center
+  }
+}
+
+/// Turns raw touch events into a combined pan and zoom for two-finger gestures.
+#[derive(Default)]
+pub struct GestureTracker {
+  touches: Vec<(u64, emath::Pos2)>,
+  primary: Option<u64>,
+  gesture: Option<Gesture>,
+  active: bool,
+}
+
+impl GestureTracker {
+  pub fn initiate(&mut self, id: egui::TouchId, phase: egui::TouchPhase, pos: emath::Pos2) {
+    match phase {
+      egui::TouchPhase::Start => {
+        // The pointer is emulated using the first touch while no other touch is being emulated.
+        if self.primary.is_none() {
+          self.primary = Some(id.0);
+        }
+        self.touches.retain(|(tid, _)| *tid != id.0);
+        self.touches.push((id.0, pos));
+      }
+      egui::TouchPhase::Move => {
+        let Some(index) = self.touches.iter().position(|(tid, _)| *tid == id.0) else {
+          return;
+        };
+
+        // Only the first two touches participate in the gesture.
+        if index > 1 || self.touches.len() < 2 {
+          self.touches[index].1 = pos;
+          return;
+        }
+
+        let (old_center, old_dist) = self.pinch();
+        let old_pos = self.touches[index].1;
+        self.touches[index].1 = pos;
+        let (new_center, new_dist) = self.pinch();
+
+        let begin = !self.active;
+        self.active = true;
+
+        let gesture = self.gesture.get_or_insert(Gesture {
+          zoom: 1.0,
+          anchor: old_center,
+          pan: emath::Vec2::ZERO,
+          drag: emath::Vec2::ZERO,
+          center: new_center,
+          begin,
+        });
+
+        if old_dist > 0.0 && new_dist > 0.0 {
+          gesture.zoom *= new_dist / old_dist;
+        }
+
+        gesture.pan += new_center - old_center;
+        gesture.center = new_center;
+        if self.primary == Some(id.0) {
+          gesture.drag += pos - old_pos;
+        }
+      }
+      egui::TouchPhase::End | egui::TouchPhase::Cancel => {
+        self.touches.retain(|(tid, _)| *tid != id.0);
+        if self.primary == Some(id.0) {
+          self.primary = None;
+        }
+
+        // Lifting one of the two fingers ends the gesture.
+        if self.touches.len() < 2 {
+          self.active = false;
+        }
+      }
+    }
+  }
+
+  /// Take the gesture accumulated since the last call.
+  pub fn take(&mut self) -> Option<Gesture> {
+    self.gesture.take()
+  }
+
+  /// Convert the tracked touch positions after a display scale change, so that the next move
+  /// doesn't look like a jump.
+  /// - `ratio`: old pixels per point divided by the new pixels per point
+  pub fn rescale(&mut self, ratio: f32) {
+    let scale = |pos: emath::Pos2| (pos.to_vec2() * ratio).to_pos2();
+    for (_, pos) in &mut self.touches {
+      *pos = scale(*pos);
+    }
+
+    if let Some(gesture) = &mut self.gesture {
+      gesture.anchor = scale(gesture.anchor);
+      gesture.center = scale(gesture.center);
+      gesture.pan *= ratio;
+      gesture.drag *= ratio;
+    }
+  }
+
+  /// Centroid and distance of the first two touches.
+  fn pinch(&self) -> (emath::Pos2, f32) {
+    let a = self.touches[0].1;
+    let b = self.touches[1].1;
+    (a + (b - a) * 0.5, a.distance(b))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use eframe::{egui, emath};
+
+  fn touch(tracker: &mut super::GestureTracker, id: u64, phase: egui::TouchPhase, x: f32, y: f32) {
+    tracker.initiate(egui::TouchId(id), phase, emath::pos2(x, y));
+  }
+
+  #[test]
+  fn test_long_press_slop() {
+    use egui::TouchPhase::*;
+    let ctx = egui::Context::default();
+    let mut tracker = super::LongPressTracker::new(&ctx);
+    let id = egui::TouchId(1);
+
+    // Small movements don't cancel the long press.
+    tracker.initiate(id, Start, emath::pos2(100.0, 100.0));
+    tracker.initiate(id, Move, emath::pos2(104.0, 105.0));
+    assert!(tracker.info.is_some());
+
+    // Moving past the slop radius does.
+    tracker.initiate(id, Move, emath::pos2(110.0, 100.0));
+    assert!(tracker.info.is_none());
+    tracker.initiate(id, End, emath::pos2(110.0, 100.0));
+
+    // A second finger cancels it as well.
+    tracker.initiate(id, Start, emath::pos2(100.0, 100.0));
+    tracker.initiate(egui::TouchId(2), Start, emath::pos2(200.0, 100.0));
+    assert!(tracker.info.is_none());
+  }
+
+  #[test]
+  fn test_single_touch_is_not_a_gesture() {
+    use egui::TouchPhase::*;
+    let mut tracker = super::GestureTracker::default();
+    touch(&mut tracker, 1, Start, 10.0, 10.0);
+    touch(&mut tracker, 1, Move, 50.0, 30.0);
+    touch(&mut tracker, 1, End, 50.0, 30.0);
+    assert!(tracker.take().is_none());
+  }
+
+  #[test]
+  fn test_pinch_zoom() {
+    use egui::TouchPhase::*;
+    let mut tracker = super::GestureTracker::default();
+    touch(&mut tracker, 1, Start, 90.0, 100.0);
+    touch(&mut tracker, 2, Start, 110.0, 100.0);
+    touch(&mut tracker, 1, Move, 80.0, 100.0);
+    touch(&mut tracker, 2, Move, 120.0, 100.0);
+
+    let gesture = tracker.take().unwrap();
+    assert!((gesture.zoom - 2.0).abs() < 1e-6);
+    assert!(gesture.anchor == emath::pos2(100.0, 100.0));
+    assert!(gesture.pan == emath::Vec2::ZERO);
+    assert!(gesture.drag == emath::vec2(-10.0, 0.0));
+    assert!(gesture.center == emath::pos2(100.0, 100.0));
+    assert!(gesture.begin);
+
+    // Nothing new since the last take.
+    assert!(tracker.take().is_none());
+
+    // Still the same gesture.
+    touch(&mut tracker, 2, Move, 130.0, 100.0);
+    assert!(!tracker.take().unwrap().begin);
+
+    // Lifting a finger ends it.
+    touch(&mut tracker, 2, End, 130.0, 100.0);
+    touch(&mut tracker, 4, Start, 120.0, 100.0);
+    touch(&mut tracker, 4, Move, 140.0, 100.0);
+    assert!(tracker.take().unwrap().begin);
+  }
+
+  #[test]
+  fn test_pinch_anchor_drift() {
+    let view = emath::vec2(800.0, 600.0);
+    let mut zoom = 0.5;
+    let mut offset = emath::vec2(1234.0, 567.0);
+    let start = emath::vec2(400.0, 300.0);
+    let anchor = super::PinchAnchor::new(offset, start, zoom);
+    let point = (offset + start) / zoom;
+
+    // 1000 alternating small zoom steps with the fingers wandering slightly.
+    for step in 0..1000 {
+      let factor = if step % 2 == 0 { 1.013 } else { 1.0 / 1.011 };
+      zoom = (zoom * factor).clamp(0.125, 1.0);
+      let wobble = (step % 7) as f32 * 0.37;
+      let center = (start + emath::vec2(wobble, -wobble)).min(view);
+
+      // The scroll area only takes whole pixels.
+      offset = anchor.offset(center, zoom).round();
+
+      // The anchor point stays under the centroid (within rounding).
+      let under = (offset + center) / zoom;
+      assert!((under - point).length() * zoom <= 1.0);
+    }
+  }
+
+  #[test]
+  fn test_pinch_with_drift() {
+    use egui::TouchPhase::*;
+    let mut tracker = super::GestureTracker::default();
+    touch(&mut tracker, 1, Start, 90.0, 100.0);
+    touch(&mut tracker, 2, Start, 110.0, 100.0);
+
+    // Both fingers drift down while spreading apart.
+    touch(&mut tracker, 1, Move, 80.0, 120.0);
+    touch(&mut tracker, 2, Move, 120.0, 120.0);
+
+    let gesture = tracker.take().unwrap();
+    assert!((gesture.zoom - 2.0).abs() < 1e-6);
+    assert!(gesture.anchor == emath::pos2(100.0, 100.0));
+    assert!(gesture.pan == emath::vec2(0.0, 20.0));
+    assert!(gesture.drag == emath::vec2(-10.0, 20.0));
+  }
+
+  #[test]
+  fn test_secondary_touch_becomes_single() {
+    use egui::TouchPhase::*;
+    let mut tracker = super::GestureTracker::default();
+    touch(&mut tracker, 1, Start, 90.0, 100.0);
+    touch(&mut tracker, 2, Start, 110.0, 100.0);
+    touch(&mut tracker, 1, End, 90.0, 100.0);
+    touch(&mut tracker, 2, Move, 150.0, 100.0);
+    assert!(tracker.take().is_none());
+
+    // The new touch becomes the primary touch, so moving the other one isn't a drag.
+    touch(&mut tracker, 3, Start, 130.0, 100.0);
+    touch(&mut tracker, 2, Move, 170.0, 100.0);
+    let gesture = tracker.take().unwrap();
+    assert!(gesture.drag == emath::Vec2::ZERO);
+    assert!(gesture.pan == emath::vec2(10.0, 0.0));
+  }
+
+  #[test]
+  fn test_rescale() {
+    use egui::TouchPhase::*;
+    let mut tracker = super::GestureTracker::default();
+    touch(&mut tracker, 1, Start, 100.0, 100.0);
+    touch(&mut tracker, 2, Start, 200.0, 100.0);
+
+    // The window moves to a monitor with twice the pixels per point while the fingers are down.
+    // Moving a finger 10 points afterward pans by 10 points rather than jumping.
+    tracker.rescale(0.5);
+    touch(&mut tracker, 2, Move, 110.0, 50.0);
+    let gesture = tracker.take().unwrap();
+    assert!((gesture.zoom - 1.2).abs() < 1e-6);
+    assert!(gesture.anchor == emath::pos2(75.0, 50.0));
+    assert!(gesture.pan == emath::vec2(5.0, 0.0));
+  }
+}
@@ -1,9 +1,17 @@
 use eframe::{egui, emath, epaint};
 use gdal::{raster, spatial_ref};
-use std::{borrow, cmp, collections, ops, path};
+use std::{borrow, cmp, collections, fmt, mem, ops, path, time};
 
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
 pub const APP_ICON: &[u8] = include_bytes!("../res/icon.png");
+pub const METERS_PER_NM: f64 = 1852.0;
+pub const METERS_PER_FOOT: f64 = 0.3048;
+
+/// Approximate meters per degree of latitude.
+pub const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Maximum number of name search matches returned unless all of them are asked for.
+pub const MAX_SEARCH_RESULTS: usize = 50;
 
 #[macro_export]
 macro_rules! debugln {
@@ -17,7 +25,7 @@ macro_rules! ok {
     match $res {
       Ok(val) => val,
       Err(err) => {
-        println!("{err:?}");
+        log::warn!("{err:?}");
         return;
       }
     }
@@ -26,7 +34,7 @@ macro_rules! ok {
     match $res {
       Ok(val) => val,
       Err(err) => {
-        println!("{err:?}");
+        log::warn!("{err:?}");
         return $ret;
       }
     }
@@ -40,34 +48,99 @@ pub enum ZipInfo {
   /// Chart raster data.
   Chart(Vec<path::PathBuf>),
 
-  /// NASR aeronautical data.
+  /// NASR aeronautical data. The airspace shape files are missing from "CSV only" downloads.
   Aero {
-    csv: path::PathBuf,
-    #[allow(unused)]
-    shp: path::PathBuf,
+    csv: CsvPath,
+    shp: Option<path::PathBuf>,
   },
 }
 
+/// Where the NASR CSV files are within a NASR zip file.
+#[derive(Debug, PartialEq)]
+pub enum CsvPath {
+  /// Inner zip file holding the CSV files (e.g. "CSV_Data/16_May_2024_CSV.zip").
+  Zip(path::PathBuf),
+
+  /// Folder holding the unzipped CSV files (empty for the top level).
+  Folder(path::PathBuf),
+}
+
+impl CsvPath {
+  /// GDAL virtual file system path for one of the CSV files.
+  /// - `zip`: path to the NASR zip file
+  /// - `file`: CSV file name (e.g. "APT_BASE.csv")
+  pub fn vsi_file(&self, zip: &path::Path, file: &str) -> Result<path::PathBuf, Error> {
+    Ok(match self {
+      Self::Zip(inner) => vsi_path("/vsizip//vsizip/", zip)?.join(inner).join(file),
+      Self::Folder(folder) => vsi_path("/vsizip/", zip)?.join(folder).join(file),
+    })
+  }
+}
+
+/// CSV files that must be present for NASR data to be usable.
+const NASR_CSV_FILES: [&str; 2] = ["APT_BASE.csv", "APT_RWY.csv"];
+
+/// Maximum folder depth searched for the NASR CSV files.
+const MAX_CSV_DEPTH: usize = 2;
+
+/// Message for airspace features when the NASR download doesn't include the shape files.
+pub const NO_AIRSPACE_DATA: &str = "Airspace data not included in this NASR download";
+
+/// Prefix a file path for GDAL's virtual file system.
+/// - `prefix`: VSI prefix, such as `/vsizip/`
+/// - `path`: file path
+///
+/// GDAL takes paths as UTF-8 strings, so paths that aren't valid unicode (or contain a nul) are
+/// rejected.
+pub fn vsi_path<P: AsRef<path::Path>>(prefix: &str, path: P) -> Result<path::PathBuf, Error> {
+  let path = path.as_ref();
+  match path.to_str() {
+    Some(text) if !text.contains('\0') => Ok([prefix, text].concat().into()),
+    _ => {
+      let path = path.to_string_lossy();
+      Err(format!("Path contains characters that cannot be used:\n{path}").into())
+    }
+  }
+}
+
+/// True if the files include the NASR CSV files in a folder.
+/// - `files`: file paths
+/// - `folder`: folder to check
+fn has_csv_files(files: &[path::PathBuf], folder: &path::Path) -> bool {
+  NASR_CSV_FILES.iter().all(|name| {
+    let path = folder.join(name);
+    files.contains(&path)
+  })
+}
+
+/// True if an inner zip file holds the NASR CSV files at its top level.
+/// - `path`: VSI path to the inner zip file
+fn inner_zip_has_csv(path: &path::Path) -> bool {
+  let Ok(path) = vsi_path("/vsizip/", path) else {
+    return false;
+  };
+
+  match gdal::vsi::read_dir(path, false) {
+    Ok(files) => has_csv_files(&files, path::Path::new("")),
+    Err(_) => false,
+  }
+}
+
 /// Returns information about what type of FAA data (if any) is contained in a zip file.
 pub fn get_zip_info<P: AsRef<path::Path>>(path: P) -> Result<ZipInfo, Error> {
   _get_zip_info(path.as_ref())
 }
 
 fn _get_zip_info(path: &path::Path) -> Result<ZipInfo, Error> {
-  let Some(path) = path.to_str() else {
-    return Err("Invalid unicode in zip file path".into());
-  };
-
-  // Concatenate the VSI prefix.
-  let path = ["/vsizip/", path].concat();
-
-  match gdal::vsi::read_dir(path, true) {
+  let path = vsi_path("/vsizip/", path)?;
+  match gdal::vsi::read_dir(&path, true) {
     Ok(files) => {
-      let mut csv = path::PathBuf::new();
+      let mut zips = Vec::new();
+      let mut folders = Vec::new();
       let mut shp = path::PathBuf::new();
       let mut tfws = collections::HashSet::new();
       let mut tifs = Vec::new();
-      for file in files {
+      for file in &files {
         let Some(ext) = file.extension() else {
           continue;
         };
@@ -77,15 +150,19 @@ fn _get_zip_info(path: &path::Path) -> Result<ZipInfo, Error> {
           continue;
         }
 
+        // Folder depth is the number of components before the file name.
+        let depth = file.components().count() - 1;
         if ext.eq_ignore_ascii_case("tfw") {
-          tfws.insert(file);
+          tfws.insert(file.clone());
         } else if ext.eq_ignore_ascii_case("tif") {
-          tifs.push(file);
-        } else if csv.as_os_str().is_empty() && ext.eq_ignore_ascii_case("zip") {
-          if let Some(stem) = file.file_stem().and_then(|stem| stem.to_str()) {
-            if stem.to_ascii_uppercase().ends_with("_CSV") {
-              csv = file;
-            }
+          tifs.push(file.clone());
+        } else if ext.eq_ignore_ascii_case("zip") {
+          if depth <= MAX_CSV_DEPTH {
+            zips.push(file.clone());
+          }
+        } else if file.file_name() == Some(NASR_CSV_FILES[0].as_ref()) {
+          if let Some(parent) = file.parent().filter(|_| depth <= MAX_CSV_DEPTH) {
+            folders.push(parent.to_owned());
           }
         } else if shp.as_os_str().is_empty() && ext.eq_ignore_ascii_case("shp") {
           if let Some(stem) = file.file_stem() {
@@ -99,8 +176,29 @@ fn _get_zip_info(path: &path::Path) -> Result<ZipInfo, Error> {
         }
       }
 
-      // Both the shape folder and CSV zip must be present for aero data to be valid.
-      if !csv.as_os_str().is_empty() && !shp.as_os_str().is_empty() {
+      // Try zips named like the FAA's CSV zip first (e.g. "16_May_2024_CSV.zip").
+      zips.sort_by_key(|file| {
+        let stem = file.file_stem().and_then(|stem| stem.to_str());
+        !stem.is_some_and(|stem| stem.to_ascii_uppercase().ends_with("_CSV"))
+      });
+
+      // Look for the CSV files in an inner zip, then unzipped in a folder.
+      let zip = zips
+        .into_iter()
+        .find(|file| inner_zip_has_csv(&path.join(file)));
+      let csv = match zip {
+        Some(zip) => Some(CsvPath::Zip(zip)),
+        None => {
+          let folder = folders
+            .into_iter()
+            .find(|folder| has_csv_files(&files, folder));
+          folder.map(CsvPath::Folder)
+        }
+      };
+
+      // The CSV files must be present for aero data to be valid. The shape folder is optional.
+      if let Some(csv) = csv {
+        let shp = (!shp.as_os_str().is_empty()).then_some(shp);
         return Ok(ZipInfo::Aero { csv, shp });
       }
 
@@ -157,14 +255,20 @@ pub struct WinInfo {
   pub pos: Option<Pos>,
   pub size: Option<Size>,
   pub maxed: bool,
+  pub fullscreen: bool,
+
+  /// Size of the monitor that the window was on.
+  pub monitor: Option<Size>,
 }
 
 impl WinInfo {
   pub fn new(info: &egui::ViewportInfo) -> Self {
     Self {
-      pos: info.inner_rect.map(|r| r.min.into()),
+      pos: info.outer_rect.map(|r| r.min.into()),
       size: info.inner_rect.map(|r| r.size().into()),
-      maxed: info.fullscreen.unwrap_or(false),
+      maxed: info.maximized.unwrap_or(false),
+      fullscreen: info.fullscreen.unwrap_or(false),
+      monitor: info.monitor_size.map(|s| s.into()),
     }
   }
 
@@ -172,15 +276,61 @@ impl WinInfo {
     if let Some(value) = value {
       let pos = value.get(WinInfo::POS_KEY).and_then(Pos::from_value);
       let size = value.get(WinInfo::SIZE_KEY).and_then(Size::from_value);
-      let maxed = value
-        .get(WinInfo::MAXED_KEY)
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-      return Self { pos, size, maxed };
+      let get_bool = |key| value.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+      let maxed = get_bool(WinInfo::MAXED_KEY);
+      let fullscreen = get_bool(WinInfo::FULLSCREEN_KEY);
+      let monitor = value.get(WinInfo::MONITOR_KEY).and_then(Size::from_value);
+      return Self {
+        pos,
+        size,
+        maxed,
+        fullscreen,
+        monitor,
+      };
     }
     WinInfo::default()
   }
 
+  /// Get the window position to restore.
+  /// > **NOTE**: the monitor layout isn't available before the window is created, and only the size
+  /// > of the monitor that the window was on is known, not its origin. The position is only kept
+  /// > on screen when it's on the primary monitor, which is the one at (0, 0). Anywhere else the
+  /// > monitor geometry is unknown, so the saved position is used as is and then checked with
+  /// > [`WinInfo::check_restored`] once the window is shown.
+  pub fn restore_pos(&self) -> Option<emath::Pos2> {
+    let pos: emath::Pos2 = self.pos?.into();
+    let (Some(size), Some(monitor)) = (self.size, self.monitor) else {
+      return Some(pos);
+    };
+
+    let screen = emath::Rect::from_min_size(emath::Pos2::ZERO, monitor.into());
+    if !screen.contains(pos) {
+      return Some(pos);
+    }
+
+    let rect = fit_to_screens(emath::Rect::from_min_size(pos, size.into()), &[screen]);
+    Some(rect.min)
+  }
+
+  /// Check the position of the restored window once it's shown. Returns a new position if the
+  /// monitor it was saved on is no longer connected.
+  /// - `outer`: outer rectangle of the window
+  /// - `monitor`: size of the monitor that the window is on
+  ///
+  /// > **NOTE**: a window that isn't on any monitor is reported as being on the primary monitor. If
+  /// > that isn't the size of the monitor the window was saved on then the saved monitor is gone,
+  /// > and the window is moved onto the primary monitor. A missing monitor that's the same size as
+  /// > the primary can't be told apart from one that's still connected.
+  pub fn check_restored(&self, outer: emath::Rect, monitor: emath::Vec2) -> Option<emath::Pos2> {
+    if self.pos.is_none() || self.maxed || self.fullscreen || self.monitor? == Size::from(monitor) {
+      return None;
+    }
+
+    let screen = emath::Rect::from_min_size(emath::Pos2::ZERO, monitor);
+    let rect = fit_to_screens(outer, &[screen]);
+    (rect.min != outer.min).then_some(rect.min)
+  }
+
   pub fn to_value(&self) -> serde_json::Value {
     let mut value = serde_json::json!({});
 
@@ -192,13 +342,313 @@ impl WinInfo {
       value[WinInfo::SIZE_KEY] = size.to_value();
     }
 
+    if let Some(monitor) = &self.monitor {
+      value[WinInfo::MONITOR_KEY] = monitor.to_value();
+    }
+
     value[WinInfo::MAXED_KEY] = serde_json::Value::Bool(self.maxed);
+    value[WinInfo::FULLSCREEN_KEY] = serde_json::Value::Bool(self.fullscreen);
     value
   }
 
   const POS_KEY: &'static str = "pos";
   const SIZE_KEY: &'static str = "size";
   const MAXED_KEY: &'static str = "maxed";
+  const FULLSCREEN_KEY: &'static str = "fullscreen";
+  const MONITOR_KEY: &'static str = "monitor";
+}
+
+/// Make sure that a window rectangle is visible on at least one screen. If it isn't then it's moved
+/// onto the first (primary) screen and shrunk if necessary.
+/// - `rect`: window rectangle
+/// - `screens`: usable screen rectangles, primary first
+pub fn fit_to_screens(rect: emath::Rect, screens: &[emath::Rect]) -> emath::Rect {
+  // Enough of the window (including where the title bar would be) needs to be visible in order to
+  // grab it.
+  const MIN_VISIBLE: emath::Vec2 = emath::Vec2::new(64.0, 32.0);
+
+  let Some(primary) = screens.first() else {
+    return rect;
+  };
+
+  for screen in screens {
+    let visible = rect.intersect(*screen);
+    if visible.is_positive()
+      && visible.width() >= MIN_VISIBLE.x
+      && visible.height() >= MIN_VISIBLE.y
+    {
+      return rect;
+    }
+  }
+
+  let size = rect.size().min(primary.size());
+  let min = rect.min.clamp(primary.min, primary.max - size);
+  emath::Rect::from_min_size(min, size)
+}
+
+/// Texture filtering used when the chart image is displayed at a different scale.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ChartFilter {
+  /// Nearest neighbor.
+  Sharp,
+
+  /// Linear.
+  #[default]
+  Smooth,
+}
+
+impl ChartFilter {
+  pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+    match value.as_str()? {
+      ChartFilter::SHARP => Some(Self::Sharp),
+      ChartFilter::SMOOTH => Some(Self::Smooth),
+      _ => None,
+    }
+  }
+
+  pub fn to_value(self) -> serde_json::Value {
+    let text = match self {
+      Self::Sharp => ChartFilter::SHARP,
+      Self::Smooth => ChartFilter::SMOOTH,
+    };
+    serde_json::Value::String(text.into())
+  }
+
+  /// Label for UI controls.
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Sharp => "Sharp (nearest)",
+      Self::Smooth => "Smooth (linear)",
+    }
+  }
+
+  /// Texture options for this filter.
+  pub fn texture_options(self) -> epaint::textures::TextureOptions {
+    match self {
+      Self::Sharp => epaint::textures::TextureOptions::NEAREST,
+      Self::Smooth => epaint::textures::TextureOptions::LINEAR,
+    }
+  }
+
+  /// Texture filter for this filter.
+  pub fn texture_filter(self) -> epaint::textures::TextureFilter {
+    match self {
+      Self::Sharp => epaint::textures::TextureFilter::Nearest,
+      Self::Smooth => epaint::textures::TextureFilter::Linear,
+    }
+  }
+
+  const SHARP: &'static str = "sharp";
+  const SMOOTH: &'static str = "smooth";
+}
+
+/// Units used to display distances and altitudes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Units {
+  /// Nautical miles and feet.
+  #[default]
+  Aviation,
+
+  /// Kilometers and meters.
+  Metric,
+}
+
+impl Units {
+  pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+    match value.as_str()? {
+      Units::AVIATION => Some(Self::Aviation),
+      Units::METRIC => Some(Self::Metric),
+      _ => None,
+    }
+  }
+
+  pub fn to_value(self) -> serde_json::Value {
+    let text = match self {
+      Self::Aviation => Units::AVIATION,
+      Self::Metric => Units::METRIC,
+    };
+    serde_json::Value::String(text.into())
+  }
+
+  /// Label for UI controls.
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Aviation => "Aviation (NM, ft)",
+      Self::Metric => "Metric (km, m)",
+    }
+  }
+
+  /// Convert meters to the distance unit.
+  pub fn distance(self, meters: f64) -> f64 {
+    match self {
+      Self::Aviation => meters / METERS_PER_NM,
+      Self::Metric => meters * 0.001,
+    }
+  }
+
+  /// Abbreviation for the distance unit.
+  pub fn distance_abv(self) -> &'static str {
+    match self {
+      Self::Aviation => "NM",
+      Self::Metric => "km",
+    }
+  }
+
+  /// Convert feet to the altitude unit.
+  pub fn altitude(self, feet: f64) -> f64 {
+    match self {
+      Self::Aviation => feet,
+      Self::Metric => feet * METERS_PER_FOOT,
+    }
+  }
+
+  /// Abbreviation for the altitude unit.
+  pub fn altitude_abv(self) -> &'static str {
+    match self {
+      Self::Aviation => "ft",
+      Self::Metric => "m",
+    }
+  }
+
+  const AVIATION: &'static str = "aviation";
+  const METRIC: &'static str = "metric";
+}
+
+/// Calendar date (proleptic Gregorian).
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Date {
+  pub year: i32,
+  pub month: u32,
+  pub day: u32,
+}
+
+impl Date {
+  pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+    if (1..=12).contains(&month) && day >= 1 && day <= Date::days_in_month(year, month) {
+      return Some(Self { year, month, day });
+    }
+    None
+  }
+
+  /// The current UTC date.
+  pub fn today() -> Self {
+    let secs = match time::SystemTime::now().duration_since(time::UNIX_EPOCH) {
+      Ok(duration) => duration.as_secs() as i64,
+      Err(err) => -(err.duration().as_secs() as i64),
+    };
+    Date::from_days(secs.div_euclid(24 * 60 * 60))
+  }
+
+  /// Create a date from the number of days since 1970-01-01.
+  pub fn from_days(days: i64) -> Self {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + era * 400) as i32 + i32::from(month <= 2);
+    Self { year, month, day }
+  }
+
+  /// The number of days since 1970-01-01.
+  pub fn to_days(self) -> i64 {
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = self.year as i64 - i64::from(self.month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (self.month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + self.day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+  }
+
+  pub fn add_days(self, days: i64) -> Self {
+    Date::from_days(self.to_days() + days)
+  }
+
+  /// Parse a date in `YYYY/MM/DD` or `YYYY-MM-DD` format.
+  pub fn parse(text: &str) -> Option<Self> {
+    let mut parts = text.trim().split(['/', '-']);
+    let year = parts.next()?;
+    let month = parts.next()?;
+    let day = parts.next()?;
+    if parts.next().is_some() || year.len() != 4 || month.len() != 2 || day.len() != 2 {
+      return None;
+    }
+    Date::new(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+  }
+
+  /// Find the first `YYYY-MM-DD` date embedded in some text (such as a file name).
+  pub fn find(text: &str) -> Option<Self> {
+    const LEN: usize = "YYYY-MM-DD".len();
+    let bytes = text.as_bytes();
+    for start in 0..bytes.len().saturating_sub(LEN - 1) {
+      let part = &bytes[start..start + LEN];
+      let valid = part.iter().enumerate().all(|(index, ch)| match index {
+        4 | 7 => *ch == b'-',
+        _ => ch.is_ascii_digit(),
+      });
+
+      if valid {
+        if let Some(date) = Date::parse(std::str::from_utf8(part).ok()?) {
+          return Some(date);
+        }
+      }
+    }
+    None
+  }
+
+  fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+      4 | 6 | 9 | 11 => 30,
+      2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+      2 => 28,
+      _ => 31,
+    }
+  }
+}
+
+impl fmt::Display for Date {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+  }
+}
+
+/// Named location.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+  pub name: String,
+
+  /// Coordinate in decimal degrees (NAD 83).
+  pub coord: Coord,
+}
+
+impl Bookmark {
+  pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+    let name = value.get(Bookmark::NAME_KEY)?.as_str()?.into();
+    let lat = value.get(Bookmark::LAT_KEY)?.as_f64()?;
+    let lon = value.get(Bookmark::LON_KEY)?.as_f64()?;
+    Some(Self {
+      name,
+      coord: Coord { x: lon, y: lat },
+    })
+  }
+
+  pub fn to_value(&self) -> serde_json::Value {
+    serde_json::json!({
+      Bookmark::NAME_KEY: self.name,
+      Bookmark::LAT_KEY: self.coord.y,
+      Bookmark::LON_KEY: self.coord.x,
+    })
+  }
+
+  const NAME_KEY: &'static str = "name";
+  const LAT_KEY: &'static str = "lat";
+  const LON_KEY: &'static str = "lon";
 }
 
 pub trait Transform {
@@ -272,9 +722,15 @@ pub struct Bounds {
 }
 
 impl Bounds {
+  /// Check if a coordinate is within the bounds. The minimum edges are inside and the maximum
+  /// edges are outside so that adjacent bounds never both contain a point.
   pub fn contains(&self, coord: Coord) -> bool {
     coord.x >= self.min.x && coord.x < self.max.x && coord.y >= self.min.y && coord.y < self.max.y
   }
+
+  pub fn area(&self) -> f64 {
+    (self.max.x - self.min.x) * (self.max.y - self.min.y)
+  }
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
@@ -396,6 +852,16 @@ pub struct Rect {
 }
 
 impl Rect {
+  pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+    let pos = Pos::from_value(value.get(0)?)?;
+    let size = Size::from_value(value.get(1)?)?;
+    Some(Self { pos, size })
+  }
+
+  pub fn to_value(self) -> serde_json::Value {
+    serde_json::json!([self.pos.to_value(), self.size.to_value()])
+  }
+
   pub fn scaled(&self, scale: f32) -> Self {
     Self {
       pos: Pos {
@@ -494,6 +960,15 @@ impl From<Hashable> for f64 {
   }
 }
 
+/// Get the size to show a dialog at, keeping it within the screen.
+/// - `ctx`: egui context
+/// - `size`: remembered dialog size
+/// - `default`: size to use if nothing was remembered
+pub fn dialog_size(ctx: &egui::Context, size: Option<Size>, default: emath::Vec2) -> emath::Vec2 {
+  let size = size.map_or(default, emath::Vec2::from);
+  size.min(ctx.screen_rect().size() * 0.9)
+}
+
 pub fn scale_rect(rect: emath::Rect, scale: f32) -> emath::Rect {
   emath::Rect {
     min: emath::Pos2 {
@@ -507,6 +982,70 @@ pub fn scale_rect(rect: emath::Rect, scale: f32) -> emath::Rect {
   }
 }
 
+/// Get the part of the chart to read for a magnified view (zoom above 1.0). The chart is read at
+/// full resolution and the texture is scaled up for display.
+/// - `rect`: display rectangle in zoomed pixels
+/// - `zoom`: display zoom
+/// - `size`: full chart size in pixels
+pub fn magnified_rect(rect: Rect, zoom: f32, size: Size) -> Rect {
+  // Cover the whole display rectangle.
+  let x0 = (rect.pos.x as f32 / zoom).floor().max(0.0);
+  let y0 = (rect.pos.y as f32 / zoom).floor().max(0.0);
+  let x1 = ((rect.pos.x as f32 + rect.size.w as f32) / zoom).ceil();
+  let y1 = ((rect.pos.y as f32 + rect.size.h as f32) / zoom).ceil();
+  let x1 = x1.min(size.w as f32).max(x0 + 1.0);
+  let y1 = y1.min(size.h as f32).max(y0 + 1.0);
+  Rect {
+    pos: Pos {
+      x: x0 as i32,
+      y: y0 as i32,
+    },
+    size: Size {
+      w: (x1 - x0) as u32,
+      h: (y1 - y0) as u32,
+    },
+  }
+}
+
+/// Get the zoom that shows a ground distance across a view.
+/// - `view_width`: view width in screen pixels
+/// - `meters_per_px`: meters per chart pixel (full resolution)
+/// - `span`: ground distance in meters
+/// - `min_zoom`: lowest allowed zoom
+/// - `max_zoom`: highest allowed zoom
+pub fn span_zoom(
+  view_width: f32,
+  meters_per_px: f64,
+  span: f64,
+  min_zoom: f32,
+  max_zoom: f32,
+) -> f32 {
+  // The view spans `view_width / zoom` chart pixels.
+  let zoom = (view_width as f64 * meters_per_px / span) as f32;
+  zoom.max(min_zoom).min(max_zoom)
+}
+
+/// Get the scroll offset that keeps the center of a view in place when the view changes size.
+/// - `offset`: current scroll offset
+/// - `old_size`: previous (unrounded) size of the view
+/// - `new_size`: new (unrounded) size of the view
+/// - `remainder`: sub-pixel remainder carried between calls
+///
+/// The returned offset is always on a whole pixel. The part that was rounded off is kept in
+/// `remainder` and added back next time, so repeatedly growing and shrinking the view doesn't walk
+/// the offset away from where it started.
+pub fn recenter_offset(
+  offset: emath::Pos2,
+  old_size: emath::Vec2,
+  new_size: emath::Vec2,
+  remainder: &mut emath::Vec2,
+) -> emath::Pos2 {
+  let exact = offset + (old_size - new_size) * 0.5 + *remainder;
+  let rounded = exact.round();
+  *remainder = exact - rounded;
+  rounded
+}
+
 /// Return the file stem portion of a path as a `String`.
 pub fn stem_string<P: AsRef<path::Path>>(path: P) -> Option<String> {
   stem_str(path.as_ref()).map(|stem| stem.to_owned())
@@ -537,8 +1076,39 @@ pub fn title_case(text: &str) -> String {
   result
 }
 
+/// Returns the number with commas separating each group of thousands (e.g. "1,250").
+pub fn group_thousands(val: i64) -> String {
+  let digits = val.unsigned_abs().to_string();
+  let mut text = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+  if val < 0 {
+    text.push('-');
+  }
+
+  for (index, ch) in digits.chars().enumerate() {
+    if index > 0 && (digits.len() - index).is_multiple_of(3) {
+      text.push(',');
+    }
+    text.push(ch);
+  }
+  text
+}
+
+/// Distance text with one decimal place (e.g. "12.5 NM").
+/// - `meters`: distance in meters
+/// - `units`: display units
+pub fn format_distance(meters: f64, units: Units) -> String {
+  format!("{:.1} {}", units.distance(meters), units.distance_abv())
+}
+
+/// Altitude text rounded to a whole number (e.g. "1,250 ft").
+/// - `feet`: altitude (or height) in feet
+/// - `units`: display units
+pub fn format_altitude(feet: f64, units: Units) -> String {
+  let altitude = units.altitude(feet).round() as i64;
+  format!("{} {}", group_thousands(altitude), units.altitude_abv())
+}
+
 /// Convert degrees, minutes, seconds to decimal degrees.
-#[allow(unused)]
 pub fn to_dec_deg(deg: f64, min: f64, sec: f64) -> Option<f64> {
   if min >= 0.0 && sec >= 0.0 {
     const DEG_PER_MIN: f64 = 1.0 / 60.0;
@@ -587,6 +1157,110 @@ pub fn format_lon(dd: f64) -> Option<String> {
   None
 }
 
+/// Parse a latitude/longitude pair in decimal degrees, degrees minutes seconds or degrees decimal
+/// minutes, e.g. `37.4613 -122.1150`, `37°27'41"N 122°06'54"W` or `N37 27.68 W122 06.90`.
+/// Hemisphere letters can come before or after the numbers. Returns a NAD83 coordinate.
+/// - `text`: coordinate text
+pub fn parse_coord(text: &str) -> Option<Coord> {
+  #[derive(Default)]
+  struct Part {
+    hemi: Option<char>,
+    nums: Vec<String>,
+  }
+
+  // Split the text into hemisphere letters and numbers.
+  let mut parts = Vec::new();
+  let mut part = Part::default();
+  let mut chars = text.trim().chars().peekable();
+  while let Some(ch) = chars.next() {
+    match ch.to_ascii_uppercase() {
+      hemi @ ('N' | 'S' | 'E' | 'W') => {
+        if part.nums.is_empty() {
+          // Leading hemisphere.
+          if part.hemi.replace(hemi).is_some() {
+            return None;
+          }
+        } else if part.hemi.is_none() {
+          // Trailing hemisphere.
+          part.hemi = Some(hemi);
+          parts.push(mem::take(&mut part));
+        } else {
+          parts.push(mem::take(&mut part));
+          part.hemi = Some(hemi);
+        }
+      }
+      '0'..='9' | '.' | '-' | '+' => {
+        let mut num = String::from(ch);
+        while let Some(ch) = chars.next_if(|ch| ch.is_ascii_digit() || *ch == '.') {
+          num.push(ch);
+        }
+        part.nums.push(num);
+      }
+      ' ' | '\t' | ',' | '°' | '\'' | '"' | '′' | '″' => (),
+      _ => return None,
+    }
+  }
+
+  if part.hemi.is_some() || !part.nums.is_empty() {
+    parts.push(part);
+  }
+
+  // Without hemisphere letters the numbers are split evenly between latitude and longitude.
+  if parts.len() == 1 && parts[0].hemi.is_none() && parts[0].nums.len() % 2 == 0 {
+    let mut lat = parts.pop().unwrap();
+    let nums = lat.nums.split_off(lat.nums.len() / 2);
+    let lon = Part { hemi: None, nums };
+    parts = vec![lat, lon];
+  }
+
+  let [first, second] = <[Part; 2]>::try_from(parts).ok()?;
+  if first.hemi.is_some() != second.hemi.is_some() {
+    return None;
+  }
+
+  // Convert a part to decimal degrees.
+  let to_dd = |part: &Part| -> Option<f64> {
+    let count = part.nums.len();
+    if !(1..=3).contains(&count) {
+      return None;
+    }
+
+    let mut vals = [0.0; 3];
+    for (index, num) in part.nums.iter().enumerate() {
+      let signed = num.starts_with(['-', '+']);
+      let val = num.parse::<f64>().ok()?;
+
+      // Only the degrees can be signed and only the last number can have a fraction.
+      if (index > 0 && signed) || (index < count - 1 && val.fract() != 0.0) {
+        return None;
+      }
+
+      if index > 0 && val >= 60.0 {
+        return None;
+      }
+      vals[index] = val;
+    }
+
+    let dd = to_dec_deg(vals[0], vals[1], vals[2])?;
+    match part.hemi {
+      Some(_) if part.nums[0].starts_with('-') => None,
+      Some('S' | 'W') => Some(-dd.abs()),
+      _ => Some(dd),
+    }
+  };
+
+  let (lat, lon) = match (first.hemi, second.hemi) {
+    (None, None) | (Some('N' | 'S'), Some('E' | 'W')) => (to_dd(&first)?, to_dd(&second)?),
+    (Some('E' | 'W'), Some('N' | 'S')) => (to_dd(&second)?, to_dd(&first)?),
+    _ => return None,
+  };
+
+  if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) {
+    return Some(Coord { x: lon, y: lat });
+  }
+  None
+}
+
 /// Check if a GDAL color will fit into an egui color.
 pub fn check_color(color: raster::RgbaEntry) -> bool {
   const COMP_RANGE: ops::Range<i16> = 0..256;
@@ -608,10 +1282,45 @@ pub fn color(color: &raster::RgbaEntry) -> epaint::Color32 {
 
 /// Convert a GDAL color to an egui color and invert the luminance.
 pub fn inverted_color(color: &raster::RgbaEntry) -> epaint::Color32 {
-  let r = color.r as f32;
-  let g = color.g as f32;
-  let b = color.b as f32;
+  let [r, g, b] = invert_luminance(color.r as f32, color.g as f32, color.b as f32);
+  epaint::Color32::from_rgba_unmultiplied(r, g, b, color.a as u8)
+}
 
+/// Convert RGB components to an egui color and invert the luminance.
+pub fn inverted_rgb(r: u8, g: u8, b: u8) -> epaint::Color32 {
+  let [r, g, b] = invert_luminance(r as f32, g as f32, b as f32);
+  epaint::Color32::from_rgb(r, g, b)
+}
+
+/// Convert a GDAL color to an egui color with more contrast and saturation.
+pub fn contrast_color(color: &raster::RgbaEntry) -> epaint::Color32 {
+  let [r, g, b] = boost_contrast(color.r as f32, color.g as f32, color.b as f32);
+  epaint::Color32::from_rgba_unmultiplied(r, g, b, color.a as u8)
+}
+
+/// Convert RGB components to an egui color with more contrast and saturation.
+pub fn contrast_rgb(r: u8, g: u8, b: u8) -> epaint::Color32 {
+  let [r, g, b] = boost_contrast(r as f32, g as f32, b as f32);
+  epaint::Color32::from_rgb(r, g, b)
+}
+
+fn boost_contrast(r: f32, g: f32, b: f32) -> [u8; 3] {
+  const CONTRAST: f32 = 1.3;
+  const SATURATION: f32 = 1.4;
+
+  // Convert to YCbCr, push the luminance away from mid gray and scale the chroma.
+  let y = (r * 0.299 + g * 0.587 + b * 0.114 - 128.0) * CONTRAST + 128.0;
+  let cb = (b * 0.5 - r * 0.168736 - g * 0.331264) * SATURATION;
+  let cr = (r * 0.5 - g * 0.418688 - b * 0.081312) * SATURATION;
+
+  // Convert back to RGB (the casts saturate).
+  let r = (y + 1.402 * cr) as u8;
+  let g = (y - 0.344136 * cb - 0.714136 * cr) as u8;
+  let b = (y + 1.772 * cb) as u8;
+  [r, g, b]
+}
+
+fn invert_luminance(r: f32, g: f32, b: f32) -> [u8; 3] {
   // Convert to YCbCr and invert the luminance.
   let y = 255.0 - (r * 0.299 + g * 0.587 + b * 0.114);
   let cb = b * 0.5 - r * 0.168736 - g * 0.331264;
@@ -621,8 +1330,7 @@ pub fn inverted_color(color: &raster::RgbaEntry) -> epaint::Color32 {
   let r = (y + 1.402 * cr) as u8;
   let g = (y - 0.344136 * cb - 0.714136 * cr) as u8;
   let b = (y + 1.772 * cb) as u8;
-
-  epaint::Color32::from_rgba_unmultiplied(r, g, b, color.a as u8)
+  [r, g, b]
 }
 
 mod test {
@@ -651,6 +1359,437 @@ mod test {
     assert!(lon == "117°08'47.00\"W");
   }
 
+  #[test]
+  fn test_parse_coord() {
+    use super::parse_coord;
+
+    let near = |text: &str, lat: f64, lon: f64| {
+      let coord = parse_coord(text).unwrap();
+      (coord.y - lat).abs() < 1.0e-6 && (coord.x - lon).abs() < 1.0e-6
+    };
+
+    // Decimal degrees.
+    assert!(near("37.4613 -122.1150", 37.4613, -122.115));
+    assert!(near("37.4613, -122.1150", 37.4613, -122.115));
+    assert!(near("37.4613N 122.1150W", 37.4613, -122.115));
+
+    // Degrees, minutes, seconds.
+    let lat = 37.0 + 27.0 / 60.0 + 41.0 / 3600.0;
+    let lon = -(122.0 + 6.0 / 60.0 + 54.0 / 3600.0);
+    assert!(near("37°27'41\"N 122°06'54\"W", lat, lon));
+    assert!(near("37°27′41″N 122°06′54″W", lat, lon));
+    assert!(near("N37 27 41 W122 06 54", lat, lon));
+
+    // Degrees, decimal minutes.
+    let lat = 37.0 + 27.68 / 60.0;
+    let lon = -(122.0 + 6.9 / 60.0);
+    assert!(near("N37 27.68 W122 06.90", lat, lon));
+    assert!(near("37 27.68N 122 06.90W", lat, lon));
+    assert!(near("n37 27.68 w122 06.90", lat, lon));
+
+    // Longitude first.
+    assert!(near("W122 06.90 N37 27.68", lat, lon));
+
+    // Southern and eastern hemispheres.
+    assert!(near("S33 52 E151 12", -(33.0 + 52.0 / 60.0), 151.2));
+
+    // Out of range.
+    assert!(parse_coord("91 0").is_none());
+    assert!(parse_coord("37 -181").is_none());
+    assert!(parse_coord("N37 60 W122 0").is_none());
+    assert!(parse_coord("N37 27 60 W122 0").is_none());
+
+    // Malformed.
+    assert!(parse_coord("").is_none());
+    assert!(parse_coord("KPAO").is_none());
+    assert!(parse_coord("SEA").is_none());
+    assert!(parse_coord("N52").is_none());
+    assert!(parse_coord("37 27 122").is_none());
+    assert!(parse_coord("37.5 30 122 0").is_none());
+    assert!(parse_coord("N-37 W122").is_none());
+    assert!(parse_coord("N37 N38").is_none());
+    assert!(parse_coord("37N 122").is_none());
+  }
+
+  #[test]
+  fn test_chart_filter() {
+    use super::ChartFilter;
+    use eframe::epaint::textures::TextureFilter;
+
+    let options = ChartFilter::Sharp.texture_options();
+    assert!(options.magnification == TextureFilter::Nearest);
+    assert!(options.minification == TextureFilter::Nearest);
+
+    let options = ChartFilter::Smooth.texture_options();
+    assert!(options.magnification == TextureFilter::Linear);
+    assert!(options.minification == TextureFilter::Linear);
+
+    for filter in [ChartFilter::Sharp, ChartFilter::Smooth] {
+      assert!(ChartFilter::from_value(&filter.to_value()) == Some(filter));
+    }
+    assert!(ChartFilter::from_value(&serde_json::json!("blurry")).is_none());
+  }
+
+  #[test]
+  fn test_date() {
+    use super::Date;
+
+    assert!(Date::from_days(0) == Date::new(1970, 1, 1).unwrap());
+    assert!(Date::new(2024, 5, 16).unwrap().to_days() == 19859);
+    assert!(Date::from_days(19859) == Date::new(2024, 5, 16).unwrap());
+    assert!(Date::new(1969, 12, 31).unwrap().to_days() == -1);
+
+    // NASR cycles are 28 days.
+    let date = Date::parse("2024/05/16").unwrap();
+    assert!(date.add_days(28) == Date::new(2024, 6, 13).unwrap());
+    assert!(date.add_days(28).to_string() == "2024-06-13");
+    assert!(Date::parse("2024-02-29") == Date::new(2024, 2, 29));
+    assert!(Date::parse(" 2024/12/31 ").is_some());
+
+    assert!(Date::parse("2023/02/29").is_none());
+    assert!(Date::parse("2024/13/01").is_none());
+    assert!(Date::parse("2024/5/16").is_none());
+    assert!(Date::parse("05/16/2024").is_none());
+    assert!(Date::parse("").is_none());
+
+    let name = "28DaySubscription_Effective_2024-05-16.zip";
+    assert!(Date::find(name) == Date::new(2024, 5, 16));
+    assert!(Date::find("/vsizip/Nasr_2024-99-16_2024-05-16.zip") == Date::new(2024, 5, 16));
+    assert!(Date::find("SanFrancisco.zip").is_none());
+  }
+
+  #[test]
+  fn test_bookmark() {
+    use super::{Bookmark, Coord};
+
+    let bookmark = Bookmark {
+      name: "Emerald Bay".into(),
+      coord: Coord {
+        x: -120.1,
+        y: 38.95,
+      },
+    };
+    let value = bookmark.to_value();
+    assert!(Bookmark::from_value(&value) == Some(bookmark));
+    assert!(Bookmark::from_value(&serde_json::json!({"name": "x"})).is_none());
+  }
+
+  #[test]
+  fn test_fit_to_screens() {
+    use eframe::emath::{pos2, vec2, Rect};
+
+    let primary = Rect::from_min_size(pos2(0.0, 0.0), vec2(1920.0, 1080.0));
+    let left = Rect::from_min_size(pos2(-1280.0, 0.0), vec2(1280.0, 1024.0));
+    let screens = [primary, left];
+
+    // Completely on a screen.
+    let rect = Rect::from_min_size(pos2(100.0, 100.0), vec2(800.0, 600.0));
+    assert!(super::fit_to_screens(rect, &screens) == rect);
+
+    // On the secondary screen.
+    let rect = Rect::from_min_size(pos2(-1000.0, 100.0), vec2(800.0, 600.0));
+    assert!(super::fit_to_screens(rect, &screens) == rect);
+
+    // Mostly off screen but still grabbable.
+    let rect = Rect::from_min_size(pos2(1800.0, 1000.0), vec2(800.0, 600.0));
+    assert!(super::fit_to_screens(rect, &screens) == rect);
+
+    // On a monitor that's no longer connected.
+    let rect = Rect::from_min_size(pos2(2500.0, 200.0), vec2(800.0, 600.0));
+    let fitted = super::fit_to_screens(rect, &screens);
+    assert!(fitted == Rect::from_min_size(pos2(1120.0, 200.0), vec2(800.0, 600.0)));
+
+    // Too big for the primary screen.
+    let rect = Rect::from_min_size(pos2(-5000.0, -5000.0), vec2(2560.0, 1440.0));
+    assert!(super::fit_to_screens(rect, &screens) == primary);
+
+    // No screens.
+    assert!(super::fit_to_screens(rect, &[]) == rect);
+  }
+
+  #[test]
+  fn test_restore_pos() {
+    use super::{Pos, Size, WinInfo};
+    use eframe::emath::pos2;
+
+    let win_info = |x, y| WinInfo {
+      pos: Some(Pos { x, y }),
+      size: Some(Size { w: 800, h: 600 }),
+      monitor: Some(Size { w: 1920, h: 1080 }),
+      ..Default::default()
+    };
+
+    // On the primary monitor.
+    assert!(win_info(100, 100).restore_pos() == Some(pos2(100.0, 100.0)));
+
+    // Below the bottom of the primary monitor.
+    assert!(win_info(100, 1070).restore_pos() == Some(pos2(100.0, 480.0)));
+
+    // On a monitor to the left or right of the primary, whose origin isn't known.
+    assert!(win_info(-1000, 100).restore_pos() == Some(pos2(-1000.0, 100.0)));
+    assert!(win_info(2500, 100).restore_pos() == Some(pos2(2500.0, 100.0)));
+
+    // Monitor size unknown.
+    let win_info = WinInfo {
+      monitor: None,
+      ..win_info(-1000, 100)
+    };
+    assert!(win_info.restore_pos() == Some(pos2(-1000.0, 100.0)));
+  }
+
+  #[test]
+  fn test_check_restored() {
+    use super::{Pos, Size, WinInfo};
+    use eframe::emath::{pos2, vec2, Rect};
+
+    // Saved on a 2560x1440 secondary monitor to the right of a 1920x1080 primary.
+    let win_info = |x, y| WinInfo {
+      pos: Some(Pos { x, y }),
+      size: Some(Size { w: 800, h: 600 }),
+      monitor: Some(Size { w: 2560, h: 1440 }),
+      ..Default::default()
+    };
+    let outer = |x, y| Rect::from_min_size(pos2(x, y), vec2(800.0, 600.0));
+    let primary = vec2(1920.0, 1080.0);
+    let secondary = vec2(2560.0, 1440.0);
+
+    // The secondary monitor is still connected.
+    let restored = win_info(2500, 100).check_restored(outer(2500.0, 100.0), secondary);
+    assert!(restored.is_none());
+
+    // The secondary monitor is gone, so the window is reported as being on the primary.
+    let restored = win_info(2500, 100).check_restored(outer(2500.0, 100.0), primary);
+    assert!(restored == Some(pos2(1120.0, 100.0)));
+    let restored = win_info(-2000, 1200).check_restored(outer(-2000.0, 1200.0), primary);
+    assert!(restored == Some(pos2(0.0, 480.0)));
+
+    // Saved on the secondary, but the window was placed on the primary anyway.
+    let restored = win_info(100, 100).check_restored(outer(100.0, 100.0), primary);
+    assert!(restored.is_none());
+
+    // Maximized windows are left alone.
+    let win_info = WinInfo {
+      maxed: true,
+      ..win_info(2500, 100)
+    };
+    let restored = win_info.check_restored(outer(2500.0, 100.0), primary);
+    assert!(restored.is_none());
+  }
+
+  #[test]
+  fn test_get_zip_info() {
+    use super::{get_zip_info, vsi_path, CsvPath, ZipInfo};
+    use std::{env, ffi, fs, path::Path, path::PathBuf};
+
+    /// Create a zip file holding small placeholder files.
+    fn create_zip(name: &str, files: &[&str]) -> PathBuf {
+      let files: Vec<(&str, &[u8])> = files.iter().map(|file| (*file, b"0".as_slice())).collect();
+      create_zip_with(name, &files)
+    }
+
+    /// Create a zip file holding files with the given contents.
+    fn create_zip_with(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+      let path = env::temp_dir().join(name).with_extension("zip");
+      let _ = fs::remove_file(&path);
+      for (file, data) in files {
+        let vsi = vsi_path("/vsizip/", &path).unwrap().join(file);
+        let vsi = ffi::CString::new(vsi.to_str().unwrap()).unwrap();
+        let mode = ffi::CString::new("wb").unwrap();
+        unsafe {
+          let handle = gdal_sys::VSIFOpenL(vsi.as_ptr(), mode.as_ptr());
+          assert!(!handle.is_null());
+          gdal_sys::VSIFWriteL(data.as_ptr().cast(), 1, data.len(), handle);
+          gdal_sys::VSIFCloseL(handle);
+        }
+      }
+      path
+    }
+
+    /// Get the CSV location from a zip file that should hold NASR data.
+    fn get_csv(path: &Path) -> CsvPath {
+      let ZipInfo::Aero { csv, .. } = get_zip_info(path).unwrap() else {
+        panic!("expected NASR data");
+      };
+      csv
+    }
+
+    let shp_files = [
+      "Additional_Data/Shape_Files/Class_Airspace.dbf",
+      "Additional_Data/Shape_Files/Class_Airspace.shp",
+      "Additional_Data/Shape_Files/Class_Airspace.shx",
+    ];
+    let csv_file = "CSV_Data/16_May_2024_CSV.zip";
+
+    // The CSV zip that goes inside the NASR zip.
+    let path = create_zip("aviate_test_csv_inner", &["APT_BASE.csv", "APT_RWY.csv"]);
+    let csv_data = fs::read(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // Full NASR download.
+    let placeholder = b"0".as_slice();
+    let mut files: Vec<(&str, &[u8])> = shp_files.map(|file| (file, placeholder)).to_vec();
+    files.push((csv_file, &csv_data));
+    let path = create_zip_with("aviate_test_nasr_full", &files);
+    let info = get_zip_info(&path).unwrap();
+    let ZipInfo::Aero { csv, shp } = info else {
+      panic!("expected NASR data");
+    };
+    assert!(csv == CsvPath::Zip(csv_file.into()));
+    assert!(shp.as_deref() == Some(Path::new("Additional_Data/Shape_Files")));
+    fs::remove_file(path).unwrap();
+
+    // CSV only download.
+    let path = create_zip_with("aviate_test_nasr_csv", &[(csv_file, &csv_data)]);
+    let info = get_zip_info(&path).unwrap();
+    let ZipInfo::Aero { csv, shp } = info else {
+      panic!("expected NASR data");
+    };
+    assert!(csv == CsvPath::Zip(csv_file.into()));
+    assert!(shp.is_none());
+    fs::remove_file(path).unwrap();
+
+    // Repackaged with the CSV zip at the top level, next to an unrelated zip.
+    let files = [("readme.zip", placeholder), ("nasr.zip", &csv_data)];
+    let path = create_zip_with("aviate_test_nasr_top", &files);
+    assert!(get_csv(&path) == CsvPath::Zip("nasr.zip".into()));
+    fs::remove_file(path).unwrap();
+
+    // Repackaged with the CSV zip in a differently named folder.
+    let files = [("NASR/Data/apt.zip", csv_data.as_slice())];
+    let path = create_zip_with("aviate_test_nasr_folder", &files);
+    assert!(get_csv(&path) == CsvPath::Zip("NASR/Data/apt.zip".into()));
+    fs::remove_file(path).unwrap();
+
+    // Repackaged with the CSV files unzipped.
+    let files = ["CSV/APT_BASE.csv", "CSV/APT_RWY.csv"];
+    let path = create_zip("aviate_test_nasr_unzipped", &files);
+    let csv = get_csv(&path);
+    assert!(csv == CsvPath::Folder("CSV".into()));
+    let vsi = csv.vsi_file(&path, "APT_BASE.csv").unwrap();
+    assert!(vsi == vsi_path("/vsizip/", &path).unwrap().join(files[0]));
+    fs::remove_file(path).unwrap();
+
+    // An inner zip without the CSV files isn't NASR data, even with the FAA's name.
+    let path = create_zip("aviate_test_nasr_bad_csv", &[csv_file]);
+    assert!(get_zip_info(&path).is_err());
+    fs::remove_file(path).unwrap();
+
+    // Too deep to be found.
+    let path = create_zip_with("aviate_test_nasr_deep", &[("a/b/c/nasr.zip", &csv_data)]);
+    assert!(get_zip_info(&path).is_err());
+    fs::remove_file(path).unwrap();
+
+    // Chart.
+    let files = ["Seattle SEC.tif", "Seattle SEC.tfw", "Seattle SEC.htm"];
+    let path = create_zip("aviate_test_chart", &files);
+    let info = get_zip_info(&path).unwrap();
+    let ZipInfo::Chart(files) = info else {
+      panic!("expected a chart");
+    };
+    assert!(files == vec![PathBuf::from("Seattle SEC.tif")]);
+    fs::remove_file(path).unwrap();
+
+    // Shape files alone aren't usable.
+    let path = create_zip("aviate_test_nasr_shp", &shp_files);
+    assert!(get_zip_info(&path).is_err());
+    fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn test_vsi_path() {
+    use super::vsi_path;
+    use std::path::Path;
+
+    let path = vsi_path("/vsizip/", "/home/user/VFR Charts/Los Angeles.zip").unwrap();
+    assert!(path == Path::new("/vsizip//home/user/VFR Charts/Los Angeles.zip"));
+
+    let path = vsi_path("/vsizip/", "/tmp/Séctional.zip").unwrap();
+    assert!(path == Path::new("/vsizip//tmp/Séctional.zip"));
+
+    assert!(vsi_path("/vsizip/", "/tmp/bad\0name.zip").is_err());
+
+    #[cfg(unix)]
+    {
+      use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+      let name = OsStr::from_bytes(b"/tmp/invalid\xff.zip");
+      assert!(vsi_path("/vsizip/", name).is_err());
+    }
+  }
+
+  #[test]
+  fn test_magnified_rect() {
+    use super::{magnified_rect, Pos, Rect, Size};
+
+    let size = Size { w: 1000, h: 800 };
+    let rect = Rect {
+      pos: Pos { x: 101, y: 50 },
+      size: Size { w: 300, h: 200 },
+    };
+
+    // Partial pixels at the edges are included.
+    let part = magnified_rect(rect, 2.0, size);
+    assert!(part.pos == Pos { x: 50, y: 25 });
+    assert!(part.size == Size { w: 151, h: 100 });
+
+    // Clamped to the chart size.
+    let rect = Rect {
+      pos: Pos { x: 1800, y: 1500 },
+      size: Size { w: 400, h: 400 },
+    };
+    let part = magnified_rect(rect, 2.0, size);
+    assert!(part.pos == Pos { x: 900, y: 750 });
+    assert!(part.size == Size { w: 100, h: 50 });
+  }
+
+  #[test]
+  fn test_span_zoom() {
+    use super::{span_zoom, METERS_PER_NM};
+
+    // 10 NM across a 1000 pixel view.
+    let span = 10.0 * METERS_PER_NM;
+    let zoom = span_zoom(1000.0, 18.52, span, 0.125, 2.0);
+    assert!((zoom - 1.0).abs() < 1.0e-6);
+    let zoom = span_zoom(500.0, 18.52, span, 0.125, 2.0);
+    assert!((zoom - 0.5).abs() < 1.0e-6);
+
+    // The view spans the distance at the zoom.
+    let zoom = span_zoom(1200.0, 21.2, span, 0.125, 2.0);
+    assert!((1200.0 / zoom as f64 * 21.2 - span).abs() < 1.0);
+
+    // Clamped to the zoom range.
+    assert!(span_zoom(1000.0, 42.3, span, 0.125, 2.0) == 2.0);
+    assert!(span_zoom(1000.0, 1.0, span, 0.125, 2.0) == 0.125);
+    assert!(span_zoom(1000.0, 18.52, 0.0, 0.125, 2.0) == 2.0);
+  }
+
+  #[test]
+  fn test_recenter_offset() {
+    use eframe::emath::{pos2, vec2, Vec2};
+
+    // Toggle a side panel with a fractional width ten times.
+    let open = vec2(1280.0 - 201.37 - 1.0, 720.0);
+    let closed = vec2(1280.0, 720.0);
+    let start = pos2(1500.0, 800.0);
+    let mut offset = start;
+    let mut remainder = Vec2::ZERO;
+    for _ in 0..10 {
+      offset = super::recenter_offset(offset, closed, open, &mut remainder);
+      assert!(offset == offset.round());
+      assert!((offset.x - (start.x + 101.185)).abs() <= 0.5);
+      offset = super::recenter_offset(offset, open, closed, &mut remainder);
+      assert!(offset == offset.round());
+    }
+    assert!(offset == start);
+
+    // Height changes (on-screen keyboard or rotation).
+    let shown = vec2(1280.0, 720.0 - 333.5);
+    for _ in 0..10 {
+      offset = super::recenter_offset(offset, closed, shown, &mut remainder);
+      offset = super::recenter_offset(offset, shown, closed, &mut remainder);
+    }
+    assert!(offset == start);
+  }
+
   #[test]
   fn test_title_case() {
     assert!(super::title_case("title case text") == "Title Case Text");
@@ -661,15 +1800,15 @@ mod test {
     use super::ToI32;
     use super::ToU32;
 
-    let val = 0 as i64;
+    let val = 0_i64;
     assert!(val.to_i32().is_some());
     assert!(val.to_u32().is_some());
 
-    let val = 1 as i64;
+    let val = 1_i64;
     assert!(val.to_i32().is_some());
     assert!(val.to_u32().is_some());
 
-    let val = -1 as i64;
+    let val = -1_i64;
     assert!(val.to_i32().is_some());
     assert!(val.to_u32().is_none());
 
@@ -701,4 +1840,80 @@ mod test {
     assert!(val.to_i32().is_none());
     assert!(val.to_u32().is_none());
   }
+
+  #[test]
+  fn test_bounds_contains() {
+    use super::{Bounds, Coord};
+
+    let bounds = Bounds {
+      min: Coord { x: -10.0, y: -5.0 },
+      max: Coord { x: 10.0, y: 5.0 },
+    };
+
+    assert!(bounds.contains(Coord { x: 0.0, y: 0.0 }));
+    assert!(!bounds.contains(Coord { x: 20.0, y: 0.0 }));
+
+    // Minimum edges and vertex.
+    assert!(bounds.contains(Coord { x: -10.0, y: 0.0 }));
+    assert!(bounds.contains(Coord { x: 0.0, y: -5.0 }));
+    assert!(bounds.contains(Coord { x: -10.0, y: -5.0 }));
+
+    // Maximum edges and vertices.
+    assert!(!bounds.contains(Coord { x: 10.0, y: 0.0 }));
+    assert!(!bounds.contains(Coord { x: 0.0, y: 5.0 }));
+    assert!(!bounds.contains(Coord { x: 10.0, y: 5.0 }));
+    assert!(!bounds.contains(Coord { x: -10.0, y: 5.0 }));
+    assert!(!bounds.contains(Coord { x: 10.0, y: -5.0 }));
+  }
+
+  #[test]
+  fn test_group_thousands() {
+    use super::group_thousands;
+
+    assert!(group_thousands(0) == "0");
+    assert!(group_thousands(999) == "999");
+    assert!(group_thousands(1250) == "1,250");
+    assert!(group_thousands(-282) == "-282");
+    assert!(group_thousands(-1250) == "-1,250");
+    assert!(group_thousands(123456789) == "123,456,789");
+  }
+
+  #[test]
+  fn test_units() {
+    use super::{format_altitude, format_distance, Units, METERS_PER_NM};
+
+    assert!(format_distance(12.5 * METERS_PER_NM, Units::Aviation) == "12.5 NM");
+    assert!(format_distance(10.0 * METERS_PER_NM, Units::Metric) == "18.5 km");
+    assert!(format_distance(0.0, Units::Metric) == "0.0 km");
+
+    assert!(format_altitude(1250.0, Units::Aviation) == "1,250 ft");
+    assert!(format_altitude(1250.0, Units::Metric) == "381 m");
+    assert!(format_altitude(-282.0, Units::Metric) == "-86 m");
+    assert!(format_altitude(14_505.0, Units::Metric) == "4,421 m");
+
+    for units in [Units::Aviation, Units::Metric] {
+      assert!(Units::from_value(&units.to_value()) == Some(units));
+    }
+    assert!(Units::from_value(&serde_json::json!("imperial")).is_none());
+  }
+
+  #[test]
+  fn test_contrast_rgb() {
+    use eframe::epaint::Color32;
+
+    // White and black are unchanged and mid gray stays put.
+    assert!(super::contrast_rgb(255, 255, 255) == Color32::WHITE);
+    assert!(super::contrast_rgb(0, 0, 0) == Color32::BLACK);
+    assert!(super::contrast_rgb(128, 128, 128) == Color32::from_gray(128));
+
+    // Light and dark grays move apart.
+    let light = super::contrast_rgb(200, 200, 200);
+    let dark = super::contrast_rgb(60, 60, 60);
+    assert!(light.r() > 200 && light.r() == light.g() && light.g() == light.b());
+    assert!(dark.r() < 60 && dark.r() == dark.g() && dark.g() == dark.b());
+
+    // Colors are more saturated.
+    let color = super::contrast_rgb(100, 140, 180);
+    assert!(color.b() - color.r() > 80);
+  }
 }
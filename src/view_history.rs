@@ -0,0 +1,160 @@
+use eframe::emath;
+
+/// Chart view position and zoom.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewState {
+  /// View center in chart pixels (full resolution).
+  pub center: emath::Pos2,
+  pub zoom: f32,
+}
+
+/// Back and forward history of chart views, like a web browser.
+#[derive(Default)]
+pub struct ViewHistory {
+  back: Vec<ViewState>,
+  forward: Vec<ViewState>,
+
+  /// Where the view settled after the last jump. Manual pans are measured from here.
+  anchor: Option<ViewState>,
+}
+
+impl ViewHistory {
+  /// Maximum number of views kept in the back history.
+  const MAX_LEN: usize = 20;
+
+  /// Record the current view before jumping away from it. This clears the forward history.
+  /// - `current`: current view
+  pub fn push(&mut self, current: ViewState) {
+    self.forward.clear();
+    self.add_back(current);
+    self.anchor = None;
+  }
+
+  /// Track the view when it comes to rest after manual navigation. Consecutive pans are combined
+  /// until the view has moved more than its own size from where it settled, then that earlier
+  /// view is recorded as a single entry.
+  /// - `current`: current view
+  /// - `view_size`: size of the view in screen pixels
+  pub fn settle(&mut self, current: ViewState, view_size: emath::Vec2) {
+    let Some(anchor) = self.anchor else {
+      self.anchor = Some(current);
+      return;
+    };
+
+    let offset = ((current.center - anchor.center) * current.zoom).abs();
+    if offset.x > view_size.x || offset.y > view_size.y {
+      self.forward.clear();
+      self.add_back(anchor);
+      self.anchor = Some(current);
+    }
+  }
+
+  /// Go back to the previous view.
+  /// - `current`: current view, which is moved to the forward history
+  pub fn back(&mut self, current: ViewState) -> Option<ViewState> {
+    let state = self.back.pop()?;
+    self.forward.push(current);
+    self.anchor = None;
+    Some(state)
+  }
+
+  /// Go forward to the next view.
+  /// - `current`: current view, which is moved to the back history
+  pub fn forward(&mut self, current: ViewState) -> Option<ViewState> {
+    let state = self.forward.pop()?;
+    self.add_back(current);
+    self.anchor = None;
+    Some(state)
+  }
+
+  pub fn can_go_back(&self) -> bool {
+    !self.back.is_empty()
+  }
+
+  pub fn can_go_forward(&self) -> bool {
+    !self.forward.is_empty()
+  }
+
+  fn add_back(&mut self, state: ViewState) {
+    if self.back.last() == Some(&state) {
+      return;
+    }
+
+    if self.back.len() == Self::MAX_LEN {
+      self.back.remove(0);
+    }
+    self.back.push(state);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{ViewHistory, ViewState};
+  use eframe::emath;
+
+  fn view(x: f32, y: f32, zoom: f32) -> ViewState {
+    ViewState {
+      center: emath::pos2(x, y),
+      zoom,
+    }
+  }
+
+  #[test]
+  fn test_back_forward() {
+    let mut history = ViewHistory::default();
+    assert!(history.back(view(0.0, 0.0, 1.0)).is_none());
+
+    history.push(view(0.0, 0.0, 1.0));
+    history.push(view(5000.0, 0.0, 1.0));
+    assert!(history.can_go_back() && !history.can_go_forward());
+
+    // Currently at the second jump's destination.
+    let current = view(9000.0, 500.0, 1.0);
+    assert!(history.back(current) == Some(view(5000.0, 0.0, 1.0)));
+    assert!(history.back(view(5000.0, 0.0, 1.0)) == Some(view(0.0, 0.0, 1.0)));
+    assert!(!history.can_go_back());
+
+    assert!(history.forward(view(0.0, 0.0, 1.0)) == Some(view(5000.0, 0.0, 1.0)));
+    assert!(history.forward(view(5000.0, 0.0, 1.0)) == Some(current));
+    assert!(!history.can_go_forward());
+
+    // A new jump clears the forward history.
+    history.back(current);
+    history.push(view(5000.0, 0.0, 1.0));
+    assert!(!history.can_go_forward());
+
+    // The history is capped.
+    for index in 0..30 {
+      history.push(view(index as f32 * 1000.0, 0.0, 1.0));
+    }
+    let mut count = 0;
+    while history.back(view(0.0, 0.0, 1.0)).is_some() {
+      count += 1;
+    }
+    assert!(count == ViewHistory::MAX_LEN);
+  }
+
+  #[test]
+  fn test_settle() {
+    let size = emath::vec2(1000.0, 800.0);
+    let mut history = ViewHistory::default();
+
+    // Small pans are combined.
+    history.settle(view(0.0, 0.0, 1.0), size);
+    history.settle(view(600.0, 0.0, 1.0), size);
+    history.settle(view(900.0, 500.0, 1.0), size);
+    assert!(!history.can_go_back());
+
+    // Moving more than the view size from where it settled records that view.
+    history.settle(view(1200.0, 0.0, 1.0), size);
+    assert!(history.back(view(1200.0, 0.0, 1.0)) == Some(view(0.0, 0.0, 1.0)));
+
+    // Distances are in screen pixels.
+    let mut history = ViewHistory::default();
+    history.settle(view(0.0, 0.0, 0.5), size);
+    history.settle(view(1800.0, 0.0, 0.5), size);
+    assert!(!history.can_go_back());
+    history.settle(view(2100.0, 0.0, 0.5), size);
+    assert!(history.can_go_back());
+  }
+}
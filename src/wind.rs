@@ -0,0 +1,87 @@
+/// Get the heading of a runway end in degrees.
+/// - `end_id`: runway end ID (e.g. "13", "31L")
+/// - `true_alignment`: true alignment of the runway end, preferred when available
+///
+/// Returns `None` for runway ends without a numbered heading, such as "N" or heliport pads "H1".
+pub fn runway_heading(end_id: &str, true_alignment: Option<f64>) -> Option<f64> {
+  if let Some(alignment) = true_alignment {
+    if (0.0..=360.0).contains(&alignment) {
+      return Some(alignment);
+    }
+  }
+
+  // Remove the left, center or right suffix.
+  let number = end_id
+    .trim()
+    .trim_end_matches(|ch: char| matches!(ch.to_ascii_uppercase(), 'L' | 'C' | 'R'));
+
+  if number.is_empty() || number.len() > 2 || !number.chars().all(|ch| ch.is_ascii_digit()) {
+    return None;
+  }
+
+  match number.parse::<u32>() {
+    Ok(number) if (1..=36).contains(&number) => Some(number as f64 * 10.0),
+    _ => None,
+  }
+}
+
+/// Headwind and crosswind components.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Components {
+  /// Headwind component (negative for a tailwind).
+  pub headwind: f64,
+
+  /// Crosswind component (positive from the right, negative from the left).
+  pub crosswind: f64,
+}
+
+/// Get the wind components for a runway heading.
+/// - `heading`: runway heading in degrees
+/// - `wind_dir`: direction that the wind is from in degrees
+/// - `wind_speed`: wind speed
+pub fn components(heading: f64, wind_dir: f64, wind_speed: f64) -> Components {
+  let angle = (wind_dir - heading).to_radians();
+  Components {
+    headwind: wind_speed * angle.cos(),
+    crosswind: wind_speed * angle.sin(),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  fn near(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+  }
+
+  #[test]
+  fn test_runway_heading() {
+    assert!(super::runway_heading("13", None) == Some(130.0));
+    assert!(super::runway_heading("31L", None) == Some(310.0));
+    assert!(super::runway_heading("04R", None) == Some(40.0));
+    assert!(super::runway_heading("36C", None) == Some(360.0));
+    assert!(super::runway_heading("13", Some(146.0)) == Some(146.0));
+    assert!(super::runway_heading("H1", None).is_none());
+    assert!(super::runway_heading("N", None).is_none());
+    assert!(super::runway_heading("", None).is_none());
+    assert!(super::runway_heading("37", None).is_none());
+    assert!(super::runway_heading("00", None).is_none());
+  }
+
+  #[test]
+  fn test_components() {
+    let wind = super::components(310.0, 310.0, 10.0);
+    assert!(near(wind.headwind, 10.0) && near(wind.crosswind, 0.0));
+
+    let wind = super::components(130.0, 310.0, 10.0);
+    assert!(near(wind.headwind, -10.0) && wind.crosswind.abs() < 1e-9);
+
+    // 30° from the right.
+    let wind = super::components(310.0, 340.0, 20.0);
+    assert!(near(wind.headwind, 20.0 * 30f64.to_radians().cos()));
+    assert!(near(wind.crosswind, 10.0));
+
+    // Direct crosswind from the left, across north.
+    let wind = super::components(10.0, 280.0, 15.0);
+    assert!(wind.headwind.abs() < 1e-9 && near(wind.crosswind, -15.0));
+  }
+}
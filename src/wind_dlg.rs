@@ -0,0 +1,139 @@
+use crate::{back, nasr, wind};
+use eframe::{egui, emath, epaint};
+
+/// Non-modal window showing the wind components for each runway end of an airport.
+pub struct WindDlg {
+  title: String,
+  id: String,
+  ends: Option<Vec<nasr::RunwayEnd>>,
+  wind_dir: String,
+  wind_speed: String,
+}
+
+impl WindDlg {
+  /// Open the runway wind window.
+  /// - `title`: airport description
+  /// - `id`: airport ID
+  pub fn open(title: String, id: String) -> Self {
+    Self {
+      title,
+      id,
+      ends: None,
+      wind_dir: String::new(),
+      wind_speed: String::new(),
+    }
+  }
+
+  /// Airport ID for this window.
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  /// Set the runway ends once they're available.
+  pub fn set_runways(&mut self, ends: Vec<nasr::RunwayEnd>) {
+    self.ends = Some(ends);
+  }
+
+  /// Show the window. Returns false when it has been closed.
+  pub fn show(&mut self, ctx: &egui::Context) -> bool {
+    let mut open = !back::register(ctx, "wind");
+    egui::Window::new(egui::RichText::from("🌬  Runway Winds").strong())
+      .open(&mut open)
+      .collapsible(false)
+      .resizable(false)
+      .anchor(emath::Align2::RIGHT_TOP, [-8.0, 8.0])
+      .show(ctx, |ui| {
+        ui.label(egui::RichText::new(&self.title).strong());
+        ui.separator();
+
+        ui.horizontal(|ui| {
+          ui.label("Wind");
+          let widget = egui::TextEdit::singleline(&mut self.wind_dir)
+            .hint_text("Dir")
+            .desired_width(40.0);
+          ui.add(widget);
+          ui.label("°");
+          let widget = egui::TextEdit::singleline(&mut self.wind_speed)
+            .hint_text("Speed")
+            .desired_width(40.0);
+          ui.add(widget);
+          ui.label("kt");
+        });
+
+        let Some(ends) = &self.ends else {
+          ui.spinner();
+          return;
+        };
+
+        // Get the runway headings, skipping ends without one.
+        let headings: Vec<_> = ends
+          .iter()
+          .filter_map(|end| Some((end, wind::runway_heading(&end.id, end.true_alignment)?)))
+          .collect();
+
+        if headings.is_empty() {
+          ui.label(egui::RichText::new("No runway headings").weak());
+          return;
+        }
+
+        let wind_dir = self.wind_dir.trim().parse::<f64>().ok();
+        let wind_speed = self.wind_speed.trim().parse::<f64>().ok();
+        let components: Vec<_> = match (wind_dir, wind_speed) {
+          (Some(dir), Some(speed)) if (0.0..=360.0).contains(&dir) && speed >= 0.0 => headings
+            .iter()
+            .map(|(_, heading)| Some(wind::components(*heading, dir, speed)))
+            .collect(),
+          _ => vec![None; headings.len()],
+        };
+
+        // The runway end with the most headwind.
+        let best = components
+          .iter()
+          .enumerate()
+          .filter_map(|(index, wind)| Some((index, (*wind)?.headwind)))
+          .max_by(|a, b| a.1.total_cmp(&b.1))
+          .map(|(index, _)| index);
+
+        ui.add_space(4.0);
+        egui::Grid::new("wind_grid")
+          .striped(true)
+          .spacing([16.0, 4.0])
+          .show(ui, |ui| {
+            ui.label(egui::RichText::new("Runway").strong());
+            ui.label(egui::RichText::new("Heading").strong());
+            ui.label(egui::RichText::new("Headwind").strong());
+            ui.label(egui::RichText::new("Crosswind").strong());
+            ui.end_row();
+
+            for (index, ((end, heading), wind)) in headings.iter().zip(&components).enumerate() {
+              let text = |text: String| {
+                let text = egui::RichText::new(text);
+                if best == Some(index) {
+                  text.strong().color(epaint::Color32::from_rgb(0, 160, 0))
+                } else {
+                  text
+                }
+              };
+
+              ui.label(text(end.id.clone()));
+              ui.label(text(format!("{heading:03.0}°")));
+              if let Some(wind) = wind {
+                let headwind = if wind.headwind >= 0.0 {
+                  format!("{:.0} kt", wind.headwind)
+                } else {
+                  format!("{:.0} kt tail", -wind.headwind)
+                };
+                let side = if wind.crosswind >= 0.0 { "R" } else { "L" };
+                ui.label(text(headwind));
+                ui.label(text(format!("{:.0} kt {side}", wind.crosswind.abs())));
+              } else {
+                ui.label("—");
+                ui.label("—");
+              }
+              ui.end_row();
+            }
+          });
+      });
+    open
+  }
+}
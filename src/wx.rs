@@ -0,0 +1,200 @@
+use crate::util;
+use eframe::egui;
+use std::{any, collections, sync::mpsc, thread, time};
+
+/// How long a fetched report is reused before asking the server again.
+const CACHE_DURATION: time::Duration = time::Duration::from_secs(5 * 60);
+
+/// WxReader fetches METAR and TAF reports from aviationweather.gov on a separate thread.
+pub struct WxReader {
+  tx: mpsc::Sender<String>,
+  rx: mpsc::Receiver<(String, WxReply)>,
+}
+
+impl WxReader {
+  /// Create a new weather reader.
+  /// - `ctx`: egui context for requesting a repaint
+  pub fn new(ctx: &egui::Context) -> Self {
+    let ctx = ctx.clone();
+    let (tx, trx) = mpsc::channel::<String>();
+    let (ttx, rx) = mpsc::channel();
+
+    thread::Builder::new()
+      .name(any::type_name::<WxReader>().to_owned())
+      .spawn(move || {
+        let mut cache: collections::HashMap<_, (time::Instant, String)> =
+          collections::HashMap::new();
+
+        // Wait for a message. Exit when the connection is closed.
+        while let Ok(station) = trx.recv() {
+          for kind in [Kind::Metar, Kind::Taf] {
+            let key = (station.clone(), kind);
+            let text = match cache.get(&key) {
+              Some((fetched, text)) if fetched.elapsed() < CACHE_DURATION => Ok(text.clone()),
+              _ => fetch(&station, kind).inspect(|text| {
+                cache.insert(key, (time::Instant::now(), text.clone()));
+              }),
+            };
+
+            let reply = match (text, kind) {
+              (Ok(text), Kind::Metar) => WxReply::Metar(text),
+              (Ok(text), Kind::Taf) => WxReply::Taf(text),
+              (Err(err), _) => WxReply::Error(err),
+            };
+
+            if ttx.send((station.clone(), reply)).is_err() {
+              return;
+            }
+            ctx.request_repaint();
+          }
+        }
+      })
+      .unwrap();
+
+    Self { tx, rx }
+  }
+
+  /// Request the current METAR and TAF for a station. Replies are tagged with the station ID.
+  /// - `station`: ICAO station ID
+  pub fn request(&self, station: String) {
+    self.tx.send(station).unwrap();
+  }
+
+  /// Get the next available reply.
+  pub fn get_reply(&self) -> Option<(String, WxReply)> {
+    self.rx.try_recv().ok()
+  }
+}
+
+pub enum WxReply {
+  /// Raw METAR text (empty if the station has no current report).
+  Metar(String),
+
+  /// Raw TAF text (empty if the station has no current forecast).
+  Taf(String),
+
+  /// Unable to fetch the report.
+  Error(util::Error),
+}
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+enum Kind {
+  Metar,
+  Taf,
+}
+
+#[cfg(feature = "wx")]
+fn fetch(station: &str, kind: Kind) -> Result<String, util::Error> {
+  let product = match kind {
+    Kind::Metar => "metar",
+    Kind::Taf => "taf",
+  };
+
+  let url = format!("https://aviationweather.gov/api/data/{product}?ids={station}&format=raw");
+  let agent = ureq::AgentBuilder::new()
+    .timeout(time::Duration::from_secs(10))
+    .build();
+
+  match agent.get(&url).call() {
+    Ok(response) => match response.into_string() {
+      Ok(text) => Ok(text.trim().to_owned()),
+      Err(err) => Err(format!("{err}").into()),
+    },
+    Err(err) => Err(format!("{err}").into()),
+  }
+}
+
+#[cfg(not(feature = "wx"))]
+fn fetch(_station: &str, _kind: Kind) -> Result<String, util::Error> {
+  Err("Weather is not supported in this build".into())
+}
+
+/// Get the ICAO station ID for an airport. The airport's ICAO ID is used if it has one, otherwise
+/// three letter IDs in the contiguous US are prefixed with "K". Returns `None` if the airport
+/// can't have a weather station ID.
+/// - `id`: airport ID
+/// - `icao_id`: ICAO ID from the NASR data
+/// - `state`: state or territory code
+pub fn station_id(id: &str, icao_id: Option<&str>, state: &str) -> Option<String> {
+  let letters = |id: &str| id.chars().all(|ch| ch.is_ascii_alphabetic());
+  if let Some(icao_id) = icao_id.filter(|icao_id| icao_id.len() == 4 && letters(icao_id)) {
+    return Some(icao_id.to_owned());
+  }
+
+  match id.len() {
+    3 if letters(id) && !NON_CONTIGUOUS.contains(&state) => Some(format!("K{id}")),
+    4 if letters(id) => Some(id.to_owned()),
+    _ => None,
+  }
+}
+
+/// State and territory codes outside the contiguous US, where ICAO IDs don't start with "K".
+const NON_CONTIGUOUS: [&str; 7] = ["AK", "AS", "GU", "HI", "MP", "PR", "VI"];
+
+/// Get the age, in minutes, of a METAR observation.
+/// - `metar`: raw METAR text
+/// - `now`: current time in seconds since the Unix epoch
+pub fn observation_age(metar: &str, now: u64) -> Option<u64> {
+  // The observation time looks like "DDHHMMZ" and follows the station ID.
+  let time = metar
+    .split_whitespace()
+    .find(|field| field.len() == 7 && field.ends_with('Z'))?;
+  let day: u64 = time.get(0..2)?.parse().ok()?;
+  let hour: u64 = time.get(2..4)?.parse().ok()?;
+  let min: u64 = time.get(4..6)?.parse().ok()?;
+  if !(1..=31).contains(&day) || hour > 23 || min > 59 {
+    return None;
+  }
+
+  // Find the most recent date with a matching day of the month.
+  let today = now / 86400;
+  let obs_min = hour * 60 + min;
+  let now_min = now / 60;
+  for back in 0..32 {
+    let days = today.checked_sub(back)?;
+    if util::Date::from_days(days as i64).day == day as u32 {
+      let obs = days * 24 * 60 + obs_min;
+      if obs <= now_min {
+        return Some(now_min - obs);
+      }
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod test {
+  #[test]
+  fn test_station_id() {
+    use super::station_id;
+
+    // The ICAO ID from the NASR data is preferred.
+    assert!(station_id("ANC", Some("PANC"), "AK").as_deref() == Some("PANC"));
+    assert!(station_id("HNL", Some("PHNL"), "HI").as_deref() == Some("PHNL"));
+    assert!(station_id("TVL", Some("KTVL"), "CA").as_deref() == Some("KTVL"));
+
+    // Only three letter IDs in the contiguous US get a "K" prefix.
+    assert!(station_id("TVL", None, "CA").as_deref() == Some("KTVL"));
+    assert!(station_id("ANC", None, "AK").is_none());
+    assert!(station_id("HNL", None, "HI").is_none());
+    assert!(station_id("PANC", None, "AK").as_deref() == Some("PANC"));
+    assert!(station_id("O22", None, "CA").is_none());
+    assert!(station_id("CA48", None, "CA").is_none());
+    assert!(station_id("", None, "").is_none());
+  }
+
+  #[test]
+  fn test_observation_age() {
+    // 2024-05-16 18:20:00Z.
+    let now = 19859 * 86400 + 18 * 3600 + 20 * 60;
+    let metar = "KTVL 161756Z AUTO 24012KT 10SM CLR 18/M03 A3012";
+    assert!(super::observation_age(metar, now) == Some(24));
+
+    // Observation from the end of the previous month.
+    let now = 19875 * 86400 + 30 * 60;
+    let metar = "KTVL 312356Z 00000KT 10SM CLR 10/M01 A3010";
+    assert!(super::observation_age(metar, now) == Some(34));
+
+    assert!(super::observation_age("KTVL AUTO", now).is_none());
+  }
+}
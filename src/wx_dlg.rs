@@ -0,0 +1,139 @@
+use crate::{back, solar, util, wx};
+use eframe::{egui, emath};
+use std::time;
+
+/// Non-modal window showing the METAR, TAF and today's sun times for an airport.
+pub struct WxDlg {
+  title: String,
+  station: Option<String>,
+  metar: Report,
+  taf: Report,
+  sun: solar::SunTimes,
+}
+
+impl WxDlg {
+  /// Open the weather window.
+  /// - `title`: airport description
+  /// - `station`: ICAO station ID or `None` if the airport doesn't have one
+  /// - `coord`: airport location in decimal degrees
+  pub fn open(title: String, station: Option<String>, coord: util::Coord) -> Self {
+    let report = if station.is_some() {
+      Report::Pending
+    } else {
+      Report::Unavailable
+    };
+
+    Self {
+      title,
+      station,
+      metar: report.clone(),
+      taf: report,
+      sun: solar::SunTimes::new(coord, util::Date::today()),
+    }
+  }
+
+  /// Station ID for this window.
+  pub fn station(&self) -> Option<&str> {
+    self.station.as_deref()
+  }
+
+  /// Apply a weather reply.
+  pub fn set_reply(&mut self, reply: wx::WxReply) {
+    match reply {
+      wx::WxReply::Metar(text) => self.metar = Report::from_text(text),
+      wx::WxReply::Taf(text) => self.taf = Report::from_text(text),
+      wx::WxReply::Error(err) => {
+        log::warn!("{err}");
+        if self.metar == Report::Pending {
+          self.metar = Report::Unavailable;
+        } else {
+          self.taf = Report::Unavailable;
+        }
+      }
+    }
+  }
+
+  /// Show the window. Returns false when it has been closed.
+  pub fn show(&mut self, ctx: &egui::Context) -> bool {
+    let mut open = !back::register(ctx, "wx");
+    egui::Window::new(egui::RichText::from("🌤  Weather").strong())
+      .open(&mut open)
+      .collapsible(false)
+      .resizable(false)
+      .anchor(emath::Align2::RIGHT_TOP, [-8.0, 8.0])
+      .show(ctx, |ui| {
+        ui.label(egui::RichText::new(&self.title).strong());
+        ui.separator();
+
+        ui.label("METAR");
+        self.metar.show(ui, true);
+        ui.add_space(4.0);
+
+        ui.label("TAF");
+        self.taf.show(ui, false);
+        ui.add_space(4.0);
+
+        ui.label("Sun (UTC)");
+        egui::Grid::new("sun_grid")
+          .spacing([16.0, 2.0])
+          .show(ui, |ui| {
+            let times = [
+              ("Civil Dawn", self.sun.dawn),
+              ("Sunrise", self.sun.sunrise),
+              ("Sunset", self.sun.sunset),
+              ("Civil Dusk", self.sun.dusk),
+            ];
+            for (label, time) in times {
+              ui.label(egui::RichText::new(label).weak());
+              ui.label(egui::RichText::new(solar::format_time(time, 0)).monospace());
+              ui.end_row();
+            }
+          });
+      });
+    open
+  }
+}
+
+#[derive(Clone, Eq, PartialEq)]
+enum Report {
+  Pending,
+  Text(String),
+  None,
+  Unavailable,
+}
+
+impl Report {
+  fn from_text(text: String) -> Self {
+    if text.is_empty() {
+      Report::None
+    } else {
+      Report::Text(text)
+    }
+  }
+
+  fn show(&self, ui: &mut egui::Ui, metar: bool) {
+    match self {
+      Report::Pending => {
+        ui.spinner();
+      }
+      Report::Text(text) => {
+        ui.add(egui::Label::new(egui::RichText::new(text).monospace()).wrap(true));
+        if metar {
+          let now = time::SystemTime::now().duration_since(time::UNIX_EPOCH);
+          let age = now
+            .ok()
+            .and_then(|now| wx::observation_age(text, now.as_secs()));
+          if let Some(age) = age {
+            ui.label(egui::RichText::new(format!("Observed {age} min ago")).weak());
+          }
+        }
+      }
+      Report::None => {
+        ui.label(egui::RichText::new("No current report").weak());
+      }
+      Report::Unavailable => {
+        ui.label(egui::RichText::new("Weather unavailable").weak());
+      }
+    }
+  }
+}